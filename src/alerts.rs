@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::display::format_elapsed;
+use crate::parse::parse_duration;
+
+/// Traffic limit for `--alert-rate`, e.g. "100/min"
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub requests: usize,
+    pub per: Duration,
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.per.as_secs() {
+            1 => write!(f, "{}/s", self.requests),
+            60 => write!(f, "{}/min", self.requests),
+            3600 => write!(f, "{}/h", self.requests),
+            _ => write!(f, "{}/{}", self.requests, format_elapsed(self.per)),
+        }
+    }
+}
+
+/// Parse a rate such as "100/min", "5/s", "1000/h" or "20/5m"
+pub fn parse_rate(value: &str) -> Result<RateLimit, String> {
+    let (requests, per) = value
+        .split_once('/')
+        .ok_or_else(|| format!("invalid rate '{}', expected e.g. 100/min", value))?;
+    let requests: usize = requests
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid request count in '{}'", value))?;
+    if requests == 0 {
+        return Err("the request count must be greater than zero".to_string());
+    }
+    let per = match per.trim() {
+        "s" | "sec" | "second" => Duration::from_secs(1),
+        "m" | "min" | "minute" => Duration::from_secs(60),
+        "h" | "hr" | "hour" => Duration::from_secs(60 * 60),
+        other => parse_duration(other)?,
+    };
+    if per.is_zero() {
+        return Err("the rate window must be longer than zero".to_string());
+    }
+    Ok(RateLimit { requests, per })
+}
+
+/// A change in the traffic health of a token
+pub enum Alert {
+    RateExceeded(RateLimit),
+    RateNormal(RateLimit),
+    Silence(Duration),
+    TrafficResumed,
+}
+
+impl Alert {
+    /// Alerts that fire hooks; the others report recovery
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Alert::RateExceeded(_) | Alert::Silence(_))
+    }
+
+    /// Short name passed to hooks in `WEBHOOK_ALERT`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Alert::RateExceeded(_) | Alert::RateNormal(_) => "rate",
+            Alert::Silence(_) | Alert::TrafficResumed => "silence",
+        }
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::RateExceeded(limit) => write!(
+                f,
+                "more than {} requests in the last {} (limit {})",
+                limit.requests,
+                format_elapsed(limit.per),
+                limit
+            ),
+            Alert::RateNormal(limit) => write!(f, "traffic back under {}", limit),
+            Alert::Silence(quiet) => write!(f, "no requests for {}", format_elapsed(*quiet)),
+            Alert::TrafficResumed => write!(f, "traffic resumed"),
+        }
+    }
+}
+
+/// Rate and silence tracking for one token
+pub struct AlertState {
+    rate: Option<RateLimit>,
+    silence: Option<Duration>,
+    // Arrival times within the rate window, at most `requests + 1` of them
+    recent: VecDeque<Instant>,
+    last_activity: Instant,
+    rate_exceeded: bool,
+    silent: bool,
+}
+
+impl AlertState {
+    pub fn new(rate: Option<RateLimit>, silence: Option<Duration>) -> Self {
+        Self {
+            rate,
+            silence,
+            recent: VecDeque::new(),
+            last_activity: Instant::now(),
+            rate_exceeded: false,
+            silent: false,
+        }
+    }
+
+    /// Record a new request
+    pub fn on_request(&mut self) -> Option<Alert> {
+        let now = Instant::now();
+        self.last_activity = now;
+
+        if let Some(limit) = self.rate {
+            self.prune(now, limit);
+            self.recent.push_back(now);
+            if self.recent.len() > limit.requests + 1 {
+                self.recent.pop_front();
+            }
+            if self.recent.len() > limit.requests && !self.rate_exceeded {
+                self.rate_exceeded = true;
+                return Some(Alert::RateExceeded(limit));
+            }
+        }
+
+        if self.silent {
+            self.silent = false;
+            return Some(Alert::TrafficResumed);
+        }
+        None
+    }
+
+    /// Periodic check for silence and for the rate falling back under the limit
+    pub fn check(&mut self) -> Option<Alert> {
+        let now = Instant::now();
+
+        if let Some(limit) = self.rate {
+            self.prune(now, limit);
+            if self.rate_exceeded && self.recent.len() <= limit.requests {
+                self.rate_exceeded = false;
+                return Some(Alert::RateNormal(limit));
+            }
+        }
+
+        if let Some(silence) = self.silence {
+            let quiet = now.duration_since(self.last_activity);
+            if quiet >= silence && !self.silent {
+                self.silent = true;
+                return Some(Alert::Silence(quiet));
+            }
+        }
+        None
+    }
+
+    fn prune(&mut self, now: Instant, limit: RateLimit) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > limit.per)
+        {
+            self.recent.pop_front();
+        }
+    }
+}
@@ -0,0 +1,842 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Transaction, params};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often retention limits are applied while writing
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The database in the archive directory
+const DATABASE_FILE: &str = "archive.db";
+
+/// Waits this long for another session writing to the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    PRAGMA auto_vacuum = INCREMENTAL;
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS requests (
+        token TEXT NOT NULL,
+        id TEXT NOT NULL,
+        -- Unix milliseconds: when the service received the request, or when
+        -- it was archived if the service's timestamp can't be read
+        received_at INTEGER NOT NULL,
+        archived_at INTEGER NOT NULL,
+        -- The request as JSON, sealed when the archive is encrypted
+        data TEXT NOT NULL,
+        PRIMARY KEY (token, id)
+    );
+    CREATE INDEX IF NOT EXISTS requests_received_at ON requests (received_at);
+    -- Full-text index of the requests stored in plain text, by rowid
+    CREATE VIRTUAL TABLE IF NOT EXISTS requests_text USING fts5(body, headers);
+    CREATE TRIGGER IF NOT EXISTS requests_text_delete AFTER DELETE ON requests BEGIN
+        DELETE FROM requests_text WHERE rowid = old.rowid;
+    END;
+";
+
+/// `PRAGMA user_version` of a database with the full-text index filled
+const INDEXED_VERSION: i64 = 1;
+
+/// Marks the start of a match in the snippets of `Archive::search`
+pub const MATCH_START: char = '\u{2}';
+/// Marks the end of a match in the snippets of `Archive::search`
+pub const MATCH_END: char = '\u{3}';
+
+const SEARCH: &str = "
+    SELECT requests.token, requests.archived_at, requests.data,
+        snippet(requests_text, 0, char(2), char(3), '…', 12),
+        snippet(requests_text, 1, char(2), char(3), '…', 12)
+    FROM requests_text JOIN requests ON requests.rowid = requests_text.rowid
+    WHERE requests_text MATCH ?1 AND (?2 IS NULL OR requests.token = ?2)
+    ORDER BY requests.received_at DESC, requests.rowid DESC
+";
+
+use crate::config::{ArchiveConfig, Retention};
+use crate::encryption::Cipher;
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+use crate::logging;
+use crate::models::WebhookRequest;
+use crate::secrets::resolve_secret;
+use crate::state::data_dir;
+
+/// Requests kept on disk after the service has rotated its log, in a SQLite
+/// database keyed by token and request ID
+pub struct Archive {
+    dir: PathBuf,
+    retention: Retention,
+    // `archive.passphrase`, resolved and turned into a key on first use
+    passphrase: Option<String>,
+    cipher: OnceLock<Cipher>,
+    // Opened on first use
+    connection: Mutex<Option<Connection>>,
+    // Per token, the IDs archived in this session or found in the database,
+    // read on the first write. Pruned requests stay here so they are not
+    // archived again while still in the service's log.
+    ids: Mutex<HashMap<String, HashSet<String>>>,
+    // Retention is applied on the first write and then at most hourly,
+    // unless a limit is exceeded
+    last_pruned: Mutex<Option<Instant>>,
+    // Write failures are reported once, not on every poll
+    warned: AtomicBool,
+}
+
+/// Requests removed by `Archive::prune`
+pub struct Pruned {
+    pub removed: usize,
+    pub kept: usize,
+}
+
+/// Archived requests of one token, for `webhook stats`
+pub struct TokenSummary {
+    pub token: String,
+    pub requests: usize,
+    /// Bytes of the stored requests
+    pub size: u64,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+
+/// Archived requests matching a search
+pub struct Found {
+    pub hits: Vec<SearchHit>,
+    /// Requests searched
+    pub searched: usize,
+}
+
+/// A request matching a search, with snippets of the body and headers that
+/// matched, the matches between `MATCH_START` and `MATCH_END`
+pub struct SearchHit {
+    pub archived: ArchivedRequest,
+    pub body: Option<String>,
+    pub headers: Option<String>,
+}
+
+/// A request in the archive
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedRequest {
+    pub token: String,
+    pub archived_at: DateTime<Utc>,
+    pub request: WebhookRequest,
+}
+
+impl Archive {
+    /// The archive configured in `[archive]`, whether or not archiving is enabled
+    pub fn new(config: &ArchiveConfig) -> Result<Self> {
+        let dir = match &config.path {
+            Some(path) => path.clone(),
+            None => data_dir()
+                .ok_or_else(|| WebhookError::config("Could not determine the data directory"))?
+                .join("webhook-cli")
+                .join("archive"),
+        };
+        Ok(Self {
+            dir,
+            retention: config.retention()?,
+            passphrase: config.passphrase.clone(),
+            cipher: OnceLock::new(),
+            connection: Mutex::new(None),
+            ids: Mutex::new(HashMap::new()),
+            last_pruned: Mutex::new(None),
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    /// The database file
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(DATABASE_FILE)
+    }
+
+    /// Store the requests not archived yet
+    pub fn store(&self, token: &str, requests: &[WebhookRequest]) {
+        if let Err(e) = self.append(token, requests)
+            && !self.warned.swap(true, Ordering::Relaxed)
+        {
+            logging::warn(format_args!(
+                "Could not write to the request archive: {:#}",
+                e
+            ));
+        }
+    }
+
+    /// The cipher of an encrypted archive, `None` when it is not encrypted
+    fn cipher(&self) -> Result<Option<&Cipher>> {
+        let Some(passphrase) = &self.passphrase else {
+            return Ok(None);
+        };
+        if let Some(cipher) = self.cipher.get() {
+            return Ok(Some(cipher));
+        }
+        let passphrase = resolve_secret(passphrase)
+            .config_err(|| "Failed to read archive.passphrase".to_string())?;
+        let cipher = Cipher::open(&self.dir, &passphrase)?;
+        Ok(Some(self.cipher.get_or_init(|| cipher)))
+    }
+
+    /// Whether anything was archived, so reading does not create an empty
+    /// database
+    fn exists(&self) -> Result<bool> {
+        Ok(self.path().exists() || !self.files()?.is_empty())
+    }
+
+    /// Run `f` on the database, opened and set up on first use
+    fn with_database<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let connection = match &mut *connection {
+            Some(connection) => connection,
+            None => connection.insert(self.open()?),
+        };
+        f(connection)
+    }
+
+    fn open(&self) -> Result<Connection> {
+        fs::create_dir_all(&self.dir)
+            .io_err(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path();
+        let mut connection =
+            Connection::open(&path).db_err(|| format!("Failed to open {}", path.display()))?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .db_err(|| format!("Failed to set up {}", path.display()))?;
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .db_err(|| self.read_error())?;
+        if version < INDEXED_VERSION {
+            self.index_existing(&mut connection)?;
+        }
+        self.import_files(&mut connection)?;
+        Ok(connection)
+    }
+
+    /// Fill the full-text index of a database written before it had one.
+    /// Encrypted archives are not indexed on disk.
+    fn index_existing(&self, connection: &mut Connection) -> Result<()> {
+        let transaction = connection.transaction().db_err(|| self.write_error())?;
+        if self.cipher()?.is_none() {
+            let plain: Vec<(i64, String)> = transaction
+                .prepare("SELECT rowid, data FROM requests WHERE data LIKE '{%'")
+                .and_then(|mut statement| {
+                    statement
+                        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect()
+                })
+                .db_err(|| self.read_error())?;
+            for (rowid, data) in plain {
+                if let Some(request) = self.decode(&data, None)? {
+                    index(&transaction, rowid, &request)?;
+                }
+            }
+        }
+        transaction
+            .pragma_update(None, "user_version", INDEXED_VERSION)
+            .and_then(|_| transaction.commit())
+            .db_err(|| self.write_error())
+    }
+
+    /// Move the NDJSON files of earlier versions, one per token, into the
+    /// database
+    fn import_files(&self, connection: &mut Connection) -> Result<()> {
+        let files = self.files()?;
+        if files.is_empty() {
+            return Ok(());
+        }
+        let cipher = self.cipher()?;
+        let transaction = connection.transaction().db_err(|| self.write_error())?;
+        let mut imported = 0;
+        for path in &files {
+            for archived in load_file(path, cipher)? {
+                imported += insert(
+                    &transaction,
+                    &archived.token,
+                    &archived.request,
+                    archived.archived_at,
+                    cipher,
+                )?;
+            }
+        }
+        transaction.commit().db_err(|| self.write_error())?;
+        for path in &files {
+            fs::remove_file(path).io_err(|| format!("Failed to remove {}", path.display()))?;
+        }
+        logging::debug(
+            1,
+            format_args!("Imported {} requests into {}", imported, DATABASE_FILE),
+        );
+        Ok(())
+    }
+
+    fn append(&self, token: &str, requests: &[WebhookRequest]) -> Result<()> {
+        let cipher = self.cipher()?;
+        let archived_at = Utc::now();
+        let cutoff = self.retention.max_age.map(|age| archived_at - age);
+        let over_limit = self.with_database(|connection| {
+            let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+            let ids = match ids.entry(token.to_string()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(token_ids(connection, token)?),
+            };
+
+            let transaction = connection.transaction().db_err(|| self.write_error())?;
+            let mut added = 0;
+            for request in requests {
+                if !ids.insert(request.id.clone()) {
+                    continue;
+                }
+                // Requests already past max_age would only be pruned again
+                if cutoff.is_some_and(|cutoff| request.date.unwrap_or(archived_at) < cutoff) {
+                    continue;
+                }
+                added += insert(&transaction, token, request, archived_at, cipher)?;
+            }
+            transaction.commit().db_err(|| self.write_error())?;
+            if added > 0 {
+                logging::debug(1, format_args!("Archived {} requests", added));
+            }
+
+            let over_count = self.retention.max_requests_per_token > 0
+                && connection
+                    .query_row(
+                        "SELECT COUNT(*) FROM requests WHERE token = ?1",
+                        [token],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .db_err(|| self.read_error())?
+                    > sql_integer(self.retention.max_requests_per_token as u64);
+            let over_size = self.retention.max_size > 0
+                && stored_size(connection)? > sql_integer(self.retention.max_size);
+            Ok(over_count || over_size)
+        })?;
+
+        if self.retention.is_unlimited() {
+            return Ok(());
+        }
+        let due = self
+            .last_pruned
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL);
+        if over_limit || due {
+            let pruned = self.prune(&self.retention)?;
+            logging::debug(
+                1,
+                format_args!("Pruned {} archived requests", pruned.removed),
+            );
+        }
+        Ok(())
+    }
+
+    /// Remove requests beyond the limits of `retention`: those older than
+    /// `max_age`, the oldest of each token beyond `max_requests_per_token`,
+    /// then the oldest overall until the stored requests fit in `max_size`.
+    /// Requests archived before encryption was enabled are encrypted.
+    pub fn prune(&self, retention: &Retention) -> Result<Pruned> {
+        if !self.exists()? {
+            return Ok(Pruned {
+                removed: 0,
+                kept: 0,
+            });
+        }
+        let cipher = self.cipher()?;
+        let pruned = self.with_database(|connection| {
+            let transaction = connection.transaction().db_err(|| self.write_error())?;
+            let mut removed = 0;
+            if let Some(age) = retention.max_age {
+                removed += transaction
+                    .execute(
+                        "DELETE FROM requests WHERE received_at < ?1",
+                        [(Utc::now() - age).timestamp_millis()],
+                    )
+                    .db_err(|| self.write_error())?;
+            }
+            if retention.max_requests_per_token > 0 {
+                removed += transaction
+                    .execute(
+                        "DELETE FROM requests WHERE rowid IN (
+                            SELECT rowid FROM (
+                                SELECT rowid, ROW_NUMBER() OVER (
+                                    PARTITION BY token ORDER BY received_at DESC, rowid DESC
+                                ) AS position
+                                FROM requests
+                            )
+                            WHERE position > ?1
+                        )",
+                        [sql_integer(retention.max_requests_per_token as u64)],
+                    )
+                    .db_err(|| self.write_error())?;
+            }
+            if retention.max_size > 0 {
+                // Newest first, the requests past the running total go
+                removed += transaction
+                    .execute(
+                        "DELETE FROM requests WHERE rowid IN (
+                            SELECT rowid FROM (
+                                SELECT rowid, SUM(length(CAST(data AS BLOB))) OVER (
+                                    ORDER BY received_at DESC, rowid DESC
+                                ) AS total
+                                FROM requests
+                            )
+                            WHERE total > ?1
+                        )",
+                        [sql_integer(retention.max_size)],
+                    )
+                    .db_err(|| self.write_error())?;
+            }
+            if let Some(cipher) = cipher {
+                seal_plain(&transaction, cipher).db_err(|| self.write_error())?;
+            }
+            let kept = transaction
+                .query_row("SELECT COUNT(*) FROM requests", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .db_err(|| self.read_error())? as usize;
+            transaction.commit().db_err(|| self.write_error())?;
+            if removed > 0 {
+                // Give the freed pages back to the file system, one per step
+                connection
+                    .prepare("PRAGMA incremental_vacuum")
+                    .and_then(|mut statement| {
+                        let mut steps = statement.query([])?;
+                        while steps.next()?.is_some() {}
+                        Ok(())
+                    })
+                    .db_err(|| self.write_error())?;
+            }
+            Ok(Pruned { removed, kept })
+        })?;
+        *self.last_pruned.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        Ok(pruned)
+    }
+
+    /// Requests archived for `token`, newest first
+    pub fn load(&self, token: &str) -> Result<Vec<ArchivedRequest>> {
+        self.query(
+            "SELECT token, archived_at, data FROM requests WHERE token = ?1
+             ORDER BY received_at DESC, rowid DESC",
+            Some(token),
+        )
+    }
+
+    /// Requests archived for every token, newest first
+    pub fn load_all(&self) -> Result<Vec<ArchivedRequest>> {
+        self.query(
+            "SELECT token, archived_at, data FROM requests
+             ORDER BY received_at DESC, rowid DESC",
+            None,
+        )
+    }
+
+    fn query(&self, sql: &str, token: Option<&str>) -> Result<Vec<ArchivedRequest>> {
+        if !self.exists()? {
+            return Ok(Vec::new());
+        }
+        let cipher = self.cipher()?;
+        let rows = self.with_database(|connection| {
+            let mut statement = connection.prepare(sql).db_err(|| self.read_error())?;
+            let rows = statement
+                .query_map(rusqlite::params_from_iter(token), |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })
+                .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+                .db_err(|| self.read_error())?;
+            Ok(rows)
+        })?;
+
+        let mut requests = Vec::with_capacity(rows.len());
+        for (token, archived_at, data) in rows {
+            let Some(request) = self.decode(&data, cipher)? else {
+                continue;
+            };
+            requests.push(ArchivedRequest {
+                token,
+                archived_at: DateTime::from_timestamp_millis(archived_at).unwrap_or_default(),
+                request,
+            });
+        }
+        Ok(requests)
+    }
+
+    /// Requests of `token` (every token when `None`) whose body or headers
+    /// contain the words of `query`, newest first; a `*` at its end matches
+    /// words starting with the last one
+    pub fn search(&self, query: &str, token: Option<&str>) -> Result<Found> {
+        if !self.exists()? {
+            return Ok(Found {
+                hits: Vec::new(),
+                searched: 0,
+            });
+        }
+        let query = match query.strip_suffix('*') {
+            Some(prefix) => format!("\"{}\"*", prefix.replace('"', "\"\"")),
+            None => format!("\"{}\"", query.replace('"', "\"\"")),
+        };
+        let (rows, searched) = match self.cipher()? {
+            // Encrypted requests are not in the index
+            None if Cipher::is_used(&self.dir) => return Err(self.encrypted()),
+            None => self.with_database(|connection| {
+                let rows = search_rows(connection, &query, token).db_err(|| self.read_error())?;
+                let searched = connection
+                    .query_row(
+                        "SELECT COUNT(*) FROM requests WHERE ?1 IS NULL OR token = ?1",
+                        [token],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .db_err(|| self.read_error())?;
+                Ok((rows, searched as usize))
+            })?,
+            Some(_) => {
+                // An index on disk would give the plain text away, so one is
+                // built in memory for each search
+                let requests = match token {
+                    Some(token) => self.load(token)?,
+                    None => self.load_all()?,
+                };
+                let mut connection = Connection::open_in_memory()
+                    .and_then(|connection| {
+                        connection.execute_batch(SCHEMA)?;
+                        Ok(connection)
+                    })
+                    .db_err(|| "Failed to index the archive in memory".to_string())?;
+                let transaction = connection
+                    .transaction()
+                    .db_err(|| "Failed to index the archive in memory".to_string())?;
+                for archived in &requests {
+                    insert(
+                        &transaction,
+                        &archived.token,
+                        &archived.request,
+                        archived.archived_at,
+                        None,
+                    )?;
+                }
+                transaction
+                    .commit()
+                    .db_err(|| "Failed to index the archive in memory".to_string())?;
+                let rows = search_rows(&connection, &query, token)
+                    .db_err(|| "Failed to search the archive".to_string())?;
+                (rows, requests.len())
+            }
+        };
+
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let Some(request) = self.decode(&row.data, None)? else {
+                continue;
+            };
+            let matched = |snippet: String| snippet.contains(MATCH_START).then_some(snippet);
+            hits.push(SearchHit {
+                archived: ArchivedRequest {
+                    token: row.token,
+                    archived_at: DateTime::from_timestamp_millis(row.archived_at)
+                        .unwrap_or_default(),
+                    request,
+                },
+                body: matched(row.body),
+                headers: matched(row.headers),
+            });
+        }
+        Ok(Found { hits, searched })
+    }
+
+    /// Requests, size and time span of each archived token, the most
+    /// recently active first
+    pub fn summary(&self) -> Result<Vec<TokenSummary>> {
+        if !self.exists()? {
+            return Ok(Vec::new());
+        }
+        self.with_database(|connection| {
+            let mut statement = connection
+                .prepare(
+                    "SELECT token, COUNT(*), SUM(length(CAST(data AS BLOB))),
+                        MIN(received_at), MAX(received_at)
+                     FROM requests GROUP BY token ORDER BY MAX(received_at) DESC",
+                )
+                .db_err(|| self.read_error())?;
+            statement
+                .query_map([], |row| {
+                    let time = |index| {
+                        row.get::<_, i64>(index).map(|millis| {
+                            DateTime::from_timestamp_millis(millis).unwrap_or_default()
+                        })
+                    };
+                    Ok(TokenSummary {
+                        token: row.get(0)?,
+                        requests: row.get::<_, i64>(1)? as usize,
+                        size: row.get::<_, i64>(2)? as u64,
+                        first: time(3)?,
+                        last: time(4)?,
+                    })
+                })
+                .and_then(|rows| rows.collect())
+                .db_err(|| self.read_error())
+        })
+    }
+
+    /// Size of the database on disk, including its write-ahead log
+    pub fn file_size(&self) -> u64 {
+        let path = self.path();
+        let wal = path.with_extension("db-wal");
+        [path, wal]
+            .iter()
+            .map(|path| fs::metadata(path).map_or(0, |metadata| metadata.len()))
+            .sum()
+    }
+
+    /// The request stored as `data`; `None` for one that can't be decrypted
+    fn decode(&self, data: &str, cipher: Option<&Cipher>) -> Result<Option<WebhookRequest>> {
+        let json = if data.starts_with('{') {
+            data.to_string()
+        } else {
+            let Some(cipher) = cipher else {
+                return Err(self.encrypted());
+            };
+            match cipher.open_line(data) {
+                Ok(json) => json,
+                Err(e) => {
+                    logging::debug(1, format_args!("Skipping an archived request: {:#}", e));
+                    return Ok(None);
+                }
+            }
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .parse_err(|| format!("Invalid request in {}", self.path().display()))
+    }
+
+    /// The NDJSON files of earlier versions
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).io_err(|| format!("Failed to read {}", self.dir.display()));
+            }
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry
+                .io_err(|| format!("Failed to read {}", self.dir.display()))?
+                .path();
+            if path.extension().is_some_and(|ext| ext == "ndjson") {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    fn encrypted(&self) -> WebhookError {
+        WebhookError::config(format!(
+            "The archive in {} is encrypted, set archive.passphrase to read it",
+            self.dir.display()
+        ))
+    }
+
+    fn read_error(&self) -> String {
+        format!("Failed to read {}", self.path().display())
+    }
+
+    fn write_error(&self) -> String {
+        format!("Failed to write {}", self.path().display())
+    }
+}
+
+/// `ResultExt` for database calls: failures are reported as file access
+/// errors
+trait DbResultExt<T> {
+    fn db_err(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> DbResultExt<T> for rusqlite::Result<T> {
+    fn db_err(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(io::Error::other).io_err(message)
+    }
+}
+
+/// Store `request` unless it is archived already, and index it when it is
+/// not encrypted; how many rows were added
+fn insert(
+    transaction: &Transaction,
+    token: &str,
+    request: &WebhookRequest,
+    archived_at: DateTime<Utc>,
+    cipher: Option<&Cipher>,
+) -> Result<usize> {
+    let json = serde_json::to_string(request)?;
+    let data = match cipher {
+        Some(cipher) => cipher.seal(&json)?,
+        None => json,
+    };
+    transaction
+        .prepare_cached(
+            "INSERT OR IGNORE INTO requests (token, id, received_at, archived_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .and_then(|mut statement| {
+            statement.execute(params![
+                token,
+                request.id,
+                request.date.unwrap_or(archived_at).timestamp_millis(),
+                archived_at.timestamp_millis(),
+                data,
+            ])
+        })
+        .map_err(io::Error::other)
+        .io_err(|| "Failed to write to the request archive".to_string())
+        .and_then(|added| {
+            if added > 0 && cipher.is_none() {
+                index(transaction, transaction.last_insert_rowid(), request)?;
+            }
+            Ok(added)
+        })
+}
+
+/// Add the body and headers of `request`, stored at `rowid`, to the
+/// full-text index
+fn index(transaction: &Transaction, rowid: i64, request: &WebhookRequest) -> Result<()> {
+    let mut headers: Vec<String> = request
+        .message_object
+        .headers
+        .iter()
+        .flat_map(|(name, values)| {
+            values
+                .iter()
+                .map(move |value| format!("{}: {}", name, value))
+        })
+        .collect();
+    headers.sort();
+    transaction
+        .prepare_cached("INSERT INTO requests_text (rowid, body, headers) VALUES (?1, ?2, ?3)")
+        .and_then(|mut statement| {
+            statement.execute(params![rowid, request.body, headers.join("\n")])
+        })
+        .db_err(|| "Failed to index the request archive".to_string())?;
+    Ok(())
+}
+
+/// A row of `SEARCH`
+struct SearchRow {
+    token: String,
+    archived_at: i64,
+    data: String,
+    body: String,
+    headers: String,
+}
+
+/// The requests matching the FTS5 `query`
+fn search_rows(
+    connection: &Connection,
+    query: &str,
+    token: Option<&str>,
+) -> rusqlite::Result<Vec<SearchRow>> {
+    connection
+        .prepare(SEARCH)?
+        .query_map(params![query, token], |row| {
+            Ok(SearchRow {
+                token: row.get(0)?,
+                archived_at: row.get(1)?,
+                data: row.get(2)?,
+                body: row.get(3)?,
+                headers: row.get(4)?,
+            })
+        })?
+        .collect()
+}
+
+/// `value` as SQLite's signed integer, capped
+fn sql_integer(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// IDs of the requests archived for `token`
+fn token_ids(connection: &Connection, token: &str) -> Result<HashSet<String>> {
+    connection
+        .prepare("SELECT id FROM requests WHERE token = ?1")
+        .and_then(|mut statement| {
+            statement
+                .query_map([token], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()
+        })
+        .db_err(|| "Failed to read the request archive".to_string())
+}
+
+/// Total bytes of the stored requests
+fn stored_size(connection: &Connection) -> Result<i64> {
+    connection
+        .query_row(
+            "SELECT COALESCE(SUM(length(CAST(data AS BLOB))), 0) FROM requests",
+            [],
+            |row| row.get(0),
+        )
+        .db_err(|| "Failed to read the request archive".to_string())
+}
+
+/// Encrypt the requests stored in plain JSON, archived before the passphrase
+/// was set
+fn seal_plain(transaction: &Transaction, cipher: &Cipher) -> rusqlite::Result<()> {
+    let plain: Vec<(i64, String)> = transaction
+        .prepare("SELECT rowid, data FROM requests WHERE data LIKE '{%'")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut update = transaction.prepare("UPDATE requests SET data = ?1 WHERE rowid = ?2")?;
+    for (rowid, json) in plain {
+        // Left in plain text if sealing fails, and tried again next time
+        if let Ok(sealed) = cipher.seal(&json) {
+            update.execute(params![sealed, rowid])?;
+        }
+    }
+    // The index holds the plain text
+    transaction.execute("DELETE FROM requests_text", [])?;
+    Ok(())
+}
+
+/// The requests of an NDJSON archive file of an earlier version. Plain lines
+/// written before encryption was enabled are read as is.
+fn load_file(path: &Path, cipher: Option<&Cipher>) -> Result<Vec<ArchivedRequest>> {
+    let file = File::open(path).io_err(|| format!("Failed to read {}", path.display()))?;
+    let mut requests = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.io_err(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line = if line.starts_with('{') {
+            line
+        } else {
+            let Some(cipher) = cipher else {
+                return Err(WebhookError::config(format!(
+                    "{} is encrypted, set archive.passphrase to read it",
+                    path.display()
+                )));
+            };
+            match cipher.open_line(&line) {
+                Ok(line) => line,
+                Err(e) => {
+                    logging::debug(
+                        1,
+                        format_args!("Skipping {} line {}: {:#}", path.display(), index + 1, e),
+                    );
+                    continue;
+                }
+            }
+        };
+        // A line cut short by an interrupted write loses only that request
+        match serde_json::from_str(&line) {
+            Ok(request) => requests.push(request),
+            Err(e) => logging::debug(
+                1,
+                format_args!("Skipping {} line {}: {}", path.display(), index + 1, e),
+            ),
+        }
+    }
+    Ok(requests)
+}
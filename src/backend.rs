@@ -0,0 +1,201 @@
+use std::io::Read;
+
+use crate::config::{Config, Provider};
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+use crate::models::{
+    WebhookRequest, parse_request, parse_request_list, read_request_lines, read_request_list,
+    truncate_bodies,
+};
+
+mod requestbin;
+mod smee;
+mod webhooksite;
+
+pub use requestbin::RequestbinBackend;
+pub use smee::SmeeBackend;
+pub use webhooksite::WebhookSiteBackend;
+
+/// What the service answers the senders of a new token's webhooks
+/// (`generate --response-*`)
+#[derive(Debug, Default)]
+pub struct TokenResponse {
+    pub status: Option<u16>,
+    pub body: Option<String>,
+    pub content_type: Option<String>,
+}
+
+impl TokenResponse {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.body.is_none() && self.content_type.is_none()
+    }
+}
+
+/// Endpoints and response formats of a webhook collection service.
+///
+/// `WebhookClient` does the HTTP work (authentication, retries, caching) and
+/// asks the backend where to send each call and how to read the answer.
+pub trait WebhookBackend: Send + Sync {
+    /// URL listing up to `count` requests for a token, newest first
+    fn list_url(&self, base_url: &str, token: &str, count: u32) -> String;
+
+    /// Decode a list response into requests, newest first
+    fn parse_list(&self, token: &str, body: &str) -> Result<Vec<WebhookRequest>>;
+
+    /// Decode a list response while it is read, truncating bodies longer than
+    /// `max_body` bytes. The default reads the whole response first.
+    fn read_list(
+        &self,
+        token: &str,
+        _content_type: &str,
+        reader: &mut dyn Read,
+        max_body: usize,
+    ) -> Result<Vec<WebhookRequest>> {
+        let mut body = String::new();
+        reader
+            .read_to_string(&mut body)
+            .io_err(|| "Failed to read response body".to_string())?;
+        let mut requests = self.parse_list(token, &body)?;
+        truncate_bodies(&mut requests, max_body);
+        Ok(requests)
+    }
+
+    /// URL of a single request by ID
+    fn request_url(&self, base_url: &str, token: &str, request_id: &str) -> String;
+
+    /// Decode the response of `request_url`
+    fn parse_request(&self, token: &str, body: &str) -> Result<WebhookRequest>;
+
+    /// Whether the service stores requests; those that don't can only be
+    /// watched over `stream_url`
+    fn keeps_history(&self) -> bool {
+        true
+    }
+
+    /// Server-Sent Events endpoint, for services that push new requests
+    fn stream_url(&self, _base_url: &str, _token: &str) -> Option<String> {
+        None
+    }
+
+    /// Decode the data of a stream event; `None` for events that are not
+    /// requests, such as heartbeats
+    fn parse_event(&self, _token: &str, data: &str) -> Option<WebhookRequest> {
+        serde_json::from_str(data).ok()
+    }
+
+    /// Endpoint that creates a token; `None` when tokens are generated
+    /// client-side
+    fn create_token_url(&self, _base_url: &str) -> Option<String> {
+        None
+    }
+
+    /// Method of the `create_token_url` call
+    fn create_token_method(&self) -> reqwest::Method {
+        reqwest::Method::POST
+    }
+
+    /// JSON body of the `create_token_url` POST that sets the answer to the
+    /// token's webhooks
+    fn create_token_body(&self, _response: &TokenResponse) -> Result<serde_json::Value> {
+        Err(WebhookError::config(
+            "This provider does not support custom responses",
+        ))
+    }
+
+    /// Read the token from the response of `create_token_url`, received
+    /// from `url` after any redirects
+    fn parse_created_token(&self, _url: &str, _body: &str) -> Result<String> {
+        Err(WebhookError::config(
+            "This provider does not create tokens on the server",
+        ))
+    }
+
+    /// URL that webhooks for a token should be sent to
+    fn webhook_url(&self, base_url: &str, token: &str) -> String {
+        Config::join_url_segments(base_url, &[token])
+    }
+
+    /// Whether `token` looks like one of the service's tokens, so that a
+    /// mistyped alias is caught before it is sent. Tokens are GUIDs unless
+    /// the service names them otherwise.
+    fn is_valid_token(&self, token: &str) -> bool {
+        is_guid(token)
+    }
+}
+
+/// A hyphenated GUID such as `0b7e3a52-6f4c-4d2e-9a1b-3c5d7e9f1a2b`
+fn is_guid(token: &str) -> bool {
+    token.len() == 36 && uuid::Uuid::try_parse(token).is_ok()
+}
+
+/// A name that fits in a URL path segment: letters, digits, '-' and '_'
+fn is_path_segment(token: &str) -> bool {
+    !token.is_empty()
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Backend for the `provider` configured for the service
+pub fn backend_for(provider: Provider) -> Box<dyn WebhookBackend> {
+    match provider {
+        Provider::Default => Box::new(DefaultBackend),
+        Provider::Requestbin => Box::new(RequestbinBackend),
+        Provider::WebhookSite => Box::new(WebhookSiteBackend),
+        Provider::Smee => Box::new(SmeeBackend),
+    }
+}
+
+/// The API this CLI was written for: `GET <base_url>/<token>/log/<count>`
+/// returning `WebhookRequest` JSON, `GET <base_url>/<token>/request/<id>` for
+/// a single request, plus an optional event stream
+pub struct DefaultBackend;
+
+impl WebhookBackend for DefaultBackend {
+    fn list_url(&self, base_url: &str, token: &str, count: u32) -> String {
+        Config::join_url_segments(base_url, &[token, "log", &count.to_string()])
+    }
+
+    fn parse_list(&self, _token: &str, body: &str) -> Result<Vec<WebhookRequest>> {
+        parse_request_list(body)
+    }
+
+    fn read_list(
+        &self,
+        _token: &str,
+        content_type: &str,
+        reader: &mut dyn Read,
+        max_body: usize,
+    ) -> Result<Vec<WebhookRequest>> {
+        if is_ndjson(content_type) {
+            read_request_lines(reader, max_body)
+                .parse_err(|| "Failed to parse NDJSON request log".to_string())
+        } else {
+            read_request_list(reader, max_body)
+                .parse_err(|| "Failed to parse list of requests".to_string())
+        }
+    }
+
+    fn request_url(&self, base_url: &str, token: &str, request_id: &str) -> String {
+        Config::join_url_segments(base_url, &[token, "request", request_id])
+    }
+
+    fn parse_request(&self, _token: &str, body: &str) -> Result<WebhookRequest> {
+        parse_request(body)
+    }
+
+    fn stream_url(&self, base_url: &str, token: &str) -> Option<String> {
+        Some(Config::join_url_segments(base_url, &[token, "stream"]))
+    }
+}
+
+/// Newline-delimited JSON, one request per line
+fn is_ndjson(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    [
+        "application/x-ndjson",
+        "application/ndjson",
+        "application/jsonl",
+    ]
+    .iter()
+    .any(|ndjson| mime.eq_ignore_ascii_case(ndjson))
+}
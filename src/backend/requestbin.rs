@@ -0,0 +1,125 @@
+use chrono::DateTime;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{WebhookBackend, is_path_segment};
+use crate::config::Config;
+use crate::error::{Result, ResultExt};
+use crate::models::{MessageObject, Method, WebhookRequest};
+
+/// Services with the classic RequestBin API: bins are created with
+/// `POST /api/v1/bins` and inspected with `GET /api/v1/bins/<bin>/requests`
+pub struct RequestbinBackend;
+
+#[derive(Deserialize)]
+struct Bin {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct BinRequest {
+    id: String,
+    /// Seconds since the Unix epoch
+    time: f64,
+    method: Method,
+    path: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    query_string: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+impl BinRequest {
+    fn into_request(self, token: &str) -> WebhookRequest {
+        let date = DateTime::from_timestamp_millis((self.time * 1000.0) as i64);
+        let headers: HashMap<String, Vec<String>> = self
+            .headers
+            .into_iter()
+            .map(|(name, value)| (name, vec![value]))
+            .collect();
+        let query_parameters = self
+            .query_string
+            .into_iter()
+            .flat_map(|(name, values)| {
+                values
+                    .into_iter()
+                    .map(move |value| format!("{}={}", name, value))
+            })
+            .collect();
+
+        WebhookRequest {
+            id: self.id,
+            date,
+            token_id: token.to_string(),
+            message_object: MessageObject {
+                method: self.method,
+                value: self.path,
+                headers,
+                query_parameters,
+            },
+            message: None,
+            body: self.body,
+            body_object: None,
+        }
+    }
+}
+
+impl WebhookBackend for RequestbinBackend {
+    fn list_url(&self, base_url: &str, token: &str, count: u32) -> String {
+        format!(
+            "{}?max={}",
+            Config::join_url_segments(base_url, &["api", "v1", "bins", token, "requests"]),
+            count
+        )
+    }
+
+    fn parse_list(&self, token: &str, body: &str) -> Result<Vec<WebhookRequest>> {
+        let requests: Vec<BinRequest> = serde_json::from_str(body).parse_err(|| {
+            format!(
+                "Failed to parse RequestBin response. Response body: {}",
+                body
+            )
+        })?;
+        let mut requests: Vec<_> = requests
+            .into_iter()
+            .map(|request| request.into_request(token))
+            .collect();
+        // Newest first, like the default API
+        requests.sort_by_key(|request| std::cmp::Reverse(request.date));
+        Ok(requests)
+    }
+
+    fn request_url(&self, base_url: &str, token: &str, request_id: &str) -> String {
+        Config::join_url_segments(
+            base_url,
+            &["api", "v1", "bins", token, "requests", request_id],
+        )
+    }
+
+    fn parse_request(&self, token: &str, body: &str) -> Result<WebhookRequest> {
+        let request: BinRequest = serde_json::from_str(body).parse_err(|| {
+            format!(
+                "Failed to parse RequestBin request. Response body: {}",
+                body
+            )
+        })?;
+        Ok(request.into_request(token))
+    }
+
+    fn create_token_url(&self, base_url: &str) -> Option<String> {
+        Some(Config::join_url_segments(base_url, &["api", "v1", "bins"]))
+    }
+
+    fn parse_created_token(&self, _url: &str, body: &str) -> Result<String> {
+        let bin: Bin = serde_json::from_str(body)
+            .parse_err(|| format!("Failed to parse created bin. Response body: {}", body))?;
+        Ok(bin.name)
+    }
+
+    /// Bin names are chosen by the service, not always GUIDs
+    fn is_valid_token(&self, token: &str) -> bool {
+        is_path_segment(token)
+    }
+}
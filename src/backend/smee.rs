@@ -0,0 +1,160 @@
+use chrono::{TimeZone, Utc};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::{WebhookBackend, is_path_segment};
+use crate::config::Config;
+use crate::error::{Result, ResultExt, WebhookError};
+use crate::models::{MessageObject, Method, WebhookRequest};
+
+/// smee.io: channels are created by `GET /new`, which redirects to the new
+/// channel, and deliver each webhook once over the channel's event stream.
+/// Nothing is stored, so there is no log to list.
+pub struct SmeeBackend;
+
+impl WebhookBackend for SmeeBackend {
+    fn list_url(&self, base_url: &str, token: &str, _count: u32) -> String {
+        Config::join_url_segments(base_url, &[token])
+    }
+
+    fn parse_list(&self, _token: &str, _body: &str) -> Result<Vec<WebhookRequest>> {
+        Err(WebhookError::config("smee.io keeps no request history"))
+    }
+
+    fn request_url(&self, base_url: &str, token: &str, _request_id: &str) -> String {
+        Config::join_url_segments(base_url, &[token])
+    }
+
+    fn parse_request(&self, _token: &str, _body: &str) -> Result<WebhookRequest> {
+        Err(WebhookError::config("smee.io keeps no request history"))
+    }
+
+    fn keeps_history(&self) -> bool {
+        false
+    }
+
+    fn stream_url(&self, base_url: &str, token: &str) -> Option<String> {
+        Some(Config::join_url_segments(base_url, &[token]))
+    }
+
+    /// Events carry the webhook's headers as top-level fields, next to
+    /// `body`, `query` and `timestamp` (milliseconds); the `ready` and `ping`
+    /// events have none of them
+    fn parse_event(&self, token: &str, data: &str) -> Option<WebhookRequest> {
+        let Ok(Value::Object(mut event)) = serde_json::from_str::<Value>(data) else {
+            return None;
+        };
+        let timestamp = event.remove("timestamp")?.as_i64()?;
+        let body = event.remove("body");
+        let query = match event.remove("query") {
+            Some(Value::Object(query)) => query,
+            _ => Map::new(),
+        };
+
+        let mut headers = HashMap::new();
+        for (name, value) in event {
+            let values = match value {
+                Value::Array(values) => values.into_iter().map(value_text).collect(),
+                value => vec![value_text(value)],
+            };
+            headers.insert(name, values);
+        }
+        let content_type = headers
+            .get("content-type")
+            .and_then(|values: &Vec<String>| values.first())
+            .cloned()
+            .unwrap_or_default();
+
+        let query_parameters: Vec<String> = query
+            .into_iter()
+            .flat_map(|(name, value)| {
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => vec![value],
+                };
+                values
+                    .into_iter()
+                    .map(move |value| format!("{}={}", name, value_text(value)))
+            })
+            .collect();
+        let mut value = format!("/{}", token);
+        if !query_parameters.is_empty() {
+            value.push('?');
+            value.push_str(&query_parameters.join("&"));
+        }
+
+        let date = Utc.timestamp_millis_opt(timestamp).single();
+
+        Some(WebhookRequest {
+            // smee.io does not number deliveries
+            id: Uuid::new_v4().to_string(),
+            date,
+            token_id: token.to_string(),
+            message_object: MessageObject {
+                // smee.io only accepts POSTs
+                method: Method::Post,
+                value,
+                headers,
+                query_parameters,
+            },
+            message: None,
+            body: body.and_then(|body| body_text(body, &content_type)),
+            body_object: None,
+        })
+    }
+
+    fn create_token_url(&self, base_url: &str) -> Option<String> {
+        Some(Config::join_url_segments(base_url, &["new"]))
+    }
+
+    fn create_token_method(&self) -> reqwest::Method {
+        reqwest::Method::GET
+    }
+
+    /// Channel names are random letters and digits
+    fn is_valid_token(&self, token: &str) -> bool {
+        is_path_segment(token)
+    }
+
+    /// The channel is the last segment of the URL `/new` redirected to
+    fn parse_created_token(&self, url: &str, _body: &str) -> Result<String> {
+        let url = url::Url::parse(url).parse_err(|| format!("Invalid channel URL {}", url))?;
+        match url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+        {
+            Some(channel) if !channel.is_empty() && channel != "new" => Ok(channel.to_string()),
+            _ => Err(WebhookError::parse(format!(
+                "smee.io did not redirect to a new channel (got {})",
+                url
+            ))),
+        }
+    }
+}
+
+fn value_text(value: Value) -> String {
+    match value {
+        Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+/// smee.io delivers the body as parsed by its server: JSON as JSON and forms
+/// as objects, which are encoded again
+fn body_text(body: Value, content_type: &str) -> Option<String> {
+    match body {
+        Value::Null => None,
+        Value::String(text) if text.is_empty() => None,
+        Value::String(text) => Some(text),
+        Value::Object(fields) if content_type.starts_with("application/x-www-form-urlencoded") => {
+            let mut form = url::form_urlencoded::Serializer::new(String::new());
+            for (name, value) in fields {
+                form.append_pair(&name, &value_text(value));
+            }
+            Some(form.finish())
+        }
+        Value::Object(fields) if fields.is_empty() => None,
+        body => Some(body.to_string()),
+    }
+}
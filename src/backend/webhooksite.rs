@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+
+use super::{TokenResponse, WebhookBackend};
+use crate::config::Config;
+use crate::error::{Result, ResultExt};
+use crate::models::{MessageObject, Method, WebhookRequest, parse_date};
+
+/// webhook.site: tokens are created with `POST /token` and their requests
+/// listed with `GET /token/<token>/requests`
+pub struct WebhookSiteBackend;
+
+#[derive(Deserialize)]
+struct Token {
+    uuid: String,
+}
+
+#[derive(Deserialize)]
+struct Page {
+    data: Vec<SiteRequest>,
+}
+
+#[derive(Deserialize)]
+struct SiteRequest {
+    uuid: String,
+    /// UTC, as `2024-01-15 14:30:00`
+    created_at: String,
+    method: Method,
+    /// The full URL the request was sent to
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    query: Option<BTreeMap<String, Value>>,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl SiteRequest {
+    fn into_request(self, token: &str) -> WebhookRequest {
+        let date = parse_date(&self.created_at);
+        // Keep `/<token>/<path>?<query>`, like the paths of the default API
+        let value = match url::Url::parse(&self.url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            Err(_) => self.url,
+        };
+        let query_parameters = self
+            .query
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(name, value)| {
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => vec![value],
+                };
+                values.into_iter().map(move |value| match value {
+                    Value::String(value) => format!("{}={}", name, value),
+                    value => format!("{}={}", name, value),
+                })
+            })
+            .collect();
+        let headers: HashMap<String, Vec<String>> = self.headers.into_iter().collect();
+
+        WebhookRequest {
+            id: self.uuid,
+            date,
+            token_id: token.to_string(),
+            message_object: MessageObject {
+                method: self.method,
+                value,
+                headers,
+                query_parameters,
+            },
+            message: None,
+            body: self.content.filter(|content| !content.is_empty()),
+            body_object: None,
+        }
+    }
+}
+
+impl WebhookBackend for WebhookSiteBackend {
+    fn list_url(&self, base_url: &str, token: &str, count: u32) -> String {
+        format!(
+            "{}?sorting=newest&per_page={}",
+            Config::join_url_segments(base_url, &["token", token, "requests"]),
+            count
+        )
+    }
+
+    fn parse_list(&self, token: &str, body: &str) -> Result<Vec<WebhookRequest>> {
+        let page: Page = serde_json::from_str(body).parse_err(|| {
+            format!(
+                "Failed to parse webhook.site response. Response body: {}",
+                body
+            )
+        })?;
+        Ok(page
+            .data
+            .into_iter()
+            .map(|request| request.into_request(token))
+            .collect())
+    }
+
+    fn request_url(&self, base_url: &str, token: &str, request_id: &str) -> String {
+        Config::join_url_segments(base_url, &["token", token, "request", request_id])
+    }
+
+    fn parse_request(&self, token: &str, body: &str) -> Result<WebhookRequest> {
+        let request: SiteRequest = serde_json::from_str(body).parse_err(|| {
+            format!(
+                "Failed to parse webhook.site request. Response body: {}",
+                body
+            )
+        })?;
+        Ok(request.into_request(token))
+    }
+
+    fn create_token_url(&self, base_url: &str) -> Option<String> {
+        Some(Config::join_url_segments(base_url, &["token"]))
+    }
+
+    fn create_token_body(&self, response: &TokenResponse) -> Result<Value> {
+        let mut body = json!({});
+        if let Some(status) = response.status {
+            body["default_status"] = json!(status);
+        }
+        if let Some(content) = &response.body {
+            body["default_content"] = json!(content);
+        }
+        if let Some(content_type) = &response.content_type {
+            body["default_content_type"] = json!(content_type);
+        }
+        Ok(body)
+    }
+
+    fn parse_created_token(&self, _url: &str, body: &str) -> Result<String> {
+        let token: Token = serde_json::from_str(body)
+            .parse_err(|| format!("Failed to parse created token. Response body: {}", body))?;
+        Ok(token.uuid)
+    }
+}
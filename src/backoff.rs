@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+/// How long the stream must stay quiet before polling starts to slow down
+const IDLE_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// Polling interval that grows while the server errors or stays idle and
+/// snaps back to the base interval as soon as traffic resumes
+pub struct PollInterval {
+    base: Duration,
+    max: Duration,
+    current: Duration,
+    last_activity: Instant,
+    consecutive_errors: u32,
+}
+
+impl PollInterval {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            current: base,
+            last_activity: Instant::now(),
+            consecutive_errors: 0,
+        }
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    pub fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    /// A poll returned new requests
+    pub fn on_activity(&mut self) {
+        self.last_activity = Instant::now();
+        self.consecutive_errors = 0;
+        self.current = self.base;
+    }
+
+    /// A poll succeeded but nothing new arrived
+    pub fn on_idle(&mut self) {
+        self.consecutive_errors = 0;
+        if self.last_activity.elapsed() >= IDLE_THRESHOLD {
+            self.current = (self.current + self.current / 2).min(self.max);
+        } else {
+            self.current = self.base;
+        }
+    }
+
+    /// A poll failed; double the interval up to the maximum
+    pub fn on_error(&mut self) {
+        self.consecutive_errors += 1;
+        self.current = (self.current * 2).min(self.max);
+    }
+
+    /// The server asked us to slow down; wait as long as it requested,
+    /// even beyond the maximum, or back off as for an error
+    pub fn on_rate_limited(&mut self, retry_after: Option<Duration>) {
+        match retry_after {
+            Some(wait) => {
+                self.consecutive_errors += 1;
+                self.current = wait.max(self.base);
+            }
+            None => self.on_error(),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::logging;
+use crate::models::WebhookRequest;
+use crate::state::cache_dir;
+
+/// Most recent log fetched per token, kept on disk so repeated `logs` and
+/// `show` invocations can be answered without calling the API
+pub struct LogCache {
+    dir: Option<PathBuf>,
+    base_url: String,
+    ttl: Duration,
+    /// Body size cap of the client, so bodies truncated under a lower cap
+    /// are not handed to a client that needs them whole
+    max_body_size: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedLogs {
+    fetched_at: DateTime<Utc>,
+    /// Number of requests asked for; fewer may have been returned
+    count: u32,
+    /// Body size cap the requests were read with; logs cached before it was
+    /// recorded are not reused
+    #[serde(default)]
+    max_body_size: usize,
+    requests: Vec<WebhookRequest>,
+}
+
+impl LogCache {
+    /// A zero `ttl` disables reading from the cache; fresh results are still stored
+    pub fn new(base_url: &str, ttl: Duration, max_body_size: usize) -> Self {
+        Self {
+            dir: cache_dir().map(|dir| dir.join("webhook-cli").join("logs")),
+            base_url: base_url.to_string(),
+            ttl,
+            max_body_size,
+        }
+    }
+
+    /// The latest `count` requests, if they were fetched within the TTL
+    pub fn recent(&self, token: &str, count: u32) -> Option<Vec<WebhookRequest>> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let cached = self.load(token)?;
+        let age = (Utc::now() - cached.fetched_at).to_std().ok()?;
+        if age > self.ttl || cached.count < count || cached.max_body_size < self.max_body_size {
+            return None;
+        }
+        logging::debug(
+            1,
+            format_args!("Using log cached {}ms ago", age.as_millis()),
+        );
+        Some(cached.requests.into_iter().take(count as usize).collect())
+    }
+
+    /// A cached request by ID, however old: requests never change once received
+    pub fn find(&self, token: &str, request_id: &str) -> Option<WebhookRequest> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let cached = self.load(token)?;
+        if cached.max_body_size < self.max_body_size {
+            return None;
+        }
+        cached
+            .requests
+            .into_iter()
+            .find(|request| request.id == request_id)
+    }
+
+    pub fn store(&self, token: &str, count: u32, requests: &[WebhookRequest]) {
+        let Some(path) = self.path(token) else {
+            return;
+        };
+        let cached = CachedLogs {
+            fetched_at: Utc::now(),
+            count,
+            max_body_size: self.max_body_size,
+            requests: requests.to_vec(),
+        };
+        // The cache is an optimization; failing to write it is not an error
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, serde_json::to_vec(&cached).unwrap_or_default()));
+        if let Err(e) = written {
+            logging::debug(
+                1,
+                format_args!("Failed to write cache {}: {}", path.display(), e),
+            );
+        }
+    }
+
+    fn load(&self, token: &str) -> Option<CachedLogs> {
+        let content = fs::read(self.path(token)?).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// One file per service and token
+    fn path(&self, token: &str) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        (&self.base_url, token).hash(&mut hasher);
+        Some(
+            self.dir
+                .as_ref()?
+                .join(format!("{:016x}.json", hasher.finish())),
+        )
+    }
+}
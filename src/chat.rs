@@ -0,0 +1,161 @@
+use anyhow::Result;
+use colored::Colorize;
+use reqwest::Client;
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::highlight::{RequestPattern, parse_pattern};
+use crate::models::{WebhookRequest, extract_path};
+use crate::secrets::resolve_secret;
+use crate::send::http_client;
+
+/// Characters of the body quoted in a chat message
+const BODY_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+enum ChatKind {
+    Slack,
+    Discord,
+    Teams,
+}
+
+impl ChatKind {
+    fn name(self) -> &'static str {
+        match self {
+            ChatKind::Slack => "Slack",
+            ChatKind::Discord => "Discord",
+            ChatKind::Teams => "Teams",
+        }
+    }
+}
+
+/// Posts a message to chat incoming webhooks for each new request in
+/// `monitor`
+pub struct ChatForwarder {
+    client: Client,
+    channels: Vec<(ChatKind, String)>,
+    /// Only requests matching this are posted
+    pattern: Option<RequestPattern>,
+}
+
+impl ChatForwarder {
+    /// The channels of `--notify-slack`, `--notify-discord` and
+    /// `--notify-teams`, or else of the `[chat]` section; `None` when there
+    /// are none
+    pub fn from_args(
+        config: &Config,
+        slack: Option<String>,
+        discord: Option<String>,
+        teams: Option<String>,
+        pattern: Option<RequestPattern>,
+    ) -> Result<Option<Self>> {
+        let settings = &config.chat;
+        let channels = [
+            (ChatKind::Slack, slack.or_else(|| settings.slack.clone())),
+            (
+                ChatKind::Discord,
+                discord.or_else(|| settings.discord.clone()),
+            ),
+            (ChatKind::Teams, teams.or_else(|| settings.teams.clone())),
+        ]
+        .into_iter()
+        .filter_map(|(kind, url)| Some((kind, url?)))
+        .map(|(kind, url)| Ok((kind, resolve_secret(&url)?)))
+        .collect::<Result<Vec<_>>>()?;
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        let pattern = match pattern {
+            Some(pattern) => Some(pattern),
+            None => settings
+                .r#match
+                .as_deref()
+                .map(parse_pattern)
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("chat.match: {}", e))?,
+        };
+        Ok(Some(ChatForwarder {
+            client: http_client(config)?,
+            channels,
+            pattern,
+        }))
+    }
+
+    /// Post `request`, received for the token `name`, to every channel in the
+    /// background, unless it does not match the pattern
+    pub fn forward(&self, name: &str, request: &WebhookRequest) -> Option<JoinHandle<()>> {
+        if self
+            .pattern
+            .as_ref()
+            .is_some_and(|pattern| !pattern.matches(request))
+        {
+            return None;
+        }
+
+        let posts: Vec<_> = self
+            .channels
+            .iter()
+            .map(|(kind, url)| {
+                let post = self.client.post(url).json(&message(*kind, name, request));
+                (*kind, post)
+            })
+            .collect();
+        Some(tokio::spawn(async move {
+            for (kind, post) in posts {
+                let failure = match post.send().await {
+                    Ok(response) if response.status().is_success() => continue,
+                    Ok(response) => response.status().to_string(),
+                    Err(e) => e.to_string(),
+                };
+                eprintln!(
+                    "{} {}",
+                    format!("{} notification failed:", kind.name()).bright_red(),
+                    failure
+                );
+            }
+        }))
+    }
+}
+
+/// The payload of an incoming webhook of `kind` describing `request`
+fn message(kind: ChatKind, name: &str, request: &WebhookRequest) -> Value {
+    let title = format!(
+        "{} {}",
+        request.message_object.method,
+        extract_path(&request.message_object.value, &request.token_id)
+    );
+    let details = format!(
+        "Token {} · {} · {}",
+        name,
+        request.date_string(),
+        request.id
+    );
+    let body = match request.body.as_deref().map(str::trim) {
+        Some(body) if !body.is_empty() => {
+            let mut snippet: String = body.chars().take(BODY_LENGTH).collect();
+            if body.chars().count() > BODY_LENGTH {
+                snippet.push('…');
+            }
+            format!("```\n{}\n```", snippet.replace("```", "'''"))
+        }
+        _ => "_(empty body)_".to_string(),
+    };
+
+    match kind {
+        ChatKind::Slack => json!({
+            "text": format!("*{}*\n{}\n{}", title, details, body),
+        }),
+        ChatKind::Discord => json!({
+            "content": format!("**{}**\n{}\n{}", title, details, body),
+        }),
+        ChatKind::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": title,
+            "title": title,
+            "text": format!("{}\n\n{}", details, body),
+        }),
+    }
+}
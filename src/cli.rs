@@ -1,77 +1,869 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::alerts::{RateLimit, parse_rate};
+use crate::highlight::{HighlightRule, RequestPattern, parse_highlight, parse_pattern};
+use crate::parse::{parse_duration, parse_size};
+use crate::signature::{Signer, parse_signer};
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "webhook")]
 #[command(about = "A CLI tool for webhook testing and monitoring")]
 #[command(version)]
 pub struct Cli {
-    /// Disable colored output
-    #[arg(long, global = true)]
+    /// When to color the output: auto (only on a terminal), always or never
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto",
+        env = "WEBHOOK_COLOR"
+    )]
+    pub color: ColorChoice,
+
+    /// Disable colored output, same as `--color never`
+    #[arg(long, global = true, env = "WEBHOOK_NO_COLOR")]
     pub no_color: bool,
 
+    /// Report progress as timestamped lines instead of spinners and progress bars, for CI
+    /// logs (on by default when CI is set)
+    #[arg(long, global = true, env = "WEBHOOK_PLAIN")]
+    pub plain: bool,
+
+    /// Print only the requests and results, without banners, counts and hints
+    #[arg(short, long, global = true, env = "WEBHOOK_QUIET")]
+    pub quiet: bool,
+
+    /// Read the token of commands given none from this file, or from stdin with "-", keeping it
+    /// out of shell history and process listings
+    #[arg(long, global = true, value_name = "PATH", env = "WEBHOOK_TOKEN_FILE")]
+    pub token_file: Option<String>,
+
+    /// Configuration file to use instead of the default locations
+    #[arg(long, global = true, value_name = "PATH", env = "WEBHOOK_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Use the settings of a `[profiles.<NAME>]` section of the config
+    #[arg(long, global = true, value_name = "NAME", env = "WEBHOOK_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Print diagnostics to stderr: API calls with status and timing (-v), plus headers
+    /// and response bodies (-vv)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Always fetch from the API instead of reusing a log fetched in the last few seconds
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Print the API calls the command would make (URL, query and headers) instead of sending
+    /// them, and stop at the first one
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Write diagnostics to this file instead of stderr (implies -v; rotated at 10 MB)
+    #[arg(long, global = true, value_name = "PATH", env = "WEBHOOK_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Timeout for API calls in seconds (overrides `timeout` in config)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Timeout for connecting to the webhook service in seconds (overrides `connect_timeout` in config)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub connect_timeout: Option<u64>,
+
+    /// Retries for failed log requests (overrides `retries` in config)
+    #[arg(long, global = true, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Proxy URL for API calls, e.g. http://proxy:8080 or socks5://127.0.0.1:1080 (overrides `proxy` in config)
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Skip TLS certificate verification (for self-hosted services with self-signed certificates)
+    #[arg(long, global = true)]
+    pub insecure: bool,
+
+    /// Bearer token for the webhook service API; accepts env:NAME, keyring:NAME and file:PATH
+    #[arg(long, global = true, value_name = "TOKEN")]
+    pub auth_token: Option<String>,
+
+    /// Extra header sent with every API call, e.g. "X-Api-Key: env:API_KEY" (repeatable)
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Check the config file for syntax errors, unknown or missing keys and invalid values
+    Validate {
+        /// Also make a test call to the webhook service
+        #[arg(long)]
+        probe: bool,
+    },
+    /// Update the config file to the current layout, keeping a backup of the old file
+    Migrate {
+        /// Only show the changes
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, read from the terminal without echo or from stdin
+    Set {
+        /// Entry name, referenced in the config as "keyring:NAME"
+        name: String,
+    },
+    /// Print a stored secret
+    Get {
+        /// Entry name, or SERVICE/ACCOUNT
+        name: String,
+    },
+    /// Remove a stored secret
+    Rm {
+        /// Entry name, or SERVICE/ACCOUNT
+        name: String,
+    },
+}
+
+/// The token a command works on, given as its first argument or with `--token`
+#[derive(Args)]
+pub struct TokenArg {
+    /// Webhook token (GUID) or saved alias, or "-" to read it from stdin (default: --token-file,
+    /// then $WEBHOOK_TOKEN, then the token used last)
+    #[arg(id = "token_name", value_name = "TOKEN")]
+    name: Option<String>,
+    /// Same as TOKEN
+    #[arg(short, long, value_name = "TOKEN", conflicts_with = "token_name")]
+    token: Option<String>,
+}
+
+impl TokenArg {
+    pub fn into_inner(self) -> Option<String> {
+        self.name.or(self.token)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum TokensCommand {
+    /// Show a token and its webhook URL
+    Show {
+        #[command(flatten)]
+        token: TokenArg,
+        /// Print a QR code of the webhook URL, to scan it into a phone or device
+        #[arg(long)]
+        qr: bool,
+    },
+}
+
+// Parsed once at startup, so the size of the monitor options does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Create a configuration file interactively
+    Init,
+    /// Generate man pages: print the page of `webhook`, or write the pages of every command
+    #[command(hide = true)]
+    Man {
+        /// Write webhook.1 and a page per subcommand (webhook-logs.1, ...) to this directory
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Inspect or migrate the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage secrets in the OS keyring
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommand,
+    },
     /// Generate a new webhook token
-    Generate,
+    Generate {
+        /// Print a QR code of the webhook URL, to scan it into a phone or device
+        #[arg(long)]
+        qr: bool,
+        /// Copy the webhook URL to the system clipboard
+        #[arg(long)]
+        copy: bool,
+        /// Status code answered to the token's webhooks (webhook.site only)
+        #[arg(long, value_name = "CODE")]
+        response_status: Option<u16>,
+        /// Body answered to the token's webhooks (webhook.site only)
+        #[arg(long, value_name = "TEXT")]
+        response_body: Option<String>,
+        /// Content-Type of the answer to the token's webhooks (webhook.site only)
+        #[arg(long, value_name = "TYPE")]
+        response_content_type: Option<String>,
+    },
+    /// Show saved or used tokens
+    Tokens {
+        #[command(subcommand)]
+        command: TokensCommand,
+    },
     /// Monitor webhook requests in real-time
     Monitor {
-        /// Webhook token (GUID)
+        /// Webhook tokens (GUID) or saved aliases to monitor, "-" to read one from stdin
+        /// (default: --token-file, then $WEBHOOK_TOKEN, then the token used last, or a newly
+        /// generated one)
+        #[arg(value_name = "TOKEN")]
+        names: Vec<String>,
+        /// Same as TOKEN; repeat to monitor several tokens
         #[arg(short, long)]
-        token: Option<String>,
+        token: Vec<String>,
+        /// Monitor every token saved in the config
+        #[arg(long)]
+        all_saved: bool,
         /// Number of recent requests to show initially
         #[arg(short, long, default_value = "10")]
         count: u32,
         /// Refresh interval in seconds
         #[arg(short, long, default_value = "3")]
         interval: u64,
+        /// Maximum refresh interval in seconds when backing off on errors or idle periods
+        #[arg(long, value_name = "SECONDS")]
+        max_interval: Option<u64>,
         /// Show only specific HTTP method
         #[arg(short, long)]
         method: Option<String>,
-        /// Show full request body with proper formatting
-        #[arg(long)]
-        full_body: bool,
-        /// Show request headers
-        #[arg(long)]
-        show_headers: bool,
+        /// Show full request body with proper formatting (`--full-body=false` overrides the config)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        full_body: Option<bool>,
+        /// Show request headers (`--show-headers=false` overrides the config)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        show_headers: Option<bool>,
         /// Parse and display only specific JSON paths from the request body (e.g., "/user/name", "/data/items/0")
         #[arg(long, value_name = "PATH")]
         parse: Vec<String>,
+        /// Highlight duplicate deliveries of identical payloads (same method, path and body)
+        #[arg(long)]
+        unique: bool,
+        /// Treat requests with the same value at this JSON path as duplicates (implies --unique)
+        #[arg(long, value_name = "PATH")]
+        unique_by: Option<String>,
+        /// Header, or JSON path such as "/id", with the delivery ID senders keep when retrying, to
+        /// label retries "RETRY #2": auto (X-GitHub-Delivery, Webhook-Id, Stripe's event id, ...)
+        /// or none
+        #[arg(long, value_name = "HEADER|PATH", default_value = "auto")]
+        delivery_id: String,
+        /// Stop after this many new requests (exits with code 7 if none arrived)
+        #[arg(long, value_name = "N")]
+        max_requests: Option<usize>,
+        /// Stop after this much time, e.g. "30s", "10m", "1h30m" (exits with code 7 if no requests arrived)
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+        /// Run a shell command for each new request, with the body on stdin and
+        /// WEBHOOK_ID, WEBHOOK_METHOD, WEBHOOK_PATH, WEBHOOK_DATE and WEBHOOK_TOKEN set
+        #[arg(long, value_name = "COMMAND")]
+        exec: Option<String>,
+        /// Show a desktop notification for each new request
+        #[arg(long)]
+        notify: bool,
+        /// Ring the terminal bell (and play `bell_sound` from config, if set) for each new request
+        #[arg(long)]
+        bell: bool,
+        /// Emphasize requests whose method, path or body match a regex, optionally
+        /// prefixed with a color (e.g. 'red:"status":"failed"'); repeatable
+        #[arg(long, value_name = "[COLOR:]REGEX", value_parser = parse_highlight)]
+        highlight: Vec<HighlightRule>,
+        /// Hide the live status line (elapsed time, request count, rate, time since last request)
+        #[arg(long)]
+        no_footer: bool,
+        /// Clear the terminal and redraw the latest requests on every poll, like `watch`
+        #[arg(long)]
+        screen: bool,
+        /// Sort rows in --screen mode by this key
+        #[arg(long, value_enum, default_value = "date", requires = "screen")]
+        sort: SortBy,
+        /// Reverse the --screen sort order
+        #[arg(long, requires = "screen")]
+        reverse: bool,
+        /// Columns shown in --screen mode, comma-separated
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "time,method,path,size,body",
+            requires = "screen"
+        )]
+        columns: Vec<Column>,
+        /// Instead of listing requests, keep a live table of request counts per
+        /// path, method or provider event type, with the time each was last seen
+        #[arg(long, value_enum, value_name = "KEY", conflicts_with = "screen")]
+        group_by: Option<GroupBy>,
+        /// Show the latest requests again instead of resuming after the last request
+        /// seen by the previous session
+        #[arg(long)]
+        from_start: bool,
+        /// Show the time since the previous request and how long after delivery each
+        /// request was seen, and summarize delivery delays at the end
+        #[arg(long)]
+        timing: bool,
+        /// Alert when a token receives more requests than this rate (e.g. 100/min, 5/s, 20/5m)
+        #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+        alert_rate: Option<RateLimit>,
+        /// Alert when a token receives no requests for this long (e.g. 10m)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        alert_silence: Option<Duration>,
+        /// Save the body of each new request to this directory, or to a file name template with
+        /// {timestamp}, {method}, {id}, {token} and {ext} (e.g. "bodies/{method}-{id}.{ext}")
+        #[arg(long, value_name = "DIR|TEMPLATE")]
+        save_body: Option<String>,
+        /// Show only requests whose signature check fails, for tokens with `verify` in the config
+        #[arg(long, conflicts_with = "screen")]
+        only_invalid: bool,
+        /// Post each new request to this Slack incoming webhook URL (accepts env:NAME)
+        #[arg(long, value_name = "URL")]
+        notify_slack: Option<String>,
+        /// Post each new request to this Discord webhook URL (accepts env:NAME)
+        #[arg(long, value_name = "URL")]
+        notify_discord: Option<String>,
+        /// Post each new request to this Microsoft Teams incoming webhook URL (accepts env:NAME)
+        #[arg(long, value_name = "URL")]
+        notify_teams: Option<String>,
+        /// Only post requests whose method and path or body match this regex to chat channels
+        #[arg(long, value_name = "REGEX", value_parser = parse_pattern)]
+        notify_match: Option<RequestPattern>,
+        /// Serve Prometheus metrics on this port at /metrics
+        #[arg(long, value_name = "PORT")]
+        metrics_port: Option<u16>,
+        /// Address the --metrics-port listener binds to
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+        metrics_bind: String,
+        /// Export each request as a span to this OpenTelemetry collector (OTLP/HTTP),
+        /// e.g. http://localhost:4318
+        #[arg(long, value_name = "URL")]
+        otlp_endpoint: Option<String>,
+        /// Append each new request as a JSON line to this file
+        #[arg(long, value_name = "PATH")]
+        log_to: Option<PathBuf>,
+        /// Rotate the --log-to file when it grows past this size (e.g. 10MB, 512KB; 0 never rotates)
+        #[arg(long, value_name = "SIZE", default_value = "10MB", value_parser = parse_size, requires = "log_to")]
+        log_max_size: u64,
+        /// Rotated --log-to files to keep, as <PATH>.1 (newest) to <PATH>.<N>
+        #[arg(long, value_name = "N", default_value = "5", requires = "log_to")]
+        log_keep: u32,
+        /// Send a line per new request to syslog: the local daemon, or host:port over UDP
+        #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "local")]
+        syslog: Option<String>,
+        /// Write each new request as a JSON line to this Unix socket or named pipe; a missing
+        /// path becomes a socket that consumers connect to (e.g. `nc -U <PATH>`)
+        #[arg(long, value_name = "PATH")]
+        pipe: Option<PathBuf>,
     },
     /// Show request logs for a token
     Logs {
-        /// Webhook token (GUID)
-        #[arg(short, long)]
-        token: String,
+        #[command(flatten)]
+        token: TokenArg,
         /// Number of requests to fetch
         #[arg(short, long, default_value = "50")]
         count: u32,
         /// Show only specific HTTP method
         #[arg(short, long)]
         method: Option<String>,
-        /// Show full request body with proper formatting
+        /// Show full request body with proper formatting (`--full-body=false` overrides the config)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        full_body: Option<bool>,
+        /// Show request headers (`--show-headers=false` overrides the config)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        show_headers: Option<bool>,
+        /// Parse and display only specific JSON paths from the request body (e.g., "/user/name", "/data/items/0")
+        #[arg(long, value_name = "PATH")]
+        parse: Vec<String>,
+        /// Collapse identical payloads (same method, path and body) into a single entry
+        #[arg(long)]
+        unique: bool,
+        /// Treat requests with the same value at this JSON path as duplicates (implies --unique)
+        #[arg(long, value_name = "PATH")]
+        unique_by: Option<String>,
+        /// Header, or JSON path such as "/id", with the delivery ID senders keep when retrying, to
+        /// label retries "RETRY #2": auto (X-GitHub-Delivery, Webhook-Id, Stripe's event id, ...)
+        /// or none
+        #[arg(long, value_name = "HEADER|PATH", default_value = "auto")]
+        delivery_id: String,
+        /// Sort requests by this key (ascending, last entry is the latest/largest)
+        #[arg(long, value_enum, default_value = "date")]
+        sort: SortBy,
+        /// Reverse the sort order
+        #[arg(long)]
+        reverse: bool,
+        /// Read the requests from the local archive instead of the service
+        #[arg(long)]
+        offline: bool,
+        /// Show only requests with this tag (see `webhook tag`)
+        #[arg(long)]
+        tag: Option<String>,
+        /// Show only requests that arrived within this long, e.g. 1h (among the --count newest)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Print only the number of matching requests; with --since, all those in the
+        /// window are counted, not only the --count newest
+        #[arg(long, conflicts_with = "interactive")]
+        count_only: bool,
+        /// Exit with status 7 when no requests match, for scripts
+        #[arg(long)]
+        expect_some: bool,
+        /// Page through the log newest first, --count requests at a time, and open requests by number
+        #[arg(short, long, conflicts_with_all = ["unique", "unique_by", "sort", "reverse"])]
+        interactive: bool,
+        /// Skip this many of the newest requests, to look further back
+        #[arg(
+            long,
+            value_name = "N",
+            default_value = "0",
+            conflicts_with = "interactive"
+        )]
+        offset: u32,
+        /// Show the Nth slice of --count requests, 1 being the newest (same as --offset (N-1)*COUNT)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["offset", "interactive"])]
+        page: Option<u32>,
+    },
+    /// Receive webhooks locally: run an HTTP server that prints incoming requests
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        /// Address to listen on; 0.0.0.0 accepts requests from other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// YAML file of per-path response rules (status, headers, body template, delay, failure rate)
+        #[arg(long, value_name = "PATH")]
+        rules: Option<PathBuf>,
+        /// Answer requests matching a recorded fixture (a directory of JSON files) with its response;
+        /// takes precedence over --rules
+        #[arg(long, value_name = "DIR")]
+        fixtures: Option<PathBuf>,
+        /// Record every exchange as a fixture in this directory
+        #[arg(long, value_name = "DIR")]
+        record: Option<PathBuf>,
+        /// Serve HTTPS with this PEM certificate (chain)
+        #[arg(long, value_name = "PATH", requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PKCS#8 PEM private key of --tls-cert
+        #[arg(long, value_name = "PATH", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Serve HTTPS with a throwaway self-signed certificate for localhost (needs the openssl command)
+        #[arg(long, conflicts_with = "tls_cert")]
+        self_signed: bool,
+        /// Answer this fraction of requests with a 500, 502 or 503 error, e.g. 0.2
+        #[arg(long, value_name = "RATE", value_parser = parse_fraction, default_value = "0")]
+        fail_rate: f64,
+        /// Close this fraction of connections without answering, e.g. 0.05
+        #[arg(long, value_name = "RATE", value_parser = parse_fraction, default_value = "0")]
+        drop_rate: f64,
+        /// Delay every answer by this long, or by a random time in a range such as 500ms..3s
+        #[arg(long, value_name = "DURATION[..DURATION]", value_parser = parse_latency)]
+        latency: Option<(Duration, Duration)>,
+        /// Expose the server on a public URL through a tunnel tool: cloudflared, ngrok or
+        /// localtunnel (default: the first one installed)
+        #[arg(long, value_enum, value_name = "PROVIDER", num_args = 0..=1, default_missing_value = "auto")]
+        tunnel: Option<TunnelProvider>,
+        /// Show the full request body
         #[arg(long)]
         full_body: bool,
         /// Show request headers
         #[arg(long)]
         show_headers: bool,
-        /// Parse and display only specific JSON paths from the request body (e.g., "/user/name", "/data/items/0")
+        /// Serve Prometheus metrics on this port at /metrics
+        #[arg(long, value_name = "PORT")]
+        metrics_port: Option<u16>,
+        /// Address the --metrics-port listener binds to
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+        metrics_bind: String,
+        /// Export each request as a span to this OpenTelemetry collector (OTLP/HTTP),
+        /// e.g. http://localhost:4318
+        #[arg(long, value_name = "URL")]
+        otlp_endpoint: Option<String>,
+    },
+    /// Expose a local server on a public URL through cloudflared, ngrok or localtunnel
+    Tunnel {
+        /// Local port to expose
+        #[arg(short, long)]
+        port: u16,
+        /// Host the local server listens on
+        #[arg(long, default_value = "localhost")]
+        host: String,
+        /// The local server speaks HTTPS
+        #[arg(long)]
+        https: bool,
+        /// Tunnel tool to use (default: the first one installed)
+        #[arg(long, value_enum, default_value = "auto")]
+        provider: TunnelProvider,
+        /// Show each request passing through the tunnel and the local server's answer
+        #[arg(long)]
+        watch: bool,
+        /// Show the full request body (with --watch)
+        #[arg(long, requires = "watch")]
+        full_body: bool,
+        /// Show request headers (with --watch)
+        #[arg(long, requires = "watch")]
+        show_headers: bool,
+    },
+    /// Send test requests to a webhook token or any URL
+    Send {
+        /// Webhook token (GUID) or saved alias to send to (default: the token used last)
+        #[arg(short, long, conflicts_with = "target")]
+        token: Option<String>,
+        /// Send to this URL instead of a webhook token
+        #[arg(long, value_name = "URL")]
+        target: Option<String>,
+        /// HTTP method of the request
+        #[arg(short = 'X', long, default_value = "POST")]
+        method: String,
+        /// Request header as "Name: value"; repeat for several headers (--header sets headers of
+        /// API calls)
+        #[arg(short = 'H', long = "request-header", value_name = "NAME: VALUE", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+        /// Request body, or @PATH to read it from a file
+        #[arg(short, long)]
+        data: Option<String>,
+        /// Send the steps of a YAML scenario instead of a single request
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["headers", "data"])]
+        scenario: Option<PathBuf>,
+        /// Set a {{NAME}} template variable, overriding the scenario's `variables`
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        variables: Vec<(String, String)>,
+        /// Sign each request like a provider: github:SECRET, stripe:SECRET or
+        /// hmac-sha256:header=NAME:SECRET (the secret accepts env:NAME and keyring:NAME)
+        #[arg(long, value_name = "SCHEME:SECRET", value_parser = parse_signer)]
+        sign: Option<Signer>,
+        /// Send the request (or the whole scenario) this many times, with fresh generated values
+        #[arg(long, value_name = "COUNT", default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        repeat: u32,
+    },
+    /// Send the requests of a recorded session to a receiver again
+    Replay {
+        /// Requests to replay, as written by `webhook export --format ndjson`
         #[arg(long, value_name = "PATH")]
-        parse: Vec<String>,
+        session: PathBuf,
+        /// URL of the receiver
+        #[arg(long, value_name = "URL")]
+        target: String,
+        /// Wait between requests as long as between their original arrivals
+        #[arg(long)]
+        preserve_timing: bool,
+        /// Play the original timing faster or slower, e.g. 2x or 0.5x
+        #[arg(long, value_name = "FACTOR", value_parser = parse_speed, requires = "preserve_timing")]
+        speed: Option<f64>,
+        /// Sign each request like a provider, see `webhook send --sign`
+        #[arg(long, value_name = "SCHEME:SECRET", value_parser = parse_signer)]
+        sign: Option<Signer>,
     },
-    /// Show details of a specific request
-    Show {
-        /// Webhook token (GUID)
+    /// Send mutated copies of captured payloads to a receiver and report the ones it fails on
+    Fuzz {
+        /// URL of the receiver under test
+        #[arg(long, value_name = "URL")]
+        target: String,
+        /// Payloads to mutate: a file (one payload, or requests exported with --format ndjson) or a
+        /// webhook token / saved alias whose latest requests are used
+        #[arg(long, value_name = "TOKEN|FILE")]
+        seed_from: String,
+        /// Number of requests to take from a token
+        #[arg(short, long, default_value = "10")]
+        count: u32,
+        /// Send at most this many mutations
+        #[arg(long, default_value = "500")]
+        limit: usize,
+    },
+    /// Check recent requests against a contract of expected events, or check their signatures
+    /// (with the given secret, or the `verify` scheme saved for the token)
+    Verify {
+        /// YAML contract: the expected events, in order, with their required and allowed fields
+        #[arg(long, value_name = "PATH")]
+        contract: Option<PathBuf>,
+        /// Check the X-Hub-Signature-256 header of each request with this GitHub webhook secret
+        /// (accepts env:NAME and keyring:NAME; repeat to try several)
+        #[arg(long, value_name = "SECRET", conflicts_with = "contract")]
+        github: Vec<String>,
+        /// Check the Stripe-Signature header of each request with this endpoint secret (accepts
+        /// env:NAME and keyring:NAME; repeat to try several, e.g. while rolling secrets)
+        #[arg(long, value_name = "SECRET", conflicts_with_all = ["contract", "github"])]
+        stripe: Vec<String>,
+        /// Maximum difference between a Stripe signature's timestamp and the request's arrival
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "5m")]
+        tolerance: Duration,
+        #[command(flatten)]
+        token: TokenArg,
+        /// Number of recent requests to check
+        #[arg(short, long, default_value = "50")]
+        count: u32,
+        /// Check the local archive instead of the latest requests on the service
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Search request bodies and headers for text
+    Search {
+        /// Text to look for (case-insensitive); with --offline, whole words, or words starting
+        /// with it when it ends with *
+        query: String,
+        /// Webhook token (GUID) or saved alias (default: the token used last, or every archived
+        /// token with --offline)
         #[arg(short, long)]
-        token: String,
-        /// Request ID to show details for
+        token: Option<String>,
+        /// Search the local archive instead of the latest requests on the service
+        #[arg(long)]
+        offline: bool,
+        /// Number of requests to search on the service
+        #[arg(short, long, default_value = "50")]
+        count: u32,
+    },
+    /// Summarize the local archive: requests per token, method and event type
+    Stats {
+        /// Webhook token (GUID) or saved alias (default: every archived token)
         #[arg(short, long)]
+        token: Option<String>,
+    },
+    /// Remove archived requests beyond the retention limits of [archive]
+    Prune {
+        /// Remove requests received longer ago than this (e.g. 30d), instead of archive.max_age
+        #[arg(long, value_parser = parse_duration)]
+        max_age: Option<Duration>,
+        /// Keep at most this many requests per token, instead of archive.max_requests_per_token
+        #[arg(long, value_name = "COUNT")]
+        max_requests_per_token: Option<usize>,
+        /// Shrink the stored requests to this many bytes, instead of archive.max_size
+        #[arg(long, value_name = "BYTES")]
+        max_size: Option<u64>,
+    },
+    /// Write requests to disk, e.g. as test fixtures
+    Export {
+        #[command(flatten)]
+        token: TokenArg,
+        /// Number of requests to export
+        #[arg(short, long, default_value = "50")]
+        count: u32,
+        /// Output layout
+        #[arg(long, value_enum, default_value = "files")]
+        format: ExportFormat,
+        /// Output directory (files) or file (ndjson)
+        #[arg(short, long, value_name = "PATH")]
+        out: PathBuf,
+        /// Export from the local archive instead of the service
+        #[arg(long)]
+        offline: bool,
+        /// Export only requests with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Replace emails, card numbers, bearer tokens and the values of `[redact]` rules with
+        /// stable fakes, so the export can be shared
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Tag a request and add a note, kept locally; without a tag, list the request's tags
+    Tag {
+        /// Request ID
         request_id: String,
+        /// Tag name, e.g. bug-1234
+        tag: Option<String>,
+        /// Note stored with the tag
+        note: Option<String>,
+        /// Remove the tag, or every tag of the request when none is given
+        #[arg(long, conflicts_with = "note")]
+        remove: bool,
+    },
+    /// Show details of a specific request
+    Show {
+        #[command(flatten)]
+        token: TokenArg,
+        /// Request ID to show details for, or -1 for the newest request, -2 for the one before
+        /// (default: pick one of the recent requests in the terminal)
+        #[arg(short, long, allow_negative_numbers = true)]
+        request_id: Option<String>,
+        /// Show the newest request, like `--request-id -1`
+        #[arg(long, conflicts_with = "request_id")]
+        latest: bool,
+        /// Save the request body to this file, or to <id>.<ext> in this directory
+        #[arg(long, value_name = "PATH")]
+        save_body: Option<PathBuf>,
         /// Parse and display only specific JSON paths from the request body (e.g., "/user/name", "/data/items/0")
         #[arg(long, value_name = "PATH")]
         parse: Vec<String>,
+        /// Sections to print, comma-separated (default: all). A single section is printed
+        /// without heading or colors, for scripts
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "SECTIONS")]
+        sections: Vec<Section>,
+        /// Print only the headers, as `Name: value` lines (same as `--sections headers`)
+        #[arg(long, conflicts_with_all = ["sections", "body_only"])]
+        headers_only: bool,
+        /// Print only the body, as received (same as `--sections body`)
+        #[arg(long, conflicts_with = "sections")]
+        body_only: bool,
+        /// Check the X-Hub-Signature-256 header with this GitHub webhook secret (accepts env:NAME
+        /// and keyring:NAME; repeat to try several)
+        #[arg(long, value_name = "SECRET")]
+        verify_github: Vec<String>,
+        /// Check the Stripe-Signature header with this endpoint secret (accepts env:NAME and
+        /// keyring:NAME; repeat to try several)
+        #[arg(long, value_name = "SECRET", conflicts_with = "verify_github")]
+        verify_stripe: Vec<String>,
+        /// Maximum difference between a Stripe signature's timestamp and the request's arrival
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "5m")]
+        tolerance: Duration,
+    },
+    /// Update webhook to the latest release
+    #[command(
+        name = "self-update",
+        long_about = "Update webhook to the latest release: download the binary for this platform from GitHub, check it against the release's SHA256SUMS and replace the running executable with it"
+    )]
+    SelfUpdate {
+        /// Only check whether a newer release is available
+        #[arg(long)]
+        check: bool,
+        /// Release information to update from, in the format of GitHub's "latest release" API (for mirrors)
+        #[arg(long, value_name = "URL", env = "WEBHOOK_RELEASES_URL", default_value = crate::update::RELEASES_URL, hide_default_value = true)]
+        releases_url: String,
     },
 }
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// A `<timestamp>_<method>_<id>` directory per request with `headers.json` and `body.<ext>`
+    Files,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TunnelProvider {
+    /// The first of cloudflared, ngrok and localtunnel found on the PATH
+    Auto,
+    /// Cloudflare quick tunnel (`cloudflared`)
+    Cloudflared,
+    /// `ngrok`, which needs an authtoken configured
+    Ngrok,
+    /// localtunnel (`lt`, from `npm install -g localtunnel`)
+    Localtunnel,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortBy {
+    /// Request date
+    Date,
+    /// Body size in bytes
+    Size,
+    /// HTTP method name
+    Method,
+}
+
+/// What `monitor --group-by` counts requests by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Request path after the token
+    Path,
+    /// HTTP method
+    Method,
+    /// Provider event type, such as `X-GitHub-Event` or Stripe's `type`
+    #[value(name = "provider-event")]
+    ProviderEvent,
+}
+
+/// When to color the output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Part of a request printed by `show`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Section {
+    /// ID, token, date, method and path, with the signature check and tags
+    Metadata,
+    /// Request headers
+    Headers,
+    /// Query parameters
+    Query,
+    /// Request body, or the fields picked with --parse
+    Body,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// Local time the request arrived
+    Time,
+    /// HTTP method
+    Method,
+    /// Request path after the token
+    Path,
+    /// Request ID
+    Id,
+    /// Body size in bytes
+    Size,
+    /// Body on a single line, truncated to the terminal width
+    Body,
+    /// Token name, useful when monitoring several tokens
+    Token,
+}
+
+/// Parse a "Name: value" header argument
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header '{}', expected 'Name: value'", value))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Parse a latency such as "500ms" or a range such as "500ms..3s"
+fn parse_latency(value: &str) -> Result<(Duration, Duration), String> {
+    let (min, max) = match value.split_once("..") {
+        Some((min, max)) => (parse_duration(min)?, parse_duration(max)?),
+        None => {
+            let latency = parse_duration(value)?;
+            (latency, latency)
+        }
+    };
+    if min > max {
+        return Err(format!("invalid latency range '{}'", value));
+    }
+    Ok((min, max))
+}
+
+/// Parse a probability between 0 and 1
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| {
+            format!(
+                "invalid rate '{}', expected a number between 0 and 1",
+                value
+            )
+        })
+}
+
+/// Parse a "NAME=VALUE" template variable
+fn parse_variable(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid variable '{}', expected 'NAME=VALUE'", value))?;
+    Ok((name.trim().to_string(), value.to_string()))
+}
+
+/// Parse a speed factor such as "2x", "0.5x" or "3"
+fn parse_speed(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .trim_end_matches(['x', 'X'])
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .ok_or_else(|| {
+            format!(
+                "invalid speed '{}', expected a factor such as 2x or 0.5x",
+                value
+            )
+        })
+}
@@ -1,69 +1,904 @@
-use anyhow::{Context, Result};
-use reqwest::{Client, StatusCode};
-use std::time::Duration;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    AUTHORIZATION, CONTENT_TYPE, ETAG, HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
-use crate::config::Config;
-use crate::models::WebhookRequest;
+use crate::archive::Archive;
+use crate::backend::{TokenResponse, WebhookBackend, backend_for};
+use crate::cache::LogCache;
+use crate::config::{AuthConfig, Config, Pagination};
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+use crate::logging;
+use crate::models::{WebhookRequest, truncate_bodies};
+use crate::secrets::resolve_secret;
+
+/// Upper bound for a single event stream connection before reconnecting
+const STREAM_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Error for listing or fetching requests from a service that keeps none
+const NO_HISTORY: &str =
+    "This provider keeps no request history; use `webhook monitor` to watch new requests";
+
+/// Characters of each response body shown with `-vv`
+const TRACE_BODY_LENGTH: usize = 500;
+
+/// Response chunks read ahead of the parser
+const RESPONSE_CHUNKS_BUFFERED: usize = 16;
+
+/// Longest `Retry-After` the client waits out itself; longer waits are left to the caller
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Most requests searched for a request ID when the service has no
+/// single-request endpoint
+const MAX_SEARCH_COUNT: u32 = 10_000;
 
 pub struct WebhookClient {
     client: Client,
     base_url: String,
+    backend: Arc<dyn WebhookBackend>,
+    retries: u32,
+    retry_backoff: Duration,
+    page_size: u32,
+    max_body_size: usize,
+    pagination: Pagination,
+    // Last response per log URL, revalidated with If-None-Match / If-Modified-Since
+    log_cache: Mutex<HashMap<String, CachedLog>>,
+    // Logs kept on disk between invocations
+    disk_cache: LogCache,
+    // Every request fetched, when `[archive]` is enabled
+    archive: Option<Arc<Archive>>,
+    // Credentials and `[auth.headers]`, sent by `client` with every call;
+    // kept to show them in dry-run mode
+    default_headers: HeaderMap,
+    dry_run: bool,
+}
+
+/// Validators and content of a log response
+#[derive(Clone)]
+struct CachedLog {
+    // URL including the query string the response belongs to
+    full_url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    requests: Vec<WebhookRequest>,
 }
 
 impl WebhookClient {
-    pub fn new(config: &Config) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
+    pub fn new(config: &Config) -> Result<Self> {
+        let mut builder = Client::builder()
+            .timeout(config.get_timeout())
+            .connect_timeout(config.get_connect_timeout());
+
+        // Without an explicit proxy reqwest picks up HTTP_PROXY, HTTPS_PROXY,
+        // ALL_PROXY and NO_PROXY from the environment
+        if let Some(proxy) = config.get_proxy() {
+            let proxy = Proxy::all(proxy)
+                .config_err(|| format!("Invalid proxy URL: {}", proxy))?
+                .no_proxy(NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = config.get_ca_cert() {
+            let pem = fs::read(path)
+                .io_err(|| format!("Failed to read CA certificate: {}", path.display()))?;
+            for cert in Certificate::from_pem_bundle(&pem)
+                .config_err(|| format!("Invalid CA certificate: {}", path.display()))?
+            {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some((cert_path, key_path)) = config.get_client_identity()? {
+            let cert = fs::read(cert_path)
+                .io_err(|| format!("Failed to read client certificate: {}", cert_path.display()))?;
+            let key = fs::read(key_path)
+                .io_err(|| format!("Failed to read client key: {}", key_path.display()))?;
+            let identity = Identity::from_pkcs8_pem(&cert, &key).config_err(|| {
+                "Invalid client certificate or key (expected PEM, PKCS#8 key)".to_string()
+            })?;
+            builder = builder.identity(identity);
+        }
+
+        let headers = auth_headers(&config.auth)?;
+        if !headers.is_empty() {
+            builder = builder.default_headers(headers.clone());
+        }
+
+        if config.is_insecure() {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let client = builder
             .build()
-            .expect("Failed to create HTTP client");
+            .config_err(|| "Failed to create HTTP client".to_string())?;
 
-        Self {
+        Ok(Self {
             client,
             base_url: config.get_base_url().to_string(),
-        }
+            backend: Arc::from(backend_for(config.get_provider())),
+            retries: config.get_retries(),
+            retry_backoff: config.get_retry_backoff(),
+            page_size: config.get_page_size(),
+            max_body_size: config.get_max_body_size(),
+            pagination: config.get_pagination(),
+            log_cache: Mutex::new(HashMap::new()),
+            disk_cache: LogCache::new(
+                config.get_base_url(),
+                config.get_cache_ttl(),
+                config.get_max_body_size(),
+            ),
+            archive: match config.archive.enabled {
+                true => Some(Arc::new(Archive::new(&config.archive)?)),
+                false => None,
+            },
+            default_headers: headers,
+            dry_run: config.is_dry_run(),
+        })
     }
 
     pub async fn get_requests(&self, token: &str, count: u32) -> Result<Vec<WebhookRequest>> {
-        let url = Config::join_url_segments(&self.base_url, &[token, "log", &count.to_string()]);
+        self.get_requests_with_progress(token, count, |_| {}).await
+    }
 
-        let response = self
+    /// Like `get_requests_with_progress`, but answered from the on-disk cache
+    /// when the same log was fetched within the cache TTL
+    pub async fn get_requests_cached(
+        &self,
+        token: &str,
+        count: u32,
+        on_page: impl Fn(usize),
+    ) -> Result<Vec<WebhookRequest>> {
+        if let Some(requests) = self.disk_cache.recent(token, count) {
+            return Ok(requests);
+        }
+        let requests = self
+            .get_requests_with_progress(token, count, on_page)
+            .await?;
+        self.disk_cache.store(token, count, &requests);
+        Ok(requests)
+    }
+
+    /// A request from the on-disk cache, if a recent fetch included it
+    pub fn cached_request(&self, token: &str, request_id: &str) -> Option<WebhookRequest> {
+        self.disk_cache.find(token, request_id)
+    }
+
+    /// Check that the service answers, by listing the log of an unused token,
+    /// or opening its stream when the service keeps no log
+    pub async fn probe(&self) -> Result<()> {
+        let token = Uuid::new_v4().to_string();
+        if !self.keeps_history() {
+            return match self.open_stream(&token).await? {
+                Some(_) => Ok(()),
+                None => Err(WebhookError::network(
+                    "The webhook service did not open an event stream",
+                )),
+            };
+        }
+        self.get_requests(&token, 1).await.map(|_| ())
+    }
+
+    /// Whether the service stores requests that can be listed and fetched;
+    /// otherwise new requests only arrive through `open_stream`
+    pub fn keeps_history(&self) -> bool {
+        self.backend.keeps_history()
+    }
+
+    /// Fetch a single request by ID from the service's single-request endpoint.
+    /// Services without one are searched with ever larger log fetches.
+    pub async fn get_request(&self, token: &str, request_id: &str) -> Result<WebhookRequest> {
+        if !self.keeps_history() {
+            return Err(WebhookError::config(NO_HISTORY));
+        }
+        let url = self.backend.request_url(&self.base_url, token, request_id);
+        let request = self
             .client
             .get(&url)
-            .header(reqwest::header::ACCEPT, "application/json")
-            .send()
+            .header(reqwest::header::ACCEPT, "application/json");
+        if self.report_dry_run(&request) {
+            return Err(WebhookError::DryRun);
+        }
+        let response = self
+            .send_with_retry(request)
             .await
-            .with_context(|| format!("Failed to fetch requests from {}", url))?;
+            .network_err(|| format!("Failed to fetch request from {}", url))?;
 
         let status = response.status();
-
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(WebhookError::RateLimited {
+                retry_after: retry_after(&response),
+            });
+        }
         if status.is_success() {
             let response_text = response
                 .text()
                 .await
-                .with_context(|| "Failed to read response body")?;
-
-            let requests: Vec<WebhookRequest> =
-                serde_json::from_str(&response_text).with_context(|| {
-                    format!(
-                        "Failed to parse response as JSON. Response body: {}",
-                        response_text
-                    )
-                })?;
+                .network_err(|| "Failed to read response body".to_string())?;
+            trace_body(&response_text);
+            match self.backend.parse_request(token, &response_text) {
+                Ok(request) => {
+                    if let Some(archive) = &self.archive {
+                        archive.store(token, std::slice::from_ref(&request));
+                    }
+                    return Ok(request);
+                }
+                Err(e) => logging::debug(1, format_args!("{:#}", e)),
+            }
+        }
+
+        logging::debug(
+            1,
+            format_args!("No single-request endpoint ({}), searching the log", status),
+        );
+        let mut count = 100;
+        loop {
+            let requests = self
+                .get_requests_with_progress(token, count, |_| {})
+                .await?;
+            let exhausted = requests.len() < count as usize;
+            if let Some(request) = requests.into_iter().find(|req| req.id == request_id) {
+                return Ok(request);
+            }
+            if exhausted || count >= MAX_SEARCH_COUNT {
+                return Err(WebhookError::NotFound(format!(
+                    "Request with ID {} not found",
+                    request_id
+                )));
+            }
+            count = (count * 10).min(MAX_SEARCH_COUNT);
+        }
+    }
+
+    /// Fetch up to `count` requests, newest first. When `count` exceeds the
+    /// configured page size the log is fetched page by page, calling
+    /// `on_page` with the number of requests fetched so far.
+    pub async fn get_requests_with_progress(
+        &self,
+        token: &str,
+        count: u32,
+        on_page: impl Fn(usize),
+    ) -> Result<Vec<WebhookRequest>> {
+        if self.page_size == 0 || count <= self.page_size {
+            let url = self.backend.list_url(&self.base_url, token, count);
+            return self.fetch_log(token, self.client.get(&url), &url).await;
+        }
+
+        let mut requests: Vec<WebhookRequest> = Vec::new();
+        let mut seen_ids = HashSet::new();
+        while requests.len() < count as usize {
+            let size = self.page_size.min(count - requests.len() as u32);
+            let url = self.backend.list_url(&self.base_url, token, size);
+            let builder = self.client.get(&url);
+            let builder = match (self.pagination, requests.last()) {
+                (Pagination::Offset, _) => builder.query(&[("offset", requests.len())]),
+                (Pagination::Cursor, Some(oldest)) => builder.query(&[("before", &oldest.id)]),
+                (Pagination::Cursor, None) => builder,
+            };
+
+            let page = self.fetch_log(token, builder, &url).await?;
+            let last_page = page.len() < size as usize;
+            let fetched = requests.len();
+            // Servers that ignore the paging parameters return the same page again
+            requests.extend(
+                page.into_iter()
+                    .filter(|req| seen_ids.insert(req.id.clone())),
+            );
+            on_page(requests.len());
+
+            if last_page || requests.len() == fetched {
+                break;
+            }
+        }
+        requests.truncate(count as usize);
+        Ok(requests)
+    }
+
+    /// Fetch only the requests that arrived after `since_id`, newest first.
+    /// Servers that ignore the `since` parameter return the full log, which is
+    /// then trimmed client-side.
+    pub async fn get_requests_since(
+        &self,
+        token: &str,
+        since_id: &str,
+        count: u32,
+    ) -> Result<Vec<WebhookRequest>> {
+        let url = self.backend.list_url(&self.base_url, token, count);
+        let requests = self
+            .fetch_log(
+                token,
+                self.client.get(&url).query(&[("since", since_id)]),
+                &url,
+            )
+            .await?;
+
+        Ok(requests
+            .into_iter()
+            .take_while(|req| req.id != since_id)
+            .collect())
+    }
+
+    async fn fetch_log(
+        &self,
+        token: &str,
+        request: reqwest::RequestBuilder,
+        url: &str,
+    ) -> Result<Vec<WebhookRequest>> {
+        if !self.keeps_history() {
+            return Err(WebhookError::config(NO_HISTORY));
+        }
+        let mut request = request.header(reqwest::header::ACCEPT, "application/json");
+
+        // Revalidate the previous response for the same URL and query, if the
+        // server sent validators for it
+        let full_url = request
+            .try_clone()
+            .and_then(|builder| builder.build().ok())
+            .map(|built| built.url().to_string())
+            .unwrap_or_else(|| url.to_string());
+        if let Some(cached) = self.cached_log(url, &full_url) {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        if self.report_dry_run(&request) {
+            return Err(WebhookError::DryRun);
+        }
+
+        let response = self
+            .send_with_retry(request)
+            .await
+            .network_err(|| format!("Failed to fetch requests from {}", url))?;
+
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            // Nothing changed since the cached response
+            return Ok(self
+                .cached_log(url, &full_url)
+                .map(|cached| cached.requests)
+                .unwrap_or_default());
+        }
+
+        if status.is_success() {
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value: &HeaderValue| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            let etag = header(ETAG);
+            let last_modified = header(LAST_MODIFIED);
+            let content_type = header(CONTENT_TYPE).unwrap_or_default();
+
+            // The archive keeps bodies whole; they are cut after storing them
+            let max_body = match self.archive {
+                Some(_) => usize::MAX,
+                None => self.max_body_size,
+            };
+            let mut requests = if logging::verbosity() >= 2 {
+                // Traced responses are read whole so they can be shown
+                let response_text = response
+                    .text()
+                    .await
+                    .network_err(|| "Failed to read response body".to_string())?;
+                trace_body(&response_text);
+                self.backend.read_list(
+                    token,
+                    &content_type,
+                    &mut response_text.as_bytes(),
+                    max_body,
+                )?
+            } else {
+                self.read_list_streaming(token, &content_type, response, max_body)
+                    .await?
+            };
+            if let Some(archive) = &self.archive {
+                archive.store(token, &requests);
+                truncate_bodies(&mut requests, self.max_body_size);
+            }
+
+            if etag.is_some() || last_modified.is_some() {
+                self.log_cache.lock().unwrap().insert(
+                    url.to_string(),
+                    CachedLog {
+                        full_url,
+                        etag,
+                        last_modified,
+                        requests: requests.clone(),
+                    },
+                );
+            }
             Ok(requests)
         } else if status == StatusCode::NOT_FOUND {
             Ok(vec![]) // No requests yet
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            Err(WebhookError::RateLimited {
+                retry_after: retry_after(&response),
+            })
         } else {
             let response_body = response
                 .text()
                 .await
                 .unwrap_or_else(|_| "(failed to read response body)".to_string());
+            Err(status_error(status, response_body))
+        }
+    }
+
+    /// Decode a list response on a blocking thread while its chunks arrive,
+    /// so that large logs are never held in memory as a whole
+    async fn read_list_streaming(
+        &self,
+        token: &str,
+        content_type: &str,
+        mut response: reqwest::Response,
+        max_body: usize,
+    ) -> Result<Vec<WebhookRequest>> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(RESPONSE_CHUNKS_BUFFERED);
+        let backend = Arc::clone(&self.backend);
+        let token = token.to_string();
+        let content_type = content_type.to_string();
+        let parser = tokio::task::spawn_blocking(move || {
+            backend.read_list(
+                &token,
+                &content_type,
+                &mut ChunkReader::new(receiver),
+                max_body,
+            )
+        });
 
-            anyhow::bail!(
-                "HTTP {} {}: {}",
-                status.as_u16(),
-                status.canonical_reason().unwrap_or("Unknown"),
-                response_body
+        while let Some(chunk) = response.chunk().await.map_err(io::Error::other).transpose() {
+            let failed = chunk.is_err();
+            // The parser stops early on malformed input
+            if sender.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+        drop(sender);
+        parser
+            .await
+            .parse_err(|| "Failed to parse response".to_string())?
+    }
+
+    /// Cached response for a log URL, when it was fetched with the same query
+    fn cached_log(&self, url: &str, full_url: &str) -> Option<CachedLog> {
+        self.log_cache
+            .lock()
+            .unwrap()
+            .get(url)
+            .filter(|cached| cached.full_url == full_url)
+            .cloned()
+    }
+
+    /// Send an idempotent request, retrying connection errors, timeouts and
+    /// 5xx responses with exponential backoff and jitter, and 429 responses
+    /// after the delay given in `Retry-After`
+    async fn send_with_retry(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .send_traced(
+                    request
+                        .try_clone()
+                        .expect("GET requests have no streaming body"),
+                )
+                .await;
+
+            let delay = match &result {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    match retry_after(response) {
+                        Some(wait) if wait > MAX_RETRY_AFTER => return result,
+                        Some(wait) => Some(wait),
+                        None => Some(retry_delay(self.retry_backoff, attempt + 1)),
+                    }
+                }
+                Ok(response) if response.status().is_server_error() => {
+                    Some(retry_delay(self.retry_backoff, attempt + 1))
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    Some(retry_delay(self.retry_backoff, attempt + 1))
+                }
+                _ => None,
+            };
+            let Some(delay) = delay.filter(|_| attempt < self.retries) else {
+                return result;
+            };
+
+            attempt += 1;
+            logging::debug(
+                1,
+                format_args!(
+                    "Retrying in {}ms (attempt {}/{})",
+                    delay.as_millis(),
+                    attempt,
+                    self.retries
+                ),
             );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// In dry-run mode, report the call `request` would make, with all its
+    /// headers, and return `true`: the caller must then not send it
+    fn report_dry_run(&self, request: &reqwest::RequestBuilder) -> bool {
+        if !self.dry_run {
+            return false;
+        }
+        let Some(request) = request.try_clone().and_then(|builder| builder.build().ok()) else {
+            return true;
+        };
+        logging::dry_run(format_args!("{} {}", request.method(), request.url()));
+        let defaults = self
+            .default_headers
+            .iter()
+            .filter(|(name, _)| !request.headers().contains_key(*name));
+        for (name, value) in defaults.chain(request.headers()) {
+            logging::dry_run(format_args!("    {}: {}", name, header_text(value)));
+        }
+        if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+            logging::dry_run(format_args!("    body: {}", String::from_utf8_lossy(body)));
+        }
+        true
+    }
+
+    /// Send a request, logging the call with `-v` and headers with `-vv`
+    async fn send_traced(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let request = request.build()?;
+        let method = request.method().clone();
+        let url = request.url().clone();
+        logging::debug(1, format_args!("→ {} {}", method, url));
+        trace_headers(request.headers());
+
+        let started = Instant::now();
+        let result = self.client.execute(request).await;
+        let elapsed = started.elapsed().as_millis();
+        match &result {
+            Ok(response) => {
+                logging::debug(
+                    1,
+                    format_args!("← {} {} {} ({}ms)", response.status(), method, url, elapsed),
+                );
+                trace_headers(response.headers());
+            }
+            Err(e) => logging::debug(
+                1,
+                format_args!("✗ {} {} failed after {}ms: {}", method, url, elapsed, e),
+            ),
+        }
+        result
+    }
+
+    /// Create a new token, on the server when the provider requires it,
+    /// answering its webhooks with `response` when the provider supports it
+    pub async fn create_token(&self, response: &TokenResponse) -> Result<String> {
+        let Some(url) = self.backend.create_token_url(&self.base_url) else {
+            if !response.is_empty() {
+                return Err(WebhookError::config(
+                    "This provider does not support custom responses",
+                ));
+            }
+            return Ok(Uuid::new_v4().to_string());
+        };
+
+        let mut request = self
+            .client
+            .request(self.backend.create_token_method(), &url)
+            .header(reqwest::header::ACCEPT, "application/json");
+        if !response.is_empty() {
+            request = request.json(&self.backend.create_token_body(response)?);
+        }
+        if self.report_dry_run(&request) {
+            return Err(WebhookError::DryRun);
+        }
+        let response = self
+            .send_traced(request)
+            .await
+            .network_err(|| format!("Failed to create a token at {}", url))?;
+        let status = response.status();
+        let final_url = response.url().to_string();
+        let body = response
+            .text()
+            .await
+            .network_err(|| "Failed to read response body".to_string())?;
+        if !status.is_success() {
+            return Err(status_error(status, body));
+        }
+        self.backend.parse_created_token(&final_url, &body)
+    }
+
+    /// URL that webhooks for a token should be sent to
+    pub fn webhook_url(&self, token: &str) -> String {
+        self.backend.webhook_url(&self.base_url, token)
+    }
+
+    /// Open the server's Server-Sent Events stream for a token.
+    /// Returns `None` when the server does not support streaming.
+    pub async fn open_stream(&self, token: &str) -> Result<Option<RequestStream>> {
+        let Some(url) = self.backend.stream_url(&self.base_url, token) else {
+            return Ok(None);
+        };
+
+        let request = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .timeout(STREAM_TIMEOUT);
+        if self.report_dry_run(&request) {
+            return Err(WebhookError::DryRun);
+        }
+        let response = self
+            .send_traced(request)
+            .await
+            .network_err(|| format!("Failed to open event stream at {}", url))?;
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+
+        let status = response.status();
+        // Only a missing endpoint or another kind of response means the
+        // service has no stream; other failures are reported like a poll's
+        if status == StatusCode::NOT_FOUND || status == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(WebhookError::RateLimited {
+                retry_after: retry_after(&response),
+            });
+        }
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "(failed to read response body)".to_string());
+            return Err(status_error(status, body));
+        }
+        if is_event_stream {
+            Ok(Some(RequestStream {
+                response,
+                buffer: Vec::new(),
+                data: String::new(),
+                token: token.to_string(),
+                backend: Arc::clone(&self.backend),
+                archive: self.archive.clone(),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Headers carrying the configured credentials, attached to every API call
+fn auth_headers(auth: &AuthConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    let authorization = match (&auth.bearer_token, &auth.username) {
+        (Some(_), Some(_)) => {
+            return Err(WebhookError::config(
+                "Configure either a bearer token or basic authentication, not both",
+            ));
+        }
+        (Some(token), None) => Some(format!("Bearer {}", resolve_secret(token)?)),
+        (None, Some(username)) => {
+            let password = match &auth.password {
+                Some(password) => resolve_secret(password)?,
+                None => String::new(),
+            };
+            let credentials = BASE64.encode(format!("{}:{}", username, password));
+            Some(format!("Basic {}", credentials))
+        }
+        (None, None) => None,
+    };
+    if let Some(authorization) = authorization {
+        let mut value = HeaderValue::from_str(&authorization)
+            .config_err(|| "Credentials contain characters not allowed in a header".to_string())?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    for (name, value) in &auth.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .config_err(|| format!("Invalid header name: {}", name))?;
+        let mut value = HeaderValue::from_str(&resolve_secret(value)?)
+            .config_err(|| format!("Invalid value for header {}", name))?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    Ok(headers)
+}
+
+/// The error for an unsuccessful response: `Auth` when the service rejected
+/// the credentials
+fn status_error(status: StatusCode, body: String) -> WebhookError {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => WebhookError::Auth {
+            message: format!(
+                "The webhook service rejected the credentials (HTTP {})",
+                status.as_u16()
+            ),
+            source: Some(
+                WebhookError::Http {
+                    status: status.as_u16(),
+                    body,
+                }
+                .into(),
+            ),
+        },
+        _ => WebhookError::Http {
+            status: status.as_u16(),
+            body,
+        },
+    }
+}
+
+/// Log headers with `-vv`, hiding credentials
+fn trace_headers(headers: &HeaderMap) {
+    if logging::verbosity() < 2 {
+        return;
+    }
+    for (name, value) in headers {
+        logging::debug(2, format_args!("    {}: {}", name, header_text(value)));
+    }
+}
+
+/// A header value as shown in diagnostics: credentials are hidden
+fn header_text(value: &HeaderValue) -> &str {
+    if value.is_sensitive() {
+        "<redacted>"
+    } else {
+        value.to_str().unwrap_or("<binary>")
+    }
+}
+
+/// Log a response body with `-vv`, truncated to keep the output readable
+fn trace_body(body: &str) {
+    if logging::verbosity() < 2 {
+        return;
+    }
+    let mut preview: String = body.chars().take(TRACE_BODY_LENGTH).collect();
+    if body.chars().count() > TRACE_BODY_LENGTH {
+        preview.push_str(&format!("… ({} bytes)", body.len()));
+    }
+    logging::debug(2, format_args!("    body: {}", preview));
+}
+
+/// Parse the `Retry-After` header, given either in seconds or as an HTTP date
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Backoff before retry number `attempt` (1-based): the base delay doubled per
+/// attempt, plus up to 50% random jitter so clients don't retry in lockstep
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt - 1));
+    let jitter = RandomState::new().build_hasher().finish() % 1000;
+    delay + delay.mul_f64(jitter as f64 / 2000.0)
+}
+
+/// New requests pushed by the server as Server-Sent Events
+pub struct RequestStream {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    data: String,
+    token: String,
+    backend: Arc<dyn WebhookBackend>,
+    archive: Option<Arc<Archive>>,
+}
+
+impl RequestStream {
+    /// Wait for the next request. Returns `None` when the server closes the stream.
+    pub async fn next_request(&mut self) -> Result<Option<WebhookRequest>> {
+        loop {
+            while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\r', '\n']);
+
+                if line.is_empty() {
+                    // Blank line dispatches the event
+                    let data = std::mem::take(&mut self.data);
+                    // Skip heartbeats and other events that are not requests
+                    if let Some(request) = self.backend.parse_event(&self.token, &data) {
+                        if let Some(archive) = &self.archive {
+                            archive.store(&self.token, std::slice::from_ref(&request));
+                        }
+                        return Ok(Some(request));
+                    }
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    if !self.data.is_empty() {
+                        self.data.push('\n');
+                    }
+                    self.data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+
+            match self
+                .response
+                .chunk()
+                .await
+                .network_err(|| "Failed to read from event stream".to_string())?
+            {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Blocking reader over response chunks sent from the async side
+struct ChunkReader<B> {
+    chunks: tokio::sync::mpsc::Receiver<io::Result<B>>,
+    current: Option<B>,
+    position: usize,
+}
+
+impl<B> ChunkReader<B> {
+    fn new(chunks: tokio::sync::mpsc::Receiver<io::Result<B>>) -> Self {
+        Self {
+            chunks,
+            current: None,
+            position: 0,
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> Read for ChunkReader<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(chunk) = &self.current {
+                let remaining = &chunk.as_ref()[self.position..];
+                if !remaining.is_empty() {
+                    let len = remaining.len().min(buf.len());
+                    buf[..len].copy_from_slice(&remaining[..len]);
+                    self.position += len;
+                    return Ok(len);
+                }
+            }
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.current = Some(chunk?);
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
         }
     }
 }
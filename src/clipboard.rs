@@ -0,0 +1,62 @@
+use anyhow::Result;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Put `text` on the system clipboard with the platform's copy command;
+/// returns the command used
+pub async fn copy(text: &str) -> Result<&'static str> {
+    let candidates = candidates();
+    if candidates.is_empty() {
+        anyhow::bail!("no display to own a clipboard (headless session)");
+    }
+    let mut missing = Vec::new();
+    for (program, args) in candidates {
+        let spawned = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = spawned else {
+            missing.push(program);
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).await?;
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", program, status);
+        }
+        return Ok(program);
+    }
+    anyhow::bail!("none of {} is installed", missing.join(", "))
+}
+
+#[cfg(target_os = "macos")]
+fn candidates() -> Vec<(&'static str, &'static [&'static str])> {
+    vec![("pbcopy", &[])]
+}
+
+#[cfg(windows)]
+fn candidates() -> Vec<(&'static str, &'static [&'static str])> {
+    vec![("clip", &[])]
+}
+
+/// Wayland or X11 tools, depending on the session; WSL has Windows' `clip.exe`
+#[cfg(not(any(target_os = "macos", windows)))]
+fn candidates() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut candidates: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        candidates.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        candidates.push(("xclip", &["-selection", "clipboard"]));
+        candidates.push(("xsel", &["--clipboard", "--input"]));
+    }
+    if std::env::var_os("WSL_DISTRO_NAME").is_some() {
+        candidates.push(("clip.exe", &[]));
+    }
+    candidates
+}
@@ -1,22 +1,38 @@
+use std::io::IsTerminal;
 use std::sync::OnceLock;
 
-static NO_COLOR: OnceLock<bool> = OnceLock::new();
+use crate::cli::ColorChoice;
 
-pub fn init(no_color: bool) {
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Decide once whether output is colored: `auto` colors only a terminal, and
+/// not when `NO_COLOR` is set or `TERM` is `dumb`
+pub fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none()
+                && std::env::var("TERM").map_or(true, |term| term != "dumb")
+                && std::io::stdout().is_terminal()
+        }
+    };
     // Ignore if already initialized; first value wins.
-    let _ = NO_COLOR.set(no_color);
+    let _ = ENABLED.set(enabled);
 
-    if no_color {
-        colored::control::set_override(false);
-    } else {
-        // Enable ANSI support on Windows
-        #[cfg(windows)]
+    colored::control::set_override(enabled);
+    // Enable ANSI support on Windows
+    #[cfg(windows)]
+    if enabled {
         enable_ansi_support();
-
-        // On Unix systems, colored crate handles this automatically
     }
 }
 
+/// Whether output is colored, for output not written through `colored`
+pub fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
 #[cfg(windows)]
 fn enable_ansi_support() {
     use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
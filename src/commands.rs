@@ -1,140 +1,104 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::ValueEnum;
 use colored::Colorize;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
 use std::time::Duration;
-use uuid::Uuid;
 
+use crate::archive::Archive;
+use crate::backend::TokenResponse;
+use crate::cli::{SecretCommand, Section, SortBy};
 use crate::client::WebhookClient;
+use crate::clipboard;
 use crate::config::Config;
+use crate::dedup::{Deliveries, DeliveryId, UniqueBy, collapse};
 use crate::display::{
-    print_full_request_body, print_request_details, print_request_headers, print_request_summary,
+    format_elapsed, print_bare_section, print_full_request_body, print_request_details,
+    print_request_headers, print_request_summary,
 };
+use crate::exit::{self, Failure};
+use crate::export;
+use crate::info;
+use crate::migrate::{CONFIG_VERSION, plan};
+use crate::models::WebhookRequest;
+use crate::progress::Progress;
+use crate::qr::QrCode;
+use crate::secrets::{keyring_delete, keyring_entry, keyring_lookup, keyring_store};
+use crate::signature::Verifier;
+use crate::tags::{Tags, print_tags};
 
-pub async fn generate_token(config: &Config) -> Result<()> {
-    let token = Uuid::new_v4();
-    let webhook_url = Config::join_url_segments(config.get_base_url(), &[&token.to_string()]);
+pub async fn generate_token(
+    client: &WebhookClient,
+    response: &TokenResponse,
+    qr: bool,
+    copy: bool,
+) -> Result<String> {
+    let token = client.create_token(response).await?;
+    let webhook_url = client.webhook_url(&token);
 
     println!("{}", "New webhook token generated!".bright_green().bold());
     println!();
-    println!(
-        "{}: {}",
-        "Token".bright_blue().bold(),
-        token.to_string().bright_white()
-    );
+    println!("{}: {}", "Token".bright_blue().bold(), token.bright_white());
     println!(
         "{}: {}",
         "Webhook URL".bright_blue().bold(),
         webhook_url.bright_white()
     );
     println!();
+    if qr {
+        print_qr(&webhook_url);
+    }
+    if copy {
+        match clipboard::copy(&webhook_url).await {
+            Ok(_) => println!("{} Webhook URL copied to the clipboard", "✓".bright_green()),
+            Err(e) => println!(
+                "{} {}; copy the URL above instead",
+                "Could not copy to the clipboard:".bright_yellow(),
+                e
+            ),
+        }
+        println!();
+    }
     println!("{}", "Usage examples:".bright_yellow());
     println!("  webhook monitor --token {}", token);
     println!("  webhook logs --token {}", token);
     println!();
 
-    Ok(())
+    Ok(token)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn monitor_requests(
-    client: &WebhookClient,
-    config: &Config,
-    token: &str,
-    initial_count: u32,
-    interval: u64,
-    method_filter: Option<&str>,
-    full_body: bool,
-    show_headers: bool,
-    parse_paths: &[String],
-) -> Result<()> {
-    println!("{}", "Starting webhook monitor...".bright_green().bold());
-    println!("Token: {}", token.bright_white());
-    if let Some(method) = method_filter {
-        println!(
-            "Filter: {} requests only",
-            method.to_uppercase().bright_cyan()
-        );
+/// `tokens show`: the token `name` (alias or token) and its webhook URL
+pub fn show_token(client: &WebhookClient, config: &Config, name: &str, qr: bool) {
+    let token = config.resolve_token(name);
+    let webhook_url = client.webhook_url(token);
+    if token != name {
+        println!("{}: {}", "Alias".bright_blue().bold(), name.bright_white());
     }
-    println!("Press {} to quit", "Ctrl+C".bright_red());
-    println!("{}", "─".repeat(80).bright_black());
-
-    let mut last_seen_ids = HashSet::new();
-    let mut first_run = true;
+    println!("{}: {}", "Token".bright_blue().bold(), token.bright_white());
+    println!(
+        "{}: {}",
+        "Webhook URL".bright_blue().bold(),
+        webhook_url.bright_white()
+    );
+    if qr {
+        println!();
+        print_qr(&webhook_url);
+    }
+}
 
-    loop {
-        match client.get_requests(token, initial_count).await {
-            Ok(requests) => {
-                let filtered_requests: Vec<_> = requests
-                    .into_iter()
-                    .filter(|req| {
-                        method_filter.is_none_or(|method| {
-                            req.message_object.method.eq_ignore_ascii_case(method)
-                        })
-                    })
-                    .collect();
-
-                if first_run {
-                    // Show existing requests on first run
-                    if filtered_requests.is_empty() {
-                        println!(
-                            "{}",
-                            "No requests yet. Waiting for incoming webhooks...".bright_yellow()
-                        );
-                    } else {
-                        println!(
-                            "{} {} recent requests:",
-                            "Found".bright_blue(),
-                            filtered_requests.len()
-                        );
-                        // Reverse the order so latest requests appear at the end
-                        for request in filtered_requests.iter().rev() {
-                            print_request_summary(
-                                request,
-                                !full_body,
-                                config.get_body_preview_length(),
-                            ); // Don't show body preview in full body mode
-                            if show_headers {
-                                print_request_headers(request);
-                            }
-                            if full_body || !parse_paths.is_empty() {
-                                print_full_request_body(request, parse_paths, full_body);
-                                println!(); // Add spacing between requests when showing full body
-                            }
-                            last_seen_ids.insert(request.id.clone());
-                        }
-                    }
-                    first_run = false;
-                } else {
-                    // Show only new requests
-                    let new_requests: Vec<_> = filtered_requests
-                        .into_iter()
-                        .filter(|req| !last_seen_ids.contains(&req.id))
-                        .collect();
-                    for request in &new_requests {
-                        println!("{}", "NEW REQUEST".bright_green().bold());
-                        print_request_summary(
-                            request,
-                            !full_body,
-                            config.get_body_preview_length(),
-                        ); // Don't show body preview in full body mode
-                        if show_headers {
-                            print_request_headers(request);
-                        }
-                        if full_body || !parse_paths.is_empty() {
-                            print_full_request_body(request, parse_paths, full_body);
-                        }
-                        println!("{}", "─".repeat(80).bright_black());
-                        last_seen_ids.insert(request.id.clone());
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("{} {}", "Error:".bright_red(), e);
+/// Print `url` as a QR code, light modules drawn for dark terminals
+fn print_qr(url: &str) {
+    match QrCode::encode(url) {
+        Ok(code) => {
+            for line in code.render() {
+                println!("{}", line);
             }
+            println!();
         }
-
-        tokio::time::sleep(Duration::from_secs(interval)).await;
+        Err(e) => eprintln!("{} {:#}", "Could not draw a QR code:".bright_yellow(), e),
     }
 }
 
@@ -144,68 +108,438 @@ pub async fn show_logs(
     config: &Config,
     token: &str,
     count: u32,
-    method_filter: Option<&str>,
+    offset: u32,
+    filter: &LogFilter<'_>,
     full_body: bool,
     show_headers: bool,
     parse_paths: &[String],
-) -> Result<()> {
-    println!("{}", "Fetching webhook logs...".bright_blue().bold());
-
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(ProgressStyle::default_spinner().template("{spinner} {msg}")?);
-    spinner.set_message("Loading requests...");
-    spinner.enable_steady_tick(Duration::from_millis(100));
-
-    let requests = client.get_requests(token, count).await?;
-    spinner.finish_and_clear();
+    unique_by: Option<&UniqueBy>,
+    delivery_id: Option<&DeliveryId>,
+    sort: SortBy,
+    reverse: bool,
+    offline: bool,
+) -> Result<usize> {
+    let tags = Tags::load()?;
+    // The client pages through the log as needed to reach the offset
+    let requests = load_requests(client, config, token, through(offset, count)?, offline).await?;
+    let attempts = retry_attempts(&requests, delivery_id);
 
     let filtered_requests: Vec<_> = requests
         .into_iter()
-        .filter(|req| {
-            method_filter
-                .is_none_or(|method| req.message_object.method.eq_ignore_ascii_case(method))
-        })
+        .skip(offset as usize)
+        .filter(|req| filter.matches(req, &tags))
         .collect();
 
     if filtered_requests.is_empty() {
-        println!("{}", "No requests found.".bright_yellow());
-        return Ok(());
+        info!("{}", "No requests found.".bright_yellow());
+        return Ok(0);
     }
 
-    println!(
+    let total_requests = filtered_requests.len();
+    let mut filtered_requests = match unique_by {
+        Some(unique_by) => collapse(filtered_requests, unique_by),
+        None => filtered_requests.into_iter().map(|req| (req, 1)).collect(),
+    };
+
+    info!(
         "{} {} requests for token {}",
         "Found".bright_blue(),
         filtered_requests.len(),
         token.bright_white()
     );
 
-    if let Some(method) = method_filter {
-        println!(
+    if offset > 0 {
+        info!(
+            "Skipped the newest {} requests",
+            offset.to_string().bright_cyan()
+        );
+    }
+    if let Some(method) = filter.method {
+        info!(
             "Filtered by method: {}",
             method.to_uppercase().bright_cyan()
         );
     }
+    if let Some(tag) = filter.tag {
+        info!("Filtered by tag: {}", tag.bright_magenta());
+    }
+    if let Some(since) = filter.since {
+        info!(
+            "Filtered to the last {}",
+            format_elapsed(since).bright_cyan()
+        );
+    }
+
+    if unique_by.is_some() && filtered_requests.len() < total_requests {
+        info!(
+            "Collapsed {} duplicate deliveries into {} unique requests",
+            (total_requests - filtered_requests.len())
+                .to_string()
+                .bright_yellow(),
+            filtered_requests.len()
+        );
+    }
 
-    println!("{}", "─".repeat(80).bright_black());
-    // Reverse the order so latest requests appear at the end
-    for request in filtered_requests.iter().rev() {
-        print_request_summary(request, !full_body, config.get_body_preview_length()); // Don't show body preview in full body mode
-        if show_headers {
+    sort_requests(&mut filtered_requests, sort, reverse, |(req, _)| req);
+
+    info!("{}", "─".repeat(80).bright_black());
+    let view = LogView {
+        full_body,
+        show_headers,
+        parse_paths,
+        body_preview_length: config.get_body_preview_length(),
+        attempts,
+    };
+    for (request, deliveries) in &filtered_requests {
+        view.print(request, *deliveries, &tags);
+    }
+
+    info!();
+    info!(
+        "{}",
+        "Use 'webhook show --token <token> --request-id <id>' for full details".bright_yellow()
+    );
+
+    Ok(filtered_requests.len())
+}
+
+/// `webhook logs --interactive`: print the log newest first, `page_size`
+/// requests at a time, and ask whether to fetch more or open a request
+#[allow(clippy::too_many_arguments)]
+pub async fn page_logs(
+    client: &WebhookClient,
+    config: &Config,
+    token: &str,
+    page_size: u32,
+    filter: &LogFilter<'_>,
+    full_body: bool,
+    show_headers: bool,
+    parse_paths: &[String],
+    delivery_id: Option<&DeliveryId>,
+    offline: bool,
+) -> Result<usize> {
+    let tags = Tags::load()?;
+    let mut view = LogView {
+        full_body,
+        show_headers,
+        parse_paths,
+        body_preview_length: config.get_body_preview_length(),
+        attempts: HashMap::new(),
+    };
+    let term = console::Term::stdout();
+    let mut seen = HashSet::new();
+    let mut listed: Vec<WebhookRequest> = Vec::new();
+
+    for page in 1.. {
+        // Each page refetches the log up to its end, so paging works with
+        // every provider; requests that arrived meanwhile push the older
+        // ones down, and those already listed are skipped
+        let requests = load_requests(client, config, token, page_size * page, offline).await?;
+        let more = requests.len() == (page_size * page) as usize;
+        view.attempts = retry_attempts(&requests, delivery_id);
+        let new: Vec<_> = requests
+            .into_iter()
+            .filter(|req| seen.insert(req.id.clone()))
+            .collect();
+
+        let first = listed.len();
+        listed.extend(new.into_iter().filter(|req| filter.matches(req, &tags)));
+        if listed.is_empty() && !more {
+            println!("{}", "No requests found.".bright_yellow());
+            return Ok(0);
+        }
+        for (index, request) in listed.iter().enumerate().skip(first) {
+            print!("{} ", format!("{:>3}.", index + 1).bright_black());
+            view.print(request, 1, &tags);
+        }
+
+        loop {
+            let prompt = if more {
+                "[space] more, [o]pen #, [q]uit"
+            } else {
+                "End of the log. [o]pen #, [q]uit"
+            };
+            term.write_str(&prompt.bright_yellow().to_string())?;
+            let key = term.read_key()?;
+            term.clear_line()?;
+            match key {
+                console::Key::Char(' ') | console::Key::Enter if more => break,
+                console::Key::Char('o') => {
+                    term.write_str("Open #: ")?;
+                    let line = term.read_line()?;
+                    let chosen = line
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|number| listed.get(number.checked_sub(1)?));
+                    match chosen {
+                        Some(request) => {
+                            show_request_details(
+                                client,
+                                token,
+                                &request.id,
+                                parse_paths,
+                                Section::value_variants(),
+                                None,
+                                None,
+                            )
+                            .await?;
+                            println!();
+                        }
+                        None => println!("{}", format!("No request #{}", line.trim()).bright_red()),
+                    }
+                }
+                console::Key::Char('q') | console::Key::Escape | console::Key::CtrlC => {
+                    return Ok(listed.len());
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(listed.len())
+}
+
+/// The number of requests to fetch to reach `count` past `offset`
+fn through(offset: u32, count: u32) -> Result<u32> {
+    offset.checked_add(count).ok_or_else(|| {
+        Failure::new(
+            exit::CONFIG,
+            format!(
+                "--offset {} with --count {} is past the largest supported position ({})",
+                offset,
+                count,
+                u32::MAX
+            ),
+        )
+        .into()
+    })
+}
+
+/// `webhook logs --count-only`: print only the number of matching requests
+#[allow(clippy::too_many_arguments)]
+pub async fn count_logs(
+    client: &WebhookClient,
+    config: &Config,
+    token: &str,
+    count: u32,
+    offset: u32,
+    filter: &LogFilter<'_>,
+    unique_by: Option<&UniqueBy>,
+    offline: bool,
+) -> Result<usize> {
+    let tags = Tags::load()?;
+    let requests = match filter.since {
+        // The whole window counts, however many requests it holds
+        Some(since) => fetch_since(client, config, token, offset, since, offline).await?,
+        None => fetch_requests(client, config, token, through(offset, count)?, offline).await?,
+    };
+    let requests: Vec<_> = requests
+        .into_iter()
+        .skip(offset as usize)
+        .filter(|req| filter.matches(req, &tags))
+        .collect();
+    let matched = match unique_by {
+        Some(unique_by) => collapse(requests, unique_by).len(),
+        None => requests.len(),
+    };
+    println!("{}", matched);
+    Ok(matched)
+}
+
+/// Which requests `logs` lists
+pub struct LogFilter<'a> {
+    pub method: Option<&'a str>,
+    pub tag: Option<&'a str>,
+    /// Only requests that arrived within this long
+    pub since: Option<Duration>,
+}
+
+impl LogFilter<'_> {
+    fn matches(&self, request: &WebhookRequest, tags: &Tags) -> bool {
+        self.method
+            .is_none_or(|method| request.message_object.method.is(method))
+            && self.tag.is_none_or(|tag| tags.has(&request.id, tag))
+            && self.since.is_none_or(|since| {
+                request.date.is_some_and(|date| {
+                    // Requests dated ahead of the local clock count as just arrived
+                    (Utc::now() - date).to_std().unwrap_or_default() <= since
+                })
+            })
+    }
+}
+
+/// How `logs` prints each request
+struct LogView<'a> {
+    full_body: bool,
+    show_headers: bool,
+    parse_paths: &'a [String],
+    body_preview_length: usize,
+    /// Attempt number of the requests that retry an earlier delivery, by ID
+    attempts: HashMap<String, usize>,
+}
+
+impl LogView<'_> {
+    /// Print a request that was delivered `deliveries` times
+    fn print(&self, request: &WebhookRequest, deliveries: usize, tags: &Tags) {
+        // Don't show body preview in full body mode
+        print_request_summary(request, !self.full_body, self.body_preview_length, None);
+        print_tags(tags.get(&request.id));
+        if deliveries > 1 {
+            println!(
+                "  {} {}",
+                "↳".bright_black(),
+                format!("delivered {} times", deliveries).bright_yellow()
+            );
+        }
+        if let Some(attempt) = self.attempts.get(&request.id) {
+            println!(
+                "  {} {}",
+                "↳".bright_black(),
+                format!("RETRY #{}", attempt).bright_yellow().bold()
+            );
+        }
+        if self.show_headers {
             print_request_headers(request);
         }
-        if full_body || !parse_paths.is_empty() {
-            print_full_request_body(request, parse_paths, full_body);
+        if self.full_body || !self.parse_paths.is_empty() {
+            print_full_request_body(request, self.parse_paths, self.full_body);
             println!(); // Add spacing between requests when showing full body
         }
     }
+}
 
-    println!();
-    println!(
-        "{}",
-        "Use 'webhook show --token <token> --request-id <id>' for full details".bright_yellow()
-    );
+/// The attempt number of each request in `requests` (newest first) that
+/// repeats the delivery ID of an older one
+fn retry_attempts(
+    requests: &[WebhookRequest],
+    delivery_id: Option<&DeliveryId>,
+) -> HashMap<String, usize> {
+    let Some(delivery_id) = delivery_id else {
+        return HashMap::new();
+    };
+    let mut deliveries = Deliveries::new(delivery_id.clone());
+    requests
+        .iter()
+        .rev()
+        .filter_map(|request| {
+            let attempt = deliveries.record(request);
+            (attempt > 1).then(|| (request.id.clone(), attempt))
+        })
+        .collect()
+}
 
-    Ok(())
+/// The latest `count` requests of `token` from the service or, with
+/// `offline`, from the local archive
+pub async fn load_requests(
+    client: &WebhookClient,
+    config: &Config,
+    token: &str,
+    count: u32,
+    offline: bool,
+) -> Result<Vec<WebhookRequest>> {
+    if offline {
+        info!("{}", "Reading archived logs...".bright_blue().bold());
+        let archive = Archive::new(&config.archive)?;
+        let archived = archive.load(token)?;
+        if archived.is_empty() && !config.archive.enabled {
+            println!(
+                "{}",
+                "The archive is disabled; set `enabled = true` in [archive] to keep fetched requests"
+                    .bright_yellow()
+            );
+        }
+        Ok(archived
+            .into_iter()
+            .take(count as usize)
+            .map(|archived| archived.request)
+            .collect())
+    } else {
+        info!("{}", "Fetching webhook logs...".bright_blue().bold());
+
+        let spinner = Progress::spinner("Loading requests...")?;
+
+        let mut requests = client
+            .get_requests_cached(token, count, |fetched| {
+                spinner.set_message(format!("Loading requests... {}/{}", fetched, count))
+            })
+            .await?;
+        spinner.finish_and_clear();
+        // Services that ignore the count return their whole log
+        requests.truncate(count as usize);
+        Ok(requests)
+    }
+}
+
+/// `load_requests` without the progress output, for scripts
+async fn fetch_requests(
+    client: &WebhookClient,
+    config: &Config,
+    token: &str,
+    count: u32,
+    offline: bool,
+) -> Result<Vec<WebhookRequest>> {
+    if offline {
+        let archive = Archive::new(&config.archive)?;
+        return Ok(archive
+            .load(token)?
+            .into_iter()
+            .take(count as usize)
+            .map(|archived| archived.request)
+            .collect());
+    }
+    let mut requests = client.get_requests_cached(token, count, |_| {}).await?;
+    requests.truncate(count as usize);
+    Ok(requests)
+}
+
+/// The newest requests back to the first one older than `since`, or the
+/// whole log. How many arrived is not known up front, so they are fetched in
+/// batches that double in size.
+async fn fetch_since(
+    client: &WebhookClient,
+    config: &Config,
+    token: &str,
+    offset: u32,
+    since: Duration,
+    offline: bool,
+) -> Result<Vec<WebhookRequest>> {
+    let mut batch = through(offset, SINCE_BATCH)?;
+    loop {
+        let requests = fetch_requests(client, config, token, batch, offline).await?;
+        let log_ended = requests.len() < batch as usize;
+        let window_passed = requests
+            .last()
+            .and_then(|oldest| oldest.date)
+            .is_some_and(|date| (Utc::now() - date).to_std().unwrap_or_default() > since);
+        if log_ended || window_passed || batch == u32::MAX {
+            return Ok(requests);
+        }
+        batch = batch.saturating_mul(2);
+    }
+}
+
+/// Requests `fetch_since` asks for first
+const SINCE_BATCH: u32 = 100;
+
+/// Sort requests for display. The server returns the newest requests first;
+/// ascending order puts the latest (or largest) request at the end.
+pub fn sort_requests<T>(
+    items: &mut [T],
+    sort: SortBy,
+    reverse: bool,
+    request: fn(&T) -> &WebhookRequest,
+) {
+    items.reverse();
+    match sort {
+        SortBy::Date => items.sort_by_key(|item| request(item).date),
+        SortBy::Size => {
+            items.sort_by_key(|item| request(item).body.as_ref().map_or(0, |b| b.len()))
+        }
+        SortBy::Method => items.sort_by_key(|item| request(item).message_object.method.to_string()),
+    }
+    if reverse {
+        items.reverse();
+    }
 }
 
 pub async fn show_request_details(
@@ -213,17 +547,218 @@ pub async fn show_request_details(
     token: &str,
     request_id: &str,
     parse_paths: &[String],
+    sections: &[Section],
+    save_body: Option<&Path>,
+    verifier: Option<&Verifier>,
 ) -> Result<()> {
-    println!("{}", "Fetching request details...".bright_blue().bold());
+    let bare = match sections {
+        [section] => Some(*section),
+        _ => None,
+    };
+    if bare.is_none() {
+        info!("{}", "Fetching request details...".bright_blue().bold());
+    }
 
-    let requests = client.get_requests(token, 100).await?; // Get more requests to find the specific one
+    let request = match client.cached_request(token, request_id) {
+        Some(request) => request,
+        None => client.get_request(token, request_id).await?,
+    };
 
-    let request = requests
-        .into_iter()
-        .find(|req| req.id == request_id)
-        .with_context(|| format!("Request with ID {} not found", request_id))?;
+    if let Some(section) = bare {
+        print_bare_section(&request, parse_paths, section);
+        if let Some(path) = save_body {
+            export::save_body(&request, path)?;
+        }
+        return Ok(());
+    }
+    print_request_details(&request, parse_paths, sections);
+
+    if sections.contains(&Section::Metadata) {
+        if let Some(verifier) = verifier {
+            println!();
+            println!("{}", "SIGNATURE".bright_cyan().bold());
+            println!("{}", "─".repeat(30).bright_black());
+            println!("{}", verifier.verify(&request).badge());
+        }
+
+        let tags = Tags::load()?;
+        let tags = tags.get(&request.id);
+        if !tags.is_empty() {
+            println!();
+            println!("{}", "TAGS".bright_cyan().bold());
+            println!("{}", "─".repeat(30).bright_black());
+            print_tags(tags);
+        }
+    }
+
+    if let Some(path) = save_body {
+        let path = export::save_body(&request, path)?;
+        println!();
+        println!("{} Saved body to {}", "✓".bright_green(), path.display());
+    }
 
-    print_request_details(&request, parse_paths, true);
+    Ok(())
+}
+
+/// `webhook prune`: apply the `[archive]` retention limits, or the given ones
+pub fn prune_archive(
+    config: &Config,
+    max_age: Option<Duration>,
+    max_requests_per_token: Option<usize>,
+    max_size: Option<u64>,
+) -> Result<()> {
+    let mut retention = config.archive.retention()?;
+    if max_age.is_some() {
+        retention.max_age = max_age;
+    }
+    if let Some(max_requests_per_token) = max_requests_per_token {
+        retention.max_requests_per_token = max_requests_per_token;
+    }
+    if let Some(max_size) = max_size {
+        retention.max_size = max_size;
+    }
+    if retention.is_unlimited() {
+        anyhow::bail!(
+            "No retention limits: set max_age, max_requests_per_token or max_size in [archive], or pass them as options"
+        );
+    }
+
+    let pruned = Archive::new(&config.archive)?.prune(&retention)?;
+    println!(
+        "{} Removed {} archived requests, {} kept",
+        "✓".bright_green(),
+        pruned.removed,
+        pruned.kept
+    );
+    Ok(())
+}
 
+/// `webhook tag`: add, remove or list the local tags of a request
+pub fn tag_request(
+    request_id: &str,
+    tag: Option<&str>,
+    note: Option<&str>,
+    remove: bool,
+) -> Result<()> {
+    let mut tags = Tags::load()?;
+    if remove {
+        if tags.remove(request_id, tag) == 0 {
+            match tag {
+                Some(tag) => anyhow::bail!("Request {} has no tag {}", request_id, tag),
+                None => anyhow::bail!("Request {} has no tags", request_id),
+            }
+        }
+        tags.save()?;
+        println!("{} Removed from {}", "✓".bright_green(), request_id);
+        return Ok(());
+    }
+
+    match tag {
+        Some(tag) => {
+            tags.add(request_id, tag, note);
+            tags.save()?;
+            println!(
+                "{} Tagged {} as {}",
+                "✓".bright_green(),
+                request_id,
+                tag.bright_magenta().bold()
+            );
+        }
+        None if tags.get(request_id).is_empty() => {
+            println!(
+                "{}",
+                format!("Request {} has no tags", request_id).bright_yellow()
+            );
+        }
+        None => print_tags(tags.get(request_id)),
+    }
+    Ok(())
+}
+
+pub fn run_secret_command(command: &SecretCommand) -> Result<()> {
+    match command {
+        SecretCommand::Set { name } => {
+            let (service, account) = keyring_entry(name)?;
+            let secret = read_secret(name)?;
+            if secret.is_empty() {
+                anyhow::bail!("Refusing to store an empty secret");
+            }
+            keyring_store(service, account, &secret)?;
+            println!(
+                "{} Stored '{}', use it in the config as {}",
+                "✓".bright_green(),
+                name,
+                format!("\"keyring:{}\"", name).bright_white()
+            );
+        }
+        SecretCommand::Get { name } => {
+            let (service, account) = keyring_entry(name)?;
+            println!("{}", keyring_lookup(service, account)?);
+        }
+        SecretCommand::Rm { name } => {
+            let (service, account) = keyring_entry(name)?;
+            keyring_delete(service, account)?;
+            println!("{} Removed '{}'", "✓".bright_green(), name);
+        }
+    }
+    Ok(())
+}
+
+/// Prompt without echo on a terminal, otherwise read the first line of stdin
+fn read_secret(name: &str) -> Result<String> {
+    if std::io::stdin().is_terminal() {
+        let term = console::Term::stderr();
+        term.write_str(&format!("Secret for '{}': ", name))?;
+        return Ok(term.read_secure_line()?);
+    }
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `webhook config migrate`: show the changes and, unless `dry_run`, apply them
+pub fn migrate_config(path: Option<&Path>, dry_run: bool) -> Result<()> {
+    let path =
+        Config::locate(path).context("No config file found. Run `webhook init` to create one")?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let migration = plan(&content)
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+    let Some(migration) = migration else {
+        println!(
+            "{} {} is up to date (version {})",
+            "✓".bright_green(),
+            path.display(),
+            CONFIG_VERSION
+        );
+        return Ok(());
+    };
+
+    println!(
+        "{} {} from version {} to {}",
+        if dry_run {
+            "Would migrate"
+        } else {
+            "Migrating"
+        }
+        .bright_blue()
+        .bold(),
+        path.display(),
+        migration.from,
+        CONFIG_VERSION
+    );
+    for change in &migration.changes {
+        println!("  {}", change);
+    }
+    if dry_run {
+        println!("{}", "Dry run, nothing was written".bright_yellow());
+        return Ok(());
+    }
+    let backup = migration.apply(&path)?;
+    println!(
+        "{} Migrated, the previous file was saved as {}",
+        "✓".bright_green(),
+        backup.display()
+    );
     Ok(())
 }
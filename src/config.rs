@@ -1,65 +1,759 @@
-use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+use crate::logging;
+use crate::migrate::{self, CONFIG_VERSION};
+use crate::parse::parse_duration;
+use crate::state::config_dir;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
+    /// Layout version of the file, see `migrate::CONFIG_VERSION`
+    #[serde(default)]
+    pub version: u32,
     pub webhook: WebhookConfig,
+    /// Saved tokens by alias
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, TokenConfig>,
+    /// Credentials sent with every API call
+    #[serde(default, skip_serializing_if = "AuthConfig::is_empty")]
+    pub auth: AuthConfig,
+    /// Local history of the requests fetched from the service
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    /// Values masked by `export --redact`
+    #[serde(default, skip_serializing_if = "RedactConfig::is_empty")]
+    pub redact: RedactConfig,
+    /// Alerts `monitor` sends for matching requests
+    #[serde(default, skip_serializing_if = "AlertsConfig::is_empty")]
+    pub alerts: AlertsConfig,
+    /// Chat channels `monitor` posts new requests to
+    #[serde(default, skip_serializing_if = "ChatConfig::is_empty")]
+    pub chat: ChatConfig,
+    /// OpenTelemetry collector the captured requests are exported to
+    #[serde(default, skip_serializing_if = "OtlpConfig::is_empty")]
+    pub otlp: OtlpConfig,
+    /// Custom signature schemes by name, usable as `verify` of a saved token
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signatures: BTreeMap<String, SignatureConfig>,
+    /// Named sets of `[webhook]` keys, with their own `auth` and `tokens`,
+    /// selected with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+/// Authentication for protected webhook services. Secret values may be given
+/// as `env:NAME` or `keyring:SERVICE/ACCOUNT` instead of in plain text.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+    /// Basic authentication user, used together with `password`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Extra headers, e.g. `X-Api-Key = "env:WEBHOOK_API_KEY"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl AuthConfig {
+    fn is_empty(&self) -> bool {
+        self.bearer_token.is_none()
+            && self.username.is_none()
+            && self.password.is_none()
+            && self.headers.is_empty()
+    }
+}
+
+/// Opt-in archive of every request seen by `logs` and `monitor`, kept after
+/// the service has rotated its log
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory of the archive, instead of `webhook-cli/archive` in the user data directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Requests received longer ago than this (e.g. "30d") are removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// Requests kept per token, newest first; 0 keeps all
+    #[serde(default)]
+    pub max_requests_per_token: usize,
+    /// Total size of the stored requests in bytes, the oldest are removed first; 0 is unlimited
+    #[serde(default)]
+    pub max_size: u64,
+    /// Encrypts the archive with a key derived from this passphrase; accepts
+    /// `env:NAME` and `keyring:NAME`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+}
+
+/// Masking rules of `export --redact`, applied in addition to the built-in
+/// detectors of emails, card numbers and bearer tokens
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RedactConfig {
+    /// Headers whose values are replaced, besides Authorization, Cookie and the like
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<String>,
+    /// JSON paths of body values that are replaced, e.g. "/customer/phone"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
+
+impl RedactConfig {
+    fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.paths.is_empty()
+    }
+}
+
+/// Alerts sent by `monitor` besides its terminal output
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AlertsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConfig>,
+}
+
+impl AlertsConfig {
+    fn is_empty(&self) -> bool {
+        self.email.is_none()
+    }
+}
+
+/// An email for each new request matching `match` on `tokens`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmailConfig {
+    /// SMTP server
+    pub host: String,
+    /// Defaults to 587 for "starttls", 465 for "tls" and 25 for "none"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// "starttls" (default), "tls" or "none"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security: Option<String>,
+    /// Accepts `env:NAME` and `keyring:NAME`, like `password`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Only requests whose method and path or body match this regex are emailed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    /// Tokens or aliases whose requests are emailed; all monitored tokens when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<String>,
+}
+
+/// Incoming webhook URLs of chat channels, used by `monitor` when no
+/// `--notify-slack`, `--notify-discord` or `--notify-teams` is given. URLs
+/// accept `env:NAME` and `keyring:NAME`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ChatConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teams: Option<String>,
+    /// Only requests whose method and path or body match this regex are posted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+}
+
+impl ChatConfig {
+    fn is_empty(&self) -> bool {
+        self.slack.is_none()
+            && self.discord.is_none()
+            && self.teams.is_none()
+            && self.r#match.is_none()
+    }
+}
+
+/// `[otlp]`: export of each captured request to an OpenTelemetry collector
+/// over OTLP/HTTP, used by `monitor` and `serve` unless `--otlp-endpoint` is
+/// given
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OtlpConfig {
+    /// Base URL of the collector, e.g. `http://localhost:4318`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// "traces" (default) exports spans, "logs" exports log records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+    /// `service.name` of the exported resource (default: webhook-cli)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    /// Headers sent to the collector, values accept `env:NAME` and `keyring:NAME`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl OtlpConfig {
+    fn is_empty(&self) -> bool {
+        self.endpoint.is_none()
+            && self.signal.is_none()
+            && self.service_name.is_none()
+            && self.headers.is_empty()
+    }
+}
+
+/// An HMAC signature of an in-house webhook producer
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SignatureConfig {
+    /// Header carrying the signature
+    pub header: String,
+    /// "sha256" (default), "sha1", "sha384" or "sha512"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    /// "hex" (default) or "base64"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Text before the signature in the header, e.g. "sha256="
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// What is signed: "body" (default), "timestamp+body" or "url+body"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    /// Header with the Unix timestamp, for "timestamp+body"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_header: Option<String>,
+    /// Joins the timestamp or URL to the body, e.g. "." (default: nothing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+}
+
+/// Limits applied when the archive is written and by `webhook prune`
+#[derive(Debug, Default, Clone)]
+pub struct Retention {
+    pub max_age: Option<Duration>,
+    pub max_requests_per_token: usize,
+    pub max_size: u64,
+}
+
+impl Retention {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age.is_none() && self.max_requests_per_token == 0 && self.max_size == 0
+    }
+}
+
+impl ArchiveConfig {
+    pub fn retention(&self) -> Result<Retention> {
+        Ok(Retention {
+            max_age: self
+                .max_age
+                .as_deref()
+                .map(parse_duration)
+                .transpose()
+                .map_err(|e| WebhookError::config(format!("archive.max_age: {}", e)))?,
+            max_requests_per_token: self.max_requests_per_token,
+            max_size: self.max_size,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TokenConfig {
+    pub token: String,
+    /// JSON paths shown for this token's requests when `--parse` is not given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse: Vec<String>,
+    /// Show request headers for this token, instead of `show_headers_by_default`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_headers: Option<bool>,
+    /// Show full request bodies for this token, instead of `show_full_body_by_default`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_body: Option<bool>,
+    /// Show only requests with this HTTP method when `--method` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Signature scheme checked on this token's requests: github, stripe,
+    /// slack or twilio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<String>,
+    /// Secrets of `verify`, each accepting env:NAME and keyring:NAME
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    /// URL the provider sends to, when it is not the token's URL on the
+    /// service (e.g. behind a proxy); signed by Twilio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WebhookConfig {
     pub base_url: String,
+    /// API flavor of the service at `base_url`
+    #[serde(default)]
+    pub provider: Provider,
     pub default_count: u32,
     pub default_interval: u64,
+    #[serde(default = "WebhookConfig::default_max_interval")]
+    pub max_interval: u64,
     pub show_headers_by_default: bool,
     pub show_full_body_by_default: bool,
+    /// JSON paths shown for every request when `--parse` is not given
+    #[serde(default)]
+    pub default_parse_paths: Vec<String>,
     #[serde(default = "WebhookConfig::default_body_preview_length")]
     pub body_preview_length: usize,
+    #[serde(default)]
+    pub transport: Transport,
+    /// Sound file played by `monitor --bell`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bell_sound: Option<String>,
+    /// Total time allowed for an API call, in seconds
+    #[serde(default = "WebhookConfig::default_timeout")]
+    pub timeout: u64,
+    /// Time allowed to establish a connection, in seconds
+    #[serde(default = "WebhookConfig::default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Retries for log requests that fail with a 5xx status or a connection error
+    #[serde(default = "WebhookConfig::default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for each further retry
+    #[serde(default = "WebhookConfig::default_retry_backoff")]
+    pub retry_backoff: u64,
+    /// Proxy for all API calls (http://, https://, socks5:// or socks5h://).
+    /// When unset, HTTP_PROXY/HTTPS_PROXY/ALL_PROXY from the environment are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM bundle of extra root certificates trusted for the webhook service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS, used together with `client_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// PEM (PKCS#8) private key for `client_cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// Skip TLS certificate verification
+    #[serde(default)]
+    pub insecure: bool,
+    /// Largest number of requests the service returns per call; larger counts
+    /// are fetched in several pages. 0 disables pagination.
+    #[serde(default)]
+    pub page_size: u32,
+    #[serde(default)]
+    pub pagination: Pagination,
+    /// Bodies longer than this many bytes are truncated when a log is read,
+    /// except by commands that need them whole; 0 (the default) keeps them whole
+    #[serde(default)]
+    pub max_body_size: usize,
+    /// Seconds a fetched log is reused by `logs` and `show`; 0 disables the cache
+    #[serde(default = "WebhookConfig::default_cache_ttl")]
+    pub cache_ttl: u64,
+    /// Report API calls instead of sending them (`--dry-run`); never read
+    /// from the file
+    #[serde(skip)]
+    pub dry_run: bool,
+}
+
+/// How `monitor` receives new requests
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// Use the server's event stream when available, otherwise poll
+    #[default]
+    Auto,
+    /// Require the server's event stream (Server-Sent Events)
+    Sse,
+    /// Always poll the request log
+    Poll,
+}
+
+/// Webhook collection services with a supported API
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// `GET <base_url>/<token>/log/<count>`
+    #[default]
+    Default,
+    /// RequestBin-compatible services (`/api/v1/bins/<bin>/requests`)
+    Requestbin,
+    /// webhook.site (`/token/<token>/requests`)
+    #[serde(rename = "webhook.site", alias = "webhooksite")]
+    WebhookSite,
+    /// smee.io channels, streamed only (`/<channel>`)
+    Smee,
+}
+
+/// How pages after the first are requested from the log endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pagination {
+    /// `?offset=<requests fetched so far>`
+    #[default]
+    Offset,
+    /// `?before=<ID of the oldest request fetched so far>`
+    Cursor,
 }
 
 impl WebhookConfig {
     fn default_body_preview_length() -> usize {
         80
     }
+
+    fn default_max_interval() -> u64 {
+        60
+    }
+
+    fn default_timeout() -> u64 {
+        30
+    }
+
+    fn default_connect_timeout() -> u64 {
+        10
+    }
+
+    fn default_retries() -> u32 {
+        2
+    }
+
+    fn default_retry_backoff() -> u64 {
+        500
+    }
+
+    fn default_cache_ttl() -> u64 {
+        10
+    }
 }
 
-impl Config {
-    pub fn load() -> Result<Self> {
-        // Try to load from local config first, then fall back to default config
-        let config_paths = ["config.local.toml", "config.toml"];
+/// `[webhook]` keys, which can also be set with `WEBHOOK_<KEY>` environment variables
+pub const WEBHOOK_KEYS: [&str; 24] = [
+    "base_url",
+    "provider",
+    "default_count",
+    "default_interval",
+    "max_interval",
+    "show_headers_by_default",
+    "show_full_body_by_default",
+    "default_parse_paths",
+    "body_preview_length",
+    "transport",
+    "bell_sound",
+    "timeout",
+    "connect_timeout",
+    "retries",
+    "retry_backoff",
+    "proxy",
+    "ca_cert",
+    "client_cert",
+    "client_key",
+    "insecure",
+    "page_size",
+    "pagination",
+    "max_body_size",
+    "cache_ttl",
+];
 
-        for path in config_paths {
-            if Path::new(path).exists() {
-                let content = fs::read_to_string(path)
-                    .with_context(|| format!("Failed to read config file: {}", path))?;
+/// `[auth]` keys that can be set with `WEBHOOK_AUTH_<KEY>` environment variables
+pub const AUTH_ENV_KEYS: [&str; 3] = ["bearer_token", "username", "password"];
 
-                let config: Config = toml::from_str(&content)
-                    .with_context(|| format!("Failed to parse config file: {}", path))?;
+/// `[webhook]` keys without a default value
+pub const REQUIRED_WEBHOOK_KEYS: [&str; 5] = [
+    "base_url",
+    "default_count",
+    "default_interval",
+    "show_headers_by_default",
+    "show_full_body_by_default",
+];
 
-                return Ok(config);
-            }
-        }
+/// `[archive]` keys, which can also be set with `WEBHOOK_ARCHIVE_<KEY>` environment variables
+pub const ARCHIVE_KEYS: [&str; 6] = [
+    "enabled",
+    "path",
+    "max_age",
+    "max_requests_per_token",
+    "max_size",
+    "passphrase",
+];
+
+/// `[redact]` keys
+pub const REDACT_KEYS: [&str; 2] = ["headers", "paths"];
+
+/// `[alerts.email]` keys
+pub const EMAIL_KEYS: [&str; 9] = [
+    "host", "port", "security", "username", "password", "from", "to", "match", "tokens",
+];
+
+/// `[chat]` keys
+pub const CHAT_KEYS: [&str; 4] = ["slack", "discord", "teams", "match"];
+
+/// `[otlp]` keys
+pub const OTLP_KEYS: [&str; 4] = ["endpoint", "signal", "service_name", "headers"];
 
-        // If no config file exists, create a default one and return default values
-        let default_config = Config {
+/// `[signatures.<name>]` keys
+pub const SIGNATURE_KEYS: [&str; 7] = [
+    "header",
+    "algorithm",
+    "encoding",
+    "prefix",
+    "payload",
+    "timestamp_header",
+    "separator",
+];
+
+/// `[tokens.<alias>]` keys
+pub const TOKEN_KEYS: [&str; 8] = [
+    "token",
+    "parse",
+    "show_headers",
+    "full_body",
+    "method",
+    "verify",
+    "secrets",
+    "url",
+];
+
+/// `base_url` of the built-in defaults and the config template
+const PLACEHOLDER_BASE_URL: &str = "https://your-webhook-service.com";
+
+/// `webhook-cli/config.toml` in the platform config directory
+pub fn user_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("webhook-cli").join("config.toml"))
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
             webhook: WebhookConfig {
-                base_url: "https://your-webhook-service.com".to_string(),
+                base_url: PLACEHOLDER_BASE_URL.to_string(),
+                provider: Provider::default(),
                 default_count: 10,
                 default_interval: 3,
+                max_interval: WebhookConfig::default_max_interval(),
                 show_headers_by_default: false,
                 show_full_body_by_default: false,
+                default_parse_paths: Vec::new(),
                 body_preview_length: WebhookConfig::default_body_preview_length(),
+                transport: Transport::default(),
+                bell_sound: None,
+                timeout: WebhookConfig::default_timeout(),
+                connect_timeout: WebhookConfig::default_connect_timeout(),
+                retries: WebhookConfig::default_retries(),
+                retry_backoff: WebhookConfig::default_retry_backoff(),
+                proxy: None,
+                ca_cert: None,
+                client_cert: None,
+                client_key: None,
+                insecure: false,
+                page_size: 0,
+                pagination: Pagination::default(),
+                max_body_size: 0,
+                cache_ttl: WebhookConfig::default_cache_ttl(),
+                dry_run: false,
             },
-        };
+            tokens: BTreeMap::new(),
+            auth: AuthConfig::default(),
+            archive: ArchiveConfig::default(),
+            redact: RedactConfig::default(),
+            alerts: AlertsConfig::default(),
+            chat: ChatConfig::default(),
+            otlp: OtlpConfig::default(),
+            signatures: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load `path` when given, otherwise the file found by `locate`
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match Self::locate(path) {
+            Some(path) => Self::load_file(&path),
+            None => {
+                // Without a config file the placeholder service is used until `webhook init` is run
+                logging::debug(1, format_args!("No config file found, using defaults"));
+                Ok(Config::default())
+            }
+        }
+    }
+
+    /// The config file to use: `path` when given, otherwise the first existing
+    /// file of `./config.local.toml`, the user config file and `./config.toml`
+    pub fn locate(path: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = path {
+            return Some(path.to_path_buf());
+        }
+
+        let config_paths = [
+            Some(PathBuf::from("config.local.toml")),
+            user_config_path(),
+            // Working directory config of earlier versions
+            Some(PathBuf::from("config.toml")),
+        ];
+        config_paths
+            .into_iter()
+            .flatten()
+            .find(|path| path.exists())
+    }
+
+    /// Apply the keys of a `[profiles.<name>]` section: `auth` replaces the
+    /// credentials, `tokens` adds saved tokens and other keys override `[webhook]`
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            WebhookError::config(if available.is_empty() {
+                format!("Unknown profile '{}': no profiles are configured", name)
+            } else {
+                format!(
+                    "Unknown profile '{}' (available: {})",
+                    name,
+                    available.join(", ")
+                )
+            })
+        })?;
+        let mut config = toml::Table::try_from(&*self)
+            .config_err(|| "Failed to serialize config".to_string())?;
+
+        for (key, value) in profile {
+            let section = match key.as_str() {
+                "auth" => {
+                    config.insert(key, value);
+                    continue;
+                }
+                "tokens" => "tokens",
+                _ => "webhook",
+            };
+            let toml::Value::Table(table) = config
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            else {
+                continue;
+            };
+            match (section, value) {
+                ("tokens", toml::Value::Table(tokens)) => table.extend(tokens),
+                (_, value) => {
+                    table.insert(key, value);
+                }
+            }
+        }
+
+        *self = config
+            .try_into()
+            .config_err(|| format!("Invalid profile '{}'", name))?;
+        logging::debug(1, format_args!("Using profile {}", name));
+        Ok(())
+    }
+
+    /// Override keys from `WEBHOOK_<KEY>` environment variables (`WEBHOOK_AUTH_<KEY>`
+    /// for `[auth]`, `WEBHOOK_ARCHIVE_<KEY>` for `[archive]`), e.g. `WEBHOOK_BASE_URL` or `WEBHOOK_DEFAULT_COUNT`
+    pub fn apply_env(&mut self) -> Result<()> {
+        let mut config = toml::Table::try_from(&*self)
+            .config_err(|| "Failed to serialize config".to_string())?;
+        let mut changed = false;
+
+        for (section, prefix, keys) in [
+            ("webhook", "WEBHOOK_", &WEBHOOK_KEYS[..]),
+            ("auth", "WEBHOOK_AUTH_", &AUTH_ENV_KEYS[..]),
+            ("archive", "WEBHOOK_ARCHIVE_", &ARCHIVE_KEYS[..]),
+        ] {
+            let toml::Value::Table(table) = config
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            else {
+                continue;
+            };
+            for key in keys {
+                let name = format!("{}{}", prefix, key.to_uppercase());
+                let Ok(value) = std::env::var(&name) else {
+                    continue;
+                };
+                // Keep the type of the current value; unset keys are strings
+                let value = match table.get(*key) {
+                    Some(toml::Value::Integer(_)) => toml::Value::Integer(
+                        value
+                            .trim()
+                            .parse()
+                            .config_err(|| format!("{} must be a number", name))?,
+                    ),
+                    Some(toml::Value::Boolean(_)) => {
+                        toml::Value::Boolean(parse_bool(&value).ok_or_else(|| {
+                            WebhookError::config(format!("{} must be true or false", name))
+                        })?)
+                    }
+                    // Comma-separated list
+                    Some(toml::Value::Array(_)) => toml::Value::Array(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|item| !item.is_empty())
+                            .map(|item| toml::Value::String(item.to_string()))
+                            .collect(),
+                    ),
+                    _ => toml::Value::String(value),
+                };
+                logging::debug(1, format_args!("Using {} from the environment", name));
+                table.insert(key.to_string(), value);
+                changed = true;
+            }
+        }
+
+        if changed {
+            *self = config
+                .try_into()
+                .config_err(|| "Invalid configuration from environment variables".to_string())?;
+        }
+        Ok(())
+    }
 
-        // Create the default config file
-        let default_content = toml::to_string_pretty(&default_config)
-            .context("Failed to serialize default config")?;
-        fs::write("config.toml", default_content).context("Failed to write default config file")?;
+    /// Write the configuration to `path`, creating its directory
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content =
+            toml::to_string_pretty(self).config_err(|| "Failed to serialize config".to_string())?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .io_err(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, content)
+            .io_err(|| format!("Failed to write config file: {}", path.display()))
+    }
+
+    /// Whether `base_url` still points at the placeholder service
+    pub fn is_placeholder(&self) -> bool {
+        self.webhook.base_url == PLACEHOLDER_BASE_URL
+    }
 
-        Ok(default_config)
+    fn load_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .io_err(|| format!("Failed to read config file: {}", path.display()))?;
+        let content = match migrate::plan(&content)
+            .config_err(|| format!("Failed to parse config file: {}", path.display()))?
+        {
+            Some(migration) => {
+                // An unwritable file is still migrated in memory
+                match migration.apply(path) {
+                    Ok(backup) => logging::warn(format_args!(
+                        "Migrated {} to version {}, the previous file was saved as {}",
+                        path.display(),
+                        CONFIG_VERSION,
+                        backup.display()
+                    )),
+                    Err(e) => {
+                        logging::warn(format_args!("Could not migrate the config file: {:#}", e))
+                    }
+                }
+                migration.content
+            }
+            None => content,
+        };
+        let config: Config = toml::from_str(&content)
+            .config_err(|| format!("Failed to parse config file: {}", path.display()))?;
+        logging::debug(1, format_args!("Using config {}", path.display()));
+        Ok(config)
     }
 
     /// Normalize a base URL by removing trailing slash
@@ -82,11 +776,155 @@ impl Config {
         url
     }
 
+    /// Resolve a saved alias to its token; anything else is taken as a token
+    pub fn resolve_token<'a>(&'a self, name: &'a str) -> &'a str {
+        self.tokens
+            .get(name)
+            .map(|saved| saved.token.as_str())
+            .unwrap_or(name)
+    }
+
+    /// Settings saved for a token, looked up by alias or by the token itself
+    pub fn token_settings(&self, name: &str) -> Option<&TokenConfig> {
+        self.tokens
+            .get(name)
+            .or_else(|| self.tokens.values().find(|saved| saved.token == name))
+    }
+
+    /// `paths` given on the command line, otherwise those saved for the token,
+    /// otherwise `default_parse_paths`
+    pub fn parse_paths_for(&self, name: &str, paths: &[String]) -> Vec<String> {
+        [
+            paths,
+            self.token_settings(name)
+                .map_or(&[][..], |saved| &saved.parse),
+            &self.webhook.default_parse_paths,
+        ]
+        .into_iter()
+        .find(|paths| !paths.is_empty())
+        .unwrap_or_default()
+        .to_vec()
+    }
+
+    /// `method` given on the command line, otherwise the one saved for the token
+    pub fn method_for(&self, name: &str, method: Option<&str>) -> Option<String> {
+        method
+            .or_else(|| self.token_settings(name)?.method.as_deref())
+            .map(str::to_string)
+    }
+
+    /// `--show-headers` when given, otherwise the token's or the global default
+    pub fn show_headers_for(&self, name: &str, show_headers: Option<bool>) -> bool {
+        show_headers
+            .or_else(|| self.token_settings(name)?.show_headers)
+            .unwrap_or(self.webhook.show_headers_by_default)
+    }
+
+    /// `--full-body` when given, otherwise the token's or the global default
+    pub fn full_body_for(&self, name: &str, full_body: Option<bool>) -> bool {
+        full_body
+            .or_else(|| self.token_settings(name)?.full_body)
+            .unwrap_or(self.webhook.show_full_body_by_default)
+    }
+
+    /// Saved tokens as (alias, token) pairs
+    pub fn saved_tokens(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tokens
+            .iter()
+            .map(|(alias, saved)| (alias.as_str(), saved.token.as_str()))
+    }
+
     pub fn get_base_url(&self) -> &str {
         &self.webhook.base_url
     }
 
+    pub fn get_provider(&self) -> Provider {
+        self.webhook.provider
+    }
+
     pub fn get_body_preview_length(&self) -> usize {
         self.webhook.body_preview_length
     }
+
+    pub fn get_max_interval(&self) -> u64 {
+        self.webhook.max_interval
+    }
+
+    pub fn get_bell_sound(&self) -> Option<&str> {
+        self.webhook.bell_sound.as_deref()
+    }
+
+    pub fn get_transport(&self) -> Transport {
+        self.webhook.transport
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_secs(self.webhook.timeout)
+    }
+
+    pub fn get_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.webhook.connect_timeout)
+    }
+
+    pub fn get_retries(&self) -> u32 {
+        self.webhook.retries
+    }
+
+    pub fn get_retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.webhook.retry_backoff)
+    }
+
+    pub fn get_proxy(&self) -> Option<&str> {
+        self.webhook.proxy.as_deref()
+    }
+
+    pub fn get_ca_cert(&self) -> Option<&Path> {
+        self.webhook.ca_cert.as_deref()
+    }
+
+    /// Client certificate and key paths for mutual TLS
+    pub fn get_client_identity(&self) -> Result<Option<(&Path, &Path)>> {
+        match (&self.webhook.client_cert, &self.webhook.client_key) {
+            (Some(cert), Some(key)) => Ok(Some((cert, key))),
+            (None, None) => Ok(None),
+            _ => Err(WebhookError::config(
+                "client_cert and client_key must be set together",
+            )),
+        }
+    }
+
+    pub fn is_insecure(&self) -> bool {
+        self.webhook.insecure
+    }
+
+    pub fn get_page_size(&self) -> u32 {
+        self.webhook.page_size
+    }
+
+    pub fn get_pagination(&self) -> Pagination {
+        self.webhook.pagination
+    }
+
+    pub fn get_max_body_size(&self) -> usize {
+        match self.webhook.max_body_size {
+            0 => usize::MAX,
+            size => size,
+        }
+    }
+
+    pub fn get_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.webhook.cache_ttl)
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.webhook.dry_run
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
 }
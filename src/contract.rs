@@ -0,0 +1,346 @@
+use anyhow::{Context, Result};
+use colored::{ColoredString, Colorize};
+use std::fs;
+use std::path::Path;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::fuzz::collect_pointers;
+use crate::models::WebhookRequest;
+use crate::rules::{glob_match, scalar};
+
+/// Expected webhook traffic, checked by `webhook verify --contract`
+#[derive(Debug)]
+pub struct Contract {
+    pub name: Option<String>,
+    /// Whether the events must arrive in the listed order
+    pub ordered: bool,
+    pub events: Vec<Event>,
+}
+
+/// An expected event: the requests it `matches`, and what their JSON body
+/// must look like
+#[derive(Debug)]
+pub struct Event {
+    pub name: String,
+    method: Option<String>,
+    /// Glob where `*` matches any characters
+    path: Option<String>,
+    /// JSON pointer → glob the value must match, e.g. `/type: invoice.*`
+    fields: Vec<(String, String)>,
+    /// JSON pointers that must be present
+    required: Vec<String>,
+    /// JSON pointers that may be present when `extra_fields` is false
+    optional: Vec<String>,
+    extra_fields: bool,
+}
+
+/// Load a contract: `events` (each with a `name`, `match` of `method`, `path`
+/// and `json` values, `required` and `optional` fields and `extra_fields`),
+/// with optional `name` and `ordered`
+pub fn load_contract(path: &Path) -> Result<Contract> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read contract file: {}", path.display()))?;
+    let documents = YamlLoader::load_from_str(&content)
+        .with_context(|| format!("Failed to parse contract file: {}", path.display()))?;
+    let document = documents.first().unwrap_or(&Yaml::Null);
+    let Yaml::Array(events) = &document["events"] else {
+        anyhow::bail!("{}: expected a list of `events`", path.display());
+    };
+    let ordered = match &document["ordered"] {
+        Yaml::BadValue => true,
+        value => value
+            .as_bool()
+            .with_context(|| format!("{}: `ordered` must be true or false", path.display()))?,
+    };
+
+    Ok(Contract {
+        name: scalar(&document["name"]),
+        ordered,
+        events: events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| {
+                parse_event(event)
+                    .with_context(|| format!("{}: event {}", path.display(), index + 1))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn parse_event(item: &Yaml) -> Result<Event> {
+    let Yaml::Hash(map) = item else {
+        anyhow::bail!("expected a mapping");
+    };
+    for key in map.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !matches!(
+            key,
+            "name" | "match" | "required" | "optional" | "extra_fields"
+        ) {
+            anyhow::bail!("unknown key `{}`", key);
+        }
+    }
+
+    let criteria = &item["match"];
+    if let Yaml::Hash(map) = criteria {
+        for key in map.keys() {
+            let key = key.as_str().unwrap_or_default();
+            if !matches!(key, "method" | "path" | "json") {
+                anyhow::bail!("unknown key `match.{}`", key);
+            }
+        }
+    }
+    let mut fields = Vec::new();
+    match &criteria["json"] {
+        Yaml::BadValue => {}
+        Yaml::Hash(map) => {
+            for (pointer, value) in map {
+                let (Some(pointer), Some(value)) = (scalar(pointer), scalar(value)) else {
+                    anyhow::bail!("`match.json` must map JSON pointers to values");
+                };
+                fields.push((pointer, value));
+            }
+        }
+        _ => anyhow::bail!("`match.json` must be a mapping"),
+    }
+    let pointers = |key: &str| -> Result<Vec<String>> {
+        match &item[key] {
+            Yaml::BadValue => Ok(Vec::new()),
+            Yaml::Array(items) => items
+                .iter()
+                .map(|pointer| {
+                    scalar(pointer)
+                        .filter(|pointer| pointer.starts_with('/'))
+                        .with_context(|| format!("`{}` must list JSON pointers", key))
+                })
+                .collect(),
+            _ => anyhow::bail!("`{}` must be a list", key),
+        }
+    };
+    let extra_fields = match &item["extra_fields"] {
+        Yaml::BadValue => true,
+        value => value
+            .as_bool()
+            .context("`extra_fields` must be true or false")?,
+    };
+
+    let method = scalar(&criteria["method"]).map(|method| method.to_uppercase());
+    let path = scalar(&criteria["path"]);
+    let name = match scalar(&item["name"]) {
+        Some(name) => name,
+        None => fields
+            .first()
+            .map(|(_, value)| value.clone())
+            .or_else(|| path.clone())
+            .context("`name` is required")?,
+    };
+    Ok(Event {
+        name,
+        method,
+        path,
+        fields,
+        required: pointers("required")?,
+        optional: pointers("optional")?,
+        extra_fields,
+    })
+}
+
+impl Event {
+    fn matches(&self, request: &WebhookRequest, json: Option<&serde_json::Value>) -> bool {
+        let message = &request.message_object;
+        let path = message.value.split('?').next().unwrap_or("");
+        self.method
+            .as_ref()
+            .is_none_or(|method| message.method.is(method))
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, path))
+            && self.fields.iter().all(|(pointer, pattern)| {
+                json.and_then(|json| json.pointer(pointer))
+                    .is_some_and(|value| glob_match(pattern, &value_text(value)))
+            })
+    }
+
+    /// Required fields missing from `json`
+    fn missing(&self, json: Option<&serde_json::Value>) -> Vec<&str> {
+        self.required
+            .iter()
+            .filter(|pointer| json.and_then(|json| json.pointer(pointer)).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Fields of `json` that are neither required nor optional, when extra
+    /// fields are not allowed. Parents and children of listed fields count
+    /// as listed.
+    fn extra(&self, json: Option<&serde_json::Value>) -> Vec<String> {
+        let Some(json) = json.filter(|_| !self.extra_fields) else {
+            return Vec::new();
+        };
+        let mut pointers = Vec::new();
+        collect_pointers(json, String::new(), &mut pointers);
+        let listed: Vec<&String> = self.required.iter().chain(&self.optional).collect();
+        pointers
+            .into_iter()
+            .filter(|pointer| {
+                !listed.iter().any(|allowed| {
+                    pointer == *allowed
+                        || pointer.starts_with(&format!("{}/", allowed))
+                        || allowed.starts_with(&format!("{}/", pointer))
+                })
+            })
+            .collect()
+    }
+}
+
+fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// The outcome of one event of the contract
+struct Check<'a> {
+    event: &'a Event,
+    request: Option<&'a WebhookRequest>,
+    /// Whether a matching request arrived after the previous event's
+    in_order: bool,
+    missing: Vec<&'a str>,
+    extra: Vec<String>,
+}
+
+impl Check<'_> {
+    fn passed(&self) -> bool {
+        self.request.is_some() && self.in_order && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// `webhook verify --contract`: check `requests` against the contract and
+/// print a pass/fail matrix, one row per event
+pub fn verify_contract(contract: &Contract, requests: &[WebhookRequest]) -> Result<()> {
+    let mut requests: Vec<&WebhookRequest> = requests.iter().collect();
+    requests.sort_by_key(|request| request.date);
+    let bodies: Vec<Option<serde_json::Value>> = requests
+        .iter()
+        .map(|request| {
+            request
+                .body
+                .as_deref()
+                .and_then(|body| serde_json::from_str(body).ok())
+        })
+        .collect();
+
+    let mut checks = Vec::new();
+    let mut position = 0;
+    for event in &contract.events {
+        let matching = |start: usize| {
+            (start..requests.len())
+                .find(|&index| event.matches(requests[index], bodies[index].as_ref()))
+        };
+        let (index, in_order) = if contract.ordered {
+            match matching(position) {
+                Some(index) => (Some(index), true),
+                // Arrived, but before the previous event
+                None => (matching(0), false),
+            }
+        } else {
+            (matching(0), true)
+        };
+        if let Some(index) = index.filter(|_| in_order) {
+            position = index + 1;
+        }
+        let json = index.and_then(|index| bodies[index].as_ref());
+        checks.push(Check {
+            event,
+            request: index.map(|index| requests[index]),
+            in_order,
+            missing: index.map(|_| event.missing(json)).unwrap_or_default(),
+            extra: index.map(|_| event.extra(json)).unwrap_or_default(),
+        });
+    }
+
+    if let Some(name) = &contract.name {
+        println!(
+            "{} {}",
+            "Contract:".bright_blue().bold(),
+            name.bright_white()
+        );
+    }
+    println!(
+        "Checked {} requests against {} events",
+        requests.len(),
+        contract.events.len()
+    );
+    println!();
+    let width = checks
+        .iter()
+        .map(|check| check.event.name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(5);
+    println!(
+        "{:<width$}  RECEIVED  ORDER  REQUIRED  EXTRA",
+        "EVENT",
+        width = width
+    );
+    for check in &checks {
+        let received = check.request.is_some();
+        println!(
+            "{:<width$}  {}  {}  {}  {}",
+            check.event.name,
+            mark(received, 8),
+            mark_if(received && contract.ordered, check.in_order, 5),
+            mark_if(received, check.missing.is_empty(), 8),
+            mark_if(
+                received && !check.event.extra_fields,
+                check.extra.is_empty(),
+                0
+            ),
+            width = width
+        );
+        if let Some(request) = check.request {
+            println!("  {} {}", "↳".bright_black(), request.id.bright_black());
+        }
+        for pointer in &check.missing {
+            println!("  {} missing {}", "✗".bright_red(), pointer);
+        }
+        for pointer in &check.extra {
+            println!("  {} unexpected {}", "✗".bright_red(), pointer);
+        }
+    }
+    println!();
+
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} events do not meet the contract",
+            failed,
+            checks.len()
+        );
+    }
+    println!(
+        "{} All {} events meet the contract",
+        "✓".bright_green(),
+        checks.len()
+    );
+    Ok(())
+}
+
+fn mark(passed: bool, width: usize) -> ColoredString {
+    if passed {
+        format!("{:<width$}", "✓", width = width).bright_green()
+    } else {
+        format!("{:<width$}", "✗", width = width).bright_red()
+    }
+}
+
+/// A mark for a check that only applies when `applies`, else a dash
+fn mark_if(applies: bool, passed: bool, width: usize) -> ColoredString {
+    if applies {
+        mark(passed, width)
+    } else {
+        format!("{:<width$}", "-", width = width).bright_black()
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use crate::models::WebhookRequest;
+
+/// Strategy used to decide whether two requests carry the same payload
+#[derive(Debug, Clone)]
+pub enum UniqueBy {
+    /// Hash of method, path and body
+    Payload,
+    /// Value found at a JSON path in the body (e.g. an idempotency key)
+    JsonPath(String),
+}
+
+impl UniqueBy {
+    /// Build the strategy from the `--unique` / `--unique-by` flags
+    pub fn from_args(unique: bool, unique_by: Option<String>) -> Option<Self> {
+        match unique_by {
+            Some(path) => Some(UniqueBy::JsonPath(path)),
+            None if unique => Some(UniqueBy::Payload),
+            None => None,
+        }
+    }
+
+    /// Compute the de-duplication key for a request
+    pub fn key(&self, request: &WebhookRequest) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        if let UniqueBy::JsonPath(path) = self
+            && let Some(value) = request
+                .body
+                .as_deref()
+                .and_then(|body| serde_json::from_str::<serde_json::Value>(body).ok())
+                .and_then(|json| json.pointer(path).cloned())
+        {
+            value.to_string().hash(&mut hasher);
+            return hasher.finish();
+        }
+
+        // Fall back to the payload itself when the key path is missing
+        request.message_object.method.as_str().hash(&mut hasher);
+        request.message_object.value.hash(&mut hasher);
+        request.body.as_deref().unwrap_or("").hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Collapse duplicate requests, keeping the first occurrence of each key
+/// together with the number of deliveries it stands for
+pub fn collapse(
+    requests: Vec<WebhookRequest>,
+    unique_by: &UniqueBy,
+) -> Vec<(WebhookRequest, usize)> {
+    let mut collapsed: Vec<(WebhookRequest, usize)> = Vec::new();
+    let mut positions: HashMap<u64, usize> = HashMap::new();
+
+    for request in requests {
+        let key = unique_by.key(&request);
+        match positions.get(&key) {
+            Some(&index) => collapsed[index].1 += 1,
+            None => {
+                positions.insert(key, collapsed.len());
+                collapsed.push((request, 1));
+            }
+        }
+    }
+
+    collapsed
+}
+
+/// Headers in which senders repeat the ID of a delivery when they retry it
+const DELIVERY_HEADERS: &[&str] = &[
+    "X-GitHub-Delivery",
+    "Webhook-Id",
+    "X-Shopify-Webhook-Id",
+    "Idempotency-Key",
+    "X-Idempotency-Key",
+];
+
+/// Delivery IDs remembered by `Deliveries`, so long monitor sessions use
+/// constant memory
+const DELIVERIES_CAPACITY: usize = 10_000;
+
+/// Where a request carries the ID its sender keeps across retries
+#[derive(Debug, Clone, Default)]
+pub enum DeliveryId {
+    /// The first of `DELIVERY_HEADERS` present, or for Stripe (whose
+    /// `Stripe-Signature` changes with every attempt) the event `id` in the body
+    #[default]
+    Auto,
+    /// A header given by name
+    Header(String),
+    /// A value at a JSON path in the body
+    JsonPath(String),
+}
+
+impl DeliveryId {
+    /// A JSON path when `value` starts with '/', otherwise a header name;
+    /// "auto" for the built-in rules
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "auto" => DeliveryId::Auto,
+            path if path.starts_with('/') => DeliveryId::JsonPath(path.to_string()),
+            header => DeliveryId::Header(header.to_string()),
+        }
+    }
+
+    /// The delivery ID of `request`, if it has one
+    pub fn of(&self, request: &WebhookRequest) -> Option<String> {
+        match self {
+            DeliveryId::Auto => DELIVERY_HEADERS
+                .iter()
+                .find_map(|name| request.header(name))
+                .map(str::to_string)
+                .or_else(|| {
+                    request.header("Stripe-Signature")?;
+                    json_value(request, "/id")
+                }),
+            DeliveryId::Header(name) => request.header(name).map(str::to_string),
+            DeliveryId::JsonPath(path) => json_value(request, path),
+        }
+        .filter(|id| !id.is_empty())
+    }
+}
+
+fn json_value(request: &WebhookRequest, path: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(request.body.as_deref()?).ok()?;
+    match json.pointer(path)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Counts the deliveries of each delivery ID, to tell retries from new events
+pub struct Deliveries {
+    id: DeliveryId,
+    attempts: HashMap<String, usize>,
+    order: VecDeque<String>,
+}
+
+impl Deliveries {
+    pub fn new(id: DeliveryId) -> Self {
+        Self {
+            id,
+            attempts: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Record a request, in arrival order, and return which attempt at its
+    /// delivery it is: 1 for the first, and for requests without an ID
+    pub fn record(&mut self, request: &WebhookRequest) -> usize {
+        let Some(id) = self.id.of(request) else {
+            return 1;
+        };
+        if let Some(attempts) = self.attempts.get_mut(&id) {
+            *attempts += 1;
+            return *attempts;
+        }
+        self.order.push_back(id.clone());
+        self.attempts.insert(id, 1);
+        if self.order.len() > DELIVERIES_CAPACITY
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.attempts.remove(&oldest);
+        }
+        1
+    }
+}
+
+/// Payload keys remembered by `Payloads`, so long monitor sessions use
+/// constant memory
+const PAYLOADS_CAPACITY: usize = 10_000;
+
+/// The first request seen with each payload key, for the latest keys
+#[derive(Default)]
+pub struct Payloads {
+    first_ids: HashMap<u64, String>,
+    order: VecDeque<u64>,
+}
+
+impl Payloads {
+    /// Record a request's payload key; the ID of the earlier request with
+    /// the same key, if any
+    pub fn record(&mut self, key: u64, request_id: &str) -> Option<String> {
+        if let Some(first_id) = self.first_ids.get(&key) {
+            return Some(first_id.clone());
+        }
+        self.first_ids.insert(key, request_id.to_string());
+        self.order.push_back(key);
+        if self.order.len() > PAYLOADS_CAPACITY
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.first_ids.remove(&oldest);
+        }
+        None
+    }
+}
+
+/// Set of request IDs that only remembers the most recent `capacity` entries,
+/// so long monitor sessions use constant memory. Polls never return more than
+/// `--count` requests, so older IDs cannot show up again.
+pub struct RecentIds {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+    capacity: usize,
+}
+
+impl RecentIds {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            order: VecDeque::with_capacity(capacity),
+            ids: HashSet::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    pub fn insert(&mut self, id: String) {
+        if !self.ids.insert(id.clone()) {
+            return;
+        }
+        self.order.push_back(id);
+        if self.order.len() > self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+    }
+}
@@ -1,22 +1,37 @@
-use chrono::{DateTime, Local};
-use colored::Colorize;
+use chrono::{DateTime, Local, Utc};
+use colored::{Color, Colorize};
+use std::time::Duration;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
 
-use crate::models::WebhookRequest;
+use crate::cli::Section;
+use crate::color_control;
+use crate::models::{WebhookRequest, extract_path};
 
 pub fn print_request_summary(
     request: &WebhookRequest,
     show_body_preview: bool,
     body_preview_length: usize,
+    highlight: Option<Color>,
 ) {
-    let time = format_date(&request.date);
-    let method = format_method(&request.message_object.method);
+    let time = format_date(request.date);
+    let method = format_method(request.message_object.method.as_str());
     let path = extract_path(&request.message_object.value, &request.token_id);
 
-    if show_body_preview {
+    if let Some(color) = highlight {
+        // Paint the whole line so matching requests stand out
+        let mut line = format!(
+            "{} {} {} ({})",
+            time, request.message_object.method, path, request.id
+        );
+        if show_body_preview {
+            line.push(' ');
+            line.push_str(&get_body_preview(&request.body, body_preview_length));
+        }
+        println!("{}", line.color(color).bold());
+    } else if show_body_preview {
         println!(
             "{} {} {} {} {}",
             time.bright_black(),
@@ -149,11 +164,80 @@ pub fn print_full_request_body(request: &WebhookRequest, parse_paths: &[String],
     }
 }
 
-pub fn print_request_details(request: &WebhookRequest, parse_paths: &[String], _full_body: bool) {
+/// Print the chosen sections of a request, each under its heading
+pub fn print_request_details(
+    request: &WebhookRequest,
+    parse_paths: &[String],
+    sections: &[Section],
+) {
+    if sections.contains(&Section::Metadata) {
+        print_request_metadata(request);
+    }
+    if sections.contains(&Section::Headers) {
+        println!("{}", "HEADERS".bright_cyan().bold());
+        println!("{}", "─".repeat(30).bright_black());
+        for (key, values) in &request.message_object.headers {
+            for value in values {
+                println!("{}: {}", key.bright_blue(), value.bright_white());
+            }
+        }
+        println!();
+    }
+    if sections.contains(&Section::Query) && !request.message_object.query_parameters.is_empty() {
+        println!("{}", "QUERY PARAMETERS".bright_cyan().bold());
+        println!("{}", "─".repeat(30).bright_black());
+        for param in &request.message_object.query_parameters {
+            println!("{}", param.bright_white());
+        }
+        println!();
+    }
+    if sections.contains(&Section::Body) {
+        print_request_body(request, parse_paths);
+    }
+}
+
+/// Print a single section without heading or colors, for scripts: headers
+/// as `Name: value` lines, the body as received (or the `parse_paths`
+/// values, one compact JSON value per line)
+pub fn print_bare_section(request: &WebhookRequest, parse_paths: &[String], section: Section) {
+    match section {
+        Section::Metadata => {
+            println!("id: {}", request.id);
+            println!("token: {}", request.token_id);
+            println!("date: {}", request.date_string());
+            println!("method: {}", request.message_object.method);
+            println!("path: {}", request.message_object.value);
+        }
+        Section::Headers => {
+            for (key, values) in &request.message_object.headers {
+                for value in values {
+                    println!("{}: {}", key, value);
+                }
+            }
+        }
+        Section::Query => {
+            for param in &request.message_object.query_parameters {
+                println!("{}", param);
+            }
+        }
+        Section::Body => {
+            let body = request.body.as_deref().unwrap_or_default();
+            if parse_paths.is_empty() {
+                print!("{}", body);
+                return;
+            }
+            let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+            for path in parse_paths {
+                println!("{}", json.pointer(path).unwrap_or(&serde_json::Value::Null));
+            }
+        }
+    }
+}
+
+fn print_request_metadata(request: &WebhookRequest) {
     println!("{}", "REQUEST DETAILS".bright_green().bold());
     println!("{}", "═".repeat(50).bright_black());
 
-    // Basic info
     println!(
         "{}: {}",
         "ID".bright_blue().bold(),
@@ -167,12 +251,12 @@ pub fn print_request_details(request: &WebhookRequest, parse_paths: &[String], _
     println!(
         "{}: {}",
         "Date".bright_blue().bold(),
-        format_date(&request.date).bright_white()
+        format_date(request.date).bright_white()
     );
     println!(
         "{}: {}",
         "Method".bright_blue().bold(),
-        format_method(&request.message_object.method)
+        format_method(request.message_object.method.as_str())
     );
     println!(
         "{}: {}",
@@ -180,28 +264,9 @@ pub fn print_request_details(request: &WebhookRequest, parse_paths: &[String], _
         request.message_object.value.bright_white()
     );
     println!();
+}
 
-    // Headers
-    println!("{}", "HEADERS".bright_cyan().bold());
-    println!("{}", "─".repeat(30).bright_black());
-    for (key, values) in &request.message_object.headers {
-        for value in values {
-            println!("{}: {}", key.bright_blue(), value.bright_white());
-        }
-    }
-    println!();
-
-    // Query Parameters
-    if !request.message_object.query_parameters.is_empty() {
-        println!("{}", "QUERY PARAMETERS".bright_cyan().bold());
-        println!("{}", "─".repeat(30).bright_black());
-        for param in &request.message_object.query_parameters {
-            println!("{}", param.bright_white());
-        }
-        println!();
-    }
-
-    // Body
+fn print_request_body(request: &WebhookRequest, parse_paths: &[String]) {
     if parse_paths.is_empty() {
         println!("{}", "REQUEST BODY".bright_cyan().bold());
         println!("{}", "─".repeat(30).bright_black());
@@ -260,6 +325,10 @@ pub fn print_request_details(request: &WebhookRequest, parse_paths: &[String], _
 }
 
 pub fn highlight_json(json: &str) {
+    if !color_control::enabled() {
+        print!("{}", json);
+        return;
+    }
     let ps = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
 
@@ -296,7 +365,7 @@ pub fn format_form_data(data: &str) -> String {
 }
 
 pub fn format_method(method: &str) -> colored::ColoredString {
-    match method.to_uppercase().as_str() {
+    match method.trim().to_uppercase().as_str() {
         "GET" => method.green().bold(),
         "POST" => method.bright_blue().bold(),
         "PUT" => method.yellow().bold(),
@@ -306,27 +375,26 @@ pub fn format_method(method: &str) -> colored::ColoredString {
     }
 }
 
-pub fn format_date(date_str: &str) -> String {
-    match DateTime::parse_from_rfc3339(date_str) {
-        Ok(dt) => {
+pub fn format_date(date: Option<DateTime<Utc>>) -> String {
+    match date {
+        Some(dt) => {
             let utc_time = dt.format("%H:%M:%S UTC").to_string();
             let local_time = dt.with_timezone(&Local).format("%H:%M:%S").to_string();
             format!("{} ({})", local_time, utc_time)
         }
-        Err(_) => date_str.to_string(),
+        None => "unknown".to_string(),
     }
 }
 
-pub fn extract_path(full_path: &str, token: &str) -> String {
-    if let Some(token_index) = full_path.find(token) {
-        let after_token = &full_path[token_index + token.len()..];
-        if after_token.is_empty() {
-            "/".to_string()
-        } else {
-            after_token.to_string()
-        }
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
     } else {
-        full_path.to_string()
+        format!("{}s", seconds)
     }
 }
 
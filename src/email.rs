@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use colored::Colorize;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::{Config, EmailConfig};
+use crate::highlight::{RequestPattern, parse_pattern};
+use crate::models::{WebhookRequest, extract_path};
+use crate::secrets::resolve_secret;
+
+/// Characters of the body included in an email
+const BODY_LENGTH: usize = 20_000;
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy)]
+enum Security {
+    /// Plain connection upgraded with STARTTLS, usually on port 587
+    StartTls,
+    /// TLS from the start, usually on port 465
+    Tls,
+    /// No encryption, for local relays
+    None,
+}
+
+/// Parse `starttls`, `tls` or `none`
+fn parse_security(value: &str) -> Result<Security> {
+    match value {
+        "starttls" => Ok(Security::StartTls),
+        "tls" => Ok(Security::Tls),
+        "none" => Ok(Security::None),
+        value => anyhow::bail!("security `{}` is not one of starttls, tls, none", value),
+    }
+}
+
+/// Emails sent by `monitor` for the new requests matching `[alerts.email]`
+pub struct EmailAlerts {
+    mailer: Arc<Mailer>,
+    pattern: Option<RequestPattern>,
+    /// Tokens whose requests are emailed; all when empty
+    tokens: Vec<String>,
+}
+
+struct Mailer {
+    host: String,
+    port: u16,
+    security: Security,
+    credentials: Option<(String, String)>,
+    from: String,
+    to: Vec<String>,
+}
+
+/// Check the `[alerts.email]` settings without resolving secrets
+pub fn check_email_config(settings: &EmailConfig) -> Result<()> {
+    parse_security(settings.security.as_deref().unwrap_or("starttls"))?;
+    if settings.to.is_empty() {
+        anyhow::bail!("`to` needs at least one address");
+    }
+    if settings.username.is_some() != settings.password.is_some() {
+        anyhow::bail!("`username` and `password` go together");
+    }
+    if let Some(pattern) = &settings.r#match {
+        parse_pattern(pattern).map_err(|e| anyhow::anyhow!("match: {}", e))?;
+    }
+    Ok(())
+}
+
+impl EmailAlerts {
+    /// The alerts of the `[alerts.email]` section, if any
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let Some(settings) = &config.alerts.email else {
+            return Ok(None);
+        };
+        check_email_config(settings).context("Invalid [alerts.email]")?;
+        let security = parse_security(settings.security.as_deref().unwrap_or("starttls"))?;
+        let port = settings.port.unwrap_or(match security {
+            Security::StartTls => 587,
+            Security::Tls => 465,
+            Security::None => 25,
+        });
+        let credentials = match (&settings.username, &settings.password) {
+            (Some(username), Some(password)) => {
+                Some((resolve_secret(username)?, resolve_secret(password)?))
+            }
+            _ => None,
+        };
+        let pattern = settings
+            .r#match
+            .as_deref()
+            .map(parse_pattern)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("alerts.email.match: {}", e))?;
+
+        Ok(Some(EmailAlerts {
+            mailer: Arc::new(Mailer {
+                host: settings.host.clone(),
+                port,
+                security,
+                credentials,
+                from: settings.from.clone(),
+                to: settings.to.clone(),
+            }),
+            pattern,
+            tokens: settings
+                .tokens
+                .iter()
+                .map(|name| config.resolve_token(name).to_string())
+                .collect(),
+        }))
+    }
+
+    /// Email `request`, received for `token` (monitored as `name`), in the
+    /// background when it matches the settings
+    pub fn send_for(
+        &self,
+        name: &str,
+        token: &str,
+        request: &WebhookRequest,
+    ) -> Option<JoinHandle<()>> {
+        if !self.tokens.is_empty() && !self.tokens.iter().any(|allowed| allowed == token) {
+            return None;
+        }
+        if self
+            .pattern
+            .as_ref()
+            .is_some_and(|pattern| !pattern.matches(request))
+        {
+            return None;
+        }
+
+        let method = request.message_object.method.to_string();
+        let path = extract_path(&request.message_object.value, &request.token_id);
+        let subject = format!("Webhook: {} {} on {}", method, path, name);
+        let mut text = format!(
+            "{} {}\nToken: {}\nDate: {}\nRequest ID: {}\n\n",
+            method,
+            path,
+            name,
+            request.date_string(),
+            request.id
+        );
+        match request.body.as_deref().map(str::trim) {
+            Some(body) if !body.is_empty() => {
+                text.extend(body.chars().take(BODY_LENGTH));
+                if body.chars().count() > BODY_LENGTH {
+                    text.push('…');
+                }
+            }
+            _ => text.push_str("(empty body)"),
+        }
+
+        let mailer = Arc::clone(&self.mailer);
+        Some(tokio::spawn(async move {
+            if let Err(e) = mailer.send(&subject, &text).await {
+                eprintln!("{} {:#}", "Email alert failed:".bright_red(), e);
+            }
+        }))
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+impl Mailer {
+    async fn send(&self, subject: &str, text: &str) -> Result<()> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", self.host, self.port))?;
+        let connector = tokio_native_tls::TlsConnector::from(
+            native_tls::TlsConnector::new().context("Failed to set up TLS")?,
+        );
+        let mut stream: BufReader<Box<dyn Stream>> = match self.security {
+            Security::Tls => BufReader::new(Box::new(
+                connector
+                    .connect(&self.host, tcp)
+                    .await
+                    .context("TLS handshake failed")?,
+            )),
+            Security::StartTls | Security::None => BufReader::new(Box::new(tcp)),
+        };
+
+        expect_reply(&mut stream, 220).await?;
+        command(&mut stream, "EHLO localhost", 250).await?;
+        if let Security::StartTls = self.security {
+            command(&mut stream, "STARTTLS", 220).await?;
+            let plain = stream.into_inner();
+            stream = BufReader::new(Box::new(
+                connector
+                    .connect(&self.host, plain)
+                    .await
+                    .context("TLS handshake failed")?,
+            ));
+            command(&mut stream, "EHLO localhost", 250).await?;
+        }
+        if let Some((username, password)) = &self.credentials {
+            let token = BASE64.encode(format!("\0{}\0{}", username, password));
+            command(&mut stream, &format!("AUTH PLAIN {}", token), 235).await?;
+        }
+
+        command(&mut stream, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        for recipient in &self.to {
+            command(&mut stream, &format!("RCPT TO:<{}>", recipient), 250).await?;
+        }
+        command(&mut stream, "DATA", 354).await?;
+        stream
+            .write_all(self.message(subject, text).as_bytes())
+            .await?;
+        command(&mut stream, ".", 250).await?;
+        // The message is accepted; a failed goodbye does not matter
+        let _ = command(&mut stream, "QUIT", 221).await;
+        Ok(())
+    }
+
+    /// The message in RFC 5322 format, its body base64-encoded so that long
+    /// lines and leading dots need no care
+    fn message(&self, subject: &str, text: &str) -> String {
+        let subject = if subject.is_ascii() {
+            subject.to_string()
+        } else {
+            format!("=?utf-8?B?{}?=", BASE64.encode(subject))
+        };
+        let encoded = BASE64.encode(text);
+        let body: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|line| std::str::from_utf8(line).unwrap_or_default())
+            .collect();
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@webhook-cli>\r\n\
+             MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            self.from,
+            self.to.join(", "),
+            subject,
+            Utc::now().to_rfc2822(),
+            Uuid::new_v4(),
+            body.join("\r\n")
+        )
+    }
+}
+
+/// Send one command line and check the reply code
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    line: &str,
+    expected: u16,
+) -> Result<()> {
+    stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    stream.flush().await?;
+    let verb = line.split_whitespace().next().unwrap_or(line);
+    expect_reply(stream, expected)
+        .await
+        .with_context(|| format!("SMTP {} failed", verb))
+}
+
+/// Read a possibly multi-line reply and check its code
+async fn expect_reply<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    expected: u16,
+) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            anyhow::bail!("The SMTP server closed the connection");
+        }
+        let line = line.trim_end();
+        let code: u16 = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .with_context(|| format!("Unexpected SMTP reply: {}", line))?;
+        // `250-` continues a multi-line reply, `250 ` ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if code != expected {
+            anyhow::bail!("{}", line);
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,134 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+
+/// Stored next to the encrypted files
+const PARAMS_FILE: &str = "encryption.json";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Sealed into the parameters to tell a wrong passphrase from a corrupt line
+const CHECK_TEXT: &str = "webhook-cli archive";
+
+/// Key derivation parameters of an encrypted directory
+#[derive(Serialize, Deserialize)]
+struct Params {
+    salt: String,
+    iterations: u32,
+    check: String,
+}
+
+/// AES-256-GCM with a key derived from a passphrase. Each line is sealed on
+/// its own, as base64 of the nonce followed by the ciphertext, so encrypted
+/// files can still be appended to and pruned line by line.
+pub struct Cipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl Cipher {
+    /// Whether `dir` was ever encrypted, so it may hold encrypted lines
+    pub fn is_used(dir: &Path) -> bool {
+        dir.join(PARAMS_FILE).exists()
+    }
+
+    /// The cipher of `dir`, whose salt is created on first use. Fails when
+    /// `passphrase` is not the one the directory was encrypted with.
+    pub fn open(dir: &Path, passphrase: &str) -> Result<Self> {
+        let path = dir.join(PARAMS_FILE);
+        let rng = SystemRandom::new();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let params: Params = serde_json::from_str(&content)
+                    .parse_err(|| format!("Failed to parse {}", path.display()))?;
+                let salt = STANDARD
+                    .decode(&params.salt)
+                    .parse_err(|| format!("Invalid salt in {}", path.display()))?;
+                let cipher = Self::derive(passphrase, &salt, params.iterations, rng)?;
+                if cipher.open_line(&params.check).ok().as_deref() != Some(CHECK_TEXT) {
+                    return Err(WebhookError::auth(format!(
+                        "Wrong passphrase for the encrypted archive in {}",
+                        dir.display()
+                    )));
+                }
+                Ok(cipher)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let mut salt = [0; 16];
+                rng.fill(&mut salt)
+                    .map_err(|_| WebhookError::auth("Failed to generate a salt"))?;
+                let cipher = Self::derive(passphrase, &salt, PBKDF2_ITERATIONS, rng)?;
+                let params = Params {
+                    salt: STANDARD.encode(salt),
+                    iterations: PBKDF2_ITERATIONS,
+                    check: cipher.seal(CHECK_TEXT)?,
+                };
+                fs::create_dir_all(dir).io_err(|| format!("Failed to create {}", dir.display()))?;
+                fs::write(&path, serde_json::to_string_pretty(&params)?)
+                    .io_err(|| format!("Failed to write {}", path.display()))?;
+                Ok(cipher)
+            }
+            Err(e) => Err(e).io_err(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn derive(passphrase: &str, salt: &[u8], iterations: u32, rng: SystemRandom) -> Result<Self> {
+        let iterations = NonZeroU32::new(iterations)
+            .ok_or_else(|| WebhookError::parse("Invalid key derivation iterations"))?;
+        let mut key = [0; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = UnboundKey::new(&AES_256_GCM, &key)
+            .map_err(|_| WebhookError::auth("Failed to create the encryption key"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng,
+        })
+    }
+
+    pub fn seal(&self, line: &str) -> Result<String> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| WebhookError::auth("Failed to generate a nonce"))?;
+        let mut data = line.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| WebhookError::auth("Failed to encrypt"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(data);
+        Ok(STANDARD.encode(sealed))
+    }
+
+    pub fn open_line(&self, line: &str) -> Result<String> {
+        let mut data = STANDARD
+            .decode(line.trim())
+            .parse_err(|| "Not base64".to_string())?;
+        if data.len() < NONCE_LEN {
+            return Err(WebhookError::parse("Encrypted line is too short"));
+        }
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&data[..NONCE_LEN]);
+        let plain = self
+            .key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut data[NONCE_LEN..],
+            )
+            .map_err(|_| WebhookError::auth("Failed to decrypt"))?;
+        String::from_utf8(plain.to_vec()).parse_err(|| "Decrypted line is not UTF-8".to_string())
+    }
+}
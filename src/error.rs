@@ -0,0 +1,160 @@
+use std::io;
+use std::time::Duration;
+
+/// The failure behind an error, kept as its `source`
+pub type Cause = Box<dyn std::error::Error + Send + Sync>;
+
+/// Result of the library's functions
+pub type Result<T, E = WebhookError> = std::result::Result<T, E>;
+
+/// Why a library call failed
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    /// The service could not be reached, or the connection broke
+    #[error("{message}")]
+    Network {
+        message: String,
+        #[source]
+        source: Option<Cause>,
+    },
+    /// The service answered with an unexpected status
+    #[error("HTTP {status} {}: {body}", reason(*status))]
+    Http { status: u16, body: String },
+    /// The service answered 429 Too Many Requests
+    #[error("Rate limited by the webhook service{}", retry_hint(*retry_after))]
+    RateLimited {
+        /// Wait requested by the service in its `Retry-After` header
+        retry_after: Option<Duration>,
+    },
+    /// A response, file or value is not in the expected format
+    #[error("{message}")]
+    Parse {
+        message: String,
+        #[source]
+        source: Option<Cause>,
+    },
+    /// The request or token does not exist
+    #[error("{0}")]
+    NotFound(String),
+    /// The configuration is invalid, or asks for something the provider
+    /// cannot do
+    #[error("{message}")]
+    Config {
+        message: String,
+        #[source]
+        source: Option<Cause>,
+    },
+    /// The service rejected the credentials, the keyring refused a secret,
+    /// or an archive could not be decrypted
+    #[error("{message}")]
+    Auth {
+        message: String,
+        #[source]
+        source: Option<Cause>,
+    },
+    /// The call was reported instead of sent, because of `dry_run` in the
+    /// config
+    #[error("Not sent (dry run)")]
+    DryRun,
+    /// A local file could not be read or written
+    #[error("{message}")]
+    Io {
+        message: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl WebhookError {
+    pub fn network(message: impl Into<String>) -> Self {
+        WebhookError::Network {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        WebhookError::Parse {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        WebhookError::Config {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn auth(message: impl Into<String>) -> Self {
+        WebhookError::Auth {
+            message: message.into(),
+            source: None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(error: serde_json::Error) -> Self {
+        WebhookError::parse(error.to_string())
+    }
+}
+
+/// Turn another error into a `WebhookError` of the given kind, described by
+/// `message`, the way anyhow's `with_context` wraps it
+pub(crate) trait ResultExt<T> {
+    fn network_err(self, message: impl FnOnce() -> String) -> Result<T>;
+    fn parse_err(self, message: impl FnOnce() -> String) -> Result<T>;
+    fn config_err(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T, E: Into<Cause>> ResultExt<T> for std::result::Result<T, E> {
+    fn network_err(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|e| WebhookError::Network {
+            message: message(),
+            source: Some(e.into()),
+        })
+    }
+
+    fn parse_err(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|e| WebhookError::Parse {
+            message: message(),
+            source: Some(e.into()),
+        })
+    }
+
+    fn config_err(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|e| WebhookError::Config {
+            message: message(),
+            source: Some(e.into()),
+        })
+    }
+}
+
+/// `ResultExt` for file access, keeping the `io::Error`
+pub(crate) trait IoResultExt<T> {
+    fn io_err(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn io_err(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|source| WebhookError::Io {
+            message: message(),
+            source,
+        })
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown")
+}
+
+fn retry_hint(retry_after: Option<Duration>) -> String {
+    retry_after
+        .map(|retry_after| format!(" (retry after {}s)", retry_after.as_secs()))
+        .unwrap_or_default()
+}
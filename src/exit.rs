@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// Exit statuses of the `webhook` command; see "Exit Codes" in the README.
+/// They are kept stable for scripts.
+pub const FAILURE: i32 = 1;
+pub const NOT_FOUND: i32 = 2;
+pub const NETWORK: i32 = 3;
+pub const HTTP: i32 = 4;
+pub const AUTH: i32 = 5;
+pub const CONFIG: i32 = 6;
+/// `logs --expect-some` matched nothing, or `monitor` stopped at
+/// `--max-requests`/`--duration` without new requests
+pub const NO_REQUESTS: i32 = 7;
+/// `verify` found requests whose signature does not check out
+pub const INVALID_SIGNATURE: i32 = 8;
+
+/// A failure that exits with `code` rather than the status of its cause
+#[derive(Debug)]
+pub struct Failure {
+    pub code: i32,
+    message: String,
+}
+
+impl Failure {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Failure {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
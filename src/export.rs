@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::ExportFormat;
+use crate::models::WebhookRequest;
+
+/// Write `requests` to `out`: a directory per request for `files`, one JSON
+/// line per request for `ndjson`
+pub fn export_requests(
+    requests: &[WebhookRequest],
+    format: ExportFormat,
+    out: &Path,
+) -> Result<()> {
+    match format {
+        ExportFormat::Files => {
+            fs::create_dir_all(out)
+                .with_context(|| format!("Failed to create {}", out.display()))?;
+            for request in requests {
+                write_request_dir(request, out)?;
+            }
+        }
+        ExportFormat::Ndjson => {
+            let mut content = String::new();
+            for request in requests {
+                content.push_str(&serde_json::to_string(request)?);
+                content.push('\n');
+            }
+            fs::write(out, content)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+        }
+    }
+
+    println!(
+        "{} Exported {} requests to {}",
+        "✓".bright_green(),
+        requests.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// File name of bodies saved by `monitor --save-body <DIR>`
+pub const BODY_FILE_TEMPLATE: &str = "{timestamp}_{method}_{id}.{ext}";
+
+/// Save the body of `request` for `show --save-body`: to `path`, or to
+/// `<id>.<ext>` inside it when it is a directory
+pub fn save_body(request: &WebhookRequest, path: &Path) -> Result<PathBuf> {
+    let path = if path.is_dir() {
+        path.join(format!(
+            "{}.{}",
+            sanitize(&request.id),
+            body_extension(request)
+        ))
+    } else {
+        path.to_path_buf()
+    };
+    write_body(request, &path)?;
+    Ok(path)
+}
+
+/// Save the body of a request received by `monitor --save-body`. `target`
+/// is a directory, or a file name template with `{timestamp}`, `{method}`,
+/// `{id}`, `{token}` and `{ext}` placeholders.
+pub fn save_body_templated(request: &WebhookRequest, token: &str, target: &str) -> Result<PathBuf> {
+    let template = if target.contains('{') {
+        target.to_string()
+    } else {
+        Path::new(target)
+            .join(BODY_FILE_TEMPLATE)
+            .to_string_lossy()
+            .into_owned()
+    };
+    let path = PathBuf::from(
+        template
+            .replace("{timestamp}", &file_timestamp(request))
+            .replace("{method}", &sanitize(&file_method(request)))
+            .replace("{id}", &sanitize(&request.id))
+            .replace("{token}", &sanitize(token))
+            .replace("{ext}", body_extension(request)),
+    );
+    write_body(request, &path)?;
+    Ok(path)
+}
+
+fn write_body(request: &WebhookRequest, path: &Path) -> Result<()> {
+    let body = request
+        .body
+        .as_deref()
+        .with_context(|| format!("Request {} has no body", request.id))?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, body).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The request date as `20240115T143022Z`
+fn file_timestamp(request: &WebhookRequest) -> String {
+    request
+        .date
+        .map(|date| date.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn file_method(request: &WebhookRequest) -> String {
+    match request.message_object.method.as_str() {
+        "" => "UNKNOWN".to_string(),
+        method => method.to_uppercase(),
+    }
+}
+
+/// `<timestamp>_<method>_<id>/` with `headers.json` and `body.<ext>`
+fn write_request_dir(request: &WebhookRequest, out: &Path) -> Result<()> {
+    let name = format!(
+        "{}_{}_{}",
+        file_timestamp(request),
+        file_method(request),
+        request.id
+    );
+    let dir = out.join(sanitize(&name));
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    // Sorted so exports diff cleanly when committed as fixtures
+    let headers: BTreeMap<_, _> = request.message_object.headers.iter().collect();
+    let path = dir.join("headers.json");
+    fs::write(&path, serde_json::to_string_pretty(&headers)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    if let Some(body) = &request.body {
+        let path = dir.join(format!("body.{}", body_extension(request)));
+        fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// File extension for the body, from the Content-Type header
+fn body_extension(request: &WebhookRequest) -> &'static str {
+    let content_type = request
+        .header("content-type")
+        .map(|value| value.to_ascii_lowercase())
+        .unwrap_or_default();
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    match mime {
+        "application/x-www-form-urlencoded" => "form",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "multipart/form-data" => "multipart",
+        _ if mime.ends_with("json") => "json",
+        _ if mime.ends_with("xml") => "xml",
+        _ if mime.ends_with("yaml") => "yaml",
+        _ if mime.starts_with("text/") => "txt",
+        "" => "txt",
+        _ => "bin",
+    }
+}
+
+/// Keep directory names portable
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::WebhookRequest;
+use crate::rules::{Response, glob_match, render};
+
+/// A recorded exchange replayed by `webhook serve --fixtures`. The request
+/// fields are patterns where `*` matches any characters.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub request: FixtureRequest,
+    pub response: FixtureResponse,
+    /// File the fixture was loaded from
+    #[serde(skip)]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixtureRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    pub path: String,
+    /// Matched against the whole body; any body matches when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixtureResponse {
+    #[serde(default = "FixtureResponse::default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Template with the placeholders of response rules
+    #[serde(default)]
+    pub body: String,
+}
+
+impl FixtureResponse {
+    fn default_status() -> u16 {
+        200
+    }
+}
+
+impl Fixture {
+    pub fn matches(&self, request: &WebhookRequest) -> bool {
+        let path = request.message_object.value.split('?').next().unwrap_or("");
+        self.request
+            .method
+            .as_ref()
+            .is_none_or(|method| request.message_object.method.is(method))
+            && glob_match(&self.request.path, path)
+            && self.request.body.as_ref().is_none_or(|pattern| {
+                glob_match(pattern, request.body.as_deref().unwrap_or_default())
+            })
+    }
+
+    pub fn response(&self, request: &WebhookRequest) -> Response {
+        Response {
+            status: self.response.status,
+            headers: self
+                .response
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            body: render(&self.response.body, request).into_bytes(),
+            delay: None,
+        }
+    }
+}
+
+/// Load the `*.json` fixtures of `dir`, in file name order
+pub fn load_fixtures(dir: &Path) -> Result<Vec<Fixture>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read fixtures directory: {}", dir.display()))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut fixture: Fixture = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse fixture {}", path.display()))?;
+            fixture.file = path;
+            Ok(fixture)
+        })
+        .collect()
+}
+
+/// Save an exchange of `webhook serve --record` as a fixture matching the
+/// same method, path and body
+pub fn record_fixture(
+    dir: &Path,
+    request: &WebhookRequest,
+    response: &Response,
+) -> Result<PathBuf> {
+    let path = request.message_object.value.split('?').next().unwrap_or("");
+    let fixture = Fixture {
+        request: FixtureRequest {
+            method: Some(request.message_object.method.to_string()),
+            path: path.to_string(),
+            body: Some(request.body.clone().unwrap_or_default()),
+        },
+        response: FixtureResponse {
+            status: response.status,
+            headers: response.headers.iter().cloned().collect(),
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+        },
+        file: PathBuf::new(),
+    };
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let name = format!(
+        "{}_{}_{}.json",
+        Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+        request.message_object.method,
+        request.id.chars().take(8).collect::<String>()
+    );
+    let file = dir.join(name);
+    fs::write(&file, serde_json::to_string_pretty(&fixture)? + "\n")
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    Ok(file)
+}
@@ -0,0 +1,312 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::models::WebhookRequest;
+use crate::progress::Progress;
+use crate::send::http_client;
+
+/// Length of the strings of `huge` mutations
+const HUGE_STRING_LENGTH: usize = 1 << 20;
+
+/// Right-to-left override, a NUL, combining marks, an emoji outside the BMP
+/// and a lone surrogate escape
+const UNICODE_STRING: &str =
+    "\u{202E}gnirts\u{202C} \u{0000} Z\u{0351}\u{0360}a\u{0362}l \u{1F4A5} 𝕳𝖊𝖑𝖑𝖔 \u{FEFF}";
+
+/// A captured payload the mutations start from
+pub struct Seed {
+    pub method: String,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// A change to a seed's body
+struct Mutation {
+    /// e.g. `drop`, `type`, `huge`
+    kind: &'static str,
+    /// JSON pointer of the changed field, or what happened to the whole body
+    target: String,
+    change: Change,
+}
+
+/// The bodies are built just before sending, as huge strings add up
+enum Change {
+    /// Remove the field or array item at the pointer
+    Drop(String),
+    Replace(String, Value),
+    /// Replace the value at the pointer with a `HUGE_STRING_LENGTH` string
+    Huge(String),
+    /// Send this body as is
+    Raw(String),
+}
+
+/// Seeds from a file: requests exported with `--format ndjson`, or a single
+/// payload
+pub fn seeds_from_file(path: &Path) -> Result<Vec<Seed>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read seed file: {}", path.display()))?;
+    let requests: Option<Vec<WebhookRequest>> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).ok())
+        .collect();
+    match requests {
+        Some(requests) if !requests.is_empty() => Ok(seeds_from_requests(&requests)),
+        _ => Ok(vec![Seed {
+            method: "POST".to_string(),
+            content_type: serde_json::from_str::<Value>(&content)
+                .is_ok()
+                .then(|| "application/json".to_string()),
+            body: content,
+        }]),
+    }
+}
+
+/// Seeds from captured requests, skipping those without a body
+pub fn seeds_from_requests(requests: &[WebhookRequest]) -> Vec<Seed> {
+    requests
+        .iter()
+        .filter_map(|request| {
+            let body = request.body.clone().filter(|body| !body.is_empty())?;
+            let content_type = request.header("content-type").map(str::to_string);
+            Some(Seed {
+                method: request.message_object.method.to_string(),
+                content_type,
+                body,
+            })
+        })
+        .collect()
+}
+
+/// `webhook fuzz`: send mutations of the seeds to `target` (at most `limit`)
+/// and report those answered with a non-2xx status, an error or a timeout
+pub async fn fuzz(config: &Config, target: &str, seeds: &[Seed], limit: usize) -> Result<()> {
+    if seeds.is_empty() {
+        anyhow::bail!("No seed payloads with a body to mutate");
+    }
+    let client = http_client(config)?;
+    let cases: Vec<(&Seed, Option<Value>, Mutation)> = seeds
+        .iter()
+        .flat_map(|seed| {
+            let json = serde_json::from_str::<Value>(&seed.body).ok();
+            mutations(&seed.body, json.as_ref())
+                .into_iter()
+                .map(move |mutation| (seed, json.clone(), mutation))
+        })
+        .take(limit)
+        .collect();
+
+    println!(
+        "{} Sending {} mutations of {} seed payloads to {}",
+        "🧪".bright_blue(),
+        cases.len(),
+        seeds.len(),
+        target.bright_white()
+    );
+    let progress = Progress::bar(cases.len() as u64)?;
+
+    // Per kind: (sent, failed)
+    let mut kinds: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut failed = 0;
+    for (seed, json, mutation) in &cases {
+        progress.set_message(format!("{} {}", mutation.kind, mutation.target));
+        let method =
+            Method::from_bytes(seed.method.to_uppercase().as_bytes()).unwrap_or(Method::POST);
+        let mut request = client
+            .request(method, target)
+            .body(mutation.body(json.as_ref()));
+        if let Some(content_type) = &seed.content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        let outcome = match request.send().await {
+            Ok(response) if response.status().is_success() => None,
+            Ok(response) => Some(response.status().to_string().bright_red()),
+            Err(e) if e.is_timeout() => Some("timeout".bright_red()),
+            Err(e) => Some(format!("error: {}", e).bright_red()),
+        };
+
+        let counts = kinds.entry(mutation.kind).or_default();
+        counts.0 += 1;
+        if let Some(outcome) = outcome {
+            counts.1 += 1;
+            failed += 1;
+            // Unlike println, also shown when the bar is hidden (not a terminal)
+            progress.suspend(|| {
+                println!(
+                    "{} {:<9} {} → {}",
+                    "✗".bright_red(),
+                    mutation.kind.bright_yellow(),
+                    mutation.target,
+                    outcome
+                )
+            });
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    println!();
+    for (kind, (sent, kind_failed)) in &kinds {
+        let summary = format!("{}/{} failed", kind_failed, sent);
+        println!(
+            "  {:<9} {}",
+            kind,
+            if *kind_failed > 0 {
+                summary.bright_red()
+            } else {
+                summary.bright_green()
+            }
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} mutations got a non-2xx response, an error or a timeout",
+            failed,
+            cases.len()
+        );
+    }
+    println!(
+        "{} The target accepted all {} mutations",
+        "✓".bright_green(),
+        cases.len()
+    );
+    Ok(())
+}
+
+/// Mutations of `body`: per field of a JSON body, drop it, change its type,
+/// null it, and make it a huge or odd unicode string; for any body, malformed
+/// variants
+fn mutations(body: &str, json: Option<&Value>) -> Vec<Mutation> {
+    let mut result = Vec::new();
+    if let Some(json) = json {
+        let mut pointers = Vec::new();
+        collect_pointers(json, String::new(), &mut pointers);
+        for pointer in pointers {
+            let value = json.pointer(&pointer).unwrap_or(&Value::Null);
+            let (changed, description) = change_type(value);
+            result.push(Mutation {
+                kind: "drop",
+                target: pointer.clone(),
+                change: Change::Drop(pointer.clone()),
+            });
+            result.push(Mutation {
+                kind: "type",
+                target: format!("{} ({})", pointer, description),
+                change: Change::Replace(pointer.clone(), changed),
+            });
+            if !value.is_null() {
+                result.push(Mutation {
+                    kind: "null",
+                    target: pointer.clone(),
+                    change: Change::Replace(pointer.clone(), Value::Null),
+                });
+            }
+            result.push(Mutation {
+                kind: "huge",
+                target: pointer.clone(),
+                change: Change::Huge(pointer.clone()),
+            });
+            result.push(Mutation {
+                kind: "unicode",
+                target: pointer.clone(),
+                change: Change::Replace(pointer, Value::String(UNICODE_STRING.to_string())),
+            });
+        }
+    }
+
+    let trimmed = body.trim_end();
+    let malformed = [
+        (
+            "truncated",
+            trimmed[..trimmed.floor_char_boundary(trimmed.len() / 2)].to_string(),
+        ),
+        ("empty body", String::new()),
+        ("trailing comma", format!("{},", trimmed)),
+        ("single quotes", body.replace('"', "'")),
+        ("unbalanced", format!("{{{}", trimmed)),
+        ("trailing garbage", format!("{}\\uD800", trimmed)),
+    ];
+    for (target, body) in malformed {
+        result.push(Mutation {
+            kind: "malformed",
+            target: target.to_string(),
+            change: Change::Raw(body),
+        });
+    }
+    result
+}
+
+impl Mutation {
+    /// The mutated body of a seed whose JSON body is `json`
+    fn body(&self, json: Option<&Value>) -> String {
+        let (pointer, replacement) = match &self.change {
+            Change::Raw(body) => return body.clone(),
+            Change::Drop(pointer) => (pointer, None),
+            Change::Replace(pointer, value) => (pointer, Some(value.clone())),
+            Change::Huge(pointer) => (pointer, Some(Value::String("A".repeat(HUGE_STRING_LENGTH)))),
+        };
+        let mut mutated = json.cloned().unwrap_or_default();
+        match replacement {
+            Some(replacement) => {
+                if let Some(slot) = mutated.pointer_mut(pointer) {
+                    *slot = replacement;
+                }
+            }
+            None => {
+                let (parent, key) = pointer.rsplit_once('/').unwrap_or_default();
+                let key = key.replace("~1", "/").replace("~0", "~");
+                match mutated.pointer_mut(parent) {
+                    Some(Value::Object(map)) => {
+                        map.remove(&key);
+                    }
+                    Some(Value::Array(items)) => {
+                        if let Ok(index) = key.parse::<usize>() {
+                            items.remove(index);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        mutated.to_string()
+    }
+}
+
+/// JSON pointers of every field and array item below `value`
+pub fn collect_pointers(value: &Value, prefix: String, pointers: &mut Vec<String>) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, child)| (key.replace('~', "~0").replace('/', "~1"), child))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, child)| (index.to_string(), child))
+            .collect(),
+        _ => return,
+    };
+    for (key, child) in children {
+        let pointer = format!("{}/{}", prefix, key);
+        pointers.push(pointer.clone());
+        collect_pointers(child, pointer, pointers);
+    }
+}
+
+/// A value of another JSON type, and a description of the change
+fn change_type(value: &Value) -> (Value, &'static str) {
+    match value {
+        Value::String(_) => (Value::from(12345), "string → number"),
+        Value::Number(number) => (Value::String(number.to_string()), "number → string"),
+        Value::Bool(flag) => (Value::String(flag.to_string()), "boolean → string"),
+        Value::Null => (Value::Object(Default::default()), "null → object"),
+        Value::Array(_) => (Value::Object(Default::default()), "array → object"),
+        Value::Object(_) => (Value::Array(Vec::new()), "object → array"),
+    }
+}
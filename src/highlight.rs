@@ -0,0 +1,63 @@
+use colored::Color;
+use syntect::parsing::Regex;
+
+use crate::models::{WebhookRequest, extract_path};
+
+const DEFAULT_COLOR: Color = Color::BrightYellow;
+
+/// A regular expression matched against a request's method and path, and
+/// its body
+#[derive(Debug, Clone)]
+pub struct RequestPattern(Regex);
+
+pub fn parse_pattern(value: &str) -> Result<RequestPattern, String> {
+    if let Some(e) = Regex::try_compile(value) {
+        return Err(format!("invalid pattern '{}': {}", value, e));
+    }
+    Ok(RequestPattern(Regex::new(value.to_string())))
+}
+
+impl RequestPattern {
+    pub fn matches(&self, request: &WebhookRequest) -> bool {
+        let line = format!(
+            "{} {}",
+            request.message_object.method,
+            extract_path(&request.message_object.value, &request.token_id)
+        );
+        let body = request.body.as_deref().unwrap_or("");
+        // `is_match` only matches at the start of the text with some backends
+        self.0.search(&line, 0, line.len(), None) || self.0.search(body, 0, body.len(), None)
+    }
+}
+
+/// A `--highlight` rule: requests whose summary line or body match the
+/// pattern are painted in the rule's color
+#[derive(Debug, Clone)]
+pub struct HighlightRule {
+    pattern: RequestPattern,
+    color: Color,
+}
+
+/// Parse `[COLOR:]PATTERN`, e.g. `red:"status":"failed"` or `timeout`
+pub fn parse_highlight(value: &str) -> Result<HighlightRule, String> {
+    let (color, pattern) = match value.split_once(':') {
+        Some((prefix, pattern)) => match prefix.replace('_', " ").parse::<Color>() {
+            Ok(color) => (color, pattern),
+            Err(_) => (DEFAULT_COLOR, value),
+        },
+        None => (DEFAULT_COLOR, value),
+    };
+
+    Ok(HighlightRule {
+        pattern: parse_pattern(pattern)?,
+        color,
+    })
+}
+
+/// Color of the first rule matching the request, if any
+pub fn highlight_color(rules: &[HighlightRule], request: &WebhookRequest) -> Option<Color> {
+    rules
+        .iter()
+        .find(|rule| rule.pattern.matches(request))
+        .map(|rule| rule.color)
+}
@@ -0,0 +1,86 @@
+use colored::Colorize;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+use crate::models::{WebhookRequest, extract_path};
+
+/// Run the `--exec` command for a request in the background, with the body
+/// on stdin and request metadata in `WEBHOOK_*` environment variables
+pub fn spawn_exec_hook(command: &str, request: &WebhookRequest) -> JoinHandle<()> {
+    let env = vec![
+        ("WEBHOOK_ID", request.id.clone()),
+        ("WEBHOOK_TOKEN", request.token_id.clone()),
+        ("WEBHOOK_DATE", request.date_string()),
+        ("WEBHOOK_METHOD", request.message_object.method.to_string()),
+        (
+            "WEBHOOK_PATH",
+            extract_path(&request.message_object.value, &request.token_id),
+        ),
+    ];
+    let stdin = request.body.clone().unwrap_or_default().into_bytes();
+
+    spawn_command(command.to_string(), env, stdin)
+}
+
+/// Run the `--exec` command when a monitor alert fires, with the alert
+/// described in `WEBHOOK_ALERT*` environment variables and on stdin
+pub fn spawn_alert_hook(command: &str, token: &str, kind: &str, message: &str) -> JoinHandle<()> {
+    let env = vec![
+        ("WEBHOOK_TOKEN", token.to_string()),
+        ("WEBHOOK_ALERT", kind.to_string()),
+        ("WEBHOOK_ALERT_MESSAGE", message.to_string()),
+    ];
+
+    spawn_command(command.to_string(), env, message.as_bytes().to_vec())
+}
+
+fn spawn_command(
+    command: String,
+    env: Vec<(&'static str, String)>,
+    stdin: Vec<u8>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = run_command(&command, env, stdin).await {
+            eprintln!("{} {}", "Exec hook failed:".bright_red(), e);
+        }
+    })
+}
+
+async fn run_command(
+    command: &str,
+    env: Vec<(&'static str, String)>,
+    stdin: Vec<u8>,
+) -> anyhow::Result<()> {
+    let mut child = shell_command(command)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut child_stdin) = child.stdin.take() {
+        // The hook may exit without reading its input; that is not an error
+        let _ = child_stdin.write_all(&stdin).await;
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        anyhow::bail!("'{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
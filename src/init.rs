@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use url::Url;
+
+use crate::client::WebhookClient;
+use crate::config::{Config, Provider, user_config_path};
+
+/// Interactively create a configuration file at `path`, or the user config file
+pub async fn run_init(path: Option<&Path>) -> Result<()> {
+    let default_path = user_config_path();
+    let path = path
+        .or(default_path.as_deref())
+        .context("Could not determine the config directory; pass --config <PATH>")?;
+
+    println!("{}", "Webhook CLI setup".bright_blue().bold());
+    println!("Press Enter to accept the value in brackets.");
+    println!();
+
+    if path.exists()
+        && !confirm(
+            &format!("{} already exists. Overwrite it?", path.display()),
+            false,
+        )?
+    {
+        return Ok(());
+    }
+
+    let mut config = Config::default();
+    config.webhook.base_url = loop {
+        let base_url = prompt("Webhook service URL", None)?;
+        match Url::parse(&base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => break base_url,
+            _ => println!("{}", "Enter an http:// or https:// URL".bright_red()),
+        }
+    };
+    config.webhook.provider = loop {
+        match prompt(
+            "API (default, requestbin, webhook.site, smee)",
+            Some("default"),
+        )?
+        .as_str()
+        {
+            "default" => break Provider::Default,
+            "requestbin" => break Provider::Requestbin,
+            "webhook.site" => break Provider::WebhookSite,
+            "smee" => break Provider::Smee,
+            _ => println!(
+                "{}",
+                "Enter default, requestbin, webhook.site or smee".bright_red()
+            ),
+        }
+    };
+
+    loop {
+        match prompt("Authentication (none, bearer, basic)", Some("none"))?.as_str() {
+            "none" => break,
+            "bearer" => {
+                config.auth.bearer_token = Some(prompt(
+                    "Bearer token (or env:NAME, keyring:SERVICE/ACCOUNT)",
+                    None,
+                )?);
+                break;
+            }
+            "basic" => {
+                config.auth.username = Some(prompt("Username", None)?);
+                config.auth.password = Some(prompt(
+                    "Password (or env:NAME, keyring:SERVICE/ACCOUNT)",
+                    None,
+                )?);
+                break;
+            }
+            _ => println!("{}", "Enter none, bearer or basic".bright_red()),
+        }
+    }
+
+    config.webhook.default_count =
+        prompt_number("Requests shown by default", config.webhook.default_count)?;
+    config.webhook.default_interval = prompt_number(
+        "Monitor refresh interval in seconds",
+        config.webhook.default_interval,
+    )?;
+
+    println!();
+    println!("Checking {}...", config.get_base_url().bright_white());
+    match WebhookClient::new(&config)?.probe().await {
+        Ok(()) => println!("{} Webhook service is reachable", "✓".bright_green()),
+        Err(e) => {
+            println!("{} {:#}", "✗".bright_red(), e);
+            if !confirm("Save the configuration anyway?", false)? {
+                return Ok(());
+            }
+        }
+    }
+
+    config.save(path)?;
+    println!();
+    println!(
+        "{} {}",
+        "Configuration written to".bright_green().bold(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn prompt(label: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        match default {
+            Some(default) => print!("{} [{}]: ", label.bright_cyan(), default),
+            None => print!("{}: ", label.bright_cyan()),
+        }
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            anyhow::bail!("Setup cancelled");
+        }
+        let answer = line.trim();
+        match (answer, default) {
+            ("", Some(default)) => return Ok(default.to_string()),
+            ("", None) => continue,
+            (answer, _) => return Ok(answer.to_string()),
+        }
+    }
+}
+
+fn prompt_number<T: std::str::FromStr + ToString>(label: &str, default: T) -> Result<T> {
+    loop {
+        match prompt(label, Some(&default.to_string()))?.parse() {
+            Ok(value) => return Ok(value),
+            Err(_) => println!("{}", "Enter a number".bright_red()),
+        }
+    }
+}
+
+fn confirm(label: &str, default: bool) -> Result<bool> {
+    let answer = prompt(
+        &format!("{} (y/n)", label),
+        Some(if default { "y" } else { "n" }),
+    )?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
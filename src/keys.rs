@@ -0,0 +1,84 @@
+use console::{Key, Term};
+use std::io::IsTerminal;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+/// Commands bound to keypresses while monitoring
+#[derive(Debug, Clone, Copy)]
+pub enum KeyCommand {
+    TogglePause,
+    ToggleHeaders,
+    ToggleFullBody,
+    ClearScreen,
+    Quit,
+}
+
+/// Read keypresses on a background thread when attached to an interactive
+/// terminal. The returned guard restores the terminal mode when dropped.
+pub fn spawn_key_reader() -> Option<(UnboundedReceiver<KeyCommand>, TerminalGuard)> {
+    let term = Term::stdout();
+    if !term.is_term() || !std::io::stdin().is_terminal() {
+        return None;
+    }
+
+    let guard = TerminalGuard::save();
+    let (sender, receiver) = unbounded_channel();
+
+    // A plain thread rather than spawn_blocking: the runtime would otherwise
+    // wait for the pending read on shutdown
+    std::thread::spawn(move || {
+        while let Ok(key) = term.read_key() {
+            let command = match key {
+                Key::Char('p') | Key::Char(' ') => KeyCommand::TogglePause,
+                Key::Char('h') => KeyCommand::ToggleHeaders,
+                Key::Char('b') => KeyCommand::ToggleFullBody,
+                Key::Char('c') => KeyCommand::ClearScreen,
+                Key::Char('q') => KeyCommand::Quit,
+                _ => continue,
+            };
+            if sender.send(command).is_err() {
+                break;
+            }
+        }
+    });
+
+    Some((receiver, guard))
+}
+
+/// Restores the terminal attributes saved at creation, in case the process
+/// exits while the key reader has the terminal in raw mode
+pub struct TerminalGuard {
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+#[cfg(unix)]
+impl TerminalGuard {
+    fn save() -> Self {
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr fully initializes the struct when it succeeds
+        let saved = unsafe {
+            (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                .then(|| termios.assume_init())
+        };
+        Self { saved }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            // SAFETY: restoring attributes previously read from the same descriptor
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, saved);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl TerminalGuard {
+    fn save() -> Self {
+        Self {}
+    }
+}
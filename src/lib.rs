@@ -0,0 +1,38 @@
+//! Client library behind the `webhook` command: fetch, stream and create the
+//! requests captured by a webhook collection service.
+//!
+//! - [`config::Config`] loads `config.toml` the way the CLI does, or can be
+//!   built in code from its `Default`.
+//! - [`client::WebhookClient`] lists requests, fetches one by ID, creates
+//!   tokens and opens the service's event stream, with the authentication,
+//!   retries and caching set in the config.
+//! - [`models`] holds [`models::WebhookRequest`] and the parsers for the
+//!   formats the services answer with.
+//! - [`client::WebhookClient::stream_requests`] follows a token for new
+//!   requests, streaming or polling with backoff as `webhook monitor` does;
+//!   [`watch`] has the options and the detailed events.
+//! - [`dedup`] collapses and filters repeated deliveries.
+//! - [`parse`] reads the durations and sizes used in the config.
+//!
+//! Functions return a [`error::WebhookError`] rather than printing, telling
+//! network failures, error statuses, missing requests and bad configuration
+//! apart. Warnings and `-v`
+//! diagnostics go to the output set with [`logging::set_output`] and are
+//! dropped when none is set.
+
+pub mod archive;
+pub mod backend;
+pub mod backoff;
+pub mod cache;
+pub mod client;
+pub mod config;
+pub mod dedup;
+pub mod encryption;
+pub mod error;
+pub mod logging;
+pub mod migrate;
+pub mod models;
+pub mod parse;
+pub mod secrets;
+pub mod state;
+pub mod watch;
@@ -0,0 +1,148 @@
+use chrono::Local;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::{IoResultExt, Result};
+
+/// Size at which the log file is rotated
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Rotated files kept next to the log file (`<file>.1` is the newest)
+const KEEP_ROTATED: u32 = 3;
+
+static VERBOSITY: OnceLock<u8> = OnceLock::new();
+static LOG_FILE: OnceLock<Mutex<LogFile>> = OnceLock::new();
+static OUTPUT: OnceLock<fn(Level, &str)> = OnceLock::new();
+
+/// Kind of a diagnostic passed to the output set with `set_output`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Shown with `-v`; goes to the log file instead when there is one
+    Debug,
+    /// Something the user should know about that did not stop the operation
+    Warning,
+    /// An API call reported instead of sent, in dry-run mode
+    DryRun,
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+/// Set the verbosity and, optionally, a file that receives the diagnostics
+/// instead of stderr. A log file without `-v` implies `-v`.
+pub fn init(verbosity: u8, log_file: Option<&Path>) -> Result<()> {
+    let verbosity = match log_file {
+        Some(_) => verbosity.max(1),
+        None => verbosity,
+    };
+    // Ignore if already initialized; first value wins.
+    let _ = VERBOSITY.set(verbosity);
+
+    if let Some(path) = log_file {
+        let file = open_log(path)?;
+        let size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let _ = LOG_FILE.set(Mutex::new(LogFile {
+            path: path.to_path_buf(),
+            file,
+            size,
+        }));
+    }
+    Ok(())
+}
+
+/// Send diagnostics to `output`; until it is set they are dropped, so the
+/// library stays silent for programs that embed it. The CLI prints them to
+/// stderr.
+pub fn set_output(output: fn(Level, &str)) {
+    // Ignore if already set; first value wins.
+    let _ = OUTPUT.set(output);
+}
+
+fn emit(level: Level, message: &str) {
+    if let Some(output) = OUTPUT.get() {
+        output(level, message);
+    }
+}
+
+/// Report a warning
+pub fn warn(message: fmt::Arguments) {
+    emit(Level::Warning, &message.to_string());
+}
+
+/// Report an API call that dry-run mode kept from being sent
+pub fn dry_run(message: fmt::Arguments) {
+    emit(Level::DryRun, &message.to_string());
+}
+
+/// Number of `-v` flags given on the command line
+pub fn verbosity() -> u8 {
+    VERBOSITY.get().copied().unwrap_or(0)
+}
+
+/// Report a diagnostic line (or write it to the log file) when running with
+/// at least `level` `-v` flags
+pub fn debug(level: u8, message: fmt::Arguments) {
+    if verbosity() < level {
+        return;
+    }
+
+    match LOG_FILE.get() {
+        Some(log) => {
+            let mut log = log.lock().unwrap();
+            let line = format!(
+                "{} {}\n",
+                Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                message
+            );
+            if let Err(e) = log.write(&line) {
+                warn(format_args!("Failed to write log file: {}", e));
+            }
+        }
+        None => emit(Level::Debug, &message.to_string()),
+    }
+}
+
+impl LogFile {
+    fn write(&mut self, line: &str) -> Result<()> {
+        if self.size + line.len() as u64 > MAX_LOG_SIZE {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .io_err(|| format!("Failed to write {}", self.path.display()))?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `<file>.N` to `<file>.N+1`, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> Result<()> {
+        let rotated = |n: u32| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        let _ = fs::remove_file(rotated(KEEP_ROTATED));
+        for n in (1..KEEP_ROTATED).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        fs::rename(&self.path, rotated(1))
+            .io_err(|| format!("Failed to rotate {}", self.path.display()))?;
+
+        self.file = open_log(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .io_err(|| format!("Failed to open log file: {}", path.display()))
+}
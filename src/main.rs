@@ -1,83 +1,645 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
 use colored::Colorize;
-use uuid::Uuid;
+use futures_util::future::join_all;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
+mod alerts;
+mod chat;
 mod cli;
-mod client;
+mod clipboard;
 mod color_control;
 mod commands;
-mod config;
+mod contract;
 mod display;
-mod models;
+mod email;
+mod exit;
+mod export;
+mod fixtures;
+mod fuzz;
+mod highlight;
+mod hooks;
+mod init;
+mod keys;
+mod man;
+mod metrics;
+mod monitor;
+mod notify;
+mod otel;
+mod picker;
+mod progress;
+mod prometheus;
+mod qr;
+mod redact;
+mod replay;
+mod rules;
+mod screen;
+mod search;
+mod send;
+mod server;
+mod signature;
+mod sinks;
+mod stats;
+mod tags;
+mod tunnel;
+mod update;
+mod validate;
 
-use cli::{Cli, Commands};
+use webhook_cli::{
+    archive, backend, client, config, dedup, error, logging, migrate, models, parse, secrets,
+    state, watch,
+};
+
+use backend::TokenResponse;
+use chat::ChatForwarder;
+use cli::{Cli, ColorChoice, Commands, ConfigCommand, Section, TokensCommand};
 use client::WebhookClient;
-use commands::{generate_token, monitor_requests, show_logs, show_request_details};
+use commands::{
+    LogFilter, count_logs, generate_token, load_requests, migrate_config, page_logs, prune_archive,
+    run_secret_command, show_logs, show_request_details, show_token, tag_request,
+};
 use config::Config;
+use contract::{load_contract, verify_contract};
+use dedup::{DeliveryId, UniqueBy};
+use display::get_body_preview;
+use email::EmailAlerts;
+use error::WebhookError;
+use exit::Failure;
+use fuzz::{fuzz, seeds_from_file, seeds_from_requests};
+use models::extract_path;
+use monitor::{MonitorOptions, MonitoredToken, monitor_requests};
+use otel::OtlpExporter;
+use prometheus::{Metrics, serve_metrics};
+use redact::Redactor;
+use replay::{load_session, replay};
+use secrets::resolve_secret;
+use send::{Step, load_scenario, send_requests};
+use server::{Chaos, ServeOptions, Tls, serve};
+use signature::{DEFAULT_TOLERANCE, Verifier, verify_signatures};
+use sinks::{CaptureLog, PipeSink, Syslog};
+use state::LastTokens;
+use tags::Tags;
+
+/// Environment variable with the token to use when none is given
+const TOKEN_ENV: &str = "WEBHOOK_TOKEN";
+
+/// `--token-file`, read when a command is given no token
+static TOKEN_FILE: OnceLock<String> = OnceLock::new();
+
+/// Print the library's warnings and `-v` diagnostics to stderr
+fn print_diagnostic(level: logging::Level, message: &str) {
+    match level {
+        logging::Level::Debug => eprintln!("{} {}", "[debug]".bright_black(), message),
+        logging::Level::Warning => eprintln!("{}", message.bright_yellow()),
+        logging::Level::DryRun => println!("{}", message),
+    }
+}
+
+/// Exit status for a failure, so scripts can tell the common ones apart
+fn exit_code(error: &anyhow::Error) -> i32 {
+    if let Some(failure) = error.downcast_ref::<Failure>() {
+        return failure.code;
+    }
+    match error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<WebhookError>())
+    {
+        Some(WebhookError::NotFound(_)) => exit::NOT_FOUND,
+        Some(WebhookError::Network { .. }) => exit::NETWORK,
+        Some(WebhookError::Http { .. } | WebhookError::RateLimited { .. }) => exit::HTTP,
+        Some(WebhookError::Auth { .. }) => exit::AUTH,
+        Some(WebhookError::Config { .. }) => exit::CONFIG,
+        _ => exit::FAILURE,
+    }
+}
+
+/// Print a failure with its causes, and what to check for the common ones
+fn report_error(error: &anyhow::Error) {
+    eprintln!("{} {:#}", "Error:".red().bold(), error);
+    let hint = match error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<WebhookError>())
+    {
+        Some(WebhookError::Network { .. }) => {
+            "Check your connection, and base_url and proxy in the config"
+        }
+        Some(WebhookError::Auth { .. }) => "Check the credentials in the [auth] section",
+        Some(WebhookError::Config { .. }) => {
+            "Check the config file (`webhook config validate`) and WEBHOOK_* environment variables"
+        }
+        Some(WebhookError::RateLimited { .. }) => {
+            "Wait a moment, or poll less often with --interval"
+        }
+        _ => return,
+    };
+    eprintln!("{}", hint.bright_black());
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(error) = run().await {
+        // `--dry-run` stops at the first call it reported
+        if let Some(WebhookError::DryRun) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<WebhookError>())
+        {
+            return;
+        }
+        report_error(&error);
+        std::process::exit(exit_code(&error));
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
 
     // Initialize color control
-    let no_color_env = std::env::var_os("NO_COLOR").is_some();
-    color_control::init(cli.no_color || no_color_env);
+    color_control::init(match cli.no_color {
+        true => ColorChoice::Never,
+        false => cli.color,
+    });
+    progress::init(cli.plain, cli.quiet);
+    if let Some(source) = &cli.token_file {
+        let _ = TOKEN_FILE.set(source.clone());
+    }
+    logging::set_output(print_diagnostic);
+    logging::init(cli.verbose, cli.log_file.as_deref())?;
+
+    if cli.dry_run
+        && matches!(
+            cli.command,
+            Commands::Send { .. } | Commands::Replay { .. } | Commands::Fuzz { .. }
+        )
+    {
+        anyhow::bail!(
+            "--dry-run only covers calls to the webhook service; `send`, `replay` and `fuzz` would still deliver requests"
+        );
+    }
+
+    if let Commands::Man { dir } = &cli.command {
+        return man::generate_man_pages(dir.as_deref());
+    }
 
-    let config = Config::load()?;
-    let client = WebhookClient::new(&config);
+    if let Commands::SelfUpdate {
+        check,
+        releases_url,
+    } = &cli.command
+    {
+        return update::self_update(releases_url, *check).await;
+    }
+
+    if let Commands::Init = cli.command {
+        return init::run_init(cli.config.as_deref()).await;
+    }
+
+    if let Commands::Secret { command } = &cli.command {
+        return run_secret_command(command);
+    }
+
+    if let Commands::Tag {
+        request_id,
+        tag,
+        note,
+        remove,
+    } = &cli.command
+    {
+        return tag_request(request_id, tag.as_deref(), note.as_deref(), *remove);
+    }
+
+    if let Commands::Config {
+        command: ConfigCommand::Validate { probe },
+    } = cli.command
+    {
+        validate::validate_config(cli.config.as_deref())?;
+        if probe {
+            validate::probe(&load_config(&cli)?).await?;
+        }
+        return Ok(());
+    }
+
+    if let Commands::Config {
+        command: ConfigCommand::Migrate { dry_run },
+    } = cli.command
+    {
+        return migrate_config(cli.config.as_deref(), dry_run);
+    }
+
+    let config = load_config(&cli)?;
+    if let Commands::Stats { token } = &cli.command {
+        // The archive is local, no service needed
+        return stats::archive_stats(
+            &config,
+            token.as_deref().map(|token| config.resolve_token(token)),
+        );
+    }
+    if let Commands::Prune {
+        max_age,
+        max_requests_per_token,
+        max_size,
+    } = cli.command
+    {
+        // The archive is local, no service needed
+        return prune_archive(&config, max_age, max_requests_per_token, max_size);
+    }
+    if let Commands::Serve {
+        port,
+        bind,
+        rules,
+        fixtures,
+        record,
+        tls_cert,
+        tls_key,
+        self_signed,
+        fail_rate,
+        drop_rate,
+        latency,
+        tunnel,
+        full_body,
+        show_headers,
+        metrics_port,
+        metrics_bind,
+        otlp_endpoint,
+    } = cli.command
+    {
+        // Requests come to us, no service needed
+        let tls = match (tls_cert, tls_key) {
+            (Some(cert), Some(key)) => Some(Tls::Files { cert, key }),
+            _ if self_signed => Some(Tls::SelfSigned),
+            _ => None,
+        };
+        let options = ServeOptions {
+            bind,
+            port,
+            rules,
+            fixtures,
+            record,
+            tls,
+            chaos: Chaos {
+                fail_rate,
+                drop_rate,
+                latency,
+            },
+            tunnel,
+            full_body,
+            show_headers,
+            metrics: start_metrics(metrics_port, &metrics_bind).await?,
+            otlp: OtlpExporter::from_args(&config, otlp_endpoint)?,
+            forward: None,
+        };
+        return serve(&config, options).await;
+    }
+    if let Commands::Tunnel {
+        port,
+        ref host,
+        https,
+        provider,
+        watch,
+        full_body,
+        show_headers,
+    } = cli.command
+    {
+        // Requests go to the local server, no service needed
+        let scheme = if https { "https" } else { "http" };
+        let local_url = format!("{}://{}:{}", scheme, host, port);
+        if !watch {
+            return tunnel::expose(provider, &local_url).await;
+        }
+        // Our own listener in front of the server sees every request
+        let options = ServeOptions {
+            bind: "127.0.0.1".to_string(),
+            port: 0,
+            rules: None,
+            fixtures: None,
+            record: None,
+            tls: None,
+            chaos: Chaos {
+                fail_rate: 0.0,
+                drop_rate: 0.0,
+                latency: None,
+            },
+            tunnel: Some(provider),
+            full_body,
+            show_headers,
+            metrics: None,
+            otlp: None,
+            forward: Some(local_url),
+        };
+        return serve(&config, options).await;
+    }
+    if let Commands::Send {
+        token,
+        target,
+        method,
+        headers,
+        data,
+        scenario,
+        variables,
+        repeat,
+        sign,
+    } = cli.command
+    {
+        let (scenario_target, mut vars, steps) = match scenario {
+            Some(path) => {
+                let scenario = load_scenario(&path)?;
+                (scenario.target, scenario.variables, scenario.steps)
+            }
+            None => {
+                let body = match data {
+                    Some(data) => Some(match data.strip_prefix('@') {
+                        Some(path) => std::fs::read_to_string(path)
+                            .with_context(|| format!("Failed to read {}", path))?,
+                        None => data,
+                    }),
+                    None => None,
+                };
+                let step = Step {
+                    name: None,
+                    method,
+                    path: String::new(),
+                    headers,
+                    body,
+                    delay: None,
+                    extract: Vec::new(),
+                };
+                (None, Default::default(), vec![step])
+            }
+        };
+        vars.extend(variables);
+        // Only a webhook token needs the service
+        let target = match target.or(scenario_target) {
+            Some(target) => target,
+            None => {
+                require_service(&config)?;
+                let token = token_or_last(token, &config, cli.profile.as_deref()).await?;
+                WebhookClient::new(&config)?.webhook_url(config.resolve_token(&token))
+            }
+        };
+        return send_requests(&config, &target, &steps, vars, repeat, sign.as_ref()).await;
+    }
+    if let Commands::Replay {
+        session,
+        target,
+        preserve_timing,
+        speed,
+        sign,
+    } = cli.command
+    {
+        let requests = load_session(&session)?;
+        let speed = preserve_timing.then_some(speed.unwrap_or(1.0));
+        return replay(&config, &target, &requests, speed, sign.as_ref()).await;
+    }
+    if let Commands::Fuzz {
+        ref seed_from,
+        ref target,
+        limit,
+        ..
+    } = cli.command
+        && Path::new(seed_from).is_file()
+    {
+        // Seeds from a file, no service needed
+        let seeds = seeds_from_file(Path::new(seed_from))?;
+        return fuzz(&config, target, &seeds, limit).await;
+    }
+    require_service(&config)?;
+    let client = WebhookClient::new(&config)?;
 
     match cli.command {
-        Commands::Generate => {
-            generate_token(&config).await?;
+        Commands::Init
+        | Commands::Man { .. }
+        | Commands::SelfUpdate { .. }
+        | Commands::Config { .. }
+        | Commands::Secret { .. }
+        | Commands::Tag { .. }
+        | Commands::Stats { .. }
+        | Commands::Prune { .. }
+        | Commands::Serve { .. }
+        | Commands::Tunnel { .. }
+        | Commands::Send { .. }
+        | Commands::Replay { .. } => {
+            unreachable!("handled before the service is configured")
+        }
+
+        Commands::Fuzz {
+            target,
+            seed_from,
+            count,
+            limit,
+        } => {
+            check_token(&config, &seed_from)?;
+            let requests = load_requests(
+                &client,
+                &config,
+                config.resolve_token(&seed_from),
+                count,
+                false,
+            )
+            .await?;
+            fuzz(&config, &target, &seeds_from_requests(&requests), limit).await?;
+        }
+
+        Commands::Generate {
+            qr,
+            copy,
+            response_status,
+            response_body,
+            response_content_type,
+        } => {
+            let response = TokenResponse {
+                status: response_status,
+                body: response_body,
+                content_type: response_content_type,
+            };
+            let token = generate_token(&client, &response, qr, copy).await?;
+            remember_token(cli.profile.as_deref(), &token);
+        }
+
+        Commands::Tokens {
+            command: TokensCommand::Show { token, qr },
+        } => {
+            let token = token_or_last(token.into_inner(), &config, cli.profile.as_deref()).await?;
+            show_token(&client, &config, &token, qr);
         }
 
         Commands::Monitor {
+            names,
             token,
+            all_saved,
             count,
             interval,
+            max_interval,
             method,
             full_body,
             show_headers,
             parse,
+            unique,
+            unique_by,
+            delivery_id,
+            max_requests,
+            duration,
+            exec,
+            notify,
+            bell,
+            highlight,
+            no_footer,
+            screen,
+            sort,
+            reverse,
+            columns,
+            group_by,
+            from_start,
+            timing,
+            alert_rate,
+            alert_silence,
+            save_body,
+            only_invalid,
+            notify_slack,
+            notify_discord,
+            notify_teams,
+            notify_match,
+            metrics_port,
+            metrics_bind,
+            otlp_endpoint,
+            log_to,
+            log_max_size,
+            log_keep,
+            syslog,
+            pipe,
         } => {
-            let token = match token {
-                Some(t) => t,
-                None => {
-                    // Generate a new token if none provided
-                    let new_token = Uuid::new_v4();
-                    println!(
-                        "{}",
-                        "No token provided, generated a new one:".bright_yellow()
-                    );
-                    println!(
-                        "{}: {}",
-                        "Token".bright_blue().bold(),
-                        new_token.to_string().bright_white()
-                    );
-                    println!(
-                        "{}: {}/{}",
-                        "Webhook URL".bright_blue().bold(),
-                        config.get_base_url(),
-                        new_token.to_string().bright_white()
-                    );
-                    println!();
-                    new_token.to_string()
-                }
+            let monitored = |name: &str, token: &str| -> Result<MonitoredToken> {
+                Ok(MonitoredToken {
+                    name: name.to_string(),
+                    token: token.to_string(),
+                    parse_paths: config.parse_paths_for(name, &parse),
+                    method_filter: config.method_for(name, method.as_deref()),
+                    verifier: Verifier::for_token(
+                        &config,
+                        name,
+                        &client.webhook_url(token),
+                        DEFAULT_TOLERANCE,
+                    )?,
+                })
             };
+            let given: Vec<String> = names.into_iter().chain(token).collect();
+            // Like token_or_last, only a token given on the command line is
+            // remembered, not one piped in or taken from the environment
+            let remember = given.first().filter(|name| *name != "-").cloned();
+            let mut token: Vec<String> = given
+                .into_iter()
+                .map(|name| match name.as_str() {
+                    "-" => read_token("-"),
+                    _ => Ok(name),
+                })
+                .collect::<Result<_>>()?;
+            if token.is_empty() && !all_saved {
+                token.extend(default_token(&config, cli.profile.as_deref())?);
+            }
+            for name in &token {
+                check_token(&config, name)?;
+            }
+            let mut tokens: Vec<MonitoredToken> = token
+                .iter()
+                .map(|name| monitored(name, config.resolve_token(name)))
+                .collect::<Result<_>>()?;
+            if all_saved {
+                for (alias, token) in config.saved_tokens() {
+                    tokens.push(monitored(alias, token)?);
+                }
+            }
 
-            monitor_requests(
-                &client,
-                &config,
-                &token,
+            if tokens.is_empty()
+                && let Some(alias) = pick_saved_token(&config).await?
+            {
+                remember_token(cli.profile.as_deref(), &alias);
+                tokens.push(monitored(&alias, config.resolve_token(&alias))?);
+            }
+            if tokens.is_empty() {
+                // Generate a new token if none provided
+                let new_token = client.create_token(&TokenResponse::default()).await?;
+                println!(
+                    "{}",
+                    "No token provided, generated a new one:".bright_yellow()
+                );
+                println!(
+                    "{}: {}",
+                    "Token".bright_blue().bold(),
+                    new_token.bright_white()
+                );
+                println!(
+                    "{}: {}",
+                    "Webhook URL".bright_blue().bold(),
+                    client.webhook_url(&new_token).bright_white()
+                );
+                println!();
+                remember_token(cli.profile.as_deref(), &new_token);
+                tokens.push(monitored(&new_token, &new_token)?);
+            }
+            if only_invalid && tokens.iter().all(|monitored| monitored.verifier.is_none()) {
+                anyhow::bail!(
+                    "--only-invalid needs a token with `verify` and `secrets` in the config"
+                );
+            }
+            if let Some(name) = &remember {
+                remember_token(cli.profile.as_deref(), name);
+            }
+
+            let options = MonitorOptions {
                 count,
                 interval,
-                method.as_deref(),
-                full_body,
-                show_headers,
-                &parse,
-            )
-            .await?;
+                max_interval: max_interval.unwrap_or_else(|| config.get_max_interval()),
+                full_body: tokens
+                    .iter()
+                    .any(|monitored| config.full_body_for(&monitored.name, full_body)),
+                show_headers: tokens
+                    .iter()
+                    .any(|monitored| config.show_headers_for(&monitored.name, show_headers)),
+                unique_by: UniqueBy::from_args(unique, unique_by),
+                delivery_id: delivery_id_from_arg(&delivery_id),
+                max_requests,
+                duration,
+                exec,
+                notify,
+                bell,
+                highlights: highlight,
+                footer: !no_footer && !progress::plain(),
+                screen,
+                sort,
+                reverse,
+                columns,
+                group_by,
+                from_start,
+                timing,
+                alert_rate,
+                alert_silence,
+                save_body,
+                only_invalid,
+                chat: ChatForwarder::from_args(
+                    &config,
+                    notify_slack,
+                    notify_discord,
+                    notify_teams,
+                    notify_match,
+                )?,
+                email: EmailAlerts::from_config(&config)?,
+                metrics: start_metrics(metrics_port, &metrics_bind).await?,
+                otlp: OtlpExporter::from_args(&config, otlp_endpoint)?,
+                log: log_to
+                    .map(|path| CaptureLog::open(&path, log_max_size, log_keep))
+                    .transpose()?
+                    .map(Mutex::new),
+                syslog: syslog.as_deref().map(Syslog::connect).transpose()?,
+                pipe: pipe
+                    .map(|path| PipeSink::open(&path))
+                    .transpose()?
+                    .map(Mutex::new),
+            };
+
+            let received = monitor_requests(&client, &config, &tokens, &options).await?;
+            // Let scripts tell whether anything arrived within the stop conditions
+            if (max_requests.is_some() || duration.is_some()) && received == 0 {
+                return Err(Failure::new(exit::NO_REQUESTS, "No new requests arrived").into());
+            }
         }
         Commands::Logs {
             token,
@@ -86,28 +648,588 @@ async fn main() -> Result<()> {
             full_body,
             show_headers,
             parse,
+            unique,
+            unique_by,
+            delivery_id,
+            sort,
+            reverse,
+            offline,
+            tag,
+            since,
+            count_only,
+            expect_some,
+            interactive,
+            offset,
+            page,
+        } => {
+            let offset = match page {
+                Some(page) => (page - 1).checked_mul(count).ok_or_else(|| {
+                    Failure::new(
+                        exit::CONFIG,
+                        format!(
+                            "--page {} of --count {} is past the largest supported position ({})",
+                            page,
+                            count,
+                            u32::MAX
+                        ),
+                    )
+                })?,
+                None => offset,
+            };
+            let token = token_or_last(token.into_inner(), &config, cli.profile.as_deref()).await?;
+            let method = config.method_for(&token, method.as_deref());
+            let filter = LogFilter {
+                method: method.as_deref(),
+                tag: tag.as_deref(),
+                since,
+            };
+            let unique_by = UniqueBy::from_args(unique, unique_by);
+            let delivery_id = delivery_id_from_arg(&delivery_id);
+            let shown = if count_only {
+                count_logs(
+                    &client,
+                    &config,
+                    config.resolve_token(&token),
+                    count,
+                    offset,
+                    &filter,
+                    unique_by.as_ref(),
+                    offline,
+                )
+                .await?
+            } else if interactive && picker::is_interactive() {
+                page_logs(
+                    &client,
+                    &config,
+                    config.resolve_token(&token),
+                    count,
+                    &filter,
+                    config.full_body_for(&token, full_body),
+                    config.show_headers_for(&token, show_headers),
+                    &config.parse_paths_for(&token, &parse),
+                    delivery_id.as_ref(),
+                    offline,
+                )
+                .await?
+            } else {
+                show_logs(
+                    &client,
+                    &config,
+                    config.resolve_token(&token),
+                    count,
+                    offset,
+                    &filter,
+                    config.full_body_for(&token, full_body),
+                    config.show_headers_for(&token, show_headers),
+                    &config.parse_paths_for(&token, &parse),
+                    unique_by.as_ref(),
+                    delivery_id.as_ref(),
+                    sort,
+                    reverse,
+                    offline,
+                )
+                .await?
+            };
+            if expect_some && shown == 0 {
+                return Err(Failure::new(exit::NO_REQUESTS, "No requests matched").into());
+            }
+        }
+
+        Commands::Export {
+            token,
+            count,
+            format,
+            out,
+            offline,
+            tag,
+            redact,
         } => {
-            show_logs(
+            let token = token_or_last(token.into_inner(), &config, cli.profile.as_deref()).await?;
+            let requests = load_requests(
                 &client,
                 &config,
-                &token,
+                config.resolve_token(&token),
                 count,
-                method.as_deref(),
-                full_body,
-                show_headers,
-                &parse,
+                offline,
             )
             .await?;
+            let mut requests: Vec<_> = match tag {
+                Some(tag) => {
+                    let tags = Tags::load()?;
+                    requests
+                        .into_iter()
+                        .filter(|request| tags.has(&request.id, &tag))
+                        .collect()
+                }
+                None => requests,
+            };
+            if redact {
+                let redactor = Redactor::new(&config.redact)?;
+                requests
+                    .iter_mut()
+                    .for_each(|request| redactor.redact(request));
+            }
+            export::export_requests(&requests, format, &out)?;
+        }
+
+        Commands::Search {
+            query,
+            token,
+            offline,
+            count,
+        } => {
+            if query.is_empty() {
+                anyhow::bail!("The search text must not be empty");
+            }
+            let token = match token {
+                None if offline => None,
+                token => Some(token_or_last(token, &config, cli.profile.as_deref()).await?),
+            };
+            search::search_requests(
+                &client,
+                &config,
+                &query,
+                token.as_deref().map(|token| config.resolve_token(token)),
+                offline,
+                count,
+            )
+            .await?;
+        }
+
+        Commands::Verify {
+            contract,
+            github,
+            stripe,
+            tolerance,
+            token,
+            count,
+            offline,
+        } => {
+            let contract = contract.as_deref().map(load_contract).transpose()?;
+            let token = token_or_last(token.into_inner(), &config, cli.profile.as_deref()).await?;
+            let verifier =
+                signature_verifier(&config, &client, &token, &github, &stripe, tolerance)?;
+            let requests = load_requests(
+                &client,
+                &config,
+                config.resolve_token(&token),
+                count,
+                offline,
+            )
+            .await?;
+            match (contract, verifier) {
+                (Some(contract), _) => verify_contract(&contract, &requests)?,
+                (None, Some(verifier)) => verify_signatures(&verifier, &requests)?,
+                (None, None) => anyhow::bail!(
+                    "Nothing to verify: pass --contract, --github or --stripe, or set `verify` and `secrets` for the token in the config"
+                ),
+            }
         }
 
         Commands::Show {
             token,
             request_id,
+            latest,
+            save_body,
             parse,
+            sections,
+            headers_only,
+            body_only,
+            verify_github,
+            verify_stripe,
+            tolerance,
         } => {
-            show_request_details(&client, &token, &request_id, &parse).await?;
+            let token = token_or_last(token.into_inner(), &config, cli.profile.as_deref()).await?;
+            let verifier = signature_verifier(
+                &config,
+                &client,
+                &token,
+                &verify_github,
+                &verify_stripe,
+                tolerance,
+            )?;
+            let resolved = config.resolve_token(&token);
+            let request_id = match request_id {
+                _ if latest => nth_newest_request(&client, resolved, 1).await?,
+                Some(request_id) => match newest_index(&request_id) {
+                    Some(n) => nth_newest_request(&client, resolved, n).await?,
+                    None => request_id,
+                },
+                None => pick_request(&client, &config, resolved).await?,
+            };
+            let sections = if headers_only {
+                vec![Section::Headers]
+            } else if body_only {
+                vec![Section::Body]
+            } else if sections.is_empty() {
+                Section::value_variants().to_vec()
+            } else {
+                sections
+            };
+            show_request_details(
+                &client,
+                config.resolve_token(&token),
+                &request_id,
+                &config.parse_paths_for(&token, &parse),
+                &sections,
+                save_body.as_deref(),
+                verifier.as_ref(),
+            )
+            .await?;
         }
     }
 
     Ok(())
 }
+
+/// Start the `--metrics-port` listener, when given
+async fn start_metrics(port: Option<u16>, bind: &str) -> Result<Option<Arc<Metrics>>> {
+    let Some(port) = port else {
+        return Ok(None);
+    };
+    let metrics = Arc::new(Metrics::default());
+    let url = serve_metrics(Arc::clone(&metrics), bind, port).await?;
+    println!("{} {}", "Metrics at".bright_blue(), url.bright_white());
+    Ok(Some(metrics))
+}
+
+/// The verifier of the signature secrets given on the command line, or else
+/// the one configured for the token, if any
+fn signature_verifier(
+    config: &Config,
+    client: &WebhookClient,
+    token: &str,
+    github: &[String],
+    stripe: &[String],
+    tolerance: Duration,
+) -> Result<Option<Verifier>> {
+    let resolve = |values: &[String]| -> Result<Vec<String>> {
+        Ok(values
+            .iter()
+            .map(|value| resolve_secret(value))
+            .collect::<Result<_, _>>()?)
+    };
+    Ok(if !github.is_empty() {
+        Some(Verifier::GitHub(resolve(github)?))
+    } else if !stripe.is_empty() {
+        Some(Verifier::Stripe {
+            secrets: resolve(stripe)?,
+            tolerance,
+        })
+    } else {
+        let url = client.webhook_url(config.resolve_token(token));
+        Verifier::for_token(config, token, &url, tolerance)?
+    })
+}
+
+fn require_service(config: &Config) -> Result<()> {
+    if config.is_placeholder() {
+        anyhow::bail!(
+            "No webhook service configured. Run `webhook init` to create a config file, or set WEBHOOK_BASE_URL"
+        );
+    }
+    Ok(())
+}
+
+/// The token given on the command line, otherwise `WEBHOOK_TOKEN`, otherwise
+/// the one used last with the profile, otherwise a saved token picked on the
+/// terminal. A token given on the command line or picked becomes the default
+/// for the next command.
+async fn token_or_last(
+    token: Option<String>,
+    config: &Config,
+    profile: Option<&str>,
+) -> Result<String> {
+    if let Some(token) = token {
+        // A token piped in is kept out of the last-token file too
+        if token == "-" {
+            let token = read_token("-")?;
+            check_token(config, &token)?;
+            return Ok(token);
+        }
+        check_token(config, &token)?;
+        remember_token(profile, &token);
+        return Ok(token);
+    }
+    if let Some(token) = default_token(config, profile)? {
+        check_token(config, &token)?;
+        return Ok(token);
+    }
+    let token = pick_saved_token(config).await?.context(
+        "No token given and none used before: pass a token, set WEBHOOK_TOKEN or run `webhook generate`",
+    )?;
+    remember_token(profile, &token);
+    Ok(token)
+}
+
+/// Fail early when `name` is neither a saved alias nor shaped like a token of
+/// the service, rather than sending it and getting a confusing HTTP error
+fn check_token(config: &Config, name: &str) -> Result<()> {
+    if config.token_settings(name).is_some()
+        || backend::backend_for(config.get_provider()).is_valid_token(name)
+    {
+        return Ok(());
+    }
+    let aliases: Vec<&str> = config.saved_tokens().map(|(alias, _)| alias).collect();
+    let hint = match aliases.is_empty() {
+        true => "no aliases are saved in [tokens] of the config".to_string(),
+        false => format!("saved aliases: {}", aliases.join(", ")),
+    };
+    Err(WebhookError::NotFound(format!(
+        "'{}' is not a saved alias or a valid token ({})",
+        name, hint
+    ))
+    .into())
+}
+
+/// The token to use when none is given: the one of `--token-file`, then
+/// `WEBHOOK_TOKEN`, then the token used last with the profile
+fn default_token(config: &Config, profile: Option<&str>) -> Result<Option<String>> {
+    if let Some(source) = TOKEN_FILE.get() {
+        return read_token(source).map(Some);
+    }
+    if let Ok(token) = std::env::var(TOKEN_ENV)
+        && !token.trim().is_empty()
+    {
+        // The token itself is a credential, and stderr often ends up in CI logs
+        if !progress::quiet() {
+            eprintln!(
+                "{}",
+                format!("Using the token from {}", TOKEN_ENV).bright_black()
+            );
+        }
+        return Ok(Some(token));
+    }
+    let Some(token) = LastTokens::load().get(profile).map(str::to_string) else {
+        return Ok(None);
+    };
+    if !progress::quiet() {
+        eprintln!(
+            "{}",
+            format!(
+                "Using the last token {} (pass a token to change)",
+                token_display(config, &token)
+            )
+            .bright_black()
+        );
+    }
+    Ok(Some(token))
+}
+
+/// A token as shown in messages: its alias when it has one, otherwise only
+/// its first characters, as the token itself is a credential
+fn token_display(config: &Config, token: &str) -> String {
+    if config.tokens.contains_key(token) {
+        return token.to_string();
+    }
+    if let Some((alias, _)) = config.saved_tokens().find(|&(_, saved)| saved == token) {
+        return alias.to_string();
+    }
+    let shown: String = token.chars().take(4).collect();
+    format!("{}…", shown)
+}
+
+/// Let the user choose one of the saved tokens, listed with the time of their
+/// newest request. `None` without saved tokens or a terminal to ask on.
+async fn pick_saved_token(config: &Config) -> Result<Option<String>> {
+    let saved: Vec<(&str, &str)> = config.saved_tokens().collect();
+    if saved.is_empty() || !picker::is_interactive() {
+        return Ok(None);
+    }
+
+    let client = WebhookClient::new(config)?;
+    let activity = join_all(saved.iter().map(|&(_, token)| {
+        let client = &client;
+        async move {
+            if !client.keeps_history() {
+                return String::new();
+            }
+            match client.get_requests(token, 1).await {
+                Ok(requests) => match requests.first().and_then(|request| request.date) {
+                    Some(date) => format!(
+                        "last request {}",
+                        date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                    ),
+                    None => "no requests".to_string(),
+                },
+                Err(_) => "unreachable".to_string(),
+            }
+        }
+    }))
+    .await;
+
+    let width = saved
+        .iter()
+        .map(|(alias, _)| alias.len())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<String> = saved
+        .iter()
+        .zip(&activity)
+        .map(|((alias, token), activity)| {
+            format!("{:<width$}  {}  {}", alias, token, activity.bright_black())
+        })
+        .collect();
+    match picker::select("Token (type to filter):", &items)? {
+        Some(index) => {
+            let alias = saved[index].0;
+            eprintln!(
+                "{}",
+                format!("Using the saved token {}", alias).bright_black()
+            );
+            Ok(Some(alias.to_string()))
+        }
+        None => anyhow::bail!("No token selected"),
+    }
+}
+
+/// `n` for a request ID of `-n`, which counts back from the newest request
+fn newest_index(request_id: &str) -> Option<u32> {
+    request_id
+        .strip_prefix('-')?
+        .parse()
+        .ok()
+        .filter(|&n| n > 0)
+}
+
+/// The ID of the token's `n`th newest request, 1 being the newest
+async fn nth_newest_request(client: &WebhookClient, token: &str, n: u32) -> Result<String> {
+    let requests = client.get_requests(token, n).await?;
+    let request = requests.into_iter().nth(n as usize - 1).ok_or_else(|| {
+        WebhookError::NotFound(match n {
+            1 => format!("No requests found for token {}", token),
+            n => format!("Token {} has fewer than {} requests", token, n),
+        })
+    })?;
+    Ok(request.id)
+}
+
+/// Let the user choose one of the token's recent requests and return its ID
+async fn pick_request(client: &WebhookClient, config: &Config, token: &str) -> Result<String> {
+    if !picker::is_interactive() {
+        anyhow::bail!("Pass the request to show with --request-id");
+    }
+    let requests = client
+        .get_requests(token, config.webhook.default_count)
+        .await?;
+    if requests.is_empty() {
+        anyhow::bail!("No requests found for token {}", token);
+    }
+
+    let items: Vec<String> = requests
+        .iter()
+        .map(|request| {
+            let time = match request.date {
+                Some(date) => date
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                None => "unknown".to_string(),
+            };
+            format!(
+                "{}  {:<7} {}  {}",
+                time,
+                request.message_object.method,
+                extract_path(&request.message_object.value, token),
+                get_body_preview(&request.body, 60).bright_black()
+            )
+        })
+        .collect();
+    match picker::select("Request (type to filter):", &items)? {
+        Some(index) => Ok(requests[index].id.clone()),
+        None => anyhow::bail!("No request selected"),
+    }
+}
+
+fn remember_token(profile: Option<&str>, token: &str) {
+    let mut last = LastTokens::load();
+    if last.get(profile) == Some(token) {
+        return;
+    }
+    last.set(profile, token);
+    if let Err(e) = last.save() {
+        eprintln!(
+            "{} {:#}",
+            "Could not save the last token:".bright_yellow(),
+            e
+        );
+    }
+}
+
+/// A token read from the file `source`, or from stdin when it is "-"
+fn read_token(source: &str) -> Result<String> {
+    let token = if source == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read the token from stdin")?
+    } else {
+        std::fs::read_to_string(source)
+            .with_context(|| format!("Failed to read the token file {}", source))?
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        match source {
+            "-" => anyhow::bail!("No token on stdin"),
+            _ => anyhow::bail!("The token file {} is empty", source),
+        }
+    }
+    Ok(token.to_string())
+}
+
+/// `--delivery-id`: `None` for "none"
+fn delivery_id_from_arg(value: &str) -> Option<DeliveryId> {
+    (value != "none").then(|| DeliveryId::parse(value))
+}
+
+/// The config file with the selected profile, environment variables and
+/// command-line overrides applied
+fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(profile) = &cli.profile {
+        config.apply_profile(profile)?;
+    }
+    config.apply_env()?;
+    if let Some(timeout) = cli.timeout {
+        config.webhook.timeout = timeout;
+    }
+    if let Some(connect_timeout) = cli.connect_timeout {
+        config.webhook.connect_timeout = connect_timeout;
+    }
+    if let Some(retries) = cli.retries {
+        config.webhook.retries = retries;
+    }
+    if let Some(proxy) = &cli.proxy {
+        config.webhook.proxy = Some(proxy.clone());
+    }
+    // Report every call, including those a cached log would save
+    if cli.no_cache || cli.dry_run {
+        config.webhook.cache_ttl = 0;
+    }
+    config.webhook.dry_run = cli.dry_run;
+    if cli.insecure {
+        config.webhook.insecure = true;
+    }
+    if let Some(token) = &cli.auth_token {
+        config.auth.bearer_token = Some(token.clone());
+        config.auth.username = None;
+    }
+    config.auth.headers.extend(cli.headers.iter().cloned());
+    if needs_whole_bodies(&cli.command, &config) {
+        config.webhook.max_body_size = 0;
+    }
+    Ok(config)
+}
+
+/// Whether `command` needs request bodies byte for byte, so `max_body_size`
+/// does not apply: it exports, saves or resends them, or checks signatures
+fn needs_whole_bodies(command: &Commands, config: &Config) -> bool {
+    match command {
+        Commands::Export { .. }
+        | Commands::Verify { .. }
+        | Commands::Show { .. }
+        | Commands::Replay { .. }
+        | Commands::Fuzz { .. } => true,
+        Commands::Monitor { save_body, .. } => {
+            save_body.is_some() || config.tokens.values().any(|saved| saved.verify.is_some())
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, Command, CommandFactory};
+use colored::Colorize;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::Cli;
+
+/// `webhook man`: print the man page of `webhook`, or with `dir`, write the
+/// pages of `webhook` and every subcommand (`webhook-logs.1`, ...) there
+pub fn generate_man_pages(dir: Option<&Path>) -> Result<()> {
+    let mut command = Cli::command();
+    command.build();
+
+    let Some(dir) = dir else {
+        print!("{}", render(&command, "webhook"));
+        return Ok(());
+    };
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut written = 0;
+    write_pages(&command, "webhook", dir, &mut written)?;
+    println!(
+        "{} Wrote {} man pages to {}",
+        "✓".bright_green(),
+        written,
+        dir.display()
+    );
+    Ok(())
+}
+
+fn write_pages(command: &Command, name: &str, dir: &Path, written: &mut usize) -> Result<()> {
+    let path = dir.join(format!("{}.1", name));
+    fs::write(&path, render(command, name))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    *written += 1;
+    for subcommand in visible_subcommands(command) {
+        let name = format!("{}-{}", name, subcommand.get_name());
+        write_pages(subcommand, &name, dir, written)?;
+    }
+    Ok(())
+}
+
+/// The roff source of the page of `command`, called `name` (`webhook-logs`)
+fn render(command: &Command, name: &str) -> String {
+    let mut page = String::new();
+    let title = name.to_uppercase();
+    let version = command.get_version().unwrap_or(env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        page,
+        ".TH {} 1 \"\" \"webhook {}\" \"User Commands\"",
+        title, version
+    );
+
+    page.push_str(".SH NAME\n");
+    let about = command.get_about().map(|about| about.to_string());
+    let _ = writeln!(
+        page,
+        "{} \\- {}",
+        escape(name),
+        escape(about.as_deref().unwrap_or_default())
+    );
+
+    page.push_str(".SH SYNOPSIS\n");
+    let usage = command.clone().render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage:").trim();
+    let _ = writeln!(page, ".B {}", escape(usage));
+
+    if let Some(long_about) = command.get_long_about() {
+        page.push_str(".SH DESCRIPTION\n");
+        paragraphs(&mut page, &long_about.to_string());
+    }
+
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .partition(|arg| arg.is_positional());
+    if !positionals.is_empty() {
+        page.push_str(".SH ARGUMENTS\n");
+        for arg in positionals {
+            let value = value_names(arg).unwrap_or_else(|| arg.get_id().to_string());
+            let _ = writeln!(page, ".TP\n\\fI{}\\fR", escape(&value));
+            describe(&mut page, arg);
+        }
+    }
+    if !options.is_empty() {
+        page.push_str(".SH OPTIONS\n");
+        for arg in options {
+            let mut flags = Vec::new();
+            if let Some(short) = arg.get_short() {
+                flags.push(format!("\\fB\\-{}\\fR", short));
+            }
+            if let Some(long) = arg.get_long() {
+                flags.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+            }
+            let mut line = flags.join(", ");
+            if takes_value(arg)
+                && let Some(value) = value_names(arg)
+            {
+                let _ = write!(line, " \\fI{}\\fR", escape(&value));
+            }
+            let _ = writeln!(page, ".TP\n{}", line);
+            describe(&mut page, arg);
+        }
+    }
+
+    let subcommands: Vec<&Command> = visible_subcommands(command).collect();
+    if !subcommands.is_empty() {
+        page.push_str(".SH SUBCOMMANDS\n");
+        for subcommand in subcommands {
+            let _ = writeln!(
+                page,
+                ".TP\n\\fB{}\\-{}\\fR(1)",
+                escape(name),
+                escape(subcommand.get_name())
+            );
+            if let Some(about) = subcommand.get_about() {
+                let _ = writeln!(page, "{}", escape(&about.to_string()));
+            }
+        }
+    }
+
+    if let Some((parent, _)) = name.rsplit_once('-') {
+        page.push_str(".SH SEE ALSO\n");
+        let _ = writeln!(page, "\\fB{}\\fR(1)", escape(parent));
+    }
+    page
+}
+
+fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help")
+}
+
+/// An argument's help, choices, default and environment variable
+fn describe(page: &mut String, arg: &Arg) {
+    let help = arg
+        .get_long_help()
+        .or_else(|| arg.get_help())
+        .map(|help| help.to_string())
+        .unwrap_or_default();
+    paragraphs(page, &help);
+
+    let choices: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set() && takes_value(arg))
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !choices.is_empty() {
+        let _ = writeln!(page, ".br\nValues: {}", escape(&choices.join(", ")));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect();
+    if !defaults.is_empty() && takes_value(arg) {
+        let _ = writeln!(page, ".br\nDefault: {}", escape(&defaults.join(", ")));
+    }
+    if let Some(env) = arg.get_env() {
+        let _ = writeln!(page, ".br\nEnvironment: {}", escape(&env.to_string_lossy()));
+    }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Set | ArgAction::Append)
+}
+
+fn value_names(arg: &Arg) -> Option<String> {
+    let names = arg.get_value_names()?;
+    Some(
+        names
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Text with blank lines between paragraphs
+fn paragraphs(page: &mut String, text: &str) {
+    for (index, paragraph) in text.split("\n\n").enumerate() {
+        if index > 0 {
+            page.push_str(".IP\n");
+        }
+        let _ = writeln!(page, "{}", escape(paragraph.trim()));
+    }
+}
+
+/// Escape text for roff: backslashes and dashes, and lines starting with a
+/// control character
+fn escape(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace('\\', "\\e").replace('-', "\\-");
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
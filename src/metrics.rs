@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::display::format_elapsed;
+use crate::models::WebhookRequest;
+
+/// Timing samples collected for the new requests of a monitor session
+#[derive(Default)]
+pub struct Timings {
+    /// Gaps between consecutive requests, by server timestamp
+    pub interarrival: Vec<Duration>,
+    /// Time between the server receiving a request and the CLI seeing it
+    pub delay: Vec<Duration>,
+    last_date: Option<DateTime<Utc>>,
+}
+
+/// Timing of a single new request
+pub struct RequestTiming {
+    pub since_previous: Option<Duration>,
+    pub delay: Option<Duration>,
+}
+
+impl Timings {
+    pub fn record(&mut self, request: &WebhookRequest) -> RequestTiming {
+        let Some(date) = request.date else {
+            return RequestTiming {
+                since_previous: None,
+                delay: None,
+            };
+        };
+
+        // Negative values mean clock skew or out-of-order delivery; skip them
+        let since_previous = self.last_date.and_then(|last| (date - last).to_std().ok());
+        let delay = (Utc::now() - date).to_std().ok();
+
+        self.interarrival.extend(since_previous);
+        self.delay.extend(delay);
+        self.last_date = Some(self.last_date.map_or(date, |last| last.max(date)));
+
+        RequestTiming {
+            since_previous,
+            delay,
+        }
+    }
+}
+
+impl RequestTiming {
+    /// One-line description, e.g. "+12.4s since previous · seen 850ms after delivery"
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(gap) = self.since_previous {
+            parts.push(format!("+{} since previous", format_short(gap)));
+        }
+        if let Some(delay) = self.delay {
+            parts.push(format!("seen {} after delivery", format_short(delay)));
+        }
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// Nearest-rank percentile (`p` in 0..=100) of the samples
+pub fn percentile(samples: &[Duration], p: f64) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Compact duration with sub-second precision for short gaps
+pub fn format_short(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else if duration < Duration::from_secs(60) {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format_elapsed(duration)
+    }
+}
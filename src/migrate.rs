@@ -0,0 +1,159 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::de::{DeTable, DeValue};
+
+use crate::error::{IoResultExt, Result, WebhookError};
+
+/// Layout version of config files written by this release. Files without a
+/// `version` key predate versioning and count as version 0.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// `[webhook]` keys renamed in each version, as (version, old key, new key).
+/// The renames also apply to profiles.
+const RENAMED_WEBHOOK_KEYS: &[(u32, &str, &str)] = &[];
+
+/// Edits bringing a config file up to `CONFIG_VERSION`
+pub struct Migration {
+    pub from: u32,
+    /// Description of each change, with its line
+    pub changes: Vec<String>,
+    /// The migrated file
+    pub content: String,
+}
+
+fn version_entry(root: &DeTable) -> Option<(Result<u32>, Range<usize>)> {
+    let (_, value) = root
+        .iter()
+        .find(|(key, _)| key.get_ref().as_ref() == "version")?;
+    let version = match value.get_ref() {
+        DeValue::Integer(version) => u32::from_str_radix(version.as_str(), version.radix())
+            .map_err(|_| WebhookError::config("Config version must be a positive number")),
+        _ => Err(WebhookError::config("Config version must be a number")),
+    };
+    Some((version, value.span()))
+}
+
+/// The edits needed to migrate `content`, or `None` when it is up to date.
+/// Fails for files written by a newer release.
+pub fn plan(content: &str) -> Result<Option<Migration>> {
+    let root = DeTable::parse(content).map_err(|e| WebhookError::config(e.to_string()))?;
+    let root = root.get_ref();
+    let version_entry = version_entry(root);
+    let from = match &version_entry {
+        Some((Ok(version), _)) => *version,
+        Some((Err(e), _)) => return Err(WebhookError::config(e.to_string())),
+        None => 0,
+    };
+    if from > CONFIG_VERSION {
+        return Err(WebhookError::config(format!(
+            "The config file is version {}, but this release of webhook-cli only supports up to version {}; please upgrade",
+            from, CONFIG_VERSION
+        )));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let line = |offset: usize| content[..offset].matches('\n').count() + 1;
+    // (span to replace, replacement)
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    // (line, description)
+    let mut changes: Vec<(usize, String)> = Vec::new();
+
+    let mut sections: Vec<(String, &DeTable)> = Vec::new();
+    for (key, value) in root.iter() {
+        let DeValue::Table(table) = value.get_ref() else {
+            continue;
+        };
+        match key.get_ref().as_ref() {
+            "webhook" => sections.push(("webhook".to_string(), table)),
+            "profiles" => {
+                for (name, profile) in table.iter() {
+                    if let DeValue::Table(profile) = profile.get_ref() {
+                        sections.push((format!("profiles.{}", name.get_ref()), profile));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for &(version, old, new) in RENAMED_WEBHOOK_KEYS {
+        if version <= from {
+            continue;
+        }
+        for (section, table) in &sections {
+            let Some((key, _)) = table.iter().find(|(key, _)| key.get_ref().as_ref() == old) else {
+                continue;
+            };
+            let span = key.span();
+            if table.iter().any(|(key, _)| key.get_ref().as_ref() == new) {
+                changes.push((
+                    line(span.start),
+                    format!(
+                        "{}.{} is superseded by {}.{}, remove it",
+                        section, old, section, new
+                    ),
+                ));
+                continue;
+            }
+            changes.push((
+                line(span.start),
+                format!("rename {}.{} to {}", section, old, new),
+            ));
+            edits.push((span, new.to_string()));
+        }
+    }
+
+    match version_entry {
+        Some((_, span)) => {
+            changes.push((
+                line(span.start),
+                format!("set version = {}", CONFIG_VERSION),
+            ));
+            edits.push((span, CONFIG_VERSION.to_string()));
+        }
+        None => {
+            // Top-level keys have to come before the first table
+            let offset = root
+                .iter()
+                .filter(|(_, value)| value.get_ref().is_table())
+                .map(|(key, _)| key.span().start)
+                .min()
+                .map_or(content.len(), |start| {
+                    content[..start].rfind('\n').map_or(0, |index| index + 1)
+                });
+            changes.push((line(offset), format!("add version = {}", CONFIG_VERSION)));
+            edits.push((offset..offset, format!("version = {}\n\n", CONFIG_VERSION)));
+        }
+    }
+
+    let mut content = content.to_string();
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, replacement) in edits {
+        content.replace_range(span, &replacement);
+    }
+    changes.sort();
+    Ok(Some(Migration {
+        from,
+        changes: changes
+            .into_iter()
+            .map(|(line, change)| format!("line {}: {}", line, change))
+            .collect(),
+        content,
+    }))
+}
+
+impl Migration {
+    /// Back up the file at `path` next to it and write the migrated file
+    pub fn apply(&self, path: &Path) -> Result<PathBuf> {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{}.bak", self.from));
+        let backup = PathBuf::from(backup);
+        fs::copy(path, &backup)
+            .io_err(|| format!("Failed to back up config file to {}", backup.display()))?;
+        fs::write(path, &self.content)
+            .io_err(|| format!("Failed to write config file: {}", path.display()))?;
+        Ok(backup)
+    }
+}
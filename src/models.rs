@@ -1,32 +1,453 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::error::{Result, ResultExt};
+use crate::logging;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WebhookRequest {
     #[serde(rename = "Id")]
     pub id: String,
-    #[serde(rename = "Date")]
-    pub date: String,
-    #[serde(rename = "TokenId")]
+    /// When the service received the request; `None` when it sent no date
+    /// or one that is not ISO 8601
+    #[serde(rename = "Date", default, deserialize_with = "deserialize_date")]
+    pub date: Option<DateTime<Utc>>,
+    #[serde(rename = "TokenId", default, deserialize_with = "null_as_default")]
     pub token_id: String,
-    #[serde(rename = "MessageObject")]
+    #[serde(
+        rename = "MessageObject",
+        default,
+        deserialize_with = "null_as_default"
+    )]
     pub message_object: MessageObject,
-    #[serde(rename = "Message")]
+    #[serde(rename = "Message", default)]
     pub message: Option<String>,
-    #[serde(rename = "Body")]
+    #[serde(rename = "Body", default)]
     pub body: Option<String>,
-    #[serde(rename = "BodyObject")]
+    #[serde(rename = "BodyObject", default)]
     pub body_object: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl WebhookRequest {
+    /// Cut the body to at most `max` bytes, noting the original size;
+    /// whether it was cut
+    pub fn truncate_body(&mut self, max: usize) -> bool {
+        if let Some(body) = &mut self.body
+            && body.len() > max
+        {
+            let total = body.len();
+            let mut end = max;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+            body.push_str(&format!("… [truncated, {} bytes]", total));
+            self.body_object = None;
+            return true;
+        }
+        false
+    }
+
+    /// The first value of the header `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.message_object.header(name)
+    }
+
+    /// The date as RFC 3339, or an empty string when there is none
+    pub fn date_string(&self) -> String {
+        self.date.map(|date| date.to_rfc3339()).unwrap_or_default()
+    }
+
+    /// The provider's event type: an event header such as `X-GitHub-Event`,
+    /// or else the `type` (Stripe, Slack) or `event` field of a JSON body
+    pub fn event_type(&self) -> Option<String> {
+        const HEADERS: [&str; 5] = [
+            "X-GitHub-Event",
+            "X-Gitlab-Event",
+            "X-Shopify-Topic",
+            "X-Event-Key",
+            "X-Event-Type",
+        ];
+        if let Some(value) = HEADERS.iter().find_map(|name| self.header(name)) {
+            return Some(value.to_string());
+        }
+        let body: serde_json::Value = match &self.body_object {
+            Some(body) => body.clone(),
+            None => serde_json::from_str(self.body.as_deref()?).ok()?,
+        };
+        // Slack's Events API nests the event type under `event`
+        ["type", "event_type", "event"]
+            .iter()
+            .find_map(|field| body.get(field)?.as_str().map(str::to_string))
+            .or_else(|| body.pointer("/event/type")?.as_str().map(str::to_string))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct MessageObject {
-    #[serde(rename = "Method")]
-    pub method: String,
-    #[serde(rename = "Value")]
+    #[serde(rename = "Method", default, deserialize_with = "null_as_default")]
+    pub method: Method,
+    #[serde(rename = "Value", default, deserialize_with = "null_as_default")]
     pub value: String,
-    #[serde(rename = "Headers")]
+    #[serde(rename = "Headers", default, deserialize_with = "null_as_default")]
     pub headers: HashMap<String, Vec<String>>,
-    #[serde(rename = "QueryParameters")]
+    #[serde(
+        rename = "QueryParameters",
+        default,
+        deserialize_with = "null_as_default"
+    )]
     pub query_parameters: Vec<String>,
 }
+
+impl MessageObject {
+    /// The first value of the header `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.header_values(name).next()
+    }
+
+    /// Every value of the header `name`, ignoring case, in the order received
+    pub fn header_values<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .flat_map(|(_, values)| values.iter().map(|value| value.as_str()))
+    }
+
+    /// Whether the header `name` was sent, ignoring case
+    pub fn has_header(&self, name: &str) -> bool {
+        self.headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case(name))
+    }
+}
+
+/// HTTP method of a captured request, compared without regard to case
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Method {
+    /// The service recorded no method
+    #[default]
+    Unknown,
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+    Trace,
+    Connect,
+    /// Any other method, in upper case
+    Other(String),
+}
+
+impl Method {
+    /// The method name in upper case, as sent on the wire, or `UNKNOWN`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Unknown => "UNKNOWN",
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Other(name) => name,
+        }
+    }
+
+    /// Whether this is the method `name`, in any case
+    pub fn is(&self, name: &str) -> bool {
+        self.as_str().eq_ignore_ascii_case(name)
+    }
+}
+
+impl From<&str> for Method {
+    fn from(name: &str) -> Self {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "" => Method::Unknown,
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "PATCH" => Method::Patch,
+            "DELETE" => Method::Delete,
+            "HEAD" => Method::Head,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "CONNECT" => Method::Connect,
+            other => Method::Other(other.to_string()),
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = std::convert::Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(Method::from(name))
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Method {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            // Read back as unknown, unlike a method called "UNKNOWN"
+            Method::Unknown => serializer.serialize_str(""),
+            method => serializer.serialize_str(method.as_str()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Method {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Method::from(String::deserialize(deserializer)?.as_str()))
+    }
+}
+
+/// Parse a date sent by a service: RFC 3339, or ISO 8601 without an offset
+/// (with `T` or a space), which is taken as UTC
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|date| date.and_utc())
+}
+
+/// Read a date with `parse_date`, keeping the request when it cannot be read
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let date = parse_date(&value);
+    if date.is_none() && !value.is_empty() {
+        logging::debug(1, format_args!("Ignoring unreadable date `{}`", value));
+    }
+    Ok(date)
+}
+
+/// Fields the CLI relies on, reported in verbose mode when the server omits them
+const REQUEST_FIELDS: [&str; 4] = ["Date", "TokenId", "MessageObject", "Body"];
+const MESSAGE_FIELDS: [&str; 4] = ["Method", "Value", "Headers", "QueryParameters"];
+
+/// Treat an explicit `null` like a missing field
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Parse a single request, given as a bare object or wrapped in a `data` field
+pub fn parse_request(body: &str) -> Result<WebhookRequest> {
+    let mut json: serde_json::Value = serde_json::from_str(body)
+        .parse_err(|| format!("Failed to parse response as JSON. Response body: {}", body))?;
+    if let Some(data) = json.get_mut("data").map(serde_json::Value::take) {
+        json = data;
+    }
+    report_missing_fields(&json);
+    serde_json::from_value(json)
+        .parse_err(|| format!("Response is not a request. Response body: {}", body))
+}
+
+/// Parse a list of requests, skipping entries that cannot be read instead of
+/// failing the whole response. Accepts a bare array or an object wrapping it
+/// in a `data`, `requests` or `items` field.
+pub fn parse_request_list(body: &str) -> Result<Vec<WebhookRequest>> {
+    read_request_list(body.as_bytes(), usize::MAX)
+        .parse_err(|| format!("Failed to parse list of requests. Response body: {}", body))
+}
+
+/// Like `parse_request_list`, but reading the response incrementally so that
+/// only one request is held as JSON at a time. Bodies longer than `max_body`
+/// bytes are truncated.
+pub fn read_request_list(reader: impl Read, max_body: usize) -> Result<Vec<WebhookRequest>> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let list = deserializer.deserialize_any(RequestListVisitor { max_body })?;
+    deserializer.end()?;
+    Ok(list.finish())
+}
+
+/// Read newline-delimited JSON with one request per line
+pub fn read_request_lines(reader: impl Read, max_body: usize) -> Result<Vec<WebhookRequest>> {
+    let mut list = RequestList::new(max_body);
+    for item in serde_json::Deserializer::from_reader(reader).into_iter() {
+        list.push(item?);
+    }
+    Ok(list.finish())
+}
+
+/// Cut the bodies of `requests` to at most `max` bytes, telling the user
+/// when any were
+pub fn truncate_bodies(requests: &mut [WebhookRequest], max: usize) {
+    let truncated = requests
+        .iter_mut()
+        .map(|request| request.truncate_body(max))
+        .filter(|&cut| cut)
+        .count();
+    warn_truncated(truncated, max);
+}
+
+/// Tell the user that `count` bodies were cut to `max_body_size`, as their
+/// signatures no longer check out
+fn warn_truncated(count: usize, max: usize) {
+    if count > 0 {
+        logging::warn(format_args!(
+            "Warning: truncated {} request bodies longer than {} bytes (max_body_size)",
+            count, max
+        ));
+    }
+}
+
+/// Fields of a wrapper object that may hold the list of requests
+const LIST_FIELDS: [&str; 3] = ["data", "requests", "items"];
+
+/// Requests read so far, with the number of entries seen
+struct RequestList {
+    max_body: usize,
+    requests: Vec<WebhookRequest>,
+    total: usize,
+    truncated: usize,
+}
+
+impl RequestList {
+    fn new(max_body: usize) -> Self {
+        Self {
+            max_body,
+            requests: Vec::new(),
+            total: 0,
+            truncated: 0,
+        }
+    }
+
+    fn push(&mut self, item: serde_json::Value) {
+        self.total += 1;
+        report_missing_fields(&item);
+        match serde_json::from_value::<WebhookRequest>(item) {
+            Ok(mut request) => {
+                if request.truncate_body(self.max_body) {
+                    self.truncated += 1;
+                }
+                self.requests.push(request);
+            }
+            Err(e) => logging::debug(1, format_args!("Skipping unreadable request: {}", e)),
+        }
+    }
+
+    fn finish(self) -> Vec<WebhookRequest> {
+        if self.requests.len() < self.total {
+            logging::warn(format_args!(
+                "Warning: skipped {} of {} requests that could not be read (use -v for details)",
+                self.total - self.requests.len(),
+                self.total
+            ));
+        }
+        warn_truncated(self.truncated, self.max_body);
+        self.requests
+    }
+}
+
+/// Reads a list of requests element by element, from an array or from the
+/// first list field of a wrapper object
+struct RequestListVisitor {
+    max_body: usize,
+}
+
+impl<'de> Visitor<'de> for RequestListVisitor {
+    type Value = RequestList;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of requests")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RequestList, A::Error> {
+        let mut list = RequestList::new(self.max_body);
+        while let Some(item) = seq.next_element()? {
+            list.push(item);
+        }
+        Ok(list)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RequestList, A::Error> {
+        let mut list = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if list.is_none() && LIST_FIELDS.contains(&key.as_str()) {
+                list = Some(map.next_value_seed(RequestListVisitor {
+                    max_body: self.max_body,
+                })?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        list.ok_or_else(|| de::Error::custom("no data, requests or items field"))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for RequestListVisitor {
+    type Value = RequestList;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<RequestList, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+fn report_missing_fields(item: &serde_json::Value) {
+    if logging::verbosity() == 0 {
+        return;
+    }
+
+    let id = item.get("Id").and_then(|id| id.as_str()).unwrap_or("?");
+    let mut missing: Vec<&str> = REQUEST_FIELDS
+        .iter()
+        .filter(|field| item.get(**field).is_none_or(|value| value.is_null()))
+        .copied()
+        .collect();
+    if let Some(message) = item.get("MessageObject").filter(|value| value.is_object()) {
+        missing.extend(
+            MESSAGE_FIELDS
+                .iter()
+                .filter(|field| message.get(**field).is_none_or(|value| value.is_null())),
+        );
+    }
+
+    if !missing.is_empty() {
+        logging::debug(
+            1,
+            format_args!("Request {} is missing {}", id, missing.join(", ")),
+        );
+    }
+}
+
+/// The part of a request's path after its token, `/` for the token's root
+pub fn extract_path(full_path: &str, token: &str) -> String {
+    if let Some(token_index) = full_path.find(token) {
+        let after_token = &full_path[token_index + token.len()..];
+        if after_token.is_empty() {
+            "/".to_string()
+        } else {
+            after_token.to_string()
+        }
+    } else {
+        full_path.to_string()
+    }
+}
@@ -0,0 +1,1078 @@
+use anyhow::Result;
+use colored::{Color, ColoredString, Colorize};
+use console::Term;
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::alerts::{Alert, AlertState, RateLimit};
+use crate::chat::ChatForwarder;
+use crate::cli::{Column, GroupBy, SortBy};
+use crate::client::WebhookClient;
+use crate::config::Config;
+use crate::dedup::{Deliveries, DeliveryId, Payloads, UniqueBy};
+use crate::display::{
+    format_elapsed, format_method, print_full_request_body, print_request_headers,
+    print_request_summary,
+};
+use crate::email::EmailAlerts;
+use crate::error::WebhookError;
+use crate::export::save_body_templated;
+use crate::highlight::{HighlightRule, highlight_color};
+use crate::hooks::{spawn_alert_hook, spawn_exec_hook};
+use crate::info;
+use crate::keys::{KeyCommand, spawn_key_reader};
+use crate::logging;
+use crate::metrics::{Timings, format_short, percentile};
+use crate::models::WebhookRequest;
+use crate::notify::{notify_alert, notify_request, ring_bell};
+use crate::otel::OtlpExporter;
+use crate::progress;
+use crate::prometheus::Metrics;
+use crate::screen::{watch_groups, watch_screen};
+use crate::signature::{Verdict, Verifier};
+use crate::sinks::{CaptureLog, PipeSink, Syslog};
+use crate::state::MonitorState;
+use crate::watch::{StreamOptions, WatchEvent};
+
+pub struct MonitorOptions {
+    /// Number of recent requests to show initially
+    pub count: u32,
+    /// Refresh interval in seconds
+    pub interval: u64,
+    /// Upper bound in seconds for the interval while backing off
+    pub max_interval: u64,
+    pub full_body: bool,
+    pub show_headers: bool,
+    pub unique_by: Option<UniqueBy>,
+    /// Where to find delivery IDs, to label retries of a delivery
+    pub delivery_id: Option<DeliveryId>,
+    /// Stop after this many new requests
+    pub max_requests: Option<usize>,
+    /// Stop after this much time
+    pub duration: Option<Duration>,
+    /// Shell command run for each new request
+    pub exec: Option<String>,
+    /// Raise a desktop notification for each new request
+    pub notify: bool,
+    /// Ring the terminal bell for each new request
+    pub bell: bool,
+    /// Rules for emphasizing matching requests
+    pub highlights: Vec<HighlightRule>,
+    /// Keep a live status line at the bottom of the terminal
+    pub footer: bool,
+    /// Redraw the latest requests full-screen on every poll instead of appending
+    pub screen: bool,
+    /// Row order in screen mode
+    pub sort: SortBy,
+    pub reverse: bool,
+    /// Columns shown in screen mode
+    pub columns: Vec<Column>,
+    /// Show a live count table per group instead of the requests
+    pub group_by: Option<GroupBy>,
+    /// Ignore the last seen request saved by the previous session
+    pub from_start: bool,
+    /// Show the gap since the previous request and the delivery delay for each request
+    pub timing: bool,
+    /// Alert when a token receives more requests than this
+    pub alert_rate: Option<RateLimit>,
+    /// Alert when a token receives nothing for this long
+    pub alert_silence: Option<Duration>,
+    /// Directory or file name template the body of each new request is saved to
+    pub save_body: Option<String>,
+    /// Show only requests that fail their token's signature check
+    pub only_invalid: bool,
+    /// Chat channels each new request is posted to
+    pub chat: Option<ChatForwarder>,
+    /// Emails sent for matching requests
+    pub email: Option<EmailAlerts>,
+    /// Counters served by `--metrics-port`
+    pub metrics: Option<Arc<Metrics>>,
+    /// Export of each new request to an OpenTelemetry collector
+    pub otlp: Option<OtlpExporter>,
+    /// `--log-to` file new requests are appended to
+    pub log: Option<Mutex<CaptureLog>>,
+    /// `--syslog` destination of a line per new request
+    pub syslog: Option<Syslog>,
+    /// `--pipe` socket or named pipe new requests are written to
+    pub pipe: Option<Mutex<PipeSink>>,
+}
+
+/// A token to monitor, with the name it was given on the command line
+pub struct MonitoredToken {
+    /// Alias from the token registry, or the token itself
+    pub name: String,
+    pub token: String,
+    /// `--parse` paths, or those saved for the token
+    pub parse_paths: Vec<String>,
+    /// `--method`, or the method saved for the token
+    pub method_filter: Option<String>,
+    /// Signature check configured for the token
+    pub verifier: Option<Verifier>,
+}
+
+/// Colors used to tell tokens apart when monitoring several at once
+const TOKEN_COLORS: [Color; 6] = [
+    Color::BrightCyan,
+    Color::BrightMagenta,
+    Color::BrightYellow,
+    Color::BrightGreen,
+    Color::BrightBlue,
+    Color::BrightRed,
+];
+
+/// Output settings shared by all sessions, adjustable with keypresses
+struct MonitorDisplay<'a> {
+    config: &'a Config,
+    options: &'a MonitorOptions,
+    show_headers: Cell<bool>,
+    full_body: Cell<bool>,
+    paused: Cell<bool>,
+    // New requests held back while output is paused
+    held: RefCell<Vec<HeldRequest>>,
+    started_at: Instant,
+    // New requests across all monitored tokens
+    total_new: Cell<usize>,
+    last_request_at: Cell<Option<Instant>>,
+    // Status line kept at the bottom of the terminal
+    footer: bool,
+    footer_visible: Cell<bool>,
+    // Rate and silence tracking per token, when alerts are enabled
+    alerts: RefCell<Vec<TokenAlerts>>,
+    alert_hooks: RefCell<Vec<JoinHandle<()>>>,
+}
+
+struct TokenAlerts {
+    token: String,
+    prefix: String,
+    state: AlertState,
+}
+
+struct HeldRequest {
+    prefix: String,
+    parse_paths: Vec<String>,
+    request: WebhookRequest,
+    label: Option<String>,
+    timing: Option<String>,
+    signature: Option<Verdict>,
+}
+
+impl<'a> MonitorDisplay<'a> {
+    fn new(config: &'a Config, options: &'a MonitorOptions) -> Self {
+        Self {
+            config,
+            options,
+            show_headers: Cell::new(options.show_headers),
+            full_body: Cell::new(options.full_body),
+            paused: Cell::new(false),
+            held: RefCell::new(Vec::new()),
+            started_at: Instant::now(),
+            total_new: Cell::new(0),
+            last_request_at: Cell::new(None),
+            footer: options.footer && Term::stdout().is_term(),
+            footer_visible: Cell::new(false),
+            alerts: RefCell::new(Vec::new()),
+            alert_hooks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Redraw the status footer in place
+    fn draw_footer(&self) {
+        if !self.footer {
+            return;
+        }
+
+        let elapsed = self.started_at.elapsed();
+        let total = self.total_new.get();
+        let per_minute = total as f64 / (elapsed.as_secs_f64() / 60.0).max(1.0 / 60.0);
+        let last = match self.last_request_at.get() {
+            Some(at) => format!("last {} ago", format_elapsed(at.elapsed())),
+            None => "no requests yet".to_string(),
+        };
+        let mut status = format!(
+            "● {} │ {} requests │ {:.1}/min │ {}",
+            format_elapsed(elapsed),
+            total,
+            per_minute,
+            last
+        );
+        if self.paused.get() {
+            status.push_str(&format!(" │ PAUSED ({} held)", self.held.borrow().len()));
+        }
+
+        let term = Term::stdout();
+        let _ = term.clear_line();
+        print!("{}", status.black().on_bright_white());
+        let _ = std::io::stdout().flush();
+        self.footer_visible.set(true);
+    }
+
+    /// Remove the status footer so regular output can be printed
+    fn clear_footer(&self) {
+        if self.footer_visible.replace(false) {
+            let _ = Term::stdout().clear_line();
+        }
+    }
+
+    /// Count a request that arrived while monitoring
+    fn record_new(&self, token: &str) {
+        self.total_new.set(self.total_new.get() + 1);
+        self.last_request_at.set(Some(Instant::now()));
+
+        let fired: Vec<_> = self
+            .alerts
+            .borrow_mut()
+            .iter_mut()
+            .filter(|entry| entry.token == token)
+            .filter_map(|entry| {
+                let alert = entry.state.on_request()?;
+                Some((entry.token.clone(), entry.prefix.clone(), alert))
+            })
+            .collect();
+        for (token, prefix, alert) in fired {
+            self.raise_alert(&token, &prefix, &alert);
+        }
+    }
+
+    /// Start tracking rate and silence alerts for a token
+    fn watch_alerts(&self, token: &str, prefix: String) {
+        if self.options.alert_rate.is_none() && self.options.alert_silence.is_none() {
+            return;
+        }
+        self.alerts.borrow_mut().push(TokenAlerts {
+            token: token.to_string(),
+            prefix,
+            state: AlertState::new(self.options.alert_rate, self.options.alert_silence),
+        });
+    }
+
+    /// Periodic check for silent tokens and rates returning to normal
+    fn check_alerts(&self) {
+        let fired: Vec<_> = self
+            .alerts
+            .borrow_mut()
+            .iter_mut()
+            .filter_map(|entry| {
+                let alert = entry.state.check()?;
+                Some((entry.token.clone(), entry.prefix.clone(), alert))
+            })
+            .collect();
+        for (token, prefix, alert) in fired {
+            self.raise_alert(&token, &prefix, &alert);
+        }
+    }
+
+    fn raise_alert(&self, token: &str, prefix: &str, alert: &Alert) {
+        self.clear_footer();
+        if !alert.is_warning() {
+            println!("{}{} {}", prefix, "✓".bright_green().bold(), alert);
+            return;
+        }
+
+        println!(
+            "{}{} {}",
+            prefix,
+            " ALERT ".white().on_red().bold(),
+            alert.to_string().bright_red().bold()
+        );
+        let message = alert.to_string();
+        if let Some(command) = &self.options.exec {
+            let mut hooks = self.alert_hooks.borrow_mut();
+            hooks.retain(|hook| !hook.is_finished());
+            hooks.push(spawn_alert_hook(command, token, alert.kind(), &message));
+        }
+        if self.options.notify {
+            notify_alert(&message);
+        }
+        if self.options.bell {
+            ring_bell(self.config.get_bell_sound());
+        }
+    }
+
+    fn print_request(
+        &self,
+        prefix: &str,
+        request: &WebhookRequest,
+        parse_paths: &[String],
+        signature: Option<&Verdict>,
+    ) {
+        let options = self.options;
+        let full_body = self.full_body.get();
+        print!("{}", prefix);
+        if let Some(signature) = signature {
+            print!("{} ", signature.mark());
+        }
+        print_request_summary(
+            request,
+            !full_body,
+            self.config.get_body_preview_length(),
+            highlight_color(&options.highlights, request),
+        ); // Don't show body preview in full body mode
+        if let Some(signature) = signature.filter(|signature| !signature.is_valid()) {
+            println!("{}  {}", prefix, signature.badge());
+        }
+        if self.show_headers.get() {
+            print_request_headers(request);
+        }
+        if full_body || !parse_paths.is_empty() {
+            print_full_request_body(request, parse_paths, full_body);
+        }
+    }
+
+    /// Print a request that arrived while monitoring, or hold it while paused
+    fn print_new(
+        &self,
+        prefix: String,
+        parse_paths: &[String],
+        request: &WebhookRequest,
+        label: Option<String>,
+        timing: Option<String>,
+        signature: Option<Verdict>,
+    ) {
+        if self.paused.get() {
+            self.held.borrow_mut().push(HeldRequest {
+                prefix,
+                parse_paths: parse_paths.to_vec(),
+                request: request.clone(),
+                label,
+                timing,
+                signature,
+            });
+            return;
+        }
+
+        self.clear_footer();
+        let label = label.unwrap_or_else(|| "NEW REQUEST".bright_green().bold().to_string());
+        match timing {
+            Some(timing) => println!("{}{} {}", prefix, label, timing.bright_black()),
+            None => println!("{}{}", prefix, label),
+        }
+        self.print_request(&prefix, request, parse_paths, signature.as_ref());
+        println!("{}", "─".repeat(80).bright_black());
+    }
+
+    /// Apply a keypress; returns `true` when monitoring should stop
+    fn handle_key(&self, command: KeyCommand) -> bool {
+        self.clear_footer();
+        match command {
+            KeyCommand::TogglePause => {
+                let paused = !self.paused.get();
+                self.paused.set(paused);
+                if paused {
+                    println!(
+                        "{}",
+                        "Output paused, press p to resume".bright_yellow().bold()
+                    );
+                } else {
+                    let held = std::mem::take(&mut *self.held.borrow_mut());
+                    println!(
+                        "{}",
+                        format!("Output resumed ({} held requests)", held.len())
+                            .bright_green()
+                            .bold()
+                    );
+                    for entry in held {
+                        self.print_new(
+                            entry.prefix,
+                            &entry.parse_paths,
+                            &entry.request,
+                            entry.label,
+                            entry.timing,
+                            entry.signature,
+                        );
+                    }
+                }
+            }
+            KeyCommand::ToggleHeaders => {
+                self.show_headers.set(!self.show_headers.get());
+                print_toggle("Headers", self.show_headers.get());
+            }
+            KeyCommand::ToggleFullBody => {
+                self.full_body.set(!self.full_body.get());
+                print_toggle("Full body", self.full_body.get());
+            }
+            KeyCommand::ClearScreen => {
+                let _ = Term::stdout().clear_screen();
+            }
+            KeyCommand::Quit => return true,
+        }
+        false
+    }
+}
+
+fn print_toggle(name: &str, enabled: bool) {
+    let state = if enabled {
+        "on".bright_green()
+    } else {
+        "off".bright_red()
+    };
+    println!("{}: {}", name.bright_blue().bold(), state);
+}
+
+/// State of a running monitor session for one token
+struct MonitorSession<'a> {
+    config: &'a Config,
+    options: &'a MonitorOptions,
+    display: &'a MonitorDisplay<'a>,
+    name: &'a str,
+    token: &'a str,
+    parse_paths: &'a [String],
+    method_filter: Option<&'a str>,
+    verifier: Option<&'a Verifier>,
+    // Prefix for output lines when several tokens are monitored
+    label: Option<ColoredString>,
+    // Newest request ID seen so far, used to fetch only the delta after the
+    // first poll and saved so the next session can resume from it
+    newest_id: Option<String>,
+    // Payload key -> ID of the first request that carried it
+    seen_payloads: Payloads,
+    // Deliveries per delivery ID, when retries are labeled
+    deliveries: Option<Deliveries>,
+    // Requests that arrived during the session, by method
+    new_by_method: BTreeMap<String, usize>,
+    // Exec hooks that may still be running
+    pending_hooks: Vec<JoinHandle<()>>,
+    timings: Timings,
+    // Whether the last poll or stream connection failed
+    unreachable: bool,
+}
+
+impl<'a> MonitorSession<'a> {
+    fn new(
+        display: &'a MonitorDisplay<'a>,
+        monitored: &'a MonitoredToken,
+        label: Option<ColoredString>,
+        resume_from: Option<String>,
+    ) -> Self {
+        Self {
+            config: display.config,
+            options: display.options,
+            display,
+            name: &monitored.name,
+            token: &monitored.token,
+            parse_paths: &monitored.parse_paths,
+            method_filter: monitored.method_filter.as_deref(),
+            verifier: monitored.verifier.as_ref(),
+            label,
+            newest_id: resume_from,
+            seen_payloads: Payloads::default(),
+            deliveries: display.options.delivery_id.clone().map(Deliveries::new),
+            new_by_method: BTreeMap::new(),
+            pending_hooks: Vec::new(),
+            timings: Timings::default(),
+            unreachable: false,
+        }
+    }
+
+    /// Token label to put in front of output lines, if any
+    fn prefix(&self) -> String {
+        self.label
+            .as_ref()
+            .map(|label| format!("{} ", label))
+            .unwrap_or_default()
+    }
+
+    fn print_label(&self) {
+        print!("{}", self.prefix());
+    }
+
+    fn matches_filter(&self, request: &WebhookRequest) -> bool {
+        self.method_filter
+            .is_none_or(|method| request.message_object.method.is(method))
+            && (!self.options.only_invalid
+                || self
+                    .signature(request)
+                    .is_some_and(|signature| !signature.is_valid()))
+    }
+
+    /// The outcome of the token's signature check, when it has one
+    fn signature(&self, request: &WebhookRequest) -> Option<Verdict> {
+        self.verifier.map(|verifier| verifier.verify(request))
+    }
+
+    /// Show the requests that already existed when monitoring started
+    fn show_initial(&mut self, requests: Vec<WebhookRequest>) {
+        let requests: Vec<_> = requests
+            .into_iter()
+            .filter(|req| self.matches_filter(req))
+            .collect();
+
+        self.display.clear_footer();
+        if requests.is_empty() {
+            info!(
+                "{}{}",
+                self.prefix(),
+                "No requests yet. Waiting for incoming webhooks...".bright_yellow()
+            );
+            return;
+        }
+
+        info!(
+            "{}{} {} recent requests:",
+            self.prefix(),
+            "Found".bright_blue(),
+            requests.len()
+        );
+        // Reverse the order so latest requests appear at the end
+        for request in requests.iter().rev() {
+            if let Some(label) = self.special_label(request) {
+                self.print_label();
+                println!("{}", label);
+            }
+            self.display.print_request(
+                &self.prefix(),
+                request,
+                self.parse_paths,
+                self.signature(request).as_ref(),
+            );
+            if self.options.full_body || !self.parse_paths.is_empty() {
+                println!(); // Add spacing between requests when showing full body
+            }
+        }
+    }
+
+    /// Announce the requests that arrived since the previous session; they are
+    /// then shown as new requests
+    fn print_resumed(&self, requests: &[WebhookRequest]) {
+        let missed = requests
+            .iter()
+            .filter(|req| self.matches_filter(req))
+            .count();
+        self.display.clear_footer();
+        if missed == 0 {
+            info!(
+                "{}{}",
+                self.prefix(),
+                "No new requests since the last session. Waiting for incoming webhooks..."
+                    .bright_yellow()
+            );
+        } else {
+            info!(
+                "{}{} {} requests since the last session:",
+                self.prefix(),
+                "Found".bright_blue(),
+                missed
+            );
+        }
+    }
+
+    /// Show a request that arrived while monitoring
+    fn show_new(&mut self, request: &WebhookRequest) {
+        if !self.matches_filter(request) {
+            return;
+        }
+
+        let label = self.special_label(request);
+        let timing = self.timings.record(request);
+        let timing = self.options.timing.then(|| timing.describe()).flatten();
+        if let Some(metrics) = &self.options.metrics {
+            metrics.record_request(self.name, request);
+        }
+        let signature = self.signature(request);
+        self.display.print_new(
+            self.prefix(),
+            self.parse_paths,
+            request,
+            label,
+            timing,
+            signature,
+        );
+        if let Some(target) = &self.options.save_body
+            && request.body.is_some()
+        {
+            match save_body_templated(request, self.token, target) {
+                Ok(path) => logging::debug(1, format_args!("Saved body to {}", path.display())),
+                Err(e) => eprintln!("{} {:#}", "Could not save body:".bright_yellow(), e),
+            }
+        }
+        if let Some(log) = &self.options.log
+            && let Err(e) = log.lock().unwrap_or_else(|e| e.into_inner()).write(request)
+        {
+            eprintln!("{} {:#}", "Could not log request:".bright_yellow(), e);
+        }
+        if let Some(syslog) = &self.options.syslog
+            && let Err(e) = syslog.send(self.name, request)
+        {
+            eprintln!("{} {:#}", "Could not log request:".bright_yellow(), e);
+        }
+        if let Some(pipe) = &self.options.pipe
+            && let Err(e) = pipe
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .write(request)
+        {
+            eprintln!("{} {:#}", "Could not log request:".bright_yellow(), e);
+        }
+        if let Some(command) = &self.options.exec {
+            self.pending_hooks.retain(|hook| !hook.is_finished());
+            self.pending_hooks.push(spawn_exec_hook(command, request));
+        }
+        if let Some(chat) = &self.options.chat
+            && let Some(post) = chat.forward(self.name, request)
+        {
+            self.pending_hooks.retain(|hook| !hook.is_finished());
+            self.pending_hooks.push(post);
+        }
+        if let Some(email) = &self.options.email
+            && let Some(send) = email.send_for(self.name, self.token, request)
+        {
+            self.pending_hooks.retain(|hook| !hook.is_finished());
+            self.pending_hooks.push(send);
+        }
+        if let Some(otlp) = &self.options.otlp {
+            self.pending_hooks.retain(|hook| !hook.is_finished());
+            self.pending_hooks.push(otlp.export(self.name, request));
+        }
+        if self.options.notify {
+            notify_request(request);
+        }
+        if self.options.bell {
+            ring_bell(self.config.get_bell_sound());
+        }
+        *self
+            .new_by_method
+            .entry(request.message_object.method.to_string())
+            .or_default() += 1;
+        self.display.record_new(self.token);
+    }
+
+    fn reached_max_requests(&self) -> bool {
+        self.options
+            .max_requests
+            .is_some_and(|max| self.display.total_new.get() >= max)
+    }
+
+    /// The label of a request that retries an earlier delivery or repeats an
+    /// earlier payload, in place of "NEW REQUEST"; a retry is the more
+    /// telling of the two
+    fn special_label(&mut self, request: &WebhookRequest) -> Option<String> {
+        let attempt = match &mut self.deliveries {
+            Some(deliveries) => deliveries.record(request),
+            None => 1,
+        };
+        let duplicate_of = self.track_duplicate(request);
+        if attempt > 1 {
+            return Some(
+                format!("RETRY #{}", attempt)
+                    .bright_yellow()
+                    .bold()
+                    .to_string(),
+            );
+        }
+        duplicate_of.map(|original_id| duplicate_label(&original_id))
+    }
+
+    /// Record the payload key of a request, returning the ID of the earlier
+    /// request with the same key if this one is a duplicate delivery
+    fn track_duplicate(&mut self, request: &WebhookRequest) -> Option<String> {
+        let key = self.options.unique_by.as_ref()?.key(request);
+        self.seen_payloads.record(key, &request.id)
+    }
+}
+
+pub async fn monitor_requests(
+    client: &WebhookClient,
+    config: &Config,
+    tokens: &[MonitoredToken],
+    options: &MonitorOptions,
+) -> Result<usize> {
+    if options.screen {
+        return watch_screen(client, tokens, options).await;
+    }
+    if let Some(group_by) = options.group_by {
+        return watch_groups(client, config, tokens, options, group_by).await;
+    }
+
+    info!("{}", "Starting webhook monitor...".bright_green().bold());
+    for monitored in tokens {
+        if monitored.name == monitored.token {
+            info!("Token: {}", monitored.token.bright_white());
+        } else {
+            info!(
+                "Token: {} ({})",
+                monitored.token.bright_white(),
+                monitored.name.bright_cyan()
+            );
+        }
+    }
+    for monitored in tokens {
+        if let Some(method) = &monitored.method_filter {
+            let mut filter = format!(
+                "Filter: {} requests only",
+                method.to_uppercase().bright_cyan()
+            );
+            if tokens.len() > 1 {
+                filter.push_str(&format!(" for {}", monitored.name.bright_cyan()));
+            }
+            info!("{}", filter);
+        }
+    }
+    if let Some(max) = options.max_requests {
+        info!(
+            "Stopping after {} new requests",
+            max.to_string().bright_cyan()
+        );
+    }
+    if let Some(duration) = options.duration {
+        info!("Stopping after {}", format_elapsed(duration).bright_cyan());
+    }
+    if let Some(rate) = options.alert_rate {
+        info!("Alerting above {}", rate.to_string().bright_cyan());
+    }
+    if let Some(silence) = options.alert_silence {
+        info!(
+            "Alerting after {} without requests",
+            format_elapsed(silence).bright_cyan()
+        );
+    }
+    info!("Press {} to quit", "Ctrl+C".bright_red());
+    let mut keys = spawn_key_reader();
+    if keys.is_some() {
+        info!(
+            "Keys: {} pause  {} headers  {} body  {} clear  {} quit",
+            "[p]".bright_cyan(),
+            "[h]".bright_cyan(),
+            "[b]".bright_cyan(),
+            "[c]".bright_cyan(),
+            "[q]".bright_cyan()
+        );
+    }
+    info!("{}", "─".repeat(80).bright_black());
+
+    let mut state = MonitorState::load();
+    let display = MonitorDisplay::new(config, options);
+    let mut sessions: Vec<_> = tokens
+        .iter()
+        .zip(TOKEN_COLORS.iter().cycle())
+        .map(|(monitored, &color)| {
+            let label = (tokens.len() > 1).then(|| token_label(monitored).color(color).bold());
+            let resume_from = (!options.from_start)
+                .then(|| state.last_seen(&monitored.token).map(str::to_string))
+                .flatten();
+            let session = MonitorSession::new(&display, monitored, label, resume_from);
+            display.watch_alerts(&monitored.token, session.prefix());
+            session
+        })
+        .collect();
+
+    let time_budget = async {
+        match options.duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::pin!(time_budget);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    // Each token's watcher, until it reaches --max-requests or fails; the
+    // others keep going when one fails
+    let mut watchers: FuturesUnordered<_> = sessions
+        .iter_mut()
+        .map(|session| async move {
+            let result = watch_requests(client, session).await;
+            (session.prefix(), result)
+        })
+        .collect();
+
+    let mut footer_tick = tokio::time::interval(Duration::from_secs(1));
+    let mut failure = None;
+
+    loop {
+        let next_key = async {
+            match &mut keys {
+                Some((receiver, _)) => receiver.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            Some((prefix, result)) = watchers.next() => {
+                display.clear_footer();
+                match result {
+                    // Nothing was fetched, so there is nothing to save or summarize
+                    Err(e) if is_dry_run(&e) => return Err(e),
+                    Err(e) if watchers.is_empty() => {
+                        // Returned once the position is saved and the summary printed
+                        failure = Some(e);
+                        break;
+                    }
+                    Err(e) => eprintln!("{}{} {:#}", prefix, "Stopped watching:".bright_red(), e),
+                    Ok(()) if options.max_requests.is_some_and(|max| display.total_new.get() >= max) => {
+                        info!("{}", "Reached the maximum number of requests".bright_yellow());
+                        break;
+                    }
+                    Ok(()) if watchers.is_empty() => break,
+                    Ok(()) => {}
+                }
+            }
+            _ = &mut time_budget => {
+                display.clear_footer();
+                info!("{}", "Monitoring time budget elapsed".bright_yellow());
+                break;
+            }
+            _ = &mut ctrl_c => {
+                display.clear_footer();
+                // Move past the ^C echoed by the terminal
+                println!();
+                break;
+            }
+            Some(command) = next_key => {
+                if display.handle_key(command) {
+                    break;
+                }
+            }
+            _ = footer_tick.tick() => {
+                display.check_alerts();
+                display.draw_footer();
+            }
+        }
+    }
+    display.clear_footer();
+    drop(watchers);
+    // Restore the terminal mode before printing the summary
+    drop(keys);
+
+    // Let hooks for the last requests finish before exiting
+    for session in &mut sessions {
+        for hook in session.pending_hooks.drain(..) {
+            let _ = hook.await;
+        }
+    }
+    let alert_hooks = std::mem::take(&mut *display.alert_hooks.borrow_mut());
+    for hook in alert_hooks {
+        let _ = hook.await;
+    }
+
+    for session in &sessions {
+        if let Some(newest_id) = &session.newest_id {
+            state.set_last_seen(session.token, newest_id);
+        }
+    }
+    if let Err(e) = state.save() {
+        eprintln!(
+            "{} {:#}",
+            "Could not save monitor state:".bright_yellow(),
+            e
+        );
+    }
+
+    print_summary(&sessions, display.started_at.elapsed(), options.timing);
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    let received = display.total_new.get();
+    // Scripts waiting for a request need to tell a service that was down
+    // apart from one that received nothing
+    let stops = options.max_requests.is_some() || options.duration.is_some();
+    if stops && received == 0 && sessions.iter().all(|session| session.unreachable) {
+        return Err(WebhookError::network(
+            "No requests arrived: the webhook service could not be reached",
+        )
+        .into());
+    }
+    Ok(received)
+}
+
+fn is_dry_run(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref(), Some(WebhookError::DryRun))
+}
+
+/// Short name used to prefix output lines for a token
+fn token_label(monitored: &MonitoredToken) -> String {
+    if monitored.name == monitored.token {
+        format!("[{}]", monitored.token.chars().take(8).collect::<String>())
+    } else {
+        format!("[{}]", monitored.name)
+    }
+}
+
+fn print_summary(sessions: &[MonitorSession], elapsed: Duration, timing: bool) {
+    if progress::quiet() {
+        return;
+    }
+    let mut by_method: BTreeMap<&str, usize> = BTreeMap::new();
+    for session in sessions {
+        for (method, count) in &session.new_by_method {
+            *by_method.entry(method).or_default() += count;
+        }
+    }
+    let total: usize = by_method.values().sum();
+
+    println!("{}", "─".repeat(80).bright_black());
+    println!("{}", "Session summary".bright_green().bold());
+    println!(
+        "{}: {}",
+        "Duration".bright_blue().bold(),
+        format_elapsed(elapsed).bright_white()
+    );
+    println!(
+        "{}: {}",
+        "New requests".bright_blue().bold(),
+        total.to_string().bright_white()
+    );
+    for (method, count) in &by_method {
+        println!("  {}: {}", format_method(method), count);
+    }
+    if sessions.len() > 1 {
+        for session in sessions {
+            if let Some(label) = &session.label {
+                println!(
+                    "  {}: {}",
+                    label,
+                    session.new_by_method.values().sum::<usize>()
+                );
+            }
+        }
+    }
+
+    let interarrival: Vec<Duration> = sessions
+        .iter()
+        .flat_map(|session| session.timings.interarrival.iter().copied())
+        .collect();
+    print_distribution("Time between requests", &interarrival);
+    if timing {
+        let delay: Vec<Duration> = sessions
+            .iter()
+            .flat_map(|session| session.timings.delay.iter().copied())
+            .collect();
+        print_distribution("Delivery delay", &delay);
+    }
+}
+
+fn print_distribution(name: &str, samples: &[Duration]) {
+    if let (Some(p50), Some(p95)) = (percentile(samples, 50.0), percentile(samples, 95.0)) {
+        println!(
+            "{}: p50 {}, p95 {} ({} samples)",
+            name.bright_blue().bold(),
+            format_short(p50).bright_white(),
+            format_short(p95).bright_white(),
+            samples.len()
+        );
+    }
+}
+
+/// Watch for new requests until `--max-requests` is reached or an
+/// unrecoverable error occurs
+async fn watch_requests(client: &WebhookClient, session: &mut MonitorSession<'_>) -> Result<()> {
+    let options = session.options;
+    let stream_options = StreamOptions {
+        interval: Duration::from_secs(options.interval),
+        max_interval: Duration::from_secs(options.max_interval),
+        count: options.count,
+        transport: session.config.get_transport(),
+        since_id: session.newest_id.clone(),
+    };
+    let closed = match client.keeps_history() {
+        true => "Event stream closed, catching up by polling",
+        false => "Event stream closed, reconnecting",
+    };
+    let mut events = pin!(client.watch(session.token, stream_options));
+
+    while let Some(event) = events.next().await {
+        match event? {
+            WatchEvent::Existing(requests) => {
+                if let Some(newest) = requests.first() {
+                    session.newest_id = Some(newest.id.clone());
+                }
+                session.show_initial(requests);
+            }
+            WatchEvent::Missed(requests) => {
+                if let Some(newest) = requests.first() {
+                    session.newest_id = Some(newest.id.clone());
+                }
+                session.print_resumed(&requests);
+                for request in requests.iter().rev() {
+                    session.show_new(request);
+                    if session.reached_max_requests() {
+                        return Ok(());
+                    }
+                }
+            }
+            WatchEvent::Request(request) => {
+                session.show_new(&request);
+                session.newest_id = Some(request.id);
+                if session.reached_max_requests() {
+                    return Ok(());
+                }
+            }
+            WatchEvent::Connected => {
+                session.unreachable = false;
+                session.display.clear_footer();
+                info!(
+                    "{}{}",
+                    session.prefix(),
+                    "Connected to event stream".bright_black()
+                );
+            }
+            WatchEvent::Disconnected(None) => {
+                session.display.clear_footer();
+                eprintln!("{}{}", session.prefix(), closed.bright_yellow());
+            }
+            WatchEvent::Disconnected(Some(e)) => {
+                session.unreachable = true;
+                session.display.clear_footer();
+                eprintln!("{}{} {}", session.prefix(), "Stream error:".bright_red(), e);
+            }
+            WatchEvent::Polled { elapsed, failed } => {
+                session.unreachable = failed;
+                if let Some(metrics) = &options.metrics {
+                    metrics.record_poll(session.name, elapsed, failed);
+                }
+            }
+            WatchEvent::Restored => {
+                session.display.clear_footer();
+                info!(
+                    "{}{}",
+                    session.prefix(),
+                    "Connection restored".bright_green()
+                );
+            }
+            // Report the first failure only, then keep retrying quietly with backoff
+            WatchEvent::Failed {
+                error,
+                retry_in,
+                consecutive: 1,
+            } => {
+                session.display.clear_footer();
+                if matches!(error, WebhookError::RateLimited { .. }) {
+                    eprintln!(
+                        "{}{}",
+                        session.prefix(),
+                        format!(
+                            "Rate limited, backing off (next poll in {})...",
+                            format_short(retry_in)
+                        )
+                        .bright_yellow()
+                    );
+                } else {
+                    eprintln!("{}{} {}", session.prefix(), "Error:".bright_red(), error);
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "Retrying with backoff (up to every {}s)...",
+                            options.max_interval.max(options.interval)
+                        )
+                        .bright_yellow()
+                    );
+                }
+            }
+            WatchEvent::Failed { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+fn duplicate_label(original_id: &str) -> String {
+    format!(
+        "{} {}",
+        "DUPLICATE".bright_yellow().bold(),
+        format!("(same payload as {})", original_id).bright_black()
+    )
+}
@@ -0,0 +1,141 @@
+use colored::Colorize;
+use std::io::Write;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::process::Command;
+
+use crate::models::{WebhookRequest, extract_path};
+
+const SNIPPET_LENGTH: usize = 120;
+
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Raise a native desktop notification for a new request
+pub fn notify_request(request: &WebhookRequest) {
+    let title = format!(
+        "Webhook: {} {}",
+        request.message_object.method,
+        extract_path(&request.message_object.value, &request.token_id)
+    );
+    let body = match request.body.as_deref().map(str::trim) {
+        Some(body) if !body.is_empty() => {
+            let mut snippet: String = body.chars().take(SNIPPET_LENGTH).collect();
+            if body.chars().count() > SNIPPET_LENGTH {
+                snippet.push('…');
+            }
+            snippet
+        }
+        _ => "(empty body)".to_string(),
+    };
+
+    notify(&title, &body);
+}
+
+/// Raise a desktop notification for a monitor alert
+pub fn notify_alert(message: &str) {
+    notify("Webhook alert", message);
+}
+
+fn notify(title: &str, body: &str) {
+    let spawned = notification_command(title, body)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    if let Err(e) = spawned
+        && !WARNED.swap(true, Ordering::Relaxed)
+    {
+        eprintln!(
+            "{} {}",
+            "Desktop notifications unavailable:".bright_yellow(),
+            e
+        );
+    }
+}
+
+/// Emit the terminal bell, and play a sound file when one is configured
+pub fn ring_bell(sound: Option<&str>) {
+    print!("\x07");
+    let _ = std::io::stdout().flush();
+
+    if let Some(sound) = sound {
+        let spawned = sound_command(sound)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Err(e) = spawned
+            && !WARNED.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("{} {}", "Cannot play bell sound:".bright_yellow(), e);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn sound_command(sound: &str) -> Command {
+    let mut cmd = Command::new("afplay");
+    cmd.arg(sound);
+    cmd
+}
+
+#[cfg(windows)]
+fn sound_command(sound: &str) -> Command {
+    let mut cmd = Command::new("powershell");
+    cmd.args([
+        "-NoProfile",
+        "-Command",
+        "(New-Object Media.SoundPlayer $env:WEBHOOK_BELL_SOUND).PlaySync()",
+    ])
+    .env("WEBHOOK_BELL_SOUND", sound);
+    cmd
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn sound_command(sound: &str) -> Command {
+    let mut cmd = Command::new("paplay");
+    cmd.arg(sound);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn notification_command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(title)
+    );
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e").arg(script);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(windows)]
+fn notification_command(title: &str, body: &str) -> Command {
+    // Balloon tip via Windows Forms, available in every PowerShell install
+    let script = "Add-Type -AssemblyName System.Windows.Forms; \
+        $n = New-Object System.Windows.Forms.NotifyIcon; \
+        $n.Icon = [System.Drawing.SystemIcons]::Information; \
+        $n.Visible = $true; \
+        $n.ShowBalloonTip(5000, $env:WEBHOOK_NOTIFY_TITLE, $env:WEBHOOK_NOTIFY_BODY, 'Info'); \
+        Start-Sleep -Seconds 6; $n.Dispose()";
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", script])
+        .env("WEBHOOK_NOTIFY_TITLE", title)
+        .env("WEBHOOK_NOTIFY_BODY", body);
+    cmd
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn notification_command(title: &str, body: &str) -> Command {
+    let mut cmd = Command::new("notify-send");
+    // Request data may start with '-'; keep it from being read as options
+    cmd.args(["--app-name", "webhook", "--", title, body]);
+    cmd
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::{Config, OtlpConfig};
+use crate::models::{WebhookRequest, extract_path};
+use crate::secrets::resolve_secret;
+use crate::send::http_client;
+
+const SCOPE: &str = "webhook-cli";
+
+/// What each captured request is exported as
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    /// A span, shown next to application traces in Jaeger or Tempo
+    Traces,
+    /// A log record, for Loki and other log backends
+    Logs,
+}
+
+impl Signal {
+    fn path(self) -> &'static str {
+        match self {
+            Signal::Traces => "v1/traces",
+            Signal::Logs => "v1/logs",
+        }
+    }
+}
+
+/// Parse `traces` or `logs`
+fn parse_signal(value: &str) -> Result<Signal> {
+    match value {
+        "traces" => Ok(Signal::Traces),
+        "logs" => Ok(Signal::Logs),
+        value => anyhow::bail!("signal `{}` is not one of traces, logs", value),
+    }
+}
+
+/// Check the `[otlp]` settings without resolving secrets
+pub fn check_otlp_config(settings: &OtlpConfig) -> Result<()> {
+    parse_signal(settings.signal.as_deref().unwrap_or("traces"))?;
+    if let Some(endpoint) = &settings.endpoint {
+        url::Url::parse(endpoint).with_context(|| format!("endpoint `{}`", endpoint))?;
+    }
+    for name in settings.headers.keys() {
+        HeaderName::try_from(name.as_str())
+            .with_context(|| format!("`{}` is not a valid header name", name))?;
+    }
+    Ok(())
+}
+
+/// Exports each captured request to an OpenTelemetry collector over
+/// OTLP/HTTP with JSON encoding
+pub struct OtlpExporter {
+    client: Client,
+    url: String,
+    /// Sent with every export, e.g. the collector's API key
+    headers: HeaderMap,
+    signal: Signal,
+    service_name: String,
+}
+
+impl OtlpExporter {
+    /// The exporter of `--otlp-endpoint`, or else of the `[otlp]` section;
+    /// `None` when neither gives an endpoint
+    pub fn from_args(config: &Config, endpoint: Option<String>) -> Result<Option<Self>> {
+        let settings = &config.otlp;
+        let Some(endpoint) = endpoint.or_else(|| settings.endpoint.clone()) else {
+            return Ok(None);
+        };
+        check_otlp_config(settings).context("Invalid [otlp]")?;
+        let signal = parse_signal(settings.signal.as_deref().unwrap_or("traces"))?;
+        url::Url::parse(&endpoint)
+            .with_context(|| format!("Invalid OTLP endpoint `{}`", endpoint))?;
+        // A full signal URL is used as is, a collector base URL gets the path
+        let url = if endpoint.trim_end_matches('/').ends_with(signal.path()) {
+            endpoint
+        } else {
+            format!("{}/{}", endpoint.trim_end_matches('/'), signal.path())
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &settings.headers {
+            let value = resolve_secret(value)?;
+            headers.insert(
+                HeaderName::try_from(name.as_str())?,
+                HeaderValue::try_from(value)
+                    .with_context(|| format!("Invalid value for OTLP header {}", name))?,
+            );
+        }
+        Ok(Some(OtlpExporter {
+            client: http_client(config)?,
+            url,
+            headers,
+            signal,
+            service_name: settings
+                .service_name
+                .clone()
+                .unwrap_or_else(|| SCOPE.to_string()),
+        }))
+    }
+
+    /// Export `request`, received for the token `name`, in the background
+    pub fn export(&self, name: &str, request: &WebhookRequest) -> JoinHandle<()> {
+        let payload = self.payload(name, request);
+        let post = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .json(&payload);
+        tokio::spawn(async move {
+            let failure = match post.send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            eprintln!(
+                "{} {}",
+                "OpenTelemetry export failed:".bright_red(),
+                failure
+            );
+        })
+    }
+
+    /// The OTLP/JSON export request describing `request`
+    fn payload(&self, name: &str, request: &WebhookRequest) -> Value {
+        let method = request.message_object.method.to_string();
+        let path = extract_path(&request.message_object.value, &request.token_id);
+        let path = path.split('?').next().unwrap_or_default().to_string();
+        let body_size = request.body.as_deref().map_or(0, str::len);
+        let received = request.date.unwrap_or_else(Utc::now);
+        let nanos = received
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut attributes = vec![
+            string_attribute("http.request.method", &method),
+            string_attribute("url.path", &path),
+            json!({"key": "http.request.body.size", "value": {"intValue": body_size.to_string()}}),
+            string_attribute("webhook.token", name),
+            string_attribute("webhook.request_id", &request.id),
+        ];
+        if let Some(event_type) = request.event_type() {
+            attributes.push(string_attribute("webhook.event_type", &event_type));
+        }
+
+        let resource = json!({
+            "attributes": [string_attribute("service.name", &self.service_name)],
+        });
+        let scope = json!({"name": SCOPE, "version": env!("CARGO_PKG_VERSION")});
+        match self.signal {
+            Signal::Traces => {
+                let trace_id = Uuid::new_v4().simple().to_string();
+                let span_id = Uuid::new_v4().simple().to_string()[..16].to_string();
+                json!({
+                    "resourceSpans": [{
+                        "resource": resource,
+                        "scopeSpans": [{
+                            "scope": scope,
+                            "spans": [{
+                                "traceId": trace_id,
+                                "spanId": span_id,
+                                "name": format!("{} {}", method, path),
+                                // SPAN_KIND_SERVER: the request was received
+                                "kind": 2,
+                                "startTimeUnixNano": nanos,
+                                "endTimeUnixNano": nanos,
+                                "attributes": attributes,
+                            }],
+                        }],
+                    }],
+                })
+            }
+            Signal::Logs => json!({
+                "resourceLogs": [{
+                    "resource": resource,
+                    "scopeLogs": [{
+                        "scope": scope,
+                        "logRecords": [{
+                            "timeUnixNano": nanos,
+                            "observedTimeUnixNano": Utc::now()
+                                .timestamp_nanos_opt()
+                                .unwrap_or_default()
+                                .to_string(),
+                            // SEVERITY_NUMBER_INFO
+                            "severityNumber": 9,
+                            "severityText": "INFO",
+                            "body": {"stringValue": format!("{} {}", method, path)},
+                            "attributes": attributes,
+                        }],
+                    }],
+                }],
+            }),
+        }
+    }
+}
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
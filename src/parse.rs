@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+/// Parse a human-friendly duration such as "500ms", "30s", "10m" or "1h30m".
+/// A bare number is interpreted as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!("invalid duration '{}'", value));
+        }
+        let amount: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("invalid duration '{}'", value))?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ms" => Duration::from_millis(amount),
+            "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount * 60),
+            "h" => Duration::from_secs(amount * 3600),
+            "d" => Duration::from_secs(amount * 86400),
+            unit => {
+                return Err(format!(
+                    "invalid duration unit '{}' in '{}' (use ms, s, m, h or d)",
+                    unit, value
+                ));
+            }
+        };
+        total += unit;
+        rest = &rest[unit_len..];
+    }
+
+    Ok(total)
+}
+
+/// Parse a size such as "512", "64KB", "10MB" or "1GB" (powers of 1024)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let amount: u64 = value[..digits]
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+    let unit = match value[digits..].trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        unit => {
+            return Err(format!(
+                "invalid size unit '{}' in '{}' (use B, KB, MB or GB)",
+                unit, value
+            ));
+        }
+    };
+    Ok(amount * unit)
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use colored::Colorize;
+use console::{Key, Term};
+use std::io::IsTerminal;
+
+/// Choices shown at once; the list scrolls with the selection
+const VISIBLE_ROWS: usize = 10;
+
+/// Whether a selector can be shown: both stdin and stderr are terminals
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && Term::stderr().is_term()
+}
+
+/// Let the user pick one of `items` on stderr. Typing narrows the list down
+/// to the items containing the typed letters in order, arrows move the
+/// selection and Enter picks it. `None` when cancelled with Esc or Ctrl-C.
+pub fn select(prompt: &str, items: &[String]) -> Result<Option<usize>> {
+    let term = Term::stderr();
+    let _ = term.hide_cursor();
+    let result = run(&term, prompt, items);
+    let _ = term.show_cursor();
+    result
+}
+
+fn run(term: &Term, prompt: &str, items: &[String]) -> Result<Option<usize>> {
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut drawn = 0;
+    loop {
+        let matches = matching(&query, items);
+        selected = selected.min(matches.len().saturating_sub(1));
+        term.clear_last_lines(drawn)?;
+        drawn = draw(term, prompt, &query, items, &matches, selected)?;
+
+        match term.read_key()? {
+            Key::Enter => {
+                if let Some(&index) = matches.get(selected) {
+                    term.clear_last_lines(drawn)?;
+                    return Ok(Some(index));
+                }
+            }
+            Key::Escape | Key::CtrlC => {
+                term.clear_last_lines(drawn)?;
+                return Ok(None);
+            }
+            Key::ArrowUp => selected = selected.saturating_sub(1),
+            Key::ArrowDown if selected + 1 < matches.len() => selected += 1,
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Print the prompt and the visible part of the list; returns the lines written
+fn draw(
+    term: &Term,
+    prompt: &str,
+    query: &str,
+    items: &[String],
+    matches: &[usize],
+    selected: usize,
+) -> Result<usize> {
+    let width = term.size().1 as usize;
+    term.write_line(&format!(
+        "{} {} {}",
+        "?".bright_green(),
+        prompt.bold(),
+        query
+    ))?;
+    if matches.is_empty() {
+        term.write_line(&format!("  {}", "(no match)".bright_black()))?;
+        return Ok(2);
+    }
+
+    let first = selected.saturating_sub(VISIBLE_ROWS - 1);
+    let rows = &matches[first..matches.len().min(first + VISIBLE_ROWS)];
+    for (row, &index) in rows.iter().enumerate() {
+        let item = console::truncate_str(&items[index], width.saturating_sub(2), "…");
+        if first + row == selected {
+            term.write_line(&format!("{} {}", ">".bright_cyan(), item.bright_cyan()))?;
+        } else {
+            term.write_line(&format!("  {}", item))?;
+        }
+    }
+    Ok(1 + rows.len())
+}
+
+/// Indices of the items matching `query`: those containing it first, then
+/// those containing its letters in order, case-insensitive
+fn matching(query: &str, items: &[String]) -> Vec<usize> {
+    let query = query.to_lowercase();
+    let (mut contained, mut scattered) = (Vec::new(), Vec::new());
+    for (index, item) in items.iter().enumerate() {
+        let item = console::strip_ansi_codes(item).to_lowercase();
+        if item.contains(&query) {
+            contained.push(index);
+        } else if is_subsequence(&query, &item) {
+            scattered.push(index);
+        }
+    }
+    contained.extend(scattered);
+    contained
+}
+
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query.chars().all(|wanted| chars.any(|c| c == wanted))
+}
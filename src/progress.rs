@@ -0,0 +1,135 @@
+use anyhow::Result;
+use chrono::Local;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static PLAIN: OnceLock<bool> = OnceLock::new();
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Use plain output when asked to, or when running in CI (`CI` is set);
+/// with `quiet`, report no progress and print no informational messages
+pub fn init(plain: bool, quiet: bool) {
+    let ci = std::env::var("CI").is_ok_and(|ci| !ci.is_empty() && ci != "false" && ci != "0");
+    // Ignore if already initialized; first value wins.
+    let _ = PLAIN.set(plain || ci);
+    let _ = QUIET.set(quiet);
+}
+
+/// Whether progress is reported as timestamped lines instead of animations
+pub fn plain() -> bool {
+    PLAIN.get().copied().unwrap_or(false)
+}
+
+/// Whether `--quiet` leaves out everything but the requests themselves
+pub fn quiet() -> bool {
+    QUIET.get().copied().unwrap_or(false)
+}
+
+/// `println!` for informational messages (banners, counts, hints), which
+/// `--quiet` leaves out
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::progress::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// A spinner or progress bar on stderr. Nothing is drawn unless stdout is a
+/// terminal, so piped output and files stay clean, or with `--quiet`; in
+/// plain mode progress is printed as timestamped lines instead.
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    len: Option<u64>,
+    position: AtomicU64,
+    /// Tenths of `len` already reported in plain mode
+    reported: AtomicU64,
+}
+
+impl Progress {
+    pub fn spinner(message: &str) -> Result<Self> {
+        let progress = Self::new(
+            None,
+            ProgressStyle::default_spinner().template("{spinner} {msg}")?,
+        );
+        if let Some(bar) = &progress.bar {
+            bar.enable_steady_tick(std::time::Duration::from_millis(100));
+        }
+        progress.set_message(message);
+        Ok(progress)
+    }
+
+    pub fn bar(len: u64) -> Result<Self> {
+        Ok(Self::new(
+            Some(len),
+            ProgressStyle::default_bar().template("{bar:40} {pos}/{len} {msg}")?,
+        ))
+    }
+
+    fn new(len: Option<u64>, style: ProgressStyle) -> Self {
+        let bar = (!plain() && !quiet()).then(|| {
+            let target = match std::io::stdout().is_terminal() {
+                true => ProgressDrawTarget::stderr(),
+                false => ProgressDrawTarget::hidden(),
+            };
+            let bar = ProgressBar::with_draw_target(len, target);
+            bar.set_style(style);
+            bar
+        });
+        Progress {
+            bar,
+            len,
+            position: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+        }
+    }
+
+    /// Show `message` next to the spinner. Plain mode prints spinner messages
+    /// only; a bar's messages change too often to be worth a line each.
+    pub fn set_message(&self, message: impl Into<String>) {
+        match &self.bar {
+            Some(bar) => bar.set_message(message.into()),
+            None if self.len.is_none() => plain_line(&message.into()),
+            None => {}
+        }
+    }
+
+    /// Advance the bar; plain mode prints a line every tenth of the way
+    pub fn inc(&self, delta: u64) {
+        if let Some(bar) = &self.bar {
+            bar.inc(delta);
+            return;
+        }
+        let position = self.position.fetch_add(delta, Ordering::Relaxed) + delta;
+        if let Some(len) = self.len.filter(|&len| len > 0) {
+            let tenths = position * 10 / len;
+            if self.reported.fetch_max(tenths, Ordering::Relaxed) < tenths {
+                plain_line(&format!("{}/{}", position, len));
+            }
+        }
+    }
+
+    /// Run `print` with the bar hidden, so its output is not drawn over
+    pub fn suspend<R>(&self, print: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(print),
+            None => print(),
+        }
+    }
+
+    pub fn finish_and_clear(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+fn plain_line(message: &str) {
+    if quiet() {
+        return;
+    }
+    eprintln!("[{}] {}", Local::now().format("%H:%M:%S"), message);
+}
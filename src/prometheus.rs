@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use crate::logging;
+use crate::models::{WebhookRequest, extract_path};
+
+/// Upper bounds of the body size buckets, in bytes
+const SIZE_BUCKETS: [f64; 6] = [100.0, 1e3, 1e4, 1e5, 1e6, 1e7];
+
+/// Upper bounds of the poll duration buckets, in seconds
+const DURATION_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Counters and histograms served on `/metrics` by `--metrics-port`
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// By (token, method, path)
+    requests: BTreeMap<(String, String, String), u64>,
+    /// By token
+    body_sizes: BTreeMap<String, Histogram>,
+    /// By token
+    polls: BTreeMap<String, Histogram>,
+    /// By token
+    poll_errors: BTreeMap<String, u64>,
+    /// By status, or `dropped`
+    responses: BTreeMap<String, u64>,
+}
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, not cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(index) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[index] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+impl Metrics {
+    /// A request received for `token` (alias or token)
+    pub fn record_request(&self, token: &str, request: &WebhookRequest) {
+        let path = extract_path(&request.message_object.value, &request.token_id);
+        let path = path.split('?').next().unwrap_or_default().to_string();
+        let size = request.body.as_deref().map_or(0, str::len);
+        let mut registry = self.lock();
+        *registry
+            .requests
+            .entry((
+                token.to_string(),
+                request.message_object.method.to_string(),
+                path,
+            ))
+            .or_default() += 1;
+        registry
+            .body_sizes
+            .entry(token.to_string())
+            .or_insert_with(|| Histogram::new(&SIZE_BUCKETS))
+            .observe(size as f64);
+    }
+
+    /// A poll of the service for `token`, and whether it failed
+    pub fn record_poll(&self, token: &str, elapsed: Duration, failed: bool) {
+        let mut registry = self.lock();
+        registry
+            .polls
+            .entry(token.to_string())
+            .or_insert_with(|| Histogram::new(&DURATION_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+        let errors = registry.poll_errors.entry(token.to_string()).or_default();
+        if failed {
+            *errors += 1;
+        }
+    }
+
+    /// An answer of `serve`, `None` when the connection was dropped
+    pub fn record_response(&self, status: Option<u16>) {
+        let status = status.map_or_else(|| "dropped".to_string(), |status| status.to_string());
+        *self.lock().responses.entry(status).or_default() += 1;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // A panic while recording leaves usable counters behind
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The metrics in the Prometheus text format
+    fn render(&self) -> String {
+        let registry = self.lock();
+        let mut out = String::new();
+        out.push_str("# HELP webhook_requests_total Webhook requests received.\n");
+        out.push_str("# TYPE webhook_requests_total counter\n");
+        for ((token, method, path), count) in &registry.requests {
+            let _ = writeln!(
+                out,
+                "webhook_requests_total{{token=\"{}\",method=\"{}\",path=\"{}\"}} {}",
+                escape(token),
+                escape(method),
+                escape(path),
+                count
+            );
+        }
+        out.push_str("# HELP webhook_request_body_bytes Size of webhook request bodies.\n");
+        out.push_str("# TYPE webhook_request_body_bytes histogram\n");
+        for (token, histogram) in &registry.body_sizes {
+            let labels = format!("token=\"{}\"", escape(token));
+            histogram.render(&mut out, "webhook_request_body_bytes", &labels);
+        }
+        if !registry.polls.is_empty() {
+            out.push_str("# HELP webhook_poll_duration_seconds Time taken by polls of the webhook service.\n");
+            out.push_str("# TYPE webhook_poll_duration_seconds histogram\n");
+            for (token, histogram) in &registry.polls {
+                let labels = format!("token=\"{}\"", escape(token));
+                histogram.render(&mut out, "webhook_poll_duration_seconds", &labels);
+            }
+            out.push_str("# HELP webhook_poll_errors_total Failed polls of the webhook service.\n");
+            out.push_str("# TYPE webhook_poll_errors_total counter\n");
+            for (token, count) in &registry.poll_errors {
+                let _ = writeln!(
+                    out,
+                    "webhook_poll_errors_total{{token=\"{}\"}} {}",
+                    escape(token),
+                    count
+                );
+            }
+        }
+        if !registry.responses.is_empty() {
+            out.push_str("# HELP webhook_responses_total Answers of the local receiver.\n");
+            out.push_str("# TYPE webhook_responses_total counter\n");
+            for (status, count) in &registry.responses {
+                let _ = writeln!(
+                    out,
+                    "webhook_responses_total{{status=\"{}\"}} {}",
+                    status, count
+                );
+            }
+        }
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve `metrics` on `http://<bind>:<port>/metrics` until the process exits;
+/// fails right away when the port cannot be bound
+pub async fn serve_metrics(metrics: Arc<Metrics>, bind: &str, port: u16) -> Result<String> {
+    let listener = TcpListener::bind((bind, port))
+        .await
+        .with_context(|| format!("Failed to listen on {}:{} for metrics", bind, port))?;
+    let url = format!("http://{}/metrics", listener.local_addr()?);
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let metrics = Arc::clone(&metrics);
+            tokio::spawn(async move {
+                if let Err(e) = answer(stream, &metrics).await {
+                    logging::debug(1, format_args!("Metrics request failed: {}", e));
+                }
+            });
+        }
+    });
+    Ok(url)
+}
+
+async fn answer(stream: tokio::net::TcpStream, metrics: &Metrics) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    // Skip the headers
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path.split('?').next() {
+        Some("/metrics") => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", "Not found, try /metrics\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,553 @@
+use anyhow::Result;
+
+/// Total codewords, error correction codewords per block and blocks of
+/// versions 1 to 10 at error correction level M
+const VERSIONS: [(usize, usize, usize); 10] = [
+    (26, 10, 1),
+    (44, 16, 1),
+    (70, 26, 1),
+    (100, 18, 2),
+    (134, 24, 2),
+    (172, 16, 4),
+    (196, 18, 4),
+    (242, 22, 4),
+    (292, 22, 5),
+    (346, 26, 5),
+];
+
+/// Centers of the alignment patterns of versions 2 to 10
+const ALIGNMENT: [&[usize]; 9] = [
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// Light modules around the code, as scanners expect
+const QUIET_ZONE: usize = 4;
+
+/// A QR code of a short text (such as a webhook URL) in byte mode, with
+/// medium error correction
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    pub fn encode(text: &str) -> Result<Self> {
+        let bytes = text.as_bytes();
+        let Some(version) = (1..=VERSIONS.len()).find(|&version| {
+            let (total, ecc, blocks) = VERSIONS[version - 1];
+            4 + count_bits(version) + bytes.len() * 8 <= (total - ecc * blocks) * 8
+        }) else {
+            anyhow::bail!("Too long for a QR code: {} bytes", bytes.len());
+        };
+
+        let mut qr = QrCode {
+            size: version * 4 + 17,
+            modules: Vec::new(),
+            function: Vec::new(),
+        };
+        qr.modules = vec![false; qr.size * qr.size];
+        qr.function = vec![false; qr.size * qr.size];
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&codewords(version, bytes));
+
+        // Keep the mask whose pattern is easiest to scan
+        let mut best = (0, u32::MAX);
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format(mask);
+            let penalty = qr.penalty();
+            if penalty < best.1 {
+                best = (mask, penalty);
+            }
+            qr.apply_mask(mask);
+        }
+        qr.apply_mask(best.0);
+        qr.draw_format(best.0);
+        Ok(qr)
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            self.draw_finder(x, y);
+        }
+        if version > 1 {
+            let centers = ALIGNMENT[version - 2];
+            let last = centers.len() - 1;
+            for (i, &x) in centers.iter().enumerate() {
+                for (j, &y) in centers.iter().enumerate() {
+                    // The corners with finder patterns have none
+                    if [(0, 0), (0, last), (last, 0)].contains(&(i, j)) {
+                        continue;
+                    }
+                    for dy in 0..5usize {
+                        for dx in 0..5usize {
+                            let ring = dx.abs_diff(2).max(dy.abs_diff(2));
+                            self.set_function(x + dx - 2, y + dy - 2, ring != 1);
+                        }
+                    }
+                }
+            }
+        }
+        // Reserve the format areas, drawn once the mask is chosen
+        self.draw_format(0);
+        if version >= 7 {
+            let mut remainder = version as u32;
+            for _ in 0..12 {
+                remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+            }
+            let bits = (version as u32) << 12 | remainder;
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    /// A finder pattern centered on (x, y), with its light separator
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let ring = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, ring != 2 && ring != 4);
+                }
+            }
+        }
+    }
+
+    /// Both copies of the error correction level (M) and mask
+    fn draw_format(&mut self, mask: u32) {
+        let data = mask;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Fill the data area in the zigzag order, two columns at a time from
+    /// the bottom right
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size;
+        let mut index = 0;
+        let mut right = size - 1;
+        loop {
+            // The vertical timing pattern is skipped
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    if !self.function[y * size + x] && index < data.len() * 8 {
+                        self.modules[y * size + x] = (data[index / 8] >> (7 - index % 8)) & 1 == 1;
+                        index += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if flip && !self.function[y * self.size + x] {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// Penalty of the current pattern: long runs, 2x2 blocks, finder-like
+    /// sequences and an unbalanced share of dark modules
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+        for horizontal in [true, false] {
+            for line in 0..size {
+                let module = |i: usize| {
+                    if horizontal {
+                        self.get(i, line)
+                    } else {
+                        self.get(line, i)
+                    }
+                };
+                let mut run = 1;
+                for i in 1..size {
+                    if module(i) == module(i - 1) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+                // Runs of the line, light ones at even indices; the quiet
+                // zone lengthens the first and last
+                let mut runs = vec![size];
+                let mut dark = false;
+                for i in 0..size {
+                    if module(i) == dark {
+                        *runs.last_mut().unwrap() += 1;
+                    } else {
+                        dark = !dark;
+                        runs.push(1);
+                    }
+                }
+                if dark {
+                    runs.push(0);
+                }
+                *runs.last_mut().unwrap() += size;
+                // Dark 1:1:3:1:1 with light four times as wide on one side
+                for window in runs.windows(7).step_by(2) {
+                    let n = window[1];
+                    if window[2..6] == [n, n * 3, n, n] {
+                        let before = window[0] >= n * 4 && window[6] >= n;
+                        let after = window[6] >= n * 4 && window[0] >= n;
+                        penalty += 40 * (before as u32 + after as u32);
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if dark == self.get(x + 1, y)
+                    && dark == self.get(x, y + 1)
+                    && dark == self.get(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&dark| dark).count();
+        let total = size * size;
+        // 10 points for each 5% away from half dark
+        let k = (dark * 20)
+            .abs_diff(total * 10)
+            .div_ceil(total)
+            .saturating_sub(1);
+        penalty + k as u32 * 10
+    }
+
+    /// The code as lines of half-block characters, two modules per
+    /// character: light modules are drawn, for terminals with a dark
+    /// background
+    pub fn render(&self) -> Vec<String> {
+        let padded = self.size + QUIET_ZONE * 2;
+        let light = |x: usize, y: usize| {
+            let inside = QUIET_ZONE..QUIET_ZONE + self.size;
+            !(inside.contains(&x)
+                && inside.contains(&y)
+                && self.get(x - QUIET_ZONE, y - QUIET_ZONE))
+        };
+        (0..padded)
+            .step_by(2)
+            .map(|y| {
+                (0..padded)
+                    .map(|x| match (light(x, y), y + 1 < padded && light(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Bits of the character count in byte mode
+fn count_bits(version: usize) -> usize {
+    if version < 10 { 8 } else { 16 }
+}
+
+/// The data and error correction codewords of `bytes`, interleaved
+fn codewords(version: usize, bytes: &[u8]) -> Vec<u8> {
+    let (total, ecc, blocks) = VERSIONS[version - 1];
+    let capacity = (total - ecc * blocks) * 8;
+
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let mut push = |value: usize, length: usize| {
+        for i in (0..length).rev() {
+            bits.push((value >> i) & 1 == 1);
+        }
+    };
+    push(0b0100, 4);
+    push(bytes.len(), count_bits(version));
+    for &byte in bytes {
+        push(byte as usize, 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    while !bits.len().is_multiple_of(8) {
+        bits.push(false);
+    }
+    let mut data: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if data.len() * 8 >= capacity {
+            break;
+        }
+        data.push(pad);
+    }
+
+    // Later blocks hold one more data codeword than the first ones
+    let short_blocks = blocks - total % blocks;
+    let short_length = total / blocks;
+    let divisor = rs_divisor(ecc);
+    let mut split = Vec::with_capacity(blocks);
+    let mut offset = 0;
+    for block in 0..blocks {
+        let length = short_length - ecc + usize::from(block >= short_blocks);
+        let chunk = &data[offset..offset + length];
+        offset += length;
+        split.push((chunk.to_vec(), rs_remainder(chunk, &divisor)));
+    }
+
+    let mut result = Vec::with_capacity(total);
+    for i in 0..short_length - ecc + 1 {
+        for (chunk, _) in &split {
+            if let Some(&byte) = chunk.get(i) {
+                result.push(byte);
+            }
+        }
+    }
+    for i in 0..ecc {
+        for (_, remainder) in &split {
+            result.push(remainder[i]);
+        }
+    }
+    result
+}
+
+/// Product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u16 >> i) & 1) * x as u16;
+    }
+    z as u8
+}
+
+/// Reed-Solomon generator polynomial of `degree`, highest coefficient
+/// (always 1) omitted
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (value, &coefficient) in result.iter_mut().zip(divisor) {
+            *value ^= gf_multiply(coefficient, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known matrices from an independent encoder (Nayuki's qrcodegen),
+    // byte mode at level M with the mask it picks
+    const VERSION_1: &[&str] = &[
+        "#######....##.#######",
+        "#.....#...#...#.....#",
+        "#.###.#.#####.#.###.#",
+        "#.###.#.#.##..#.###.#",
+        "#.###.#.#.#.#.#.###.#",
+        "#.....#.#..#..#.....#",
+        "#######.#.#.#.#######",
+        "........###..........",
+        "#.#####....#..#####..",
+        "#..#.#.###.########.#",
+        "#####.#.##..##.#.###.",
+        "##..##..######..#####",
+        "#....###.##.#.###..#.",
+        "........##..#..##.###",
+        "#######...##.##....#.",
+        "#.....#.##...#..#####",
+        "#.###.#.#.##..#.#...#",
+        "#.###.#.#...#####....",
+        "#.###.#.#...#.....#..",
+        "#.....#..#####..###..",
+        "#######.#.....#.#..#.",
+    ];
+
+    const VERSION_7: &[&str] = &[
+        "#######....#..#.#..#.#...#.#######..#.#######",
+        "#.....#..#.###.#..#######.##...##..#..#.....#",
+        "#.###.#.##...#.#.#.#.#..#..###.###.#..#.###.#",
+        "#.###.#.#...#..#..#.#.###....##....##.#.###.#",
+        "#.###.#.#.#.#.####.######...#.##..###.#.###.#",
+        "#.....#.#.##......#.#...###..#..##....#.....#",
+        "#######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######",
+        "........####....#####...##.####.#...#........",
+        "#.#####...........#.######...###..#...#####..",
+        "..........##..#..##..#..#..#.##.#..##...#.###",
+        "..#...#..#.#.##.#.#..###..#..#.#.###.###...#.",
+        "##.#...##..#.##.#####....#..#.#.#.##.#.##.#..",
+        ".##.#.###....###..#.####..##.#.........#.#..#",
+        "#...##.##.##.####..###.#....#.#.##..##..#####",
+        "#..##.#.#....##.....#...####.#.#..###.##.##..",
+        "#.##...##.##.....###.#...#..#...#..###..####.",
+        ".#..###.##....#..####..##..#.#.#.#.#.#.....##",
+        ".#.#.#....#..##..#...#......#.###..###.#..###",
+        ".#...##.##.#.##.###.#.#####....#####.###.###.",
+        "######.##.#.....##.#.#.##.#.######.#.##.#.#..",
+        ".#..#####..#......########.#.##..##.######..#",
+        "##.##...#....###.#.##...#....#####.##...###.#",
+        "..#.#.#.##..#....#.##.#.###......####.#.#.##.",
+        "..#.#...#.#####.#..##...###.#.###...#...#####",
+        "...######.#..#...#.########....#...#######.#.",
+        ".#.#.......#..#...##..#......###....##.#..#.#",
+        ".###..##....###.##..##.#.#####..###........#.",
+        "..#..#..#.##...#.##.##..#.#####.#######.#####",
+        "##.#..####...##.##..#..........#....#.#.#....",
+        ".##..#.###.###...##..#####.#..###...#.#...#.#",
+        "..#.###.##...##..#..#...#.#..#...####..#.....",
+        "##......#..#.#..###.....#...##..#..#..##..#..",
+        "...#.###.#..##..#....#..####...#....#...#...#",
+        ".#.#.#.#..#.#.#.#######......##.....####..#.#",
+        "....#.#####....#..#.#.##.##.##..#####....###.",
+        ".####..#...##..#.##.###.##.####.#.#####..##..",
+        "#..##.###.#.###....#######....##....#####..#.",
+        "........##..#.#...#.#...#..##.#..#.##...#####",
+        "#######..#......##.##.#.###..#.#..###.#.#.#..",
+        "#.....#.#.###..#.##.#...#..##...##.##...####.",
+        "#.###.#.####..##.#..#####....###.#########.##",
+        "#.###.#.#.#.####..###.####..#.#....#....#####",
+        "#.###.#.##.#..##.#...#..#.##.#.####.###...##.",
+        "#.....#......##.#...####...#######..##..#.#..",
+        "#######.#..##......#....##...#........#.#..#.",
+    ];
+
+    fn rows(qr: &QrCode) -> Vec<String> {
+        (0..qr.size)
+            .map(|y| {
+                (0..qr.size)
+                    .map(|x| if qr.get(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn version_1_matrix() {
+        assert_eq!(rows(&QrCode::encode("webhook-cli").unwrap()), VERSION_1);
+    }
+
+    #[test]
+    fn version_7_matrix() {
+        // Version 7 is the first with version information blocks
+        let url = "https://webhook.site/0f4c9e1a-7b2d-4c8e-9a3f-5d6e7f8a9b0c/github/push?delivery=72d3162e-cc78-11e3-81ab-4c9367dc0958";
+        assert_eq!(rows(&QrCode::encode(url).unwrap()), VERSION_7);
+    }
+
+    #[test]
+    fn render_has_four_module_quiet_zone() {
+        let lines = QrCode::encode("webhook-cli").unwrap().render();
+        let width: usize = 21 + 4 * 2;
+        assert_eq!(lines.len(), width.div_ceil(2));
+        for line in &lines[..2] {
+            assert_eq!(line, &"█".repeat(width));
+        }
+        let (last, lines) = lines.split_last().unwrap();
+        for line in lines {
+            assert!(line.starts_with("████") && line.ends_with("████"));
+        }
+        // The odd last row has only the upper half
+        assert_eq!(last, &"▀".repeat(width));
+    }
+
+    #[test]
+    fn too_long_text() {
+        assert!(QrCode::encode(&"x".repeat(400)).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::config::RedactConfig;
+use crate::models::WebhookRequest;
+use crate::state::data_dir;
+
+/// Headers whose values are always replaced, in addition to `redact.headers`
+const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Anonymizes requests for `export --redact`. Values are replaced by fakes
+/// derived from a keyed hash of the original, so the same email or card number
+/// maps to the same fake everywhere without the original being recoverable.
+pub struct Redactor {
+    key: hmac::Key,
+    headers: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactConfig) -> Result<Self> {
+        Ok(Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &load_key()?),
+            headers: SENSITIVE_HEADERS
+                .iter()
+                .map(|name| name.to_string())
+                .chain(config.headers.iter().map(|name| name.to_ascii_lowercase()))
+                .collect(),
+            paths: config.paths.clone(),
+        })
+    }
+
+    pub fn redact(&self, request: &mut WebhookRequest) {
+        if let Some(body) = &mut request.body {
+            self.mask_paths(body);
+            *body = self.scrub(body);
+        }
+        if let Some(value) = &mut request.body_object {
+            for path in &self.paths {
+                if let Some(value) = value.pointer_mut(path) {
+                    *value = serde_json::Value::String(self.fake_value(&value.to_string()));
+                }
+            }
+            self.scrub_json(value);
+        }
+        if let Some(message) = &mut request.message {
+            *message = self.scrub(message);
+        }
+
+        let message = &mut request.message_object;
+        message.value = self.scrub(&message.value);
+        for parameter in &mut message.query_parameters {
+            *parameter = self.scrub(parameter);
+        }
+        for (name, values) in &mut message.headers {
+            let sensitive = self.headers.contains(&name.to_ascii_lowercase());
+            for value in values {
+                *value = if sensitive {
+                    self.fake_value(value)
+                } else {
+                    self.scrub(value)
+                };
+            }
+        }
+    }
+
+    /// Replace the values at `redact.paths` of a JSON body, keeping its layout
+    /// (pretty-printed or compact)
+    fn mask_paths(&self, body: &mut String) {
+        if self.paths.is_empty() {
+            return;
+        }
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(body) else {
+            return;
+        };
+        let mut masked = false;
+        for path in &self.paths {
+            if let Some(value) = value.pointer_mut(path) {
+                *value = serde_json::Value::String(self.fake_value(&value.to_string()));
+                masked = true;
+            }
+        }
+        if !masked {
+            return;
+        }
+        let json = if body.trim().contains('\n') {
+            serde_json::to_string_pretty(&value)
+        } else {
+            serde_json::to_string(&value)
+        };
+        if let Ok(json) = json {
+            *body = json;
+        }
+    }
+
+    fn scrub_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.scrub(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.scrub_json(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.scrub_json(v)),
+            _ => {}
+        }
+    }
+
+    /// Replace emails, card numbers and bearer tokens found in `text`
+    fn scrub(&self, text: &str) -> String {
+        let text = replace_ranges(text, find_bearer_tokens(text), |token| {
+            format!("redacted-{}", self.digest_hex(token, 16))
+        });
+        let text = replace_ranges(&text, find_emails(&text), |email| {
+            format!("user-{}@example.com", self.digest_hex(email, 8))
+        });
+        replace_ranges(&text, find_card_numbers(&text), |card| self.fake_card(card))
+    }
+
+    fn fake_value(&self, value: &str) -> String {
+        format!("redacted-{}", self.digest_hex(value, 8))
+    }
+
+    /// A Luhn-valid number with the same length and separators
+    fn fake_card(&self, card: &str) -> String {
+        let digest = self.digest(card);
+        let count = card.chars().filter(char::is_ascii_digit).count();
+        // Test card numbers start with 4 (Visa)
+        let mut digits: Vec<u32> = std::iter::once(4)
+            .chain(digest.as_ref().iter().map(|byte| u32::from(*byte) % 10))
+            .take(count - 1)
+            .collect();
+        let check = (10 - luhn_sum(&digits, true) % 10) % 10;
+        digits.push(check);
+
+        let mut digits = digits.into_iter();
+        card.chars()
+            .map(|c| {
+                if c.is_ascii_digit() {
+                    char::from_digit(digits.next().unwrap_or(0), 10).unwrap_or('0')
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    fn digest(&self, value: &str) -> hmac::Tag {
+        hmac::sign(&self.key, value.as_bytes())
+    }
+
+    fn digest_hex(&self, value: &str, len: usize) -> String {
+        self.digest(value)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()[..len]
+            .to_string()
+    }
+}
+
+/// The key of the fakes, created on first use so they stay the same across
+/// exports on this machine
+fn load_key() -> Result<Vec<u8>> {
+    let path = key_path()?;
+    match fs::read(&path) {
+        Ok(key) if !key.is_empty() => return Ok(key),
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+    let mut key = vec![0; 32];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| anyhow::anyhow!("Failed to generate a redaction key"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, &key).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(key)
+}
+
+fn key_path() -> Result<PathBuf> {
+    Ok(data_dir()
+        .context("Could not determine the data directory")?
+        .join("webhook-cli")
+        .join("redact.key"))
+}
+
+fn replace_ranges(text: &str, ranges: Vec<Range<usize>>, fake: impl Fn(&str) -> String) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut end = 0;
+    for range in ranges {
+        result.push_str(&text[end..range.start]);
+        result.push_str(&fake(&text[range.clone()]));
+        end = range.end;
+    }
+    result.push_str(&text[end..]);
+    result
+}
+
+/// The token after each `Bearer ` (case-insensitive)
+fn find_bearer_tokens(text: &str) -> Vec<Range<usize>> {
+    let lower = text.to_ascii_lowercase();
+    let bytes = text.as_bytes();
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    while let Some(index) = lower[from..].find("bearer ") {
+        let start = from + index + "bearer ".len();
+        let end = start
+            + bytes[start..]
+                .iter()
+                .take_while(|&&b| b.is_ascii_alphanumeric() || b"-._~+/=".contains(&b))
+                .count();
+        if end > start {
+            ranges.push(start..end);
+        }
+        from = end;
+    }
+    ranges
+}
+
+fn find_emails(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || b"._%+-".contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'.';
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (at, _) in text.match_indices('@') {
+        if ranges.last().is_some_and(|last| at < last.end) {
+            continue;
+        }
+        let start = at
+            - bytes[..at]
+                .iter()
+                .rev()
+                .take_while(|&&b| is_local(b))
+                .count();
+        let mut end = at
+            + 1
+            + bytes[at + 1..]
+                .iter()
+                .take_while(|&&b| is_domain(b))
+                .count();
+        while end > at + 1 && bytes[end - 1] == b'.' {
+            end -= 1;
+        }
+        let domain = &text[at + 1..end];
+        let valid_tld = domain.rsplit_once('.').is_some_and(|(name, tld)| {
+            !name.is_empty() && tld.len() >= 2 && tld.bytes().all(|b| b.is_ascii_alphabetic())
+        });
+        if start < at && valid_tld {
+            ranges.push(start..end);
+        }
+    }
+    ranges
+}
+
+/// Runs of 13 to 19 digits, optionally grouped with spaces or dashes, that
+/// pass the Luhn check and start like a card number (2 to 6)
+fn find_card_numbers(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let preceded = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'.');
+        if !bytes[i].is_ascii_digit() || preceded {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i;
+        let mut digits = Vec::new();
+        while end < bytes.len() {
+            if bytes[end].is_ascii_digit() {
+                digits.push(u32::from(bytes[end] - b'0'));
+                end += 1;
+            } else if matches!(bytes[end], b' ' | b'-')
+                && bytes.get(end + 1).is_some_and(u8::is_ascii_digit)
+            {
+                end += 1;
+            } else {
+                break;
+            }
+        }
+        let followed = bytes
+            .get(end)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'.');
+        if (13..=19).contains(&digits.len())
+            && (2..=6).contains(&digits[0])
+            && !followed
+            && luhn_sum(&digits, false).is_multiple_of(10)
+        {
+            ranges.push(start..end);
+        }
+        i = end;
+    }
+    ranges
+}
+
+/// Luhn sum of `digits`; with `pending_check`, as if a check digit followed
+fn luhn_sum(digits: &[u32], pending_check: bool) -> u32 {
+    digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, &digit)| {
+            if (index % 2 == 0) == pending_check {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum()
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use reqwest::Method;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::display::format_method;
+use crate::metrics::format_short;
+use crate::models::{self, WebhookRequest};
+use crate::send::http_client;
+use crate::signature::Signer;
+
+/// Headers of the original connection, not copied to replayed requests
+const CONNECTION_HEADERS: [&str; 6] = [
+    "host",
+    "content-length",
+    "connection",
+    "keep-alive",
+    "transfer-encoding",
+    "expect",
+];
+
+/// Load a session exported with `--format ndjson`, oldest request first
+pub fn load_session(path: &Path) -> Result<Vec<WebhookRequest>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read session file: {}", path.display()))?;
+    let mut requests = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<WebhookRequest>(line).with_context(|| {
+                format!("{}: invalid request on line {}", path.display(), index + 1)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    requests.sort_by_key(|request| request.date);
+    Ok(requests)
+}
+
+/// `webhook replay`: send `requests` to `target` in order. With a `speed`,
+/// wait between requests as long as between their original arrivals,
+/// divided by the speed.
+pub async fn replay(
+    config: &Config,
+    target: &str,
+    requests: &[WebhookRequest],
+    speed: Option<f64>,
+    signer: Option<&Signer>,
+) -> Result<()> {
+    if requests.is_empty() {
+        anyhow::bail!("The session has no requests");
+    }
+    let client = http_client(config)?;
+
+    let started = Instant::now();
+    let first = requests[0].date;
+    let mut failed = 0;
+    for (index, recorded) in requests.iter().enumerate() {
+        // Sleeping until an offset from the start keeps slow responses from
+        // stretching the schedule
+        let offset = speed.and_then(|speed| {
+            let date = recorded.date?;
+            let gap = (date - first?).to_std().ok()?;
+            Some(gap.div_f64(speed))
+        });
+        if let Some(offset) = offset {
+            tokio::time::sleep_until((started + offset).into()).await;
+        }
+
+        let message = &recorded.message_object;
+        if message.method == models::Method::Unknown {
+            anyhow::bail!("Request {} has no recorded method to replay", recorded.id);
+        }
+        let method = Method::from_bytes(message.method.as_str().as_bytes())
+            .with_context(|| format!("Invalid method: {}", message.method))?;
+        let body = recorded.body.clone().unwrap_or_default();
+        // Replaces the recorded signature, made with another secret
+        let signature = signer.map(|signer| signer.sign(&body));
+        let mut request = client.request(method.clone(), target);
+        for (name, values) in &message.headers {
+            if CONNECTION_HEADERS.contains(&name.to_lowercase().as_str())
+                || signature
+                    .as_ref()
+                    .is_some_and(|(header, _)| header.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            for value in values {
+                request = request.header(name, value);
+            }
+        }
+        if let Some((name, value)) = signature {
+            request = request.header(name, value);
+        }
+
+        let sent = Instant::now();
+        let at = sent.duration_since(started);
+        let outcome = request.body(body).send().await;
+        let elapsed = sent.elapsed();
+        let status = match &outcome {
+            Ok(response) if response.status().is_success() => {
+                response.status().to_string().bright_green()
+            }
+            Ok(response) => response.status().to_string().bright_red(),
+            Err(e) => format!("error: {}", e).bright_red(),
+        };
+        if !outcome.is_ok_and(|response| response.status().is_success()) {
+            failed += 1;
+        }
+        println!(
+            "{} {} {} {} {}",
+            format!("[{}/{}]", index + 1, requests.len()).bright_black(),
+            format!("+{}", format_short(at)).bright_black(),
+            format_method(method.as_str()),
+            status,
+            format_short(elapsed).bright_black()
+        );
+    }
+
+    println!();
+    let total = format_short(started.elapsed());
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} requests failed (replayed in {})",
+            failed,
+            requests.len(),
+            total
+        );
+    }
+    println!(
+        "{} Replayed {} requests in {}",
+        "✓".bright_green(),
+        requests.len(),
+        total
+    );
+    Ok(())
+}
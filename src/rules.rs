@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::models::WebhookRequest;
+use crate::parse::parse_duration;
+
+/// How `webhook serve` answers requests matching `method` and `path`
+#[derive(Debug)]
+pub struct Rule {
+    method: Option<String>,
+    /// Glob where `*` matches any characters, e.g. `/payments/*`
+    path: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Template with `{{...}}` placeholders, see `render`
+    body: Option<String>,
+    delay: Option<Duration>,
+    /// Probability of answering with `failure_status` instead
+    failure_rate: f64,
+    failure_status: u16,
+}
+
+/// A response chosen by the rules
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub delay: Option<Duration>,
+}
+
+impl Response {
+    /// The answer when no rule matches
+    pub fn ok() -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: b"{\"ok\":true}".to_vec(),
+            delay: None,
+        }
+    }
+}
+
+/// Load response rules from a YAML file: a list of rules, each with a `path`
+/// and optionally `method`, `status`, `headers`, `body`, `delay`,
+/// `failure_rate` and `failure_status`
+pub fn load_rules(path: &Path) -> Result<Vec<Rule>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read rules file: {}", path.display()))?;
+    let documents = YamlLoader::load_from_str(&content)
+        .with_context(|| format!("Failed to parse rules file: {}", path.display()))?;
+    let Some(document) = documents.first() else {
+        return Ok(Vec::new());
+    };
+    let items = match document {
+        Yaml::Array(items) => items,
+        Yaml::Hash(_) => match &document["rules"] {
+            Yaml::Array(items) => items,
+            _ => anyhow::bail!("{}: expected a list of rules", path.display()),
+        },
+        _ => anyhow::bail!("{}: expected a list of rules", path.display()),
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            parse_rule(item).with_context(|| format!("{}: rule {}", path.display(), index + 1))
+        })
+        .collect()
+}
+
+fn parse_rule(item: &Yaml) -> Result<Rule> {
+    let Yaml::Hash(map) = item else {
+        anyhow::bail!("expected a mapping");
+    };
+    for key in map.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !matches!(
+            key,
+            "path"
+                | "method"
+                | "status"
+                | "headers"
+                | "body"
+                | "delay"
+                | "failure_rate"
+                | "failure_status"
+        ) {
+            anyhow::bail!("unknown key `{}`", key);
+        }
+    }
+
+    let path = scalar(&item["path"]).context("`path` is required")?;
+    let status = match &item["status"] {
+        Yaml::BadValue => 200,
+        value => status_code(value).context("`status` must be an HTTP status code")?,
+    };
+    let mut headers = Vec::new();
+    match &item["headers"] {
+        Yaml::BadValue => {}
+        Yaml::Hash(map) => {
+            for (name, value) in map {
+                let name = scalar(name).context("invalid header name")?;
+                let value =
+                    scalar(value).with_context(|| format!("invalid value for header {}", name))?;
+                headers.push((name, value));
+            }
+        }
+        _ => anyhow::bail!("`headers` must be a mapping"),
+    }
+    let delay = match &item["delay"] {
+        Yaml::BadValue => None,
+        value => Some(
+            parse_duration(&scalar(value).context("`delay` must be a duration")?)
+                .map_err(|e| anyhow::anyhow!("`delay`: {}", e))?,
+        ),
+    };
+    let failure_rate = match &item["failure_rate"] {
+        Yaml::BadValue => 0.0,
+        value => value
+            .as_f64()
+            .or_else(|| value.as_i64().map(|rate| rate as f64))
+            .context("`failure_rate` must be a number between 0 and 1")?,
+    };
+    if !(0.0..=1.0).contains(&failure_rate) {
+        anyhow::bail!("`failure_rate` must be between 0 and 1");
+    }
+    let failure_status = match &item["failure_status"] {
+        Yaml::BadValue => 500,
+        value => status_code(value).context("`failure_status` must be an HTTP status code")?,
+    };
+
+    Ok(Rule {
+        method: scalar(&item["method"]).map(|method| method.to_uppercase()),
+        path,
+        status,
+        headers,
+        body: scalar(&item["body"]),
+        delay,
+        failure_rate,
+        failure_status,
+    })
+}
+
+pub fn scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(value) => Some(value.clone()),
+        Yaml::Integer(value) => Some(value.to_string()),
+        Yaml::Real(value) => Some(value.clone()),
+        Yaml::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn status_code(value: &Yaml) -> Option<u16> {
+    value
+        .as_i64()
+        .and_then(|status| u16::try_from(status).ok())
+        .filter(|status| (100..=599).contains(status))
+}
+
+impl Rule {
+    fn matches(&self, request: &WebhookRequest) -> bool {
+        let method = &request.message_object.method;
+        let path = request.message_object.value.split('?').next().unwrap_or("");
+        self.method
+            .as_ref()
+            .is_none_or(|expected| method.is(expected))
+            && glob_match(&self.path, path)
+    }
+}
+
+/// The response of the first rule matching `request`
+pub fn respond(rules: &[Rule], request: &WebhookRequest) -> Response {
+    let Some(rule) = rules.iter().find(|rule| rule.matches(request)) else {
+        return Response::ok();
+    };
+    let status = if rule.failure_rate > 0.0 && random_fraction() < rule.failure_rate {
+        rule.failure_status
+    } else {
+        rule.status
+    };
+    Response {
+        status,
+        headers: rule.headers.clone(),
+        body: rule
+            .body
+            .as_deref()
+            .map(|body| render(body, request).into_bytes())
+            .unwrap_or_default(),
+        delay: rule.delay,
+    }
+}
+
+/// Fill in `{{id}}`, `{{method}}`, `{{path}}`, `{{query}}`, `{{date}}`,
+/// `{{body}}`, `{{header:NAME}}` and `{{json:/POINTER}}`; unknown
+/// placeholders are kept as is
+pub fn render(template: &str, request: &WebhookRequest) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        match placeholder(name, request) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn placeholder(name: &str, request: &WebhookRequest) -> Option<String> {
+    let message = &request.message_object;
+    let (path, query) = message
+        .value
+        .split_once('?')
+        .unwrap_or((&message.value, ""));
+    match name {
+        "id" => Some(request.id.clone()),
+        "method" => Some(message.method.to_string()),
+        "path" => Some(path.to_string()),
+        "query" => Some(query.to_string()),
+        "date" => Some(request.date_string()),
+        "body" => Some(request.body.clone().unwrap_or_default()),
+        _ => {
+            if let Some(header) = name.strip_prefix("header:") {
+                return Some(
+                    message
+                        .header(header.trim())
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+            }
+            let pointer = name.strip_prefix("json:")?;
+            let json: serde_json::Value = serde_json::from_str(request.body.as_deref()?).ok()?;
+            Some(match json.pointer(pointer.trim())? {
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+        }
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A uniformly distributed number in [0, 1)
+pub fn random_fraction() -> f64 {
+    let mut bytes = [0; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 0.0;
+    }
+    (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,512 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use colored::Colorize;
+use console::Term;
+use futures_util::future::join_all;
+use futures_util::stream::{StreamExt, select_all};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use crate::cli::{Column, GroupBy};
+use crate::client::WebhookClient;
+use crate::commands::sort_requests;
+use crate::config::Config;
+use crate::display::{format_elapsed, format_method};
+use crate::highlight::highlight_color;
+use crate::keys::{KeyCommand, spawn_key_reader};
+use crate::models::{WebhookRequest, extract_path};
+use crate::monitor::{MonitorOptions, MonitoredToken};
+use crate::watch::{StreamOptions, WatchEvent};
+
+/// Requests shown in a screen refresh, with the name of the token they belong to
+struct Row<'a> {
+    token_name: &'a str,
+    request: WebhookRequest,
+}
+
+/// Requests counted under one `--group-by` key
+#[derive(Default)]
+struct Group {
+    count: usize,
+    /// Requests that arrived after monitoring started
+    new: usize,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+/// Full-screen mode: clear and redraw the latest requests on every poll,
+/// like `watch`, instead of appending to the scrollback
+pub async fn watch_screen(
+    client: &WebhookClient,
+    tokens: &[MonitoredToken],
+    options: &MonitorOptions,
+) -> Result<usize> {
+    let term = Term::stdout();
+    let _ = term.hide_cursor();
+    let mut keys = spawn_key_reader();
+
+    // IDs present on the first refresh, to tell new arrivals apart
+    let mut initial_ids: Option<HashSet<String>> = None;
+    let mut new_ids: HashSet<String> = HashSet::new();
+
+    let time_budget = async {
+        match options.duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(time_budget);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let refresh = async {
+            let (rows, errors) = fetch_rows(client, tokens, options).await;
+
+            let initial = initial_ids
+                .get_or_insert_with(|| rows.iter().map(|row| row.request.id.clone()).collect());
+            for row in &rows {
+                if !initial.contains(&row.request.id) {
+                    new_ids.insert(row.request.id.clone());
+                }
+            }
+
+            render(&term, tokens, options, &rows, &errors, &new_ids);
+            tokio::time::sleep(Duration::from_secs(options.interval)).await;
+        };
+
+        let next_key = async {
+            match &mut keys {
+                Some((receiver, _)) => receiver.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = refresh => {}
+            _ = &mut time_budget => break,
+            _ = &mut ctrl_c => break,
+            Some(KeyCommand::Quit) = next_key => break,
+        }
+
+        if options.max_requests.is_some_and(|max| new_ids.len() >= max) {
+            break;
+        }
+    }
+
+    drop(keys);
+    let _ = term.show_cursor();
+    println!();
+    println!(
+        "{}: {}",
+        "New requests".bright_blue().bold(),
+        new_ids.len().to_string().bright_white()
+    );
+    Ok(new_ids.len())
+}
+
+async fn fetch_rows<'a>(
+    client: &WebhookClient,
+    tokens: &'a [MonitoredToken],
+    options: &MonitorOptions,
+) -> (Vec<Row<'a>>, Vec<String>) {
+    let results = join_all(
+        tokens
+            .iter()
+            .map(|monitored| client.get_requests(&monitored.token, options.count)),
+    )
+    .await;
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (monitored, result) in tokens.iter().zip(results) {
+        match result {
+            Ok(requests) => rows.extend(
+                requests
+                    .into_iter()
+                    .filter(|req| {
+                        monitored
+                            .method_filter
+                            .as_deref()
+                            .is_none_or(|method| req.message_object.method.is(method))
+                    })
+                    .map(|request| Row {
+                        token_name: &monitored.name,
+                        request,
+                    }),
+            ),
+            Err(e) => errors.push(format!("{}: {}", monitored.name, e)),
+        }
+    }
+
+    // Several tokens may return up to --count requests each; keep the latest N
+    // before applying the display order
+    rows.sort_by_key(|row| std::cmp::Reverse(row.request.date));
+    rows.truncate(options.count as usize);
+    sort_requests(&mut rows, options.sort, options.reverse, |row| &row.request);
+
+    (rows, errors)
+}
+
+fn render(
+    term: &Term,
+    tokens: &[MonitoredToken],
+    options: &MonitorOptions,
+    rows: &[Row],
+    errors: &[String],
+    new_ids: &HashSet<String>,
+) {
+    let (_, width) = term.size();
+    let width = width as usize;
+
+    let _ = term.clear_screen();
+    let names: Vec<&str> = tokens.iter().map(|t| t.name.as_str()).collect();
+    println!(
+        "{} {}  {}",
+        format!("Every {}s:", options.interval).bright_black(),
+        names.join(", ").bright_white(),
+        Local::now().format("%H:%M:%S").to_string().bright_black()
+    );
+    println!(
+        "{} requests, {} new since start",
+        rows.len(),
+        new_ids.len().to_string().bright_green()
+    );
+    println!();
+
+    let header: Vec<String> = options
+        .columns
+        .iter()
+        .map(|column| pad(column_title(*column), column_width(*column, options, width)))
+        .collect();
+    println!("  {}", header.join(" ").bright_cyan().bold());
+
+    for row in rows {
+        let request = &row.request;
+        let highlight = highlight_color(&options.highlights, request);
+        let cells: Vec<String> = options
+            .columns
+            .iter()
+            .map(|column| {
+                let cell = pad(
+                    &column_value(*column, row),
+                    column_width(*column, options, width),
+                );
+                match (*column, highlight) {
+                    (_, Some(color)) => cell.color(color).bold().to_string(),
+                    (Column::Method, None) => format_method(&cell).to_string(),
+                    _ => cell,
+                }
+            })
+            .collect();
+
+        let marker = if new_ids.contains(&request.id) {
+            "●".bright_green()
+        } else {
+            " ".normal()
+        };
+        println!("{} {}", marker, cells.join(" "));
+    }
+
+    for error in errors {
+        println!("{} {}", "Error:".bright_red(), error);
+    }
+}
+
+/// `monitor --group-by`: keep a full-screen table of request counts per
+/// group, redrawn every second, instead of listing each request
+pub async fn watch_groups(
+    client: &WebhookClient,
+    config: &Config,
+    tokens: &[MonitoredToken],
+    options: &MonitorOptions,
+    group_by: GroupBy,
+) -> Result<usize> {
+    let term = Term::stdout();
+    let _ = term.hide_cursor();
+    let mut keys = spawn_key_reader();
+
+    let mut events = select_all(tokens.iter().map(|monitored| {
+        let stream_options = StreamOptions {
+            interval: Duration::from_secs(options.interval),
+            max_interval: Duration::from_secs(options.max_interval),
+            count: options.count,
+            transport: config.get_transport(),
+            since_id: None,
+        };
+        client
+            .watch(&monitored.token, stream_options)
+            .map(move |event| (monitored, event))
+            .boxed_local()
+    }));
+
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    // The latest failure of each token, until it recovers
+    let mut errors: BTreeMap<&str, String> = BTreeMap::new();
+    let mut new_requests = 0;
+
+    let time_budget = async {
+        match options.duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(time_budget);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut redraw = tokio::time::interval(Duration::from_secs(1));
+
+    let result = loop {
+        let next_key = async {
+            match &mut keys {
+                Some((receiver, _)) => receiver.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            event = events.next() => {
+                let Some((monitored, event)) = event else { break Ok(()) };
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => break Err(e),
+                };
+                let accepts = |request: &WebhookRequest| {
+                    monitored
+                        .method_filter
+                        .as_deref()
+                        .is_none_or(|method| request.message_object.method.is(method))
+                };
+                match event {
+                    WatchEvent::Existing(requests) | WatchEvent::Missed(requests) => {
+                        for request in requests.iter().filter(|request| accepts(request)) {
+                            count_request(&mut groups, group_by, request, false);
+                        }
+                    }
+                    WatchEvent::Request(request) if accepts(&request) => {
+                        count_request(&mut groups, group_by, &request, true);
+                        new_requests += 1;
+                    }
+                    WatchEvent::Failed { error, .. } | WatchEvent::Disconnected(Some(error)) => {
+                        errors.insert(&monitored.name, error.to_string());
+                    }
+                    WatchEvent::Restored | WatchEvent::Connected => {
+                        errors.remove(monitored.name.as_str());
+                    }
+                    _ => {}
+                }
+            }
+            _ = redraw.tick() => render_groups(&term, tokens, group_by, &groups, &errors, new_requests),
+            _ = &mut time_budget => break Ok(()),
+            _ = &mut ctrl_c => break Ok(()),
+            Some(KeyCommand::Quit) = next_key => break Ok(()),
+        }
+
+        if options.max_requests.is_some_and(|max| new_requests >= max) {
+            break Ok(());
+        }
+    };
+
+    drop(keys);
+    if result.is_ok() {
+        render_groups(&term, tokens, group_by, &groups, &errors, new_requests);
+    }
+    let _ = term.show_cursor();
+    result?;
+    println!();
+    println!(
+        "{}: {}",
+        "New requests".bright_blue().bold(),
+        new_requests.to_string().bright_white()
+    );
+    Ok(new_requests)
+}
+
+fn count_request(
+    groups: &mut HashMap<String, Group>,
+    group_by: GroupBy,
+    request: &WebhookRequest,
+    new: bool,
+) {
+    let key = match group_by {
+        GroupBy::Path => {
+            let path = extract_path(&request.message_object.value, &request.token_id);
+            path.split('?').next().unwrap_or_default().to_string()
+        }
+        GroupBy::Method => request.message_object.method.to_string(),
+        GroupBy::ProviderEvent => request
+            .event_type()
+            .unwrap_or_else(|| "(no event type)".to_string()),
+    };
+    let group = groups.entry(key).or_default();
+    group.count += 1;
+    if new {
+        group.new += 1;
+    }
+    if request.date > group.last_seen {
+        group.last_seen = request.date;
+    }
+}
+
+/// Busiest groups first; as many as fit on the terminal
+fn render_groups(
+    term: &Term,
+    tokens: &[MonitoredToken],
+    group_by: GroupBy,
+    groups: &HashMap<String, Group>,
+    errors: &BTreeMap<&str, String>,
+    new_requests: usize,
+) {
+    const COUNT_WIDTH: usize = 7;
+    const LAST_SEEN_WIDTH: usize = 22;
+    let (height, width) = term.size();
+    let key_width = (width as usize)
+        .saturating_sub(2 + 2 * (COUNT_WIDTH + 1) + LAST_SEEN_WIDTH)
+        .max(10);
+
+    let _ = term.clear_screen();
+    let names: Vec<&str> = tokens.iter().map(|t| t.name.as_str()).collect();
+    println!(
+        "{} {}  {}",
+        format!("Grouped by {}:", group_title(group_by).to_lowercase()).bright_black(),
+        names.join(", ").bright_white(),
+        Local::now().format("%H:%M:%S").to_string().bright_black()
+    );
+    let total: usize = groups.values().map(|group| group.count).sum();
+    println!(
+        "{} requests in {} groups, {} new since start",
+        total,
+        groups.len(),
+        new_requests.to_string().bright_green()
+    );
+    println!();
+    println!(
+        "  {}",
+        format!(
+            "{} {:>COUNT_WIDTH$} {:>COUNT_WIDTH$} {}",
+            pad(group_title(group_by), key_width),
+            "COUNT",
+            "NEW",
+            "LAST SEEN"
+        )
+        .bright_cyan()
+        .bold()
+    );
+
+    let mut rows: Vec<(&String, &Group)> = groups.iter().collect();
+    rows.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+    // Header lines, errors and the "more groups" line
+    let room = (height as usize).saturating_sub(5 + errors.len()).max(1);
+    let now = Utc::now();
+    for (key, group) in rows.iter().take(room) {
+        let last_seen = group
+            .last_seen
+            .map(|date| {
+                let ago = (now - date).to_std().unwrap_or_default();
+                format!(
+                    "{} ({} ago)",
+                    date.with_timezone(&Local).format("%H:%M:%S"),
+                    format_elapsed(ago)
+                )
+            })
+            .unwrap_or_default();
+        let new = format!("{:>COUNT_WIDTH$}", group.new);
+        let (marker, new) = match group.new {
+            0 => (" ".normal(), new.normal()),
+            _ => ("●".bright_green(), new.bright_green()),
+        };
+        println!(
+            "{} {} {:>COUNT_WIDTH$} {} {}",
+            marker,
+            pad(key, key_width),
+            group.count,
+            new,
+            last_seen.bright_black()
+        );
+    }
+    if rows.len() > room {
+        println!(
+            "  {}",
+            format!("… {} more groups", rows.len() - room).bright_black()
+        );
+    }
+
+    for (name, error) in errors {
+        println!("{} {}: {}", "Error:".bright_red(), name, error);
+    }
+}
+
+fn group_title(group_by: GroupBy) -> &'static str {
+    match group_by {
+        GroupBy::Path => "PATH",
+        GroupBy::Method => "METHOD",
+        GroupBy::ProviderEvent => "EVENT",
+    }
+}
+
+fn column_title(column: Column) -> &'static str {
+    match column {
+        Column::Time => "TIME",
+        Column::Method => "METHOD",
+        Column::Path => "PATH",
+        Column::Id => "ID",
+        Column::Size => "SIZE",
+        Column::Body => "BODY",
+        Column::Token => "TOKEN",
+    }
+}
+
+fn column_value(column: Column, row: &Row) -> String {
+    let request = &row.request;
+    match column {
+        Column::Time => request
+            .date
+            .map(|dt| dt.with_timezone(&Local).format("%H:%M:%S").to_string())
+            .unwrap_or_default(),
+        Column::Method => request.message_object.method.to_string(),
+        Column::Path => extract_path(&request.message_object.value, &request.token_id),
+        Column::Id => request.id.clone(),
+        Column::Size => request.body.as_ref().map_or(0, |b| b.len()).to_string(),
+        Column::Body => request
+            .body
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        Column::Token => row.token_name.to_string(),
+    }
+}
+
+/// Fixed widths for most columns; the body takes whatever is left
+fn column_width(column: Column, options: &MonitorOptions, terminal_width: usize) -> usize {
+    match column {
+        Column::Time => 8,
+        Column::Method => 7,
+        Column::Path => 30,
+        Column::Id => 36,
+        Column::Size => 8,
+        Column::Token => 12,
+        Column::Body => {
+            let fixed: usize = options
+                .columns
+                .iter()
+                .filter(|c| **c != Column::Body)
+                .map(|c| column_width(*c, options, terminal_width) + 1)
+                .sum();
+            // Two characters for the new-request marker
+            terminal_width.saturating_sub(fixed + 2).max(10)
+        }
+    }
+}
+
+/// Truncate or pad a value to exactly `width` characters
+fn pad(value: &str, width: usize) -> String {
+    let count = value.chars().count();
+    if count > width {
+        let mut truncated: String = value.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    } else {
+        format!("{}{}", value, " ".repeat(width - count))
+    }
+}
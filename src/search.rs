@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::archive::{Archive, MATCH_END, MATCH_START};
+use crate::client::WebhookClient;
+use crate::config::Config;
+use crate::display::print_request_summary;
+use crate::models::WebhookRequest;
+use crate::tags::{Tags, print_tags};
+
+/// Characters of context shown on each side of a match
+const SNIPPET_CONTEXT: usize = 30;
+
+/// Where a request matched the query
+struct Match {
+    /// "body" or "header"
+    field: String,
+    snippet: String,
+}
+
+/// `webhook search`: find requests whose body or headers contain `query`,
+/// among the latest `count` requests of `token` on the service or, with
+/// `offline`, in the full-text index of the local archive of `token` (every
+/// token when `None`)
+pub async fn search_requests(
+    client: &WebhookClient,
+    config: &Config,
+    query: &str,
+    token: Option<&str>,
+    offline: bool,
+    count: u32,
+) -> Result<()> {
+    let (hits, searched): (Vec<(String, WebhookRequest, Vec<Match>)>, usize) = if offline {
+        let found = Archive::new(&config.archive)?.search(query, token)?;
+        let hits = found
+            .hits
+            .into_iter()
+            .map(|hit| {
+                let snippets = [("body", hit.body), ("header", hit.headers)];
+                let matches = snippets
+                    .into_iter()
+                    .filter_map(|(field, snippet)| {
+                        Some(Match {
+                            field: field.to_string(),
+                            snippet: highlight(&snippet?),
+                        })
+                    })
+                    .collect();
+                (hit.archived.token, hit.archived.request, matches)
+            })
+            .collect();
+        (hits, found.searched)
+    } else {
+        let token = token.context("A token is required to search the service")?;
+        let requests = client.get_requests_cached(token, count, |_| {}).await?;
+        let requests = &requests[..requests.len().min(count as usize)];
+        let needle = query.to_ascii_lowercase();
+        let hits = requests
+            .iter()
+            .filter_map(|request| {
+                let matches = find_matches(request, &needle);
+                (!matches.is_empty()).then(|| (token.to_string(), request.clone(), matches))
+            })
+            .collect();
+        (hits, requests.len())
+    };
+
+    let tags = Tags::load()?;
+    let found = hits.len();
+    for (request_token, request, matches) in &hits {
+        if token.is_none() {
+            print!("{} ", format!("[{}]", request_token).bright_black());
+        }
+        print_request_summary(request, false, config.get_body_preview_length(), None);
+        print_tags(tags.get(&request.id));
+        for Match { field, snippet } in matches {
+            println!("  {} {}", format!("{}:", field).bright_cyan(), snippet);
+        }
+    }
+
+    if found == 0 {
+        println!(
+            "{} in {} requests",
+            format!("No matches for \"{}\"", query).bright_yellow(),
+            searched
+        );
+    } else {
+        println!();
+        println!(
+            "{} {} of {} requests",
+            "Matched".bright_blue(),
+            found,
+            searched
+        );
+    }
+    Ok(())
+}
+
+/// Case-insensitive (ASCII) matches of `needle` in the body and header values
+fn find_matches(request: &WebhookRequest, needle: &str) -> Vec<Match> {
+    let mut matches = Vec::new();
+    if let Some(body) = &request.body
+        && let Some(snippet) = snippet(body, needle)
+    {
+        matches.push(Match {
+            field: "body".to_string(),
+            snippet,
+        });
+    }
+    let mut headers: Vec<_> = request.message_object.headers.iter().collect();
+    headers.sort();
+    for (name, values) in headers {
+        for value in values {
+            let line = format!("{}: {}", name, value);
+            if let Some(snippet) = snippet(&line, needle) {
+                matches.push(Match {
+                    field: "header".to_string(),
+                    snippet,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// The first match of `needle` in `text` with some context, highlighted
+fn snippet(text: &str, needle: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets the same as in `text`
+    let start = text.to_ascii_lowercase().find(needle)?;
+    let end = start + needle.len();
+    let before: String = text[..start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[end..].chars().take(SNIPPET_CONTEXT).collect();
+    Some(format!(
+        "{}{}{}{}{}",
+        if before.len() < start { "…" } else { "" },
+        one_line(&before),
+        one_line(&text[start..end]).bright_yellow().bold(),
+        one_line(&after),
+        if end + after.len() < text.len() {
+            "…"
+        } else {
+            ""
+        }
+    ))
+}
+
+/// A snippet of the archive's index on one line, its matches highlighted
+fn highlight(snippet: &str) -> String {
+    snippet
+        .split([MATCH_START, MATCH_END])
+        .enumerate()
+        .map(|(index, part)| {
+            // Matches are between the markers, at odd indices
+            if index % 2 == 1 {
+                one_line(part).bright_yellow().bold().to_string()
+            } else {
+                one_line(part)
+            }
+        })
+        .collect()
+}
+
+fn one_line(text: &str) -> String {
+    text.replace(['\r', '\n', '\t'], " ")
+}
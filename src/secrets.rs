@@ -0,0 +1,181 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::error::{IoResultExt, Result, ResultExt, WebhookError};
+
+/// Keyring service used for entries given without one (`keyring:NAME`),
+/// which is where `webhook secret set` stores them
+pub const KEYRING_SERVICE: &str = "webhook-cli";
+
+/// Resolve a secret from the config or command line. `env:NAME` reads an
+/// environment variable, `keyring:NAME` or `keyring:SERVICE/ACCOUNT` reads
+/// the OS keyring, `file:PATH` reads a file (without its trailing newline)
+/// and anything else is used as is.
+pub fn resolve_secret(value: &str) -> Result<String> {
+    if let Some(name) = value.strip_prefix("env:") {
+        return std::env::var(name)
+            .config_err(|| format!("Environment variable {} is not set", name));
+    }
+    if let Some(entry) = value.strip_prefix("keyring:") {
+        let (service, account) = keyring_entry(entry)?;
+        return keyring_lookup(service, account);
+    }
+    if let Some(path) = value.strip_prefix("file:") {
+        let secret = std::fs::read_to_string(path)
+            .io_err(|| format!("Failed to read the secret file {}", path))?;
+        return Ok(secret.trim_end_matches(['\r', '\n']).to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Split a keyring entry into service and account, defaulting the service
+/// to `webhook-cli`
+pub fn keyring_entry(entry: &str) -> Result<(&str, &str)> {
+    let (service, account) = entry.split_once('/').unwrap_or((KEYRING_SERVICE, entry));
+    if service.is_empty() || account.is_empty() {
+        return Err(WebhookError::config(format!(
+            "Invalid keyring entry '{}', expected NAME or SERVICE/ACCOUNT",
+            entry
+        )));
+    }
+    Ok((service, account))
+}
+
+pub fn keyring_lookup(service: &str, account: &str) -> Result<String> {
+    let output = keyring_command(KeyringOp::Lookup, service, account)?
+        .output()
+        .io_err(|| "Failed to run the keyring tool".to_string())?;
+    if !output.status.success() {
+        return Err(WebhookError::NotFound(format!(
+            "No keyring entry for service '{}' and account '{}'",
+            service, account
+        )));
+    }
+    let secret = String::from_utf8(output.stdout)
+        .parse_err(|| "Keyring entry is not valid UTF-8".to_string())?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Store a secret in the OS keyring, replacing an existing entry. The
+/// secret is written to the tool's stdin, never passed as an argument where
+/// other local users could see it in the process list.
+pub fn keyring_store(service: &str, account: &str, secret: &str) -> Result<()> {
+    let input = keyring_store_input(service, account, secret)?;
+    let mut child = keyring_command(KeyringOp::Store, service, account)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .io_err(|| "Failed to run the keyring tool".to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .io_err(|| "Failed to pass the secret to the keyring tool".to_string())?;
+    }
+    let status = child
+        .wait()
+        .io_err(|| "Failed to run the keyring tool".to_string())?;
+    if !status.success() {
+        return Err(WebhookError::auth(format!(
+            "Failed to store keyring entry for service '{}' and account '{}'",
+            service, account
+        )));
+    }
+    // `security -i` exits successfully even when the command it read failed
+    if keyring_lookup(service, account)? != secret {
+        return Err(WebhookError::auth(format!(
+            "Failed to store keyring entry for service '{}' and account '{}'",
+            service, account
+        )));
+    }
+    Ok(())
+}
+
+/// What `keyring_store` writes to the tool: `security -i` reads the whole
+/// command line from stdin, with quoted arguments
+#[cfg(target_os = "macos")]
+fn keyring_store_input(service: &str, account: &str, secret: &str) -> Result<String> {
+    if secret.contains(['\r', '\n']) {
+        return Err(WebhookError::config(
+            "Secrets stored in the macOS keychain cannot contain line breaks",
+        ));
+    }
+    fn quote(value: &str) -> String {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    // -U updates an existing item instead of failing
+    Ok(format!(
+        "add-generic-password -U -s {} -a {} -w {}\n",
+        quote(service),
+        quote(account),
+        quote(secret)
+    ))
+}
+
+/// What `keyring_store` writes to the tool: secret-tool reads the secret
+/// from stdin
+#[cfg(not(target_os = "macos"))]
+fn keyring_store_input(_service: &str, _account: &str, secret: &str) -> Result<String> {
+    Ok(secret.to_string())
+}
+
+pub fn keyring_delete(service: &str, account: &str) -> Result<()> {
+    // Neither tool reports a missing entry reliably, so look it up first
+    keyring_lookup(service, account)?;
+    let status = keyring_command(KeyringOp::Delete, service, account)?
+        .stdout(Stdio::null())
+        .status()
+        .io_err(|| "Failed to run the keyring tool".to_string())?;
+    if !status.success() {
+        return Err(WebhookError::auth(format!(
+            "Failed to remove keyring entry for service '{}' and account '{}'",
+            service, account
+        )));
+    }
+    Ok(())
+}
+
+enum KeyringOp {
+    Lookup,
+    Store,
+    Delete,
+}
+
+#[cfg(target_os = "macos")]
+fn keyring_command(op: KeyringOp, service: &str, account: &str) -> Result<Command> {
+    let mut cmd = Command::new("security");
+    match op {
+        KeyringOp::Lookup => {
+            cmd.args(["find-generic-password", "-s", service, "-a", account, "-w"])
+        }
+        // Interactive mode, so the secret comes from stdin; see keyring_store_input
+        KeyringOp::Store => cmd.arg("-i"),
+        KeyringOp::Delete => cmd.args(["delete-generic-password", "-s", service, "-a", account]),
+    };
+    Ok(cmd)
+}
+
+#[cfg(windows)]
+fn keyring_command(_op: KeyringOp, _service: &str, _account: &str) -> Result<Command> {
+    Err(WebhookError::config(
+        "The OS keyring is not supported on Windows, use env:NAME instead",
+    ))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn keyring_command(op: KeyringOp, service: &str, account: &str) -> Result<Command> {
+    // libsecret (GNOME Keyring, KWallet via the Secret Service API)
+    let mut cmd = Command::new("secret-tool");
+    match op {
+        KeyringOp::Lookup => cmd.args(["lookup", "service", service, "account", account]),
+        KeyringOp::Store => cmd.args([
+            "store",
+            &format!("--label={}/{}", service, account),
+            "service",
+            service,
+            "account",
+            account,
+        ]),
+        KeyringOp::Delete => cmd.args(["clear", "service", service, "account", account]),
+    };
+    Ok(cmd)
+}
@@ -0,0 +1,337 @@
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use colored::Colorize;
+use reqwest::{Client, Method};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::config::Config;
+use crate::display::format_method;
+use crate::metrics::format_short;
+use crate::parse::parse_duration;
+use crate::rules::{random_fraction, scalar};
+use crate::signature::Signer;
+
+/// Characters of the response body shown after each request
+const RESPONSE_PREVIEW_LENGTH: usize = 200;
+
+/// A request of `webhook send`, before its templates are filled in
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub name: Option<String>,
+    pub method: String,
+    /// Appended to the target URL
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// Wait before sending
+    pub delay: Option<Duration>,
+    /// Variables set from the JSON response, as (name, JSON pointer)
+    pub extract: Vec<(String, String)>,
+}
+
+/// An ordered list of requests sent by `webhook send --scenario`
+#[derive(Debug)]
+pub struct Scenario {
+    pub target: Option<String>,
+    pub variables: HashMap<String, String>,
+    pub steps: Vec<Step>,
+}
+
+/// Load a scenario: `steps` (each with `method`, `path`, `headers`, `body`,
+/// `delay` and `extract`), with optional `target` and `variables`
+pub fn load_scenario(path: &Path) -> Result<Scenario> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read scenario file: {}", path.display()))?;
+    let documents = YamlLoader::load_from_str(&content)
+        .with_context(|| format!("Failed to parse scenario file: {}", path.display()))?;
+    let document = documents.first().unwrap_or(&Yaml::Null);
+    let Yaml::Array(steps) = &document["steps"] else {
+        anyhow::bail!("{}: expected a list of `steps`", path.display());
+    };
+
+    let mut variables = HashMap::new();
+    if let Yaml::Hash(map) = &document["variables"] {
+        for (name, value) in map {
+            let (Some(name), Some(value)) = (scalar(name), scalar(value)) else {
+                anyhow::bail!("{}: `variables` must map names to values", path.display());
+            };
+            variables.insert(name, value);
+        }
+    }
+
+    Ok(Scenario {
+        target: scalar(&document["target"]),
+        variables,
+        steps: steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                parse_step(step).with_context(|| format!("{}: step {}", path.display(), index + 1))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn parse_step(item: &Yaml) -> Result<Step> {
+    let Yaml::Hash(map) = item else {
+        anyhow::bail!("expected a mapping");
+    };
+    for key in map.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !matches!(
+            key,
+            "name" | "method" | "path" | "headers" | "body" | "delay" | "extract"
+        ) {
+            anyhow::bail!("unknown key `{}`", key);
+        }
+    }
+
+    let pairs = |key: &str| -> Result<Vec<(String, String)>> {
+        match &item[key] {
+            Yaml::BadValue => Ok(Vec::new()),
+            Yaml::Hash(map) => map
+                .iter()
+                .map(|(name, value)| match (scalar(name), scalar(value)) {
+                    (Some(name), Some(value)) => Ok((name, value)),
+                    _ => anyhow::bail!("`{}` must map names to values", key),
+                })
+                .collect(),
+            _ => anyhow::bail!("`{}` must be a mapping", key),
+        }
+    };
+    let delay = match &item["delay"] {
+        Yaml::BadValue => None,
+        value => Some(
+            parse_duration(&scalar(value).context("`delay` must be a duration")?)
+                .map_err(|e| anyhow::anyhow!("`delay`: {}", e))?,
+        ),
+    };
+
+    Ok(Step {
+        name: scalar(&item["name"]),
+        method: scalar(&item["method"]).unwrap_or_else(|| "POST".to_string()),
+        path: scalar(&item["path"]).unwrap_or_default(),
+        headers: pairs("headers")?,
+        body: scalar(&item["body"]),
+        delay,
+        extract: pairs("extract")?,
+    })
+}
+
+/// A client for requests to receivers, with the timeouts and TLS settings of
+/// the config but none of the API's authentication
+pub fn http_client(config: &Config) -> Result<Client> {
+    Client::builder()
+        .timeout(config.get_timeout())
+        .connect_timeout(config.get_connect_timeout())
+        .danger_accept_invalid_certs(config.is_insecure())
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// `webhook send`: send `steps` to `target` in order, `repeat` times, filling
+/// `{{variable}}` placeholders, signing the bodies with `signer` and extracting
+/// variables from the responses
+pub async fn send_requests(
+    config: &Config,
+    target: &str,
+    steps: &[Step],
+    mut variables: HashMap<String, String>,
+    repeat: u32,
+    signer: Option<&Signer>,
+) -> Result<()> {
+    let client = http_client(config)?;
+
+    let mut failed = 0;
+    let rounds =
+        (1..=repeat).flat_map(|round| steps.iter().enumerate().map(move |step| (round, step)));
+    for (round, (index, step)) in rounds {
+        if let Some(delay) = step.delay {
+            tokio::time::sleep(delay).await;
+        }
+        let method = render(&step.method, &variables).to_uppercase();
+        let method = Method::from_bytes(method.as_bytes())
+            .with_context(|| format!("Invalid method: {}", step.method))?;
+        let url = format!(
+            "{}{}",
+            target.trim_end_matches('/'),
+            render(&step.path, &variables)
+        );
+        let mut request = client.request(method.clone(), &url);
+        for (name, value) in &step.headers {
+            request = request.header(name, render(value, &variables));
+        }
+        let body = step.body.as_deref().map(|body| render(body, &variables));
+        if let Some(signer) = signer {
+            let (name, value) = signer.sign(body.as_deref().unwrap_or_default());
+            request = request.header(name, value);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        let mut label = match &step.name {
+            Some(name) => format!("[{}] ", name),
+            None if steps.len() > 1 => format!("[{}] ", index + 1),
+            None => String::new(),
+        };
+        if repeat > 1 {
+            label.insert_str(0, &format!("#{} ", round));
+        }
+        let started = Instant::now();
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("{}Failed to send {} {}", label, method, url))?;
+        let elapsed = started.elapsed();
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        let status_text = status.to_string();
+        println!(
+            "{}{} {} {} {}",
+            label.bright_black(),
+            format_method(method.as_str()),
+            url.bright_white(),
+            if status.is_success() {
+                status_text.bright_green()
+            } else {
+                status_text.bright_red()
+            },
+            format_short(elapsed).bright_black()
+        );
+        let preview: String = body
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(RESPONSE_PREVIEW_LENGTH)
+            .collect();
+        if !preview.is_empty() {
+            println!("  {}", preview.bright_black());
+        }
+        if !status.is_success() {
+            failed += 1;
+        }
+
+        if !step.extract.is_empty() {
+            let json: serde_json::Value = serde_json::from_str(&body).with_context(|| {
+                format!("{}Response is not JSON, cannot extract variables", label)
+            })?;
+            for (name, pointer) in &step.extract {
+                let value = json
+                    .pointer(pointer)
+                    .with_context(|| format!("{}Response has no {}", label, pointer))?;
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                println!(
+                    "  {} {} = {}",
+                    "→".bright_black(),
+                    name.bright_cyan(),
+                    value
+                );
+                variables.insert(name.clone(), value);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} requests failed",
+            failed,
+            steps.len() * repeat as usize
+        );
+    }
+    Ok(())
+}
+
+/// Fill in `{{name}}` placeholders from `variables`, then the generated values
+/// `{{uuid}}`, `{{now_iso}}`, `{{now_unix}}`, `{{randint MIN MAX}}` and
+/// `{{fake.KIND}}` (fresh for every placeholder); unknown placeholders are kept
+/// as is
+pub fn render(template: &str, variables: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        match variables.get(name).cloned().or_else(|| generated(name)) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+const FIRST_NAMES: [&str; 12] = [
+    "Alice", "Bob", "Carol", "David", "Emma", "Farid", "Grace", "Hiro", "Ines", "Jonas", "Kemi",
+    "Lena",
+];
+const LAST_NAMES: [&str; 10] = [
+    "Smith", "Garcia", "Müller", "Rossi", "Tanaka", "Kowalski", "Nguyen", "Okafor", "Silva",
+    "Larsen",
+];
+const WORDS: [&str; 12] = [
+    "alpha", "bravo", "cedar", "delta", "ember", "falcon", "granite", "harbor", "indigo",
+    "juniper", "kestrel", "lumen",
+];
+const CITIES: [&str; 8] = [
+    "Lisbon",
+    "Osaka",
+    "Toronto",
+    "Nairobi",
+    "Berlin",
+    "Austin",
+    "Melbourne",
+    "Bogotá",
+];
+
+fn generated(name: &str) -> Option<String> {
+    match name {
+        "uuid" => Some(Uuid::new_v4().to_string()),
+        "now_iso" => Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        "now_unix" => Some(Utc::now().timestamp().to_string()),
+        "fake.first_name" => Some(pick(&FIRST_NAMES).to_string()),
+        "fake.last_name" => Some(pick(&LAST_NAMES).to_string()),
+        "fake.name" => Some(format!("{} {}", pick(&FIRST_NAMES), pick(&LAST_NAMES))),
+        "fake.email" => Some(format!(
+            "{}.{}{}@example.com",
+            pick(&FIRST_NAMES).to_lowercase(),
+            pick(&WORDS),
+            randint(1, 999)
+        )),
+        "fake.word" => Some(pick(&WORDS).to_string()),
+        "fake.city" => Some(pick(&CITIES).to_string()),
+        "fake.phone" => Some(format!("+1555{:07}", randint(0, 9_999_999))),
+        "fake.amount" => Some(format!("{}.{:02}", randint(1, 999), randint(0, 99))),
+        _ => {
+            let mut args = name.strip_prefix("randint ")?.split_whitespace();
+            let min = args.next()?.parse().ok()?;
+            let max = args.next()?.parse().ok()?;
+            (args.next().is_none() && min <= max).then(|| randint(min, max).to_string())
+        }
+    }
+}
+
+/// A uniformly distributed number in [min, max]
+fn randint(min: i64, max: i64) -> i64 {
+    let span = (max - min) as f64 + 1.0;
+    min + ((random_fraction() * span) as i64).min(max - min)
+}
+
+fn pick<'a>(values: &[&'a str]) -> &'a str {
+    values[randint(0, values.len() as i64 - 1) as usize]
+}
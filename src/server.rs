@@ -0,0 +1,752 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+use native_tls::Identity;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+use uuid::Uuid;
+
+use crate::archive::Archive;
+use crate::cli::TunnelProvider;
+use crate::config::Config;
+use crate::display::{print_full_request_body, print_request_headers, print_request_summary};
+use crate::fixtures::{Fixture, load_fixtures, record_fixture};
+use crate::logging;
+use crate::metrics::format_short;
+use crate::models::{MessageObject, Method, WebhookRequest};
+use crate::otel::OtlpExporter;
+use crate::prometheus::Metrics;
+use crate::rules::{Response, Rule, load_rules, random_fraction, respond};
+use crate::tunnel::open_tunnel;
+
+/// Token under which requests received by `webhook serve` are archived
+pub const LOCAL_TOKEN: &str = "local";
+
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+pub struct ServeOptions {
+    pub bind: String,
+    pub port: u16,
+    pub rules: Option<PathBuf>,
+    pub fixtures: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub tls: Option<Tls>,
+    pub chaos: Chaos,
+    pub tunnel: Option<TunnelProvider>,
+    pub full_body: bool,
+    pub show_headers: bool,
+    /// Counters served by `--metrics-port`
+    pub metrics: Option<Arc<Metrics>>,
+    /// Export of each request to an OpenTelemetry collector
+    pub otlp: Option<OtlpExporter>,
+    /// Pass every request on to this server and answer with its response,
+    /// instead of the rules (`webhook tunnel --watch`)
+    pub forward: Option<String>,
+}
+
+/// Certificate to serve HTTPS with
+pub enum Tls {
+    /// PEM certificate and PKCS#8 PEM key
+    Files { cert: PathBuf, key: PathBuf },
+    /// Generated for localhost with the openssl command
+    SelfSigned,
+}
+
+/// Faults injected by `serve` to test how senders retry
+pub struct Chaos {
+    /// Fraction of requests answered with a server error
+    pub fail_rate: f64,
+    /// Fraction of connections closed without an answer
+    pub drop_rate: f64,
+    /// Added to every answer, uniformly distributed in the range
+    pub latency: Option<(Duration, Duration)>,
+}
+
+/// Statuses of injected failures
+const CHAOS_STATUSES: [u16; 3] = [500, 502, 503];
+
+impl Chaos {
+    fn is_active(&self) -> bool {
+        self.fail_rate > 0.0 || self.drop_rate > 0.0 || self.latency.is_some()
+    }
+
+    /// Apply the faults to `response`; returns whether to drop the connection instead
+    fn apply(&self, response: &mut Response) -> bool {
+        if let Some((min, max)) = self.latency {
+            let latency = min + (max - min).mul_f64(random_fraction());
+            response.delay = Some(response.delay.unwrap_or_default() + latency);
+        }
+        if self.drop_rate > 0.0 && random_fraction() < self.drop_rate {
+            return true;
+        }
+        if self.fail_rate > 0.0 && random_fraction() < self.fail_rate {
+            let index = (random_fraction() * CHAOS_STATUSES.len() as f64) as usize;
+            *response = Response {
+                status: CHAOS_STATUSES[index.min(CHAOS_STATUSES.len() - 1)],
+                headers: Vec::new(),
+                body: b"Injected failure\n".to_vec(),
+                delay: response.delay,
+            };
+        }
+        false
+    }
+}
+
+struct Server {
+    rules: Vec<Rule>,
+    fixtures: Vec<Fixture>,
+    record: Option<PathBuf>,
+    chaos: Chaos,
+    archive: Option<Archive>,
+    full_body: bool,
+    show_headers: bool,
+    body_preview_length: usize,
+    received: AtomicUsize,
+    metrics: Option<Arc<Metrics>>,
+    otlp: Option<OtlpExporter>,
+    forward: Option<Forward>,
+}
+
+/// The server requests are passed on to
+struct Forward {
+    client: reqwest::Client,
+    upstream: String,
+}
+
+/// Headers that only concern one connection, not passed on
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// A request as read from the connection
+struct Incoming {
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Incoming {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn keep_alive(&self) -> bool {
+        match self.header("connection") {
+            Some(value) if value.eq_ignore_ascii_case("close") => false,
+            Some(value) if value.eq_ignore_ascii_case("keep-alive") => true,
+            _ => self.version == "HTTP/1.1",
+        }
+    }
+
+    /// The request in the service's format, so it prints and archives like fetched ones
+    fn into_request(self) -> WebhookRequest {
+        let mut headers: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, value) in self.headers {
+            headers.entry(name).or_default().push(value);
+        }
+        let query_parameters = self
+            .target
+            .split_once('?')
+            .map(|(_, query)| {
+                query
+                    .split('&')
+                    .filter(|parameter| !parameter.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let body =
+            (!self.body.is_empty()).then(|| String::from_utf8_lossy(&self.body).into_owned());
+        WebhookRequest {
+            id: Uuid::new_v4().to_string(),
+            date: Some(Utc::now()),
+            token_id: String::new(),
+            message_object: MessageObject {
+                method: Method::from(self.method.as_str()),
+                value: self.target,
+                headers,
+                query_parameters,
+            },
+            message: None,
+            body,
+            body_object: None,
+        }
+    }
+}
+
+/// `webhook serve`: receive webhooks on a local port and print them as they arrive
+pub async fn serve(config: &Config, options: ServeOptions) -> Result<()> {
+    let rules = match &options.rules {
+        Some(path) => load_rules(path)?,
+        None => Vec::new(),
+    };
+    let fixtures = match &options.fixtures {
+        Some(dir) => load_fixtures(dir)?,
+        None => Vec::new(),
+    };
+    let archive = match config.archive.enabled {
+        true => Some(Archive::new(&config.archive)?),
+        false => None,
+    };
+    // The directory of a self-signed certificate stays until the server stops
+    let (acceptor, _certificate_dir) = match &options.tls {
+        Some(Tls::Files { cert, key }) => {
+            let cert = fs::read(cert)
+                .with_context(|| format!("Failed to read TLS certificate: {}", cert.display()))?;
+            let key = fs::read(key)
+                .with_context(|| format!("Failed to read TLS key: {}", key.display()))?;
+            (Some(tls_acceptor(&cert, &key)?), None)
+        }
+        Some(Tls::SelfSigned) => {
+            let (dir, cert, key) = generate_self_signed()?;
+            println!(
+                "{} {}",
+                "Self-signed certificate:".bright_blue(),
+                cert.display()
+            );
+            let cert = fs::read(&cert)
+                .with_context(|| format!("Failed to read TLS certificate: {}", cert.display()))?;
+            (Some(tls_acceptor(&cert, &key)?), Some(dir))
+        }
+        None => (None, None),
+    };
+    let listener = TcpListener::bind((options.bind.as_str(), options.port))
+        .await
+        .with_context(|| format!("Failed to listen on {}:{}", options.bind, options.port))?;
+    let address = listener.local_addr()?;
+    let scheme = if acceptor.is_some() { "https" } else { "http" };
+
+    println!(
+        "{} {}",
+        "Listening on".bright_green().bold(),
+        format!("{}://{}", scheme, address).bright_white()
+    );
+    // Stays open until the server stops
+    let _tunnel = match options.tunnel {
+        Some(provider) => {
+            let local_url = format!("{}://localhost:{}", scheme, address.port());
+            let tunnel = open_tunnel(provider, &local_url).await?;
+            println!(
+                "{} {} {}",
+                "Public URL".bright_green().bold(),
+                tunnel.url.bright_white().bold(),
+                format!("(via {})", tunnel.provider.command()).bright_black()
+            );
+            Some(tunnel)
+        }
+        None => None,
+    };
+    if let Some(path) = &options.rules {
+        println!(
+            "{} {} response rules from {}",
+            "Using".bright_blue(),
+            rules.len(),
+            path.display()
+        );
+    }
+    if let Some(dir) = &options.fixtures {
+        println!(
+            "{} {} fixtures from {}",
+            "Replaying".bright_blue(),
+            fixtures.len(),
+            dir.display()
+        );
+    }
+    if let Some(upstream) = &options.forward {
+        println!("{} {}", "Forwarding to".bright_blue(), upstream);
+    }
+    if let Some(dir) = &options.record {
+        println!(
+            "{} exchanges to {}",
+            "Recording".bright_blue(),
+            dir.display()
+        );
+    }
+    println!("{}", "Press Ctrl+C to stop".bright_black());
+    println!();
+
+    let forward = match options.forward {
+        Some(upstream) => Some(Forward {
+            client: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .danger_accept_invalid_certs(config.is_insecure())
+                .build()
+                .context("Failed to create HTTP client")?,
+            upstream: upstream.trim_end_matches('/').to_string(),
+        }),
+        None => None,
+    };
+    let server = Arc::new(Server {
+        rules,
+        fixtures,
+        record: options.record,
+        chaos: options.chaos,
+        archive,
+        full_body: options.full_body,
+        show_headers: options.show_headers,
+        body_preview_length: config.get_body_preview_length(),
+        received: AtomicUsize::new(0),
+        metrics: options.metrics,
+        otlp: options.otlp,
+        forward,
+    });
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        logging::debug(1, format_args!("Failed to accept a connection: {}", e));
+                        continue;
+                    }
+                };
+                let server = Arc::clone(&server);
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let result = match acceptor {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(stream) => server.handle_connection(stream).await,
+                            Err(e) => Err(anyhow::Error::new(e).context("TLS handshake failed")),
+                        },
+                        None => server.handle_connection(stream).await,
+                    };
+                    if let Err(e) = result {
+                        logging::debug(1, format_args!("Connection from {}: {:#}", peer, e));
+                    }
+                });
+            }
+            _ = &mut ctrl_c => break,
+        }
+    }
+
+    println!();
+    println!(
+        "{} {} requests",
+        "Received".bright_blue(),
+        server.received.load(Ordering::Relaxed)
+    );
+    Ok(())
+}
+
+impl Server {
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: S) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+        loop {
+            let incoming = match read_request(&mut stream).await {
+                Ok(Some(incoming)) => incoming,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    let response = Response {
+                        status: match e.downcast_ref::<BodyTooLarge>() {
+                            Some(_) => 413,
+                            None => 400,
+                        },
+                        headers: Vec::new(),
+                        body: format!("{:#}\n", e).into_bytes(),
+                        delay: None,
+                    };
+                    // The client may already be gone
+                    let _ = write_response(&mut stream, &response, false).await;
+                    return Err(e);
+                }
+            };
+            let keep_alive = incoming.keep_alive();
+            // Forwarded as received, binary bodies included
+            let body = self.forward.is_some().then(|| incoming.body.clone());
+            let request = incoming.into_request();
+            self.received.fetch_add(1, Ordering::Relaxed);
+
+            let mut response = match (&self.forward, body) {
+                (Some(forward), Some(body)) => forward.send(&request, body).await,
+                _ => match self.fixtures.iter().find(|f| f.matches(&request)) {
+                    Some(fixture) => {
+                        logging::debug(1, format_args!("Matched {}", fixture.file.display()));
+                        fixture.response(&request)
+                    }
+                    None => respond(&self.rules, &request),
+                },
+            };
+            let drop = self.chaos.apply(&mut response);
+            if let Some(dir) = &self.record
+                && !drop
+            {
+                match record_fixture(dir, &request, &response) {
+                    Ok(file) => logging::debug(1, format_args!("Recorded {}", file.display())),
+                    Err(e) => eprintln!("{} {:#}", "Could not record fixture:".bright_yellow(), e),
+                }
+            }
+            self.print(&request, &response, drop);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_request(LOCAL_TOKEN, &request);
+                metrics.record_response((!drop).then_some(response.status));
+            }
+            if let Some(otlp) = &self.otlp {
+                otlp.export(LOCAL_TOKEN, &request);
+            }
+            if let Some(archive) = &self.archive {
+                archive.store(LOCAL_TOKEN, std::slice::from_ref(&request));
+            }
+            if let Some(delay) = response.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if drop {
+                return Ok(());
+            }
+            write_response(&mut stream, &response, keep_alive).await?;
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    fn print(&self, request: &WebhookRequest, response: &Response, drop: bool) {
+        print_request_summary(request, !self.full_body, self.body_preview_length, None);
+        if !self.rules.is_empty()
+            || !self.fixtures.is_empty()
+            || self.chaos.is_active()
+            || self.forward.is_some()
+        {
+            let answer = if drop {
+                "dropped".bright_red()
+            } else if response.status >= 500 {
+                format!("{} {}", response.status, reason(response.status)).bright_red()
+            } else {
+                format!("{} {}", response.status, reason(response.status)).bright_black()
+            };
+            match response.delay {
+                Some(delay) => println!(
+                    "  {} {} {}",
+                    "→".bright_black(),
+                    answer,
+                    format!("after {}", format_short(delay)).bright_black()
+                ),
+                None => println!("  {} {}", "→".bright_black(), answer),
+            }
+        }
+        if self.show_headers {
+            print_request_headers(request);
+        }
+        if self.full_body {
+            print_full_request_body(request, &[], true);
+        }
+        if self.show_headers || self.full_body {
+            println!("{}", "─".repeat(80).bright_black());
+        }
+    }
+}
+
+impl Forward {
+    /// The upstream's answer to `request`, or a 502 when it cannot be reached
+    async fn send(&self, request: &WebhookRequest, body: Vec<u8>) -> Response {
+        match self.try_send(request, body).await {
+            Ok(response) => response,
+            Err(e) => Response {
+                status: 502,
+                headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+                body: format!("{:#}\n", e).into_bytes(),
+                delay: None,
+            },
+        }
+    }
+
+    async fn try_send(&self, request: &WebhookRequest, body: Vec<u8>) -> Result<Response> {
+        let method =
+            reqwest::Method::from_bytes(request.message_object.method.as_str().as_bytes())?;
+        let url = format!("{}{}", self.upstream, request.message_object.value);
+        let mut upstream = self.client.request(method, &url).body(body);
+        for (name, values) in &request.message_object.headers {
+            // Recorded fixtures keep the body as text, so ask for it uncompressed
+            if HOP_BY_HOP.contains(&name.to_ascii_lowercase().as_str())
+                || name.eq_ignore_ascii_case("accept-encoding")
+            {
+                continue;
+            }
+            for value in values {
+                upstream = upstream.header(name, value);
+            }
+        }
+        let response = upstream
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.upstream))?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !HOP_BY_HOP.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        // As received: binary bodies are not valid text
+        let body = response
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to read the answer of {}", self.upstream))?
+            .to_vec();
+        Ok(Response {
+            status,
+            headers,
+            body,
+            delay: None,
+        })
+    }
+}
+
+fn tls_acceptor(cert: &[u8], key: &[u8]) -> Result<TlsAcceptor> {
+    let identity = Identity::from_pkcs8(cert, key)
+        .context("Invalid TLS certificate or key (the key must be PKCS#8 PEM)")?;
+    let acceptor = native_tls::TlsAcceptor::new(identity).context("Failed to set up TLS")?;
+    Ok(TlsAcceptor::from(acceptor))
+}
+
+/// A certificate for localhost valid for a day: the private temporary
+/// directory holding it, removed when dropped, the certificate's path and
+/// the key, which is read and deleted at once
+fn generate_self_signed() -> Result<(TempDir, PathBuf, Vec<u8>)> {
+    let dir = tempfile::Builder::new()
+        .prefix("webhook-cli-serve-")
+        .tempdir()
+        .context("Failed to create a temporary directory for the certificate")?;
+    let cert = dir.path().join("cert.pem");
+    let key = dir.path().join("key.pem");
+    // Owner-only before openssl writes the key into it
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to restrict {}", dir.path().display()))?;
+        options.mode(0o600);
+    }
+    options
+        .open(&key)
+        .with_context(|| format!("Failed to create {}", key.display()))?;
+    let output = Command::new("openssl")
+        .args([
+            "req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", "1",
+        ])
+        .args(["-subj", "/CN=localhost"])
+        .args([
+            "-addext",
+            "subjectAltName=DNS:localhost,IP:127.0.0.1,IP:::1",
+        ])
+        .arg("-keyout")
+        .arg(&key)
+        .arg("-out")
+        .arg(&cert)
+        .output()
+        .context("Failed to run openssl to generate a self-signed certificate")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "openssl failed to generate a self-signed certificate: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let key_pem =
+        fs::read(&key).with_context(|| format!("Failed to read TLS key: {}", key.display()))?;
+    fs::remove_file(&key).with_context(|| format!("Failed to delete {}", key.display()))?;
+    Ok((dir, cert, key_pem))
+}
+
+/// Read the next request of the connection, `None` when the client closed it
+async fn read_request<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+) -> Result<Option<Incoming>> {
+    let mut head = Vec::new();
+    let mut lines = Vec::new();
+    loop {
+        let mut line = Vec::new();
+        let read = stream.read_until(b'\n', &mut line).await?;
+        if read == 0 {
+            if head.is_empty() && lines.is_empty() {
+                return Ok(None);
+            }
+            anyhow::bail!("Connection closed in the middle of the request");
+        }
+        head.extend_from_slice(&line);
+        if head.len() > MAX_HEAD_SIZE {
+            anyhow::bail!("Request head is larger than {} bytes", MAX_HEAD_SIZE);
+        }
+        let line = String::from_utf8_lossy(&line).trim_end().to_string();
+        if line.is_empty() {
+            // Blank lines before the request line are allowed
+            if lines.is_empty() {
+                continue;
+            }
+            break;
+        }
+        lines.push(line);
+    }
+
+    let mut request_line = lines[0].split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        anyhow::bail!("Malformed request line: {}", lines[0]);
+    };
+    let mut headers = Vec::new();
+    for line in &lines[1..] {
+        let (name, value) = line
+            .split_once(':')
+            .with_context(|| format!("Malformed header: {}", line))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let mut incoming = Incoming {
+        method: method.to_string(),
+        target: target.to_string(),
+        version: version.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    if incoming
+        .header("expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"))
+    {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+        stream.flush().await?;
+    }
+
+    if incoming
+        .header("transfer-encoding")
+        .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"))
+    {
+        incoming.body = read_chunked(stream).await?;
+    } else if let Some(length) = incoming.header("content-length") {
+        let length: usize = length
+            .parse()
+            .with_context(|| format!("Invalid Content-Length: {}", length))?;
+        if length > MAX_BODY_SIZE {
+            return Err(BodyTooLarge.into());
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await?;
+        incoming.body = body;
+    }
+    Ok(Some(incoming))
+}
+
+/// A request body over `MAX_BODY_SIZE`, answered with 413
+#[derive(Debug)]
+struct BodyTooLarge;
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request body is larger than {} bytes", MAX_BODY_SIZE)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+async fn read_chunked<S: AsyncRead + Unpin>(stream: &mut BufReader<S>) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .with_context(|| format!("Invalid chunk size: {}", size))?;
+        if size == 0 {
+            // Skip trailers
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        // Compared this way round, a huge chunk size cannot overflow
+        if size > MAX_BODY_SIZE - body.len() {
+            return Err(BodyTooLarge.into());
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+        let mut crlf = String::new();
+        stream.read_line(&mut crlf).await?;
+    }
+}
+
+async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: &Response,
+    keep_alive: bool,
+) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        if name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("connection") {
+            continue;
+        }
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    head.push_str(if keep_alive {
+        "Connection: keep-alive\r\n"
+    } else {
+        "Connection: close\r\n"
+    });
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
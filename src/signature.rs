@@ -0,0 +1,744 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use colored::{ColoredString, Colorize};
+use ring::hmac;
+use std::time::Duration;
+
+use crate::cli::SortBy;
+use crate::commands::sort_requests;
+use crate::config::{Config, SignatureConfig};
+use crate::display::{format_elapsed, print_request_summary};
+use crate::exit::{self, Failure};
+use crate::models::{WebhookRequest, extract_path};
+use crate::secrets::resolve_secret;
+
+/// How `--sign` signs outgoing requests, in the style of a webhook provider
+#[derive(Clone)]
+pub enum Signer {
+    /// `X-Hub-Signature-256: sha256=<hex>` over the body
+    GitHub(hmac::Key),
+    /// `Stripe-Signature: t=<timestamp>,v1=<hex>` over `<timestamp>.<body>`
+    Stripe(hmac::Key),
+    /// `<header>: <hex>` over the body
+    HmacSha256 { header: String, key: hmac::Key },
+}
+
+/// Parse `github:SECRET`, `stripe:SECRET` or
+/// `hmac-sha256:header=NAME:SECRET`; the secret accepts env:NAME and
+/// keyring:NAME
+pub fn parse_signer(value: &str) -> Result<Signer, String> {
+    let (scheme, rest) = value.split_once(':').ok_or_else(|| {
+        format!(
+            "invalid signature '{}', expected github:SECRET, stripe:SECRET or hmac-sha256:header=NAME:SECRET",
+            value
+        )
+    })?;
+    let key = |secret: &str| -> Result<hmac::Key, String> {
+        if secret.is_empty() {
+            return Err(format!("missing secret in '{}'", value));
+        }
+        let secret = resolve_secret(secret).map_err(|e| e.to_string())?;
+        Ok(hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()))
+    };
+    match scheme.to_lowercase().as_str() {
+        "github" => Ok(Signer::GitHub(key(rest)?)),
+        "stripe" => Ok(Signer::Stripe(key(rest)?)),
+        "hmac-sha256" => {
+            let (header, secret) = rest
+                .strip_prefix("header=")
+                .and_then(|rest| rest.split_once(':'))
+                .filter(|(header, _)| !header.trim().is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "invalid signature '{}', expected hmac-sha256:header=NAME:SECRET",
+                        value
+                    )
+                })?;
+            Ok(Signer::HmacSha256 {
+                header: header.trim().to_string(),
+                key: key(secret)?,
+            })
+        }
+        scheme => Err(format!(
+            "unknown signature scheme '{}' (use github, stripe or hmac-sha256)",
+            scheme
+        )),
+    }
+}
+
+impl Signer {
+    /// The header carrying the signature of `body`
+    pub fn sign(&self, body: &str) -> (String, String) {
+        match self {
+            Signer::GitHub(key) => (
+                "X-Hub-Signature-256".to_string(),
+                format!("sha256={}", hex_digest(key, body.as_bytes())),
+            ),
+            Signer::Stripe(key) => {
+                let timestamp = Utc::now().timestamp();
+                let payload = format!("{}.{}", timestamp, body);
+                (
+                    "Stripe-Signature".to_string(),
+                    format!("t={},v1={}", timestamp, hex_digest(key, payload.as_bytes())),
+                )
+            }
+            Signer::HmacSha256 { header, key } => {
+                (header.clone(), hex_digest(key, body.as_bytes()))
+            }
+        }
+    }
+}
+
+fn hex_digest(key: &hmac::Key, data: &[u8]) -> String {
+    hmac::sign(key, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Schemes that `verify` in a `[tokens.<alias>]` section can name, besides
+/// those of the `[signatures]` section
+pub const SCHEMES: [&str; 4] = ["github", "stripe", "slack", "twilio"];
+
+/// Maximum skew between a signature's timestamp and the request's arrival,
+/// unless given on the command line
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// How the signatures of captured requests are checked
+pub enum Verifier {
+    /// `X-Hub-Signature-256: sha256=<hex>` over the raw body, with any of the
+    /// secrets
+    GitHub(Vec<String>),
+    /// `Stripe-Signature: t=<timestamp>,v1=<hex>[,v1=<hex>...]` over
+    /// `<timestamp>.<body>`, with any of the secrets, sent at most
+    /// `tolerance` before or after the request arrived
+    Stripe {
+        secrets: Vec<String>,
+        tolerance: Duration,
+    },
+    /// `X-Slack-Signature: v0=<hex>` over `v0:<timestamp>:<body>`, with the
+    /// timestamp in `X-Slack-Request-Timestamp`
+    Slack {
+        secrets: Vec<String>,
+        tolerance: Duration,
+    },
+    /// `X-Twilio-Signature: <base64 HMAC-SHA1>` over the URL followed by the
+    /// sorted form parameters, with any of the auth tokens. `url` is the
+    /// webhook URL of the token; the request's path and query are appended.
+    Twilio { secrets: Vec<String>, url: String },
+    /// A `[signatures.<name>]` scheme of the config
+    Custom {
+        scheme: CustomScheme,
+        secrets: Vec<String>,
+        url: String,
+        tolerance: Duration,
+    },
+}
+
+/// A signature scheme defined in the config
+pub struct CustomScheme {
+    header: String,
+    algorithm: hmac::Algorithm,
+    base64: bool,
+    prefix: String,
+    payload: Payload,
+    separator: String,
+}
+
+/// What a custom scheme signs, before the body
+enum Payload {
+    Body,
+    /// The Unix timestamp in this header
+    Timestamp(String),
+    /// The URL the request was sent to
+    Url,
+}
+
+impl CustomScheme {
+    pub fn new(settings: &SignatureConfig) -> Result<Self> {
+        let algorithm = match settings.algorithm.as_deref().unwrap_or("sha256") {
+            "sha1" => hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            "sha256" => hmac::HMAC_SHA256,
+            "sha384" => hmac::HMAC_SHA384,
+            "sha512" => hmac::HMAC_SHA512,
+            algorithm => anyhow::bail!(
+                "algorithm `{}` is not one of sha1, sha256, sha384, sha512",
+                algorithm
+            ),
+        };
+        let base64 = match settings.encoding.as_deref().unwrap_or("hex") {
+            "hex" => false,
+            "base64" => true,
+            encoding => anyhow::bail!("encoding `{}` is not one of hex, base64", encoding),
+        };
+        let payload = match settings.payload.as_deref().unwrap_or("body") {
+            "body" => Payload::Body,
+            "timestamp+body" => Payload::Timestamp(
+                settings
+                    .timestamp_header
+                    .clone()
+                    .context("payload `timestamp+body` needs `timestamp_header`")?,
+            ),
+            "url+body" => Payload::Url,
+            payload => anyhow::bail!(
+                "payload `{}` is not one of body, timestamp+body, url+body",
+                payload
+            ),
+        };
+        if settings.header.trim().is_empty() {
+            anyhow::bail!("`header` is empty");
+        }
+        Ok(CustomScheme {
+            header: settings.header.trim().to_string(),
+            algorithm,
+            base64,
+            prefix: settings.prefix.clone().unwrap_or_default(),
+            payload,
+            separator: settings.separator.clone().unwrap_or_default(),
+        })
+    }
+}
+
+/// The outcome of checking a request's signature
+pub enum Verdict {
+    Valid(String),
+    Invalid(String),
+    /// The signature header is missing
+    Unsigned(String),
+}
+
+impl Verifier {
+    /// A verifier of one of `SCHEMES`. `url` is the webhook URL of the token,
+    /// for Twilio.
+    pub fn new(scheme: &str, secrets: Vec<String>, url: &str, tolerance: Duration) -> Result<Self> {
+        Ok(match scheme.to_lowercase().as_str() {
+            "github" => Verifier::GitHub(secrets),
+            "stripe" => Verifier::Stripe { secrets, tolerance },
+            "slack" => Verifier::Slack { secrets, tolerance },
+            "twilio" => Verifier::Twilio {
+                secrets,
+                url: url.to_string(),
+            },
+            scheme => anyhow::bail!(
+                "Unknown signature scheme `{}` (use {})",
+                scheme,
+                SCHEMES.join(", ")
+            ),
+        })
+    }
+
+    /// The verifier set up with `verify` and `secrets` for a saved token,
+    /// looked up by alias or token; `webhook_url` is the token's URL on the
+    /// service. `verify` names one of `SCHEMES` or of the `[signatures]`
+    /// section.
+    pub fn for_token(
+        config: &Config,
+        name: &str,
+        webhook_url: &str,
+        tolerance: Duration,
+    ) -> Result<Option<Self>> {
+        let Some(saved) = config.token_settings(name) else {
+            return Ok(None);
+        };
+        let Some(scheme) = &saved.verify else {
+            return Ok(None);
+        };
+        if saved.secrets.is_empty() {
+            anyhow::bail!("`verify` of token {} needs `secrets`", name);
+        }
+        let secrets = saved
+            .secrets
+            .iter()
+            .map(|secret| resolve_secret(secret))
+            .collect::<Result<_, _>>()?;
+        let url = saved.url.as_deref().unwrap_or(webhook_url);
+        if let Some(settings) = config.signatures.get(scheme) {
+            let scheme = CustomScheme::new(settings)
+                .with_context(|| format!("Invalid signature scheme `{}`", scheme))?;
+            return Ok(Some(Verifier::Custom {
+                scheme,
+                secrets,
+                url: url.to_string(),
+                tolerance,
+            }));
+        }
+        Self::new(scheme, secrets, url, tolerance)
+            .with_context(|| format!("Invalid `verify` for token {}", name))
+            .map(Some)
+    }
+
+    pub fn verify(&self, request: &WebhookRequest) -> Verdict {
+        // The body exactly as received: a re-serialized body would not match
+        let body = request.body.as_deref().unwrap_or_default().as_bytes();
+        match self {
+            Verifier::GitHub(secrets) => {
+                let name = "X-Hub-Signature-256";
+                let Some(value) = request.header(name) else {
+                    return Verdict::Unsigned(format!("no {} header", name));
+                };
+                let Some(signature) = value.strip_prefix("sha256=").and_then(decode_hex) else {
+                    return Verdict::Invalid(format!("malformed {} header", name));
+                };
+                match matching_secret(hmac::HMAC_SHA256, secrets, body, &signature) {
+                    Some(index) => Verdict::Valid(describe_match(name, index, secrets.len())),
+                    None => Verdict::Invalid("signature does not match the body".to_string()),
+                }
+            }
+            Verifier::Stripe { secrets, tolerance } => {
+                let name = "Stripe-Signature";
+                let Some(value) = request.header(name) else {
+                    return Verdict::Unsigned(format!("no {} header", name));
+                };
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for part in value.split(',') {
+                    match part.trim().split_once('=') {
+                        Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                        // Stripe sends one per active secret while rolling them
+                        Some(("v1", value)) => signatures.extend(decode_hex(value)),
+                        _ => {}
+                    }
+                }
+                let Some(timestamp) = timestamp.filter(|_| !signatures.is_empty()) else {
+                    return Verdict::Invalid(format!("malformed {} header", name));
+                };
+
+                let mut payload = format!("{}.", timestamp).into_bytes();
+                payload.extend_from_slice(body);
+                let Some(index) = signatures.iter().find_map(|signature| {
+                    matching_secret(hmac::HMAC_SHA256, secrets, &payload, signature)
+                }) else {
+                    return Verdict::Invalid("no v1 signature matches the body".to_string());
+                };
+                timed_verdict(request, timestamp, *tolerance, name, index, secrets.len())
+            }
+            Verifier::Slack { secrets, tolerance } => {
+                let name = "X-Slack-Signature";
+                let Some(value) = request.header(name) else {
+                    return Verdict::Unsigned(format!("no {} header", name));
+                };
+                let Some(timestamp) = request
+                    .header("X-Slack-Request-Timestamp")
+                    .and_then(|timestamp| timestamp.trim().parse::<i64>().ok())
+                else {
+                    return Verdict::Invalid(
+                        "missing X-Slack-Request-Timestamp header".to_string(),
+                    );
+                };
+                let Some(signature) = value.strip_prefix("v0=").and_then(decode_hex) else {
+                    return Verdict::Invalid(format!("malformed {} header", name));
+                };
+
+                let mut payload = format!("v0:{}:", timestamp).into_bytes();
+                payload.extend_from_slice(body);
+                let Some(index) = matching_secret(hmac::HMAC_SHA256, secrets, &payload, &signature)
+                else {
+                    return Verdict::Invalid("signature does not match the body".to_string());
+                };
+                timed_verdict(request, timestamp, *tolerance, name, index, secrets.len())
+            }
+            Verifier::Twilio { secrets, url } => {
+                let name = "X-Twilio-Signature";
+                let Some(value) = request.header(name) else {
+                    return Verdict::Unsigned(format!("no {} header", name));
+                };
+                let Ok(signature) = BASE64.decode(value.trim()) else {
+                    return Verdict::Invalid(format!("malformed {} header", name));
+                };
+
+                let mut data = called_url(url, request);
+                let is_form = request
+                    .header("Content-Type")
+                    .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+                if is_form {
+                    let text = String::from_utf8_lossy(body);
+                    let mut params = form_params(&text);
+                    params.sort();
+                    for (key, value) in params {
+                        data.push_str(&key);
+                        data.push_str(&value);
+                    }
+                }
+
+                let Some(index) = matching_secret(
+                    hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+                    secrets,
+                    data.as_bytes(),
+                    &signature,
+                ) else {
+                    return Verdict::Invalid(format!("signature does not match {}", data));
+                };
+                Verdict::Valid(describe_match(name, index, secrets.len()))
+            }
+            Verifier::Custom {
+                scheme,
+                secrets,
+                url,
+                tolerance,
+            } => {
+                let name = scheme.header.as_str();
+                let Some(value) = request.header(name) else {
+                    return Verdict::Unsigned(format!("no {} header", name));
+                };
+                let signature =
+                    value
+                        .trim()
+                        .strip_prefix(scheme.prefix.as_str())
+                        .and_then(|value| {
+                            if scheme.base64 {
+                                BASE64.decode(value).ok()
+                            } else {
+                                decode_hex(value)
+                            }
+                        });
+                let Some(signature) = signature else {
+                    return Verdict::Invalid(format!("malformed {} header", name));
+                };
+
+                let mut timestamp = None;
+                let mut payload = match &scheme.payload {
+                    Payload::Body => Vec::new(),
+                    Payload::Timestamp(timestamp_header) => {
+                        let Some(value) = request
+                            .header(timestamp_header)
+                            .and_then(|value| value.trim().parse::<i64>().ok())
+                        else {
+                            return Verdict::Invalid(format!(
+                                "missing {} header",
+                                timestamp_header
+                            ));
+                        };
+                        timestamp = Some(value);
+                        format!("{}{}", value, scheme.separator).into_bytes()
+                    }
+                    Payload::Url => {
+                        format!("{}{}", called_url(url, request), scheme.separator).into_bytes()
+                    }
+                };
+                payload.extend_from_slice(body);
+                let Some(index) = matching_secret(scheme.algorithm, secrets, &payload, &signature)
+                else {
+                    return Verdict::Invalid("signature does not match the body".to_string());
+                };
+                match timestamp {
+                    Some(timestamp) => {
+                        timed_verdict(request, timestamp, *tolerance, name, index, secrets.len())
+                    }
+                    None => Verdict::Valid(describe_match(name, index, secrets.len())),
+                }
+            }
+        }
+    }
+}
+
+/// The URL a request was sent to: `url` followed by the request's path and
+/// query
+fn called_url(url: &str, request: &WebhookRequest) -> String {
+    let path = extract_path(&request.message_object.value, &request.token_id);
+    let mut called = url.trim_end_matches('/').to_string();
+    if path != "/" {
+        called.push_str(&path);
+    }
+    called
+}
+
+/// The verdict on a matching signature made at `timestamp` (Unix seconds):
+/// invalid when it is more than `tolerance` from the request's arrival
+fn timed_verdict(
+    request: &WebhookRequest,
+    timestamp: i64,
+    tolerance: Duration,
+    header: &str,
+    index: usize,
+    secrets: usize,
+) -> Verdict {
+    // Captured requests are checked against their arrival, not now
+    let arrived = request.date.unwrap_or_else(Utc::now).timestamp();
+    let skew = Duration::from_secs(arrived.abs_diff(timestamp));
+    let skew_text = format!(
+        "skew {}{}",
+        format_elapsed(skew),
+        if timestamp > arrived { " ahead" } else { "" }
+    );
+    if skew > tolerance {
+        return Verdict::Invalid(format!(
+            "timestamp {}, beyond the {} tolerance",
+            skew_text,
+            format_elapsed(tolerance)
+        ));
+    }
+    Verdict::Valid(format!(
+        "{}, {}",
+        describe_match(header, index, secrets),
+        skew_text
+    ))
+}
+
+impl Verdict {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Verdict::Valid(_))
+    }
+
+    /// `✓` or `✗`, shown before a request summary
+    pub fn mark(&self) -> ColoredString {
+        match self {
+            Verdict::Valid(_) => "✓".bright_green(),
+            Verdict::Invalid(_) => "✗".bright_red(),
+            Verdict::Unsigned(_) => "✗".bright_yellow(),
+        }
+    }
+
+    /// A one-line badge, e.g. `✓ signature valid (X-Hub-Signature-256)`
+    pub fn badge(&self) -> ColoredString {
+        match self {
+            Verdict::Valid(detail) => format!("✓ signature valid ({})", detail).bright_green(),
+            Verdict::Invalid(detail) => format!("✗ signature invalid: {}", detail).bright_red(),
+            Verdict::Unsigned(detail) => format!("✗ unsigned: {}", detail).bright_yellow(),
+        }
+    }
+}
+
+/// `webhook verify` with a secret: check the signature of each request, the
+/// latest last
+pub fn verify_signatures(verifier: &Verifier, requests: &[WebhookRequest]) -> Result<()> {
+    let mut requests: Vec<&WebhookRequest> = requests.iter().collect();
+    sort_requests(&mut requests, SortBy::Date, false, |request| request);
+
+    let mut invalid = 0;
+    for request in &requests {
+        let verdict = verifier.verify(request);
+        print_request_summary(request, false, 0, None);
+        println!("  {}", verdict.badge());
+        if !verdict.is_valid() {
+            invalid += 1;
+        }
+    }
+    println!();
+    if invalid > 0 {
+        return Err(Failure::new(
+            exit::INVALID_SIGNATURE,
+            format!(
+                "{} of {} requests are not validly signed",
+                invalid,
+                requests.len()
+            ),
+        )
+        .into());
+    }
+    println!(
+        "{} All {} requests are validly signed",
+        "✓".bright_green(),
+        requests.len()
+    );
+    Ok(())
+}
+
+/// Index of the secret whose HMAC of `data` is `signature`
+fn matching_secret(
+    algorithm: hmac::Algorithm,
+    secrets: &[String],
+    data: &[u8],
+    signature: &[u8],
+) -> Option<usize> {
+    secrets.iter().position(|secret| {
+        let key = hmac::Key::new(algorithm, secret.as_bytes());
+        // Constant time, unlike comparing the hex digests
+        hmac::verify(&key, data, signature).is_ok()
+    })
+}
+
+fn describe_match(header: &str, index: usize, secrets: usize) -> String {
+    if secrets > 1 {
+        format!("{}, secret {} of {}", header, index + 1, secrets)
+    } else {
+        header.to_string()
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    // from_str_radix alone would take a sign, as in "+a"
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Decoded `key=value` pairs of a form-encoded body
+fn form_params(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (form_decode(key), form_decode(value))
+        })
+        .collect()
+}
+
+/// `text` percent-decoded, with form encoding's `+` for a space
+fn form_decode(text: &str) -> String {
+    let spaced = text.replace('+', " ");
+    String::from_utf8_lossy(&urlencoding::decode_binary(spaced.as_bytes())).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageObject;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    const TOLERANCE: Duration = Duration::from_secs(300);
+
+    fn request(headers: &[(&str, &str)], body: &str, arrived: i64) -> WebhookRequest {
+        let headers: HashMap<_, _> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), vec![value.to_string()]))
+            .collect();
+        WebhookRequest {
+            id: "request".to_string(),
+            date: Utc.timestamp_opt(arrived, 0).single(),
+            token_id: "token".to_string(),
+            message_object: MessageObject {
+                value: "/token".to_string(),
+                headers,
+                ..Default::default()
+            },
+            message: None,
+            body: Some(body.to_string()),
+            body_object: None,
+        }
+    }
+
+    fn verify(verifier: &Verifier, request: &WebhookRequest) -> bool {
+        verifier.verify(request).is_valid()
+    }
+
+    #[test]
+    fn github_documented_example() {
+        // From GitHub's "Validating webhook deliveries"
+        let verifier = Verifier::new(
+            "github",
+            vec!["It's a Secret to Everybody".to_string()],
+            "",
+            TOLERANCE,
+        )
+        .unwrap();
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        let signed = request(&[("X-Hub-Signature-256", signature)], "Hello, World!", 0);
+        assert!(verify(&verifier, &signed));
+        let tampered = request(&[("X-Hub-Signature-256", signature)], "Hello, World?", 0);
+        assert!(!verify(&verifier, &tampered));
+    }
+
+    #[test]
+    fn stripe_signed_payload() {
+        let verifier = Verifier::new(
+            "stripe",
+            vec!["whsec_test_secret".to_string()],
+            "",
+            TOLERANCE,
+        )
+        .unwrap();
+        let header = "t=1492774577,\
+            v1=22f7d74836ddad3284a2b80853dd2fe731655c1bfddcff3c3666a500cf3abd80,\
+            v0=6ffbb59b2300aae63f272406069a9788598b792a944a07aba816edb039989a39";
+        let body = r#"{"id":"evt_test"}"#;
+        assert!(verify(
+            &verifier,
+            &request(&[("Stripe-Signature", header)], body, 1492774577)
+        ));
+        // Signed more than the tolerance before it arrived
+        assert!(!verify(
+            &verifier,
+            &request(&[("Stripe-Signature", header)], body, 1492774577 + 301)
+        ));
+    }
+
+    #[test]
+    fn slack_signed_payload() {
+        let verifier = Verifier::new(
+            "slack",
+            vec!["8f742231b10e8888abcd99yyyzzz85a5".to_string()],
+            "",
+            TOLERANCE,
+        )
+        .unwrap();
+        let body = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow\
+            &channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner\
+            &command=%2Fwebbot&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands\
+            %2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN\
+            &trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+        let headers = [
+            ("X-Slack-Request-Timestamp", "1531420618"),
+            (
+                "X-Slack-Signature",
+                "v0=bc33a7b8fff46fe303f20f307c19c8f69540cb2fc952bcc56232698f62fd114a",
+            ),
+        ];
+        assert!(verify(&verifier, &request(&headers, body, 1531420618)));
+    }
+
+    #[test]
+    fn twilio_documented_example() {
+        // From Twilio's "Webhooks security", with the parameters form-encoded
+        let verifier = Verifier::new(
+            "twilio",
+            vec!["12345".to_string()],
+            "https://mycompany.com/myapp.php?foo=1&bar=2",
+            TOLERANCE,
+        )
+        .unwrap();
+        let body = "CallSid=CA1234567890ABCDE&Caller=%2B12349013030&Digits=1234\
+            &From=%2B12349013030&To=%2B18005551212";
+        let headers = [
+            ("Content-Type", "application/x-www-form-urlencoded"),
+            ("X-Twilio-Signature", "0/KCTR6DLpKmkAf8muzZqo1nDgQ="),
+        ];
+        assert!(verify(&verifier, &request(&headers, body, 0)));
+    }
+
+    #[test]
+    fn shopify_as_custom_scheme() {
+        let settings = SignatureConfig {
+            header: "X-Shopify-Hmac-Sha256".to_string(),
+            algorithm: None,
+            encoding: Some("base64".to_string()),
+            prefix: None,
+            payload: None,
+            timestamp_header: None,
+            separator: None,
+        };
+        let verifier = Verifier::Custom {
+            scheme: CustomScheme::new(&settings).unwrap(),
+            secrets: vec!["shpss_test_secret".to_string()],
+            url: String::new(),
+            tolerance: TOLERANCE,
+        };
+        let headers = [(
+            "X-Shopify-Hmac-Sha256",
+            "AU5n5C4JV1EbE8g4yEzV+ZHbqkXpM29HXkzNAa5zicA=",
+        )];
+        let body = r#"{"id":820982911946154508}"#;
+        assert!(verify(&verifier, &request(&headers, body, 0)));
+    }
+
+    #[test]
+    fn decode_hex_rejects_signs() {
+        assert_eq!(decode_hex("0aff"), Some(vec![0x0a, 0xff]));
+        assert_eq!(decode_hex("+a+b"), None);
+        assert_eq!(decode_hex("-a"), None);
+    }
+
+    #[test]
+    fn form_decode_handles_plus_and_percent() {
+        assert_eq!(form_decode("a+b%2Bc%3D"), "a b+c=");
+        assert_eq!(form_decode("100%"), "100%");
+    }
+}
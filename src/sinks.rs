@@ -0,0 +1,376 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+
+use crate::models::{WebhookRequest, extract_path};
+
+/// `monitor --log-to`: new requests appended as JSON lines, in the format of
+/// `export --format ndjson`, rotated by size
+pub struct CaptureLog {
+    path: PathBuf,
+    /// Rotate before the file grows past this many bytes; 0 never rotates
+    max_size: u64,
+    /// Rotated files kept, `<path>.1` being the newest
+    keep: u32,
+    file: File,
+    size: u64,
+}
+
+impl CaptureLog {
+    pub fn open(path: &Path, max_size: u64, keep: u32) -> Result<Self> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = open_append(path)?;
+        let size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        Ok(CaptureLog {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            file,
+            size,
+        })
+    }
+
+    /// Append `request` as one line, rotating first when it would not fit
+    pub fn write(&mut self, request: &WebhookRequest) -> Result<()> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        let len = line.len() as u64;
+        if self.max_size > 0 && self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> Result<()> {
+        let rotated = |index: u32| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", index));
+            PathBuf::from(name)
+        };
+        if self.keep == 0 {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("Failed to remove {}", self.path.display()))?;
+        } else {
+            let _ = fs::remove_file(rotated(self.keep));
+            for index in (1..self.keep).rev() {
+                let from = rotated(index);
+                if from.exists() {
+                    fs::rename(&from, rotated(index + 1))
+                        .with_context(|| format!("Failed to rotate {}", from.display()))?;
+                }
+            }
+            fs::rename(&self.path, rotated(1))
+                .with_context(|| format!("Failed to rotate {}", self.path.display()))?;
+        }
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Sockets of the local syslog daemon on Linux, macOS and the BSDs
+#[cfg(unix)]
+const LOCAL_SOCKETS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// Facility `user` (1), severity `info` (6)
+const PRIORITY: u8 = 14;
+
+/// `monitor --syslog`: a line per new request, in the BSD syslog format
+/// (RFC 3164) understood by rsyslog, syslog-ng and journald
+pub struct Syslog {
+    transport: SyslogTransport,
+    hostname: String,
+}
+
+enum SyslogTransport {
+    #[cfg(unix)]
+    Local(std::os::unix::net::UnixDatagram),
+    Udp(UdpSocket),
+}
+
+impl Syslog {
+    /// Connect to `local` (the daemon's socket) or to `host:port` over UDP
+    pub fn connect(address: &str) -> Result<Self> {
+        let transport = if address == "local" {
+            local_transport()?
+        } else {
+            let socket = UdpSocket::bind("0.0.0.0:0").context("Failed to open a UDP socket")?;
+            socket
+                .connect(address)
+                .with_context(|| format!("Failed to reach syslog at {}", address))?;
+            SyslogTransport::Udp(socket)
+        };
+        Ok(Syslog {
+            transport,
+            hostname: hostname(),
+        })
+    }
+
+    /// Send `request`, received for the token `name`
+    pub fn send(&self, name: &str, request: &WebhookRequest) -> Result<()> {
+        let path = extract_path(&request.message_object.value, &request.token_id);
+        let mut message = format!(
+            "{} {} {} id={} size={}",
+            name,
+            request.message_object.method,
+            path,
+            request.id,
+            request.body.as_deref().map_or(0, str::len)
+        );
+        if let Some(event_type) = request.event_type() {
+            message.push_str(&format!(" event={}", event_type));
+        }
+        let line = format!(
+            "<{}>{} {} webhook[{}]: {}",
+            PRIORITY,
+            Local::now().format("%b %e %H:%M:%S"),
+            self.hostname,
+            std::process::id(),
+            message.replace('\n', " ")
+        );
+        match &self.transport {
+            #[cfg(unix)]
+            SyslogTransport::Local(socket) => socket.send(line.as_bytes()),
+            SyslogTransport::Udp(socket) => socket.send(line.as_bytes()),
+        }
+        .context("Failed to send to syslog")?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn local_transport() -> Result<SyslogTransport> {
+    let socket =
+        std::os::unix::net::UnixDatagram::unbound().context("Failed to open a syslog socket")?;
+    let path = LOCAL_SOCKETS
+        .iter()
+        .find(|path| socket.connect(path).is_ok())
+        .context("No local syslog daemon found; pass --syslog <HOST:PORT> to send over UDP")?;
+    crate::logging::debug(1, format_args!("Sending to syslog at {}", path));
+    Ok(SyslogTransport::Local(socket))
+}
+
+#[cfg(not(unix))]
+fn local_transport() -> Result<SyslogTransport> {
+    anyhow::bail!("There is no local syslog on this platform; pass --syslog <HOST:PORT>")
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    match std::str::from_utf8(&buffer[..end]) {
+        Ok(name) if result == 0 && !name.is_empty() => name.to_string(),
+        _ => "localhost".to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "localhost".to_string())
+}
+
+/// `monitor --pipe`: new requests written as JSON lines to a Unix socket or
+/// named pipe, for other local processes to consume
+pub struct PipeSink {
+    path: PathBuf,
+    #[cfg(unix)]
+    target: unix_pipe::Target,
+}
+
+impl PipeSink {
+    /// Write to the named pipe or listening socket at `path`, or else listen
+    /// on a new socket there
+    #[cfg(unix)]
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(PipeSink {
+            path: path.to_path_buf(),
+            target: unix_pipe::Target::open(path)?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open(_path: &Path) -> Result<Self> {
+        anyhow::bail!("--pipe needs Unix sockets or named pipes, which this platform lacks")
+    }
+
+    /// Write `request` as one line to every consumer; none may be connected
+    pub fn write(&mut self, request: &WebhookRequest) -> Result<()> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        #[cfg(unix)]
+        self.target
+            .write(line.as_bytes())
+            .with_context(|| format!("Failed to write to {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod unix_pipe {
+    use anyhow::{Context, Result};
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{ErrorKind, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use crate::logging;
+
+    /// A consumer slower than this loses its connection
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    pub enum Target {
+        /// A FIFO made with `mkfifo`, opened while a reader has it open
+        Fifo { path: PathBuf, file: Option<File> },
+        /// A socket another process listens on, connected to as needed
+        Socket {
+            path: PathBuf,
+            stream: Option<UnixStream>,
+        },
+        /// Our own socket, written to every connected consumer
+        Listener {
+            path: PathBuf,
+            listener: UnixListener,
+            clients: Vec<UnixStream>,
+        },
+    }
+
+    impl Target {
+        pub fn open(path: &Path) -> Result<Self> {
+            let path = path.to_path_buf();
+            match fs::metadata(&path) {
+                Ok(meta) if meta.file_type().is_fifo() => Ok(Target::Fifo { path, file: None }),
+                Ok(meta) if meta.file_type().is_socket() => match UnixStream::connect(&path) {
+                    Ok(stream) => {
+                        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                        Ok(Target::Socket {
+                            path,
+                            stream: Some(stream),
+                        })
+                    }
+                    Err(_) => {
+                        // Left behind by a previous session that listened there
+                        fs::remove_file(&path)
+                            .with_context(|| format!("Failed to remove {}", path.display()))?;
+                        Self::listen(path)
+                    }
+                },
+                Ok(_) => anyhow::bail!(
+                    "{} is neither a Unix socket nor a named pipe",
+                    path.display()
+                ),
+                Err(_) => Self::listen(path),
+            }
+        }
+
+        fn listen(path: PathBuf) -> Result<Self> {
+            let listener = UnixListener::bind(&path)
+                .with_context(|| format!("Failed to listen on {}", path.display()))?;
+            listener.set_nonblocking(true)?;
+            Ok(Target::Listener {
+                path,
+                listener,
+                clients: Vec::new(),
+            })
+        }
+
+        pub fn write(&mut self, line: &[u8]) -> Result<()> {
+            match self {
+                Target::Fifo { path, file } => {
+                    if file.is_none() {
+                        *file = open_fifo(path)?;
+                    }
+                    if let Some(writer) = file
+                        && let Err(e) = writer.write_all(line)
+                    {
+                        // The reader went away; reopen for the next one
+                        *file = None;
+                        if e.kind() != ErrorKind::BrokenPipe {
+                            return Err(e.into());
+                        }
+                    }
+                }
+                Target::Socket { path, stream } => {
+                    if stream.is_none() {
+                        *stream = UnixStream::connect(&*path).ok();
+                        if let Some(stream) = stream {
+                            stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                        }
+                    }
+                    if let Some(writer) = stream
+                        && writer.write_all(line).is_err()
+                    {
+                        *stream = None;
+                        logging::debug(1, format_args!("Lost {}", path.display()));
+                    }
+                }
+                Target::Listener {
+                    listener, clients, ..
+                } => {
+                    while let Ok((client, _)) = listener.accept() {
+                        client.set_nonblocking(false)?;
+                        client.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                        clients.push(client);
+                    }
+                    clients.retain_mut(|client| client.write_all(line).is_ok());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Target {
+        fn drop(&mut self) {
+            if let Target::Listener { path, .. } = self {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Open the FIFO for writing; `None` while no process reads it
+    fn open_fifo(path: &Path) -> Result<Option<File>> {
+        // Without a reader, a blocking open would hang and a non-blocking one fails
+        match OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => {
+                // SAFETY: the descriptor stays open for the duration of the calls
+                unsafe {
+                    let flags = libc::fcntl(file.as_raw_fd(), libc::F_GETFL);
+                    libc::fcntl(file.as_raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK);
+                }
+                Ok(Some(file))
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to open {}", path.display())),
+        }
+    }
+}
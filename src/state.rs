@@ -0,0 +1,132 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{IoResultExt, Result, WebhookError};
+
+/// State kept between monitor sessions: the newest request seen per token,
+/// so a restarted monitor picks up where the previous one left off
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MonitorState {
+    #[serde(default)]
+    last_seen: BTreeMap<String, String>,
+}
+
+impl MonitorState {
+    /// Load the saved state. A missing or unreadable file yields empty state.
+    pub fn load() -> Self {
+        read_state("monitor-state.json")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write_state("monitor-state.json", self)
+    }
+
+    pub fn last_seen(&self, token: &str) -> Option<&str> {
+        self.last_seen.get(token).map(String::as_str)
+    }
+
+    pub fn set_last_seen(&mut self, token: &str, request_id: &str) {
+        self.last_seen
+            .insert(token.to_string(), request_id.to_string());
+    }
+}
+
+/// The token most recently used with each profile, taken by `logs`,
+/// `monitor` and `show` when `--token` is omitted
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LastTokens {
+    #[serde(default)]
+    by_profile: BTreeMap<String, String>,
+}
+
+impl LastTokens {
+    /// Load the saved tokens. A missing or unreadable file yields none.
+    pub fn load() -> Self {
+        read_state("last-tokens.json")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write_state("last-tokens.json", self)
+    }
+
+    pub fn get(&self, profile: Option<&str>) -> Option<&str> {
+        self.by_profile
+            .get(profile.unwrap_or(DEFAULT_PROFILE))
+            .map(String::as_str)
+    }
+
+    pub fn set(&mut self, profile: Option<&str>, token: &str) {
+        self.by_profile.insert(
+            profile.unwrap_or(DEFAULT_PROFILE).to_string(),
+            token.to_string(),
+        );
+    }
+}
+
+/// Key for the settings used without `--profile`
+const DEFAULT_PROFILE: &str = "default";
+
+fn read_state<T: DeserializeOwned + Default>(file: &str) -> T {
+    state_path(file)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_state<T: Serialize>(file: &str, state: &T) -> Result<()> {
+    let path = state_path(file)
+        .ok_or_else(|| WebhookError::config("Could not determine the cache directory"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).io_err(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let content = serde_json::to_string_pretty(state)?;
+    fs::write(&path, content).io_err(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+fn state_path(file: &str) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("webhook-cli").join(file))
+}
+
+/// Per-user cache directory for the current platform
+pub fn cache_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        env_dir("XDG_CACHE_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".cache")))
+    }
+}
+
+/// Per-user configuration directory for the current platform
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// Per-user data directory for the current platform
+pub fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::HashMap;
+
+use crate::archive::Archive;
+use crate::config::Config;
+
+/// Event types listed per token, the most frequent first
+const TOP_EVENT_TYPES: usize = 10;
+
+/// `webhook stats`: requests, size and time span of each archived token and,
+/// for `token` or every token, the requests by method and event type
+pub fn archive_stats(config: &Config, token: Option<&str>) -> Result<()> {
+    let archive = Archive::new(&config.archive)?;
+    let mut summary = archive.summary()?;
+    if let Some(token) = token {
+        summary.retain(|tokens| tokens.token == token);
+    }
+    if summary.is_empty() {
+        let message = match token {
+            Some(_) => "No archived requests for this token",
+            None if !config.archive.enabled => {
+                "The archive is disabled; set `enabled = true` in [archive] to keep fetched requests"
+            }
+            None => "The archive is empty",
+        };
+        println!("{}", message.bright_yellow());
+        return Ok(());
+    }
+
+    println!(
+        "{} {} {}",
+        "Archive".bright_blue().bold(),
+        archive.path().display(),
+        format!("({} on disk)", format_size(archive.file_size())).bright_black()
+    );
+    println!();
+    let name = |token: &str| {
+        config
+            .saved_tokens()
+            .find(|&(_, saved)| saved == token)
+            .map_or(token.to_string(), |(alias, _)| alias.to_string())
+    };
+    let width = summary
+        .iter()
+        .map(|tokens| name(&tokens.token).chars().count())
+        .max()
+        .unwrap_or(0);
+    for tokens in &summary {
+        println!(
+            "{:<width$}  {:>7} requests  {:>9}  {} {} {}",
+            name(&tokens.token).bright_white().bold(),
+            tokens.requests,
+            format_size(tokens.size),
+            tokens.first.format("%Y-%m-%d %H:%M"),
+            "→".bright_black(),
+            tokens.last.format("%Y-%m-%d %H:%M UTC"),
+            width = width
+        );
+    }
+
+    let requests = match token {
+        Some(token) => archive.load(token)?,
+        None => archive.load_all()?,
+    };
+    let mut methods = HashMap::new();
+    let mut event_types = HashMap::new();
+    for archived in &requests {
+        *methods
+            .entry(archived.request.message_object.method.to_string())
+            .or_insert(0) += 1;
+        if let Some(event_type) = archived.request.event_type() {
+            *event_types.entry(event_type).or_insert(0) += 1;
+        }
+    }
+    println!();
+    print_counts("By method:", methods, usize::MAX);
+    if !event_types.is_empty() {
+        print_counts("By event type:", event_types, TOP_EVENT_TYPES);
+    }
+    Ok(())
+}
+
+/// `counts` the most frequent first, at most `limit` of them
+fn print_counts(title: &str, counts: HashMap<String, usize>, limit: usize) {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let others: usize = counts.iter().skip(limit).map(|(_, count)| count).sum();
+    counts.truncate(limit);
+    println!("{}", title.bright_cyan());
+    for (name, count) in counts {
+        println!("  {:>7}  {}", count, name);
+    }
+    if others > 0 {
+        println!("  {:>7}  {}", others, "others".bright_black());
+    }
+}
+
+/// `bytes` in B, KB, MB or GB (powers of 1024, as `--log-max-size` reads them)
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::state::data_dir;
+
+/// Tags and notes attached to requests with `webhook tag`, by request ID
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Tags {
+    #[serde(default)]
+    requests: BTreeMap<String, Vec<Tag>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Tags {
+    /// Load the saved tags. Unlike cached state, an unreadable file is an
+    /// error: saving over it would lose the user's notes.
+    pub fn load() -> Result<Self> {
+        let path = tags_path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = tags_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, request_id: &str) -> &[Tag] {
+        self.requests.get(request_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn has(&self, request_id: &str, tag: &str) -> bool {
+        self.get(request_id).iter().any(|t| t.name == tag)
+    }
+
+    /// Tag a request, replacing the note of an existing tag of the same name
+    pub fn add(&mut self, request_id: &str, name: &str, note: Option<&str>) {
+        let tags = self.requests.entry(request_id.to_string()).or_default();
+        let note = note.map(str::to_string);
+        match tags.iter_mut().find(|tag| tag.name == name) {
+            Some(tag) => tag.note = note,
+            None => tags.push(Tag {
+                name: name.to_string(),
+                note,
+            }),
+        }
+    }
+
+    /// Remove one tag, or every tag when `name` is `None`; returns how many were removed
+    pub fn remove(&mut self, request_id: &str, name: Option<&str>) -> usize {
+        let Some(tags) = self.requests.get_mut(request_id) else {
+            return 0;
+        };
+        let before = tags.len();
+        tags.retain(|tag| name.is_some_and(|name| tag.name != name));
+        let removed = before - tags.len();
+        if tags.is_empty() {
+            self.requests.remove(request_id);
+        }
+        removed
+    }
+}
+
+/// Print the tags of a request below its summary or details
+pub fn print_tags(tags: &[Tag]) {
+    for tag in tags {
+        match &tag.note {
+            Some(note) => println!(
+                "  {} {} {} {}",
+                "⚑".bright_magenta(),
+                tag.name.bright_magenta().bold(),
+                "—".bright_black(),
+                note.bright_white()
+            ),
+            None => println!(
+                "  {} {}",
+                "⚑".bright_magenta(),
+                tag.name.bright_magenta().bold()
+            ),
+        }
+    }
+}
+
+fn tags_path() -> Result<PathBuf> {
+    Ok(data_dir()
+        .context("Could not determine the data directory")?
+        .join("webhook-cli")
+        .join("tags.json"))
+}
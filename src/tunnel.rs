@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+
+use crate::cli::TunnelProvider;
+use crate::logging;
+
+/// How long to wait for the tunnel tool to print its public URL
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Providers tried by `--tunnel` without a value
+const AUTO_ORDER: [TunnelProvider; 3] = [
+    TunnelProvider::Cloudflared,
+    TunnelProvider::Ngrok,
+    TunnelProvider::Localtunnel,
+];
+
+/// A public URL forwarding to the local server, open while the tool runs
+pub struct Tunnel {
+    pub url: String,
+    pub provider: TunnelProvider,
+    // Killed when the tunnel is dropped
+    _child: Child,
+}
+
+/// Start a tunnel to `local_url` (e.g. `http://localhost:8080`) with the
+/// provider's command line tool and wait for its public URL
+pub async fn open_tunnel(provider: TunnelProvider, local_url: &str) -> Result<Tunnel> {
+    let provider = match provider {
+        TunnelProvider::Auto => AUTO_ORDER
+            .into_iter()
+            .find(|provider| on_path(provider.command()))
+            .context(
+                "No tunnel tool found; install cloudflared, ngrok or localtunnel (`npm install -g localtunnel`)",
+            )?,
+        provider => provider,
+    };
+    let https = local_url.starts_with("https://");
+    let port = local_url.rsplit(':').next().unwrap_or_default();
+
+    let mut command = match provider {
+        TunnelProvider::Cloudflared => {
+            let mut command = Command::new("cloudflared");
+            command.args(["tunnel", "--url", local_url]);
+            if https {
+                command.arg("--no-tls-verify");
+            }
+            command
+        }
+        TunnelProvider::Ngrok => {
+            let mut command = Command::new("ngrok");
+            command.args(["http", local_url, "--log", "stdout", "--log-format", "json"]);
+            command
+        }
+        TunnelProvider::Localtunnel | TunnelProvider::Auto => {
+            let mut command = Command::new("lt");
+            command.args(["--port", port]);
+            if https {
+                command.args(["--local-https", "--allow-invalid-cert"]);
+            }
+            command
+        }
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", provider.command()))?;
+
+    // Both streams are drained for as long as the tool runs, so it never
+    // blocks on a full pipe
+    let (lines, mut received) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout, lines.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_lines(stderr, lines);
+    }
+
+    let url = tokio::time::timeout(STARTUP_TIMEOUT, async {
+        while let Some(line) = received.recv().await {
+            if let Some(url) = public_url(provider, &line) {
+                return Some(url);
+            }
+        }
+        None
+    })
+    .await
+    .with_context(|| {
+        format!(
+            "{} did not report a public URL within {} seconds",
+            provider.command(),
+            STARTUP_TIMEOUT.as_secs()
+        )
+    })?
+    .with_context(|| {
+        format!(
+            "{} exited before reporting a public URL",
+            provider.command()
+        )
+    })?;
+
+    Ok(Tunnel {
+        url,
+        provider,
+        _child: child,
+    })
+}
+
+/// `webhook tunnel` without `--watch`: expose `local_url` until Ctrl+C
+pub async fn expose(provider: TunnelProvider, local_url: &str) -> Result<()> {
+    let tunnel = open_tunnel(provider, local_url).await?;
+    println!(
+        "{} {} {}",
+        "Public URL".bright_green().bold(),
+        tunnel.url.bright_white().bold(),
+        format!("(via {})", tunnel.provider.command()).bright_black()
+    );
+    println!("{} {}", "Forwarding to".bright_blue(), local_url);
+    println!("{}", "Press Ctrl+C to stop".bright_black());
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+fn forward_lines<R: AsyncRead + Unpin + Send + 'static>(
+    reader: R,
+    lines: mpsc::UnboundedSender<String>,
+) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = reader.next_line().await {
+            logging::debug(2, format_args!("    tunnel: {}", line));
+            // Nobody listens once the URL is known
+            let _ = lines.send(line);
+        }
+    });
+}
+
+/// The public URL in a line of the tool's output
+fn public_url(provider: TunnelProvider, line: &str) -> Option<String> {
+    let start = line.find("https://")?;
+    let url: String = line[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '|' | '\''))
+        .collect();
+    let found = match provider {
+        // Quick tunnels; other links in its banner point at cloudflare.com
+        TunnelProvider::Cloudflared => url.contains(".trycloudflare.com"),
+        TunnelProvider::Ngrok => line.contains("\"url\""),
+        TunnelProvider::Localtunnel | TunnelProvider::Auto => line.contains("your url is"),
+    };
+    found.then_some(url)
+}
+
+fn on_path(command: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        let candidate = dir.join(command);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+    })
+}
+
+impl TunnelProvider {
+    /// The command line tool of the provider
+    pub fn command(self) -> &'static str {
+        match self {
+            TunnelProvider::Cloudflared => "cloudflared",
+            TunnelProvider::Ngrok => "ngrok",
+            TunnelProvider::Localtunnel | TunnelProvider::Auto => "lt",
+        }
+    }
+}
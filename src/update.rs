@@ -0,0 +1,270 @@
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use reqwest::Client;
+use ring::digest::{SHA256, digest};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::info;
+use crate::progress::Progress;
+
+/// The latest release of webhook-cli on GitHub
+pub const RELEASES_URL: &str = "https://api.github.com/repos/SpeedSX/webhook-cli/releases/latest";
+
+/// The asset listing the SHA-256 checksums of a release's binaries, one
+/// "<hex>  <file name>" line each, as written by `sha256sum`
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// `webhook self-update`: install the latest release from `releases_url` in
+/// place of the running executable, after checking its SHA-256 checksum;
+/// with `check`, only report whether there is a newer one
+pub async fn self_update(releases_url: &str, check: bool) -> Result<()> {
+    let client = Client::builder()
+        .user_agent(concat!("webhook-cli/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let progress = Progress::spinner("Checking for updates...")?;
+    let release = fetch_release(&client, releases_url).await;
+    progress.finish_and_clear();
+    let release = release?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = release.tag_name.trim_start_matches('v');
+    if compare_versions(latest, current) != Ordering::Greater {
+        println!(
+            "{} webhook {} is up to date",
+            "✓".bright_green(),
+            current.bright_cyan()
+        );
+        return Ok(());
+    }
+
+    let name = asset_name();
+    if check {
+        println!(
+            "{} webhook {} is available (installed: {})",
+            "↑".bright_yellow(),
+            latest.bright_cyan(),
+            current
+        );
+        if !release.html_url.is_empty() {
+            info!("{}", release.html_url.bright_black());
+        }
+        match release.asset(&name) {
+            Some(_) => info!("Run {} to install it", "webhook self-update".bright_white()),
+            None => info!(
+                "The release has no {} binary to install",
+                name.bright_white()
+            ),
+        }
+        return Ok(());
+    }
+
+    let asset = release.asset(&name).with_context(|| {
+        format!(
+            "Release {} has no {} binary for this platform",
+            release.tag_name, name
+        )
+    })?;
+    let checksums = release.asset(CHECKSUMS_ASSET).with_context(|| {
+        format!(
+            "Release {} has no {} file; not installing a binary that cannot be verified",
+            release.tag_name, CHECKSUMS_ASSET
+        )
+    })?;
+
+    let sums = download(&client, &checksums.browser_download_url, false).await?;
+    let expected = expected_checksum(&String::from_utf8_lossy(&sums), &name)
+        .with_context(|| format!("{} lists no checksum for {}", CHECKSUMS_ASSET, name))?;
+    let binary = download(&client, &asset.browser_download_url, true).await?;
+    let actual = sha256_hex(&binary);
+    if !actual.eq_ignore_ascii_case(&expected) {
+        bail!(
+            "Checksum mismatch for {}: expected {}, got {}; the executable was not replaced",
+            name,
+            expected,
+            actual
+        );
+    }
+
+    let exe = std::env::current_exe().context("Failed to locate the running executable")?;
+    replace_executable(&exe, &binary)?;
+    println!(
+        "{} Updated webhook {} → {} ({})",
+        "✓".bright_green(),
+        current,
+        latest.bright_cyan(),
+        exe.display()
+    );
+    Ok(())
+}
+
+async fn fetch_release(client: &Client, url: &str) -> Result<Release> {
+    let response = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "Failed to fetch the latest release from {}: HTTP {}",
+            url,
+            status
+        );
+    }
+    response
+        .json()
+        .await
+        .with_context(|| format!("Unexpected release information from {}", url))
+}
+
+/// The body at `url`, with a progress bar when `show_progress` and the size is known
+async fn download(client: &Client, url: &str, show_progress: bool) -> Result<Vec<u8>> {
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+    if !response.status().is_success() {
+        bail!("Failed to download {}: HTTP {}", url, response.status());
+    }
+
+    let progress = match response.content_length() {
+        Some(len) if show_progress => Some(Progress::bar(len)?),
+        _ => None,
+    };
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?
+    {
+        body.extend_from_slice(&chunk);
+        if let Some(progress) = &progress {
+            progress.inc(chunk.len() as u64);
+        }
+    }
+    if let Some(progress) = &progress {
+        progress.finish_and_clear();
+    }
+    Ok(body)
+}
+
+/// The name of the release binary for this platform, such as
+/// `webhook-linux-x86_64` or `webhook-windows-x86_64.exe`
+fn asset_name() -> String {
+    format!(
+        "webhook-{}-{}{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// The checksum of `name` in a `sha256sum` listing
+fn expected_checksum(sums: &str, name: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, file) = line.split_once(char::is_whitespace)?;
+        // `sha256sum -b` marks binary files with a '*'
+        let file = file.trim().trim_start_matches('*');
+        (file == name).then(|| hash.to_string())
+    })
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Compare "1.2.3" style versions part by part; parts after a '-' or '+'
+/// (pre-releases, build metadata) are ignored
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    let (a, b) = (parts(a), parts(b));
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let part = |parts: &[u64]| parts.get(i).copied().unwrap_or(0);
+            part(&a).cmp(&part(&b))
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Write `binary` next to `exe` and move it into its place. Windows cannot
+/// overwrite a running executable, but can rename it out of the way.
+fn replace_executable(exe: &Path, binary: &[u8]) -> Result<()> {
+    let staged = sibling(exe, "new");
+    fs::write(&staged, binary).with_context(|| {
+        format!(
+            "Failed to write {}; is the directory writable by you?",
+            staged.display()
+        )
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(exe)
+            .map(|metadata| metadata.permissions().mode())
+            .unwrap_or(0o755);
+        fs::set_permissions(&staged, fs::Permissions::from_mode(mode))
+            .with_context(|| format!("Failed to make {} executable", staged.display()))?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    }
+
+    fs::rename(&staged, exe).with_context(|| {
+        let _ = fs::remove_file(&staged);
+        format!("Failed to replace {}", exe.display())
+    })
+}
+
+/// `exe` with `suffix` added to its file name (`webhook.new`, `webhook.exe.old`)
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    exe.with_file_name(name)
+}
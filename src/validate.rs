@@ -0,0 +1,411 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+use url::Url;
+
+use crate::client::WebhookClient;
+use crate::config::{
+    ARCHIVE_KEYS, AUTH_ENV_KEYS, CHAT_KEYS, Config, EMAIL_KEYS, OTLP_KEYS, REDACT_KEYS,
+    REQUIRED_WEBHOOK_KEYS, SIGNATURE_KEYS, TOKEN_KEYS, WEBHOOK_KEYS,
+};
+use crate::email::check_email_config;
+use crate::highlight::parse_pattern;
+use crate::migrate::{self, CONFIG_VERSION};
+use crate::otel::check_otlp_config;
+use crate::signature::{CustomScheme, SCHEMES};
+
+/// A problem found in the config file, with the line it was found on
+struct Problem {
+    line: Option<usize>,
+    message: String,
+    /// Warnings don't stop the configuration from loading
+    warning: bool,
+}
+
+/// Collects problems while walking the config file
+struct Report<'a> {
+    content: &'a str,
+    problems: Vec<Problem>,
+}
+
+impl Report<'_> {
+    fn error(&mut self, span: Option<Range<usize>>, message: String) {
+        self.push(span, message, false);
+    }
+
+    fn warning(&mut self, span: Option<Range<usize>>, message: String) {
+        self.push(span, message, true);
+    }
+
+    fn push(&mut self, span: Option<Range<usize>>, message: String, warning: bool) {
+        let line = span.map(|span| {
+            let start = span.start.min(self.content.len());
+            self.content[..start].matches('\n').count() + 1
+        });
+        self.problems.push(Problem {
+            line,
+            message,
+            warning,
+        });
+    }
+
+    /// Report keys of `table` that are not in `known`, and return the subtables
+    /// among them by name
+    fn check_keys<'t, 'i>(
+        &mut self,
+        table: &'t DeTable<'i>,
+        section: &str,
+        known: &[&str],
+    ) -> Vec<(&'t str, Range<usize>, &'t DeTable<'i>)> {
+        let mut tables = Vec::new();
+        for (key, value) in table.iter() {
+            let name = key.get_ref().as_ref();
+            if !known.contains(&name) {
+                self.warning(
+                    Some(key.span()),
+                    format!("unknown key `{}{}` is ignored", section, name),
+                );
+            } else if let DeValue::Table(subtable) = value.get_ref() {
+                tables.push((name, key.span(), subtable));
+            }
+        }
+        tables
+    }
+
+    fn check_required(
+        &mut self,
+        table: &DeTable,
+        span: Range<usize>,
+        section: &str,
+        required: &[&str],
+    ) {
+        for key in required {
+            if !table
+                .iter()
+                .any(|(name, _)| name.get_ref().as_ref() == *key)
+            {
+                self.error(
+                    Some(span.clone()),
+                    format!("missing key `{}{}`", section, key),
+                );
+            }
+        }
+    }
+
+    fn check_tokens(&mut self, tokens: &DeTable, section: &str) {
+        for (alias, value) in tokens.iter() {
+            let section = format!("{}tokens.{}.", section, alias.get_ref());
+            match value.get_ref() {
+                DeValue::Table(token) => {
+                    self.check_keys(token, &section, &TOKEN_KEYS);
+                    self.check_required(token, alias.span(), &section, &["token"]);
+                }
+                _ => self.error(
+                    Some(alias.span()),
+                    format!("`{}` must be a table", section.trim_end_matches('.')),
+                ),
+            }
+        }
+    }
+
+    fn check_auth(&mut self, auth: &DeTable, section: &str) {
+        let known: Vec<&str> = AUTH_ENV_KEYS.iter().copied().chain(["headers"]).collect();
+        self.check_keys(auth, &format!("{}auth.", section), &known);
+    }
+
+    /// Settings that only fail once they are used, checked for the `[webhook]`
+    /// section or a profile (`section`)
+    fn check_values(&mut self, config: &Config, section: &str, span: Option<Range<usize>>) {
+        match Url::parse(config.get_base_url()) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => self.error(
+                span.clone(),
+                format!(
+                    "{}: base_url `{}` is not an http:// or https:// URL",
+                    section,
+                    config.get_base_url()
+                ),
+            ),
+        }
+        if config.is_placeholder() {
+            self.warning(
+                span.clone(),
+                format!(
+                    "{}: base_url is the placeholder service; run `webhook init`",
+                    section
+                ),
+            );
+        }
+        if let Some(proxy) = config.get_proxy()
+            && let Err(e) = reqwest::Proxy::all(proxy)
+        {
+            self.error(
+                span.clone(),
+                format!("{}: proxy `{}`: {}", section, proxy, e),
+            );
+        }
+        match config.get_client_identity() {
+            Ok(identity) => {
+                let files = config
+                    .get_ca_cert()
+                    .into_iter()
+                    .chain(identity.into_iter().flat_map(|(cert, key)| [cert, key]));
+                for file in files.filter(|file| !file.exists()) {
+                    self.error(
+                        span.clone(),
+                        format!("{}: file {} does not exist", section, file.display()),
+                    );
+                }
+            }
+            Err(e) => self.error(span.clone(), format!("{}: {}", section, e)),
+        }
+        for (alias, saved) in &config.tokens {
+            let Some(scheme) = &saved.verify else {
+                continue;
+            };
+            if !SCHEMES.contains(&scheme.to_lowercase().as_str())
+                && !config.signatures.contains_key(scheme)
+            {
+                let names: Vec<&str> = SCHEMES
+                    .iter()
+                    .copied()
+                    .chain(config.signatures.keys().map(String::as_str))
+                    .collect();
+                self.error(
+                    span.clone(),
+                    format!(
+                        "{}: tokens.{}.verify `{}` is not one of {}",
+                        section,
+                        alias,
+                        scheme,
+                        names.join(", ")
+                    ),
+                );
+            }
+            if saved.secrets.is_empty() {
+                self.error(
+                    span.clone(),
+                    format!("{}: tokens.{}.verify needs `secrets`", section, alias),
+                );
+            }
+        }
+    }
+}
+
+/// Check the config file for syntax errors, unknown and missing keys and
+/// invalid values, printing every problem found
+pub fn validate_config(path: Option<&Path>) -> Result<()> {
+    let path =
+        Config::locate(path).context("No config file found. Run `webhook init` to create one")?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    println!("{} {}", "Checking".bright_blue().bold(), path.display());
+
+    let mut report = Report {
+        content: &content,
+        problems: Vec::new(),
+    };
+    match DeTable::parse(&content) {
+        Ok(root) => check_document(&mut report, &content, &root),
+        Err(e) => report.error(e.span(), e.message().to_string()),
+    }
+
+    report
+        .problems
+        .sort_by_key(|problem| (problem.line.is_none(), problem.line));
+    let errors = report.problems.iter().filter(|p| !p.warning).count();
+    for problem in &report.problems {
+        let location = problem
+            .line
+            .map(|line| format!("line {}: ", line))
+            .unwrap_or_default();
+        if problem.warning {
+            println!("  {} {}{}", "!".bright_yellow(), location, problem.message);
+        } else {
+            println!("  {} {}{}", "✗".bright_red(), location, problem.message);
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!(
+            "{} has {} error{}",
+            path.display(),
+            errors,
+            if errors == 1 { "" } else { "s" }
+        );
+    }
+    println!("{} Configuration is valid", "✓".bright_green());
+    Ok(())
+}
+
+fn check_document(report: &mut Report, content: &str, root: &Spanned<DeTable>) {
+    let mut webhook_span = None;
+    let mut archive_span = None;
+    for (name, span, table) in report.check_keys(
+        root.get_ref(),
+        "",
+        &[
+            "version",
+            "webhook",
+            "tokens",
+            "auth",
+            "archive",
+            "redact",
+            "alerts",
+            "chat",
+            "otlp",
+            "signatures",
+            "profiles",
+        ],
+    ) {
+        match name {
+            "webhook" => {
+                report.check_keys(table, "webhook.", &WEBHOOK_KEYS);
+                report.check_required(table, span.clone(), "webhook.", &REQUIRED_WEBHOOK_KEYS);
+                webhook_span = Some(span);
+            }
+            "tokens" => report.check_tokens(table, ""),
+            "auth" => report.check_auth(table, ""),
+            "archive" => {
+                report.check_keys(table, "archive.", &ARCHIVE_KEYS);
+                archive_span = Some(span);
+            }
+            "redact" => {
+                report.check_keys(table, "redact.", &REDACT_KEYS);
+            }
+            "alerts" => {
+                for (_, span, email) in report.check_keys(table, "alerts.", &["email"]) {
+                    report.check_keys(email, "alerts.email.", &EMAIL_KEYS);
+                    report.check_required(email, span, "alerts.email.", &["host", "from", "to"]);
+                }
+            }
+            "chat" => {
+                report.check_keys(table, "chat.", &CHAT_KEYS);
+            }
+            "otlp" => {
+                report.check_keys(table, "otlp.", &OTLP_KEYS);
+            }
+            "signatures" => {
+                for (name, value) in table.iter() {
+                    let section = format!("signatures.{}.", name.get_ref());
+                    match value.get_ref() {
+                        DeValue::Table(scheme) => {
+                            report.check_keys(scheme, &section, &SIGNATURE_KEYS);
+                            report.check_required(scheme, name.span(), &section, &["header"]);
+                        }
+                        _ => report.error(
+                            Some(name.span()),
+                            format!("`{}` must be a table", section.trim_end_matches('.')),
+                        ),
+                    }
+                }
+            }
+            _ => {
+                for (profile, value) in table.iter() {
+                    let section = format!("profiles.{}.", profile.get_ref());
+                    let DeValue::Table(profile) = value.get_ref() else {
+                        report.error(
+                            Some(profile.span()),
+                            format!("`{}` must be a table", section),
+                        );
+                        continue;
+                    };
+                    let known: Vec<&str> = WEBHOOK_KEYS
+                        .iter()
+                        .copied()
+                        .chain(["auth", "tokens"])
+                        .collect();
+                    for (name, _, table) in report.check_keys(profile, &section, &known) {
+                        match name {
+                            "auth" => report.check_auth(table, &section),
+                            "tokens" => report.check_tokens(table, &section),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if webhook_span.is_none() {
+        report.error(None, "missing [webhook] section".to_string());
+    }
+    match migrate::plan(content) {
+        Ok(Some(migration)) => report.warning(
+            None,
+            format!(
+                "the file is version {} and is migrated to version {} when next loaded; preview with `webhook config migrate --dry-run`",
+                migration.from, CONFIG_VERSION
+            ),
+        ),
+        Ok(None) => {}
+        Err(e) => {
+            let span = root
+                .get_ref()
+                .iter()
+                .find(|(key, _)| key.get_ref().as_ref() == "version")
+                .map(|(key, _)| key.span());
+            report.error(span, format!("{:#}", e));
+        }
+    }
+    if report.problems.iter().any(|problem| !problem.warning) {
+        return;
+    }
+
+    // Types and values, as the CLI would load them
+    let config: Config = match toml::from_str(content) {
+        Ok(config) => config,
+        Err(e) => {
+            report.error(e.span(), e.message().to_string());
+            return;
+        }
+    };
+    report.check_values(&config, "webhook", webhook_span);
+    if let Err(e) = config.archive.retention() {
+        report.error(archive_span, format!("{:#}", e));
+    }
+    if let Some(pattern) = &config.chat.r#match
+        && let Err(e) = parse_pattern(pattern)
+    {
+        report.error(None, format!("chat.match: {}", e));
+    }
+    if let Some(email) = &config.alerts.email
+        && let Err(e) = check_email_config(email)
+    {
+        report.error(None, format!("alerts.email: {:#}", e));
+    }
+    if let Err(e) = check_otlp_config(&config.otlp) {
+        report.error(None, format!("otlp: {:#}", e));
+    }
+    for (name, settings) in &config.signatures {
+        if let Err(e) = CustomScheme::new(settings) {
+            report.error(None, format!("signatures.{}: {:#}", name, e));
+        }
+    }
+    for name in config.profiles.keys() {
+        let section = format!("profiles.{}", name);
+        let mut profile: Config = match toml::from_str(content) {
+            Ok(profile) => profile,
+            Err(_) => continue,
+        };
+        match profile.apply_profile(name) {
+            Ok(()) => report.check_values(&profile, &section, None),
+            Err(e) => report.error(None, format!("{:#}", e)),
+        }
+    }
+}
+
+/// Make a test call with the effective configuration
+pub async fn probe(config: &Config) -> Result<()> {
+    println!(
+        "{} {}",
+        "Probing".bright_blue().bold(),
+        config.get_base_url().bright_white()
+    );
+    WebhookClient::new(config)?.probe().await?;
+    println!("{} Webhook service is reachable", "✓".bright_green());
+    Ok(())
+}
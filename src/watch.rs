@@ -0,0 +1,348 @@
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::backoff::PollInterval;
+use crate::client::{RequestStream, WebhookClient};
+use crate::config::{Config, Transport};
+use crate::dedup::RecentIds;
+use crate::error::{Result, WebhookError};
+use crate::models::WebhookRequest;
+
+/// Minimum number of request IDs remembered per token to skip repeats
+const SEEN_IDS_CAPACITY: usize = 1000;
+
+/// How `WebhookClient::watch` and `WebhookClient::stream_requests` follow a token
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Time between polls while requests keep arriving
+    pub interval: Duration,
+    /// Longest time between polls while the token is idle or the service fails
+    pub max_interval: Duration,
+    /// Requests fetched per poll
+    pub count: u32,
+    /// Whether to use the service's event stream or poll
+    pub transport: Transport,
+    /// Resume after this request: the requests that arrived since are
+    /// reported as missed instead of listing the existing ones
+    pub since_id: Option<String>,
+}
+
+impl StreamOptions {
+    /// The intervals, count and transport set in the config
+    pub fn from_config(config: &Config) -> Self {
+        StreamOptions {
+            interval: Duration::from_secs(config.webhook.default_interval),
+            max_interval: Duration::from_secs(config.get_max_interval()),
+            count: config.webhook.default_count,
+            transport: config.get_transport(),
+            since_id: None,
+        }
+    }
+}
+
+/// What happened while watching a token
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The requests the token had when watching started, newest first
+    Existing(Vec<WebhookRequest>),
+    /// The requests that arrived after `since_id` before watching resumed,
+    /// newest first
+    Missed(Vec<WebhookRequest>),
+    /// A request that arrived while watching
+    Request(Box<WebhookRequest>),
+    /// Connected to the service's event stream
+    Connected,
+    /// The event stream was closed by the service (`None`) or failed
+    Disconnected(Option<WebhookError>),
+    /// A poll of the request log finished
+    Polled { elapsed: Duration, failed: bool },
+    /// A poll failed, for the `consecutive`th time in a row; polling
+    /// continues in `retry_in`
+    Failed {
+        error: WebhookError,
+        retry_in: Duration,
+        consecutive: u32,
+    },
+    /// A poll succeeded after failures
+    Restored,
+}
+
+impl WebhookClient {
+    /// Watch a token for new requests: the event stream when the service has
+    /// one, otherwise the request log, polled with backoff while idle or
+    /// failing. Requests are reported once even when a poll and the stream
+    /// both deliver them.
+    ///
+    /// Failures that can be retried are reported as events; the stream ends
+    /// after an error item, when watching cannot continue.
+    pub fn watch<'a>(
+        &'a self,
+        token: &str,
+        options: StreamOptions,
+    ) -> impl Stream<Item = Result<WatchEvent>> + 'a {
+        let watcher = Watcher::new(self, token, options);
+        stream::unfold(watcher, |mut watcher| async move {
+            watcher.next().await.map(|event| (event, watcher))
+        })
+    }
+
+    /// New requests for a token, oldest first, as they arrive; see `watch`.
+    /// A run of failed polls is reported by its first error.
+    pub fn stream_requests<'a>(
+        &'a self,
+        token: &str,
+        options: StreamOptions,
+    ) -> impl Stream<Item = Result<WebhookRequest>> + 'a {
+        self.watch(token, options).flat_map(|event| {
+            let items: Vec<Result<WebhookRequest>> = match event {
+                Ok(WatchEvent::Missed(requests)) => requests.into_iter().rev().map(Ok).collect(),
+                Ok(WatchEvent::Request(request)) => vec![Ok(*request)],
+                Ok(WatchEvent::Failed {
+                    error,
+                    consecutive: 1,
+                    ..
+                }) => vec![Err(error)],
+                Ok(_) => Vec::new(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(items)
+        })
+    }
+}
+
+/// What the watcher does next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Poll,
+    Stream,
+    Sleep,
+    Done,
+}
+
+struct Watcher<'a> {
+    client: &'a WebhookClient,
+    token: String,
+    options: StreamOptions,
+    phase: Phase,
+    first_run: bool,
+    streaming: bool,
+    stream: Option<RequestStream>,
+    interval: PollInterval,
+    seen: RecentIds,
+    /// Newest request ID seen, to fetch only the delta after the first poll
+    newest_id: Option<String>,
+    pending: VecDeque<WatchEvent>,
+}
+
+impl<'a> Watcher<'a> {
+    fn new(client: &'a WebhookClient, token: &str, options: StreamOptions) -> Self {
+        Watcher {
+            client,
+            token: token.to_string(),
+            phase: Phase::Poll,
+            first_run: true,
+            streaming: options.transport != Transport::Poll,
+            stream: None,
+            interval: PollInterval::new(options.interval, options.max_interval),
+            seen: RecentIds::with_capacity((options.count as usize * 2).max(SEEN_IDS_CAPACITY)),
+            newest_id: options.since_id.clone(),
+            pending: VecDeque::new(),
+            options,
+        }
+    }
+
+    async fn next(&mut self) -> Option<Result<WatchEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.phase {
+                Phase::Done => return None,
+                Phase::Poll if !self.client.keeps_history() => {
+                    // Nothing to list: start from the stream right away
+                    if self.options.transport == Transport::Poll {
+                        self.phase = Phase::Done;
+                        return Some(Err(WebhookError::config(
+                            "This provider has no request log to poll; set transport to auto or sse",
+                        )));
+                    }
+                    self.first_run = false;
+                    self.phase = Phase::Stream;
+                    return Some(Ok(WatchEvent::Existing(Vec::new())));
+                }
+                Phase::Poll => {
+                    if let Err(e) = self.poll().await {
+                        self.phase = Phase::Done;
+                        return Some(Err(e));
+                    }
+                }
+                Phase::Sleep => {
+                    tokio::time::sleep(self.interval.current()).await;
+                    self.phase = if self.streaming && !self.first_run {
+                        Phase::Stream
+                    } else {
+                        Phase::Poll
+                    };
+                }
+                Phase::Stream => {
+                    if let Err(e) = self.follow_stream().await {
+                        self.phase = Phase::Done;
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record `request` as seen; `false` when it already was
+    fn accept(&mut self, request: &WebhookRequest) -> bool {
+        if self.seen.contains(&request.id) {
+            return false;
+        }
+        self.seen.insert(request.id.clone());
+        true
+    }
+
+    /// Poll the log once. Failures are reported as events and retried with
+    /// backoff, except in dry-run mode, which ends the watch.
+    async fn poll(&mut self) -> Result<()> {
+        let started = Instant::now();
+        let result = match &self.newest_id {
+            Some(since_id) => {
+                self.client
+                    .get_requests_since(&self.token, since_id, self.options.count)
+                    .await
+            }
+            None => {
+                self.client
+                    .get_requests(&self.token, self.options.count)
+                    .await
+            }
+        };
+        self.pending.push_back(WatchEvent::Polled {
+            elapsed: started.elapsed(),
+            failed: result.is_err(),
+        });
+
+        match result {
+            Ok(requests) => {
+                if self.interval.consecutive_errors() > 0 {
+                    self.pending.push_back(WatchEvent::Restored);
+                }
+                if requests.is_empty() {
+                    self.interval.on_idle();
+                } else {
+                    self.interval.on_activity();
+                }
+                if let Some(newest) = requests.first() {
+                    self.newest_id = Some(newest.id.clone());
+                }
+
+                let requests: Vec<_> = requests
+                    .into_iter()
+                    .filter(|request| self.accept(request))
+                    .collect();
+                if self.first_run && self.options.since_id.is_none() {
+                    self.pending.push_back(WatchEvent::Existing(requests));
+                } else if self.first_run {
+                    self.pending.push_back(WatchEvent::Missed(requests));
+                } else {
+                    // The service returns newest first, report them in arrival order
+                    self.pending.extend(
+                        requests
+                            .into_iter()
+                            .rev()
+                            .map(|request| WatchEvent::Request(Box::new(request))),
+                    );
+                }
+                // Go straight to the event stream after the first poll
+                self.phase = if self.first_run && self.streaming {
+                    Phase::Stream
+                } else {
+                    Phase::Sleep
+                };
+                self.first_run = false;
+            }
+            Err(WebhookError::DryRun) => return Err(WebhookError::DryRun),
+            Err(error) => {
+                match error {
+                    WebhookError::RateLimited { retry_after } => {
+                        self.interval.on_rate_limited(retry_after)
+                    }
+                    _ => self.interval.on_error(),
+                }
+                self.pending.push_back(WatchEvent::Failed {
+                    error,
+                    retry_in: self.interval.current(),
+                    consecutive: self.interval.consecutive_errors(),
+                });
+                self.phase = Phase::Sleep;
+            }
+        }
+        Ok(())
+    }
+
+    /// Connect to the event stream or read its next request. A dropped
+    /// stream is followed by a poll to catch up, or for services without a
+    /// log, by a reconnect after the backoff interval.
+    async fn follow_stream(&mut self) -> Result<()> {
+        let after_stream = match self.client.keeps_history() {
+            true => Phase::Poll,
+            false => Phase::Sleep,
+        };
+        let Some(stream) = &mut self.stream else {
+            match self.client.open_stream(&self.token).await {
+                Ok(Some(stream)) => {
+                    if !self.client.keeps_history() {
+                        self.interval.on_activity();
+                    }
+                    self.stream = Some(stream);
+                    self.pending.push_back(WatchEvent::Connected);
+                }
+                Ok(None)
+                    if self.options.transport == Transport::Sse || !self.client.keeps_history() =>
+                {
+                    return Err(WebhookError::config(
+                        "The webhook service does not support event streaming",
+                    ));
+                }
+                Ok(None) => {
+                    // No streaming endpoint, stay on polling from now on
+                    self.streaming = false;
+                    self.phase = Phase::Poll;
+                }
+                Err(WebhookError::DryRun) => return Err(WebhookError::DryRun),
+                Err(e) => {
+                    if !self.client.keeps_history() {
+                        self.interval.on_error();
+                    }
+                    self.pending.push_back(WatchEvent::Disconnected(Some(e)));
+                    self.phase = after_stream;
+                }
+            }
+            return Ok(());
+        };
+
+        match stream.next_request().await {
+            Ok(Some(request)) => {
+                if self.accept(&request) {
+                    self.newest_id = Some(request.id.clone());
+                    self.pending
+                        .push_back(WatchEvent::Request(Box::new(request)));
+                }
+            }
+            Ok(None) => {
+                self.stream = None;
+                self.pending.push_back(WatchEvent::Disconnected(None));
+                self.phase = after_stream;
+            }
+            Err(e) => {
+                self.stream = None;
+                self.pending.push_back(WatchEvent::Disconnected(Some(e)));
+                self.phase = after_stream;
+            }
+        }
+        Ok(())
+    }
+}